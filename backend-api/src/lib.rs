@@ -5,6 +5,7 @@ use std::any::Any;
 use std::fmt;
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::ops::Range;
 use std::path::Path;
 use std::slice::Iter;
 use std::sync::mpsc::Receiver;
@@ -41,6 +42,7 @@ pub struct WindowConfig {
     pub height: u32,
 }
 
+///GLFW key token, independent of the keyboard layout. Printable keys are their US layout character.
 pub type Code = u32;
 
 #[derive(Clone, Debug)]
@@ -78,6 +80,12 @@ pub struct PipelineDescriptor<'a, B: Backend> {
     pub layout: &'a B::PipelineLayout,
     pub vertex_buffers: Vec<VertexBufferDescriptor>,
     pub vertex_attributes: Vec<AttributeDescriptor>,
+    pub rasterizer: RasterizerState,
+    pub depth_stencil: DepthStencilState,
+    ///Blend state of each colour attachment. Attachments without a state, or past the end, are written without blending.
+    pub blend: Vec<Option<BlendState>>,
+    ///Pass the pipeline is built for, or the window's pass when `None`. Vulkan rebuilds the pipeline for passes of other formats.
+    pub render_pass: Option<&'a B::RenderPass>,
 }
 
 impl<'a, B> PipelineDescriptor<'a, B> where B: Backend {
@@ -88,6 +96,10 @@ impl<'a, B> PipelineDescriptor<'a, B> where B: Backend {
             layout: layout,
             vertex_buffers: Vec::new(),
             vertex_attributes: Vec::new(),
+            rasterizer: RasterizerState::default(),
+            depth_stencil: DepthStencilState::default(),
+            blend: Vec::new(),
+            render_pass: None,
         }
     }
 
@@ -113,11 +125,47 @@ pub struct AttributeDescriptor {
 }
 
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum Usage {
     Vertex,
     Index,
     Uniform,
+    ///Staging source of `CommandBuffer::copy_buffer_to_image`.
+    TransferSrc,
+    ///Readback destination of `CommandBuffer::copy_image_to_buffer`.
+    TransferDst,
+    ///Arguments of `CommandBuffer::draw_indexed_indirect`.
+    Indirect,
+}
+
+///Arguments of one draw of `CommandBuffer::draw_indexed_indirect`, laid out like the GL and Vulkan commands.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct DrawIndexedIndirectCommand {
+    pub index_count: u32,
+    pub instance_count: u32,
+    ///Offset in indices, not bytes, from the start of the bound index buffer.
+    pub first_index: u32,
+    pub base_vertex: i32,
+    pub first_instance: u32,
+}
+
+impl DrawIndexedIndirectCommand {
+    ///Decodes a command from the start of `bytes`, as written to an indirect buffer on the host.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let word = |i: usize| {
+            let mut w = [0; 4];
+            w.copy_from_slice(&bytes[i * 4..i * 4 + 4]);
+            w
+        };
+        DrawIndexedIndirectCommand {
+            index_count: u32::from_ne_bytes(word(0)),
+            instance_count: u32::from_ne_bytes(word(1)),
+            first_index: u32::from_ne_bytes(word(2)),
+            base_vertex: i32::from_ne_bytes(word(3)),
+            first_instance: u32::from_ne_bytes(word(4)),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -126,16 +174,226 @@ pub struct VertexData {
     pub data_type: DataType,
 }
 
-#[derive(Debug)]
+///Compares a fragment's depth, or the stencil reference, with the stored value. The fragment passes when the comparison holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Never,
+    Less,
+    Equal,
+    LessOrEqual,
+    Greater,
+    NotEqual,
+    GreaterOrEqual,
+    Always,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CullMode {
+    None,
+    Front,
+    Back,
+}
+
+///Winding of the triangles that face the camera, as seen in window space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrontFace {
+    CounterClockwise,
+    Clockwise,
+}
+
+///How triangles are rasterized. `Line` draws their edges as a wireframe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolygonMode {
+    Fill,
+    Line,
+    Point,
+}
+
+///Offset added to the depth of fragments. `constant` is in units of the smallest resolvable depth difference
+///and `slope` multiplies the triangle's depth slope.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DepthBias {
+    pub constant: f32,
+    pub slope: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RasterizerState {
+    ///Culls triangles facing away from the camera by default.
+    pub cull_mode: CullMode,
+    pub front_face: FrontFace,
+    pub polygon_mode: PolygonMode,
+    ///Offsets the depth of fragments, which keeps shadow casters from shadowing themselves.
+    pub depth_bias: Option<DepthBias>,
+    ///Width of lines in pixels. Widths other than 1 are not supported by every device.
+    pub line_width: f32,
+}
+
+impl Default for RasterizerState {
+    fn default() -> Self {
+        RasterizerState {
+            cull_mode: CullMode::Back,
+            front_face: FrontFace::CounterClockwise,
+            polygon_mode: PolygonMode::Fill,
+            depth_bias: None,
+            line_width: 1.,
+        }
+    }
+}
+
+///Operation applied to the stored stencil value after the stencil test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StencilOp {
+    Keep,
+    Zero,
+    ///Stores the reference.
+    Replace,
+    IncrementAndClamp,
+    DecrementAndClamp,
+    Invert,
+    IncrementAndWrap,
+    DecrementAndWrap,
+}
+
+///Stencil test and operations for the triangles of one facing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StencilFaceState {
+    ///Compares the masked reference against the masked stored value.
+    pub compare: CompareOp,
+    ///Applied when the stencil test fails.
+    pub fail: StencilOp,
+    ///Applied when the stencil test passes but the depth test fails.
+    pub depth_fail: StencilOp,
+    pub pass: StencilOp,
+}
+
+impl Default for StencilFaceState {
+    fn default() -> Self {
+        StencilFaceState {
+            compare: CompareOp::Always,
+            fail: StencilOp::Keep,
+            depth_fail: StencilOp::Keep,
+            pass: StencilOp::Keep,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StencilState {
+    pub front: StencilFaceState,
+    pub back: StencilFaceState,
+    ///Bits of the reference and the stored value compared.
+    pub read_mask: u32,
+    ///Bits of the stored value the operations change.
+    pub write_mask: u32,
+    pub reference: u32,
+}
+
+impl Default for StencilState {
+    fn default() -> Self {
+        StencilState {
+            front: StencilFaceState::default(),
+            back: StencilFaceState::default(),
+            read_mask: !0,
+            write_mask: !0,
+            reference: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DepthStencilState {
+    ///Compares fragment depth with the depth attachment, `Less` by default.
+    pub depth_compare: CompareOp,
+    ///Whether fragments that pass the depth test write their depth, true by default.
+    pub depth_write: bool,
+    ///Tests against the stencil of a `D24S8` attachment. Every fragment passes without it.
+    pub stencil: Option<StencilState>,
+}
+
+impl Default for DepthStencilState {
+    fn default() -> Self {
+        DepthStencilState {
+            depth_compare: CompareOp::Less,
+            depth_write: true,
+            stencil: None,
+        }
+    }
+}
+
+///Multiplier of a blended colour or alpha. `Src` is the fragment and `Dst` the attachment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendFactor {
+    Zero,
+    One,
+    SrcColor,
+    OneMinusSrcColor,
+    DstColor,
+    OneMinusDstColor,
+    SrcAlpha,
+    OneMinusSrcAlpha,
+    DstAlpha,
+    OneMinusDstAlpha,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendOp {
+    Add,
+    ///Source minus destination.
+    Subtract,
+    ///Destination minus source.
+    ReverseSubtract,
+    Min,
+    Max,
+}
+
+///Colour is `src_color * src + dst_color * dst` combined by `color_op`, alpha likewise with its own factors.
+///Factors are ignored by `Min` and `Max`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlendState {
+    pub src_color: BlendFactor,
+    pub dst_color: BlendFactor,
+    pub color_op: BlendOp,
+    pub src_alpha: BlendFactor,
+    pub dst_alpha: BlendFactor,
+    pub alpha_op: BlendOp,
+}
+
+impl BlendState {
+    ///Blends fragments over the attachment by their alpha, for unpremultiplied transparency.
+    pub const ALPHA: BlendState = BlendState {
+        src_color: BlendFactor::SrcAlpha,
+        dst_color: BlendFactor::OneMinusSrcAlpha,
+        color_op: BlendOp::Add,
+        src_alpha: BlendFactor::One,
+        dst_alpha: BlendFactor::OneMinusSrcAlpha,
+        alpha_op: BlendOp::Add,
+    };
+
+    ///Adds fragments to the attachment, for glows and particles.
+    pub const ADDITIVE: BlendState = BlendState {
+        src_color: BlendFactor::One,
+        dst_color: BlendFactor::One,
+        color_op: BlendOp::Add,
+        src_alpha: BlendFactor::One,
+        dst_alpha: BlendFactor::One,
+        alpha_op: BlendOp::Add,
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Primitive {
+    Points,
+    Lines,
+    LinesStrip,
     Triangles,
     TrianglesFan,
     TrianglesStrip,
-    Quads,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum DataType {
+    Float32,
     Vec3f32,
     Vec2f32,
     Mat4f32,
@@ -143,9 +401,16 @@ pub enum DataType {
 
 pub struct ShaderModDescriptor {
     pub stype: ShaderType,
-    pub source: String,
+    pub source: ShaderSource,
 }
 
+#[derive(Debug, Clone)]
+pub enum ShaderSource {
+    Glsl(String),
+    SpirV(Vec<u32>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum ShaderType {
     Vertex,
     Fragment,
@@ -188,7 +453,9 @@ pub struct DescriptorSetWrite<'a, B: Backend> {
 }
 
 pub enum Descriptor<'a, B: Backend> {
-    Buffer(&'a B::Buffer)
+    Buffer(&'a B::Buffer),
+    ///Bound to a `DescriptorType::Sampler` binding.
+    CombinedImageSampler(&'a B::ImageView, &'a B::Sampler),
 }
 
 pub trait PlatformManager<B: Backend> {
@@ -204,17 +471,70 @@ pub trait PlatformManager<B: Backend> {
     fn imgui_renderer(&mut self, imgui: &mut imgui::Context) -> B::ImGuiRenderer;
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    ShaderCompile { stage: ShaderType, log: String },
+    Link(String),
+    MissingAttribute { location: u32 },
+    AttributeTypeMismatch { location: u32, expected: DataType },
+    InvalidUniformBlock { binding: u32, name: Option<String> },
+    ///Framebuffer attachment at `index` does not match the render pass.
+    InvalidAttachment { index: usize },
+    ///Attachments are valid on their own but cannot be rendered to together.
+    IncompleteFramebuffer(String),
+    ///Resource does not fit the memory at `offset`, is misaligned or the memory type is not allowed.
+    InvalidMemoryBinding { offset: u32 },
+    OutOfMemory,
+    ///Id of a `kind` of resource that was never registered.
+    UnknownResource { kind: &'static str, id: u32 },
+    Unsupported(String),
+    ///Command recorded in a state the device cannot execute it in.
+    InvalidCommand(String),
+    ///Failure reported by the underlying API.
+    Backend(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::ShaderCompile { stage, log } => write!(f, "{:?} shader compilation failed: {}", stage, log),
+            Error::Link(log) => write!(f, "Program link failed: {}", log),
+            Error::MissingAttribute { location } => write!(f, "No active attribute at location {}", location),
+            Error::AttributeTypeMismatch { location, expected } =>
+                write!(f, "Attribute at location {} is not {:?}", location, expected),
+            Error::InvalidUniformBlock { binding, name: Some(name) } =>
+                write!(f, "Uniform block {} for binding {} not found", name, binding),
+            Error::InvalidUniformBlock { binding, name: None } =>
+                write!(f, "Uniform block for binding {} not found", binding),
+            Error::InvalidAttachment { index } => write!(f, "Attachment {} does not match render pass", index),
+            Error::IncompleteFramebuffer(why) => write!(f, "Incomplete framebuffer: {}", why),
+            Error::InvalidMemoryBinding { offset } => write!(f, "Resource can not be bound to memory at offset {}", offset),
+            Error::OutOfMemory => write!(f, "Out of memory"),
+            Error::UnknownResource { kind, id } => write!(f, "Unknown {} {}", kind, id),
+            Error::Unsupported(what) => write!(f, "Unsupported: {}", what),
+            Error::InvalidCommand(why) => write!(f, "Invalid command: {}", why),
+            Error::Backend(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
 pub trait RendererDevice<B: Backend> {
     //mem
+    fn memory_types(&self) -> Vec<memory::MemoryType>;
+
     fn allocate_memory(
         &self,
+        memory_type: memory::TypeId,
         size: u32,
-    ) -> B::Memory;
+    ) -> Result<B::Memory, Error>;
 
+    ///Mapping is counted, nested maps of one memory return the same pointer to its start.
     fn map_memory(
         &self,
         memory: &B::Memory,
-    ) -> *mut u8;
+    ) -> Result<*mut u8, Error>;
     fn flush_memory(&self,
                     memory: &B::Memory,
     );
@@ -224,20 +544,26 @@ pub trait RendererDevice<B: Backend> {
         memory: &B::Memory,
     );
 
-    fn bind_buffer_memory(
+    fn get_buffer_requirements(
         &self,
-        memory: &mut B::Memory,
         buffer: &B::Buffer,
-    );
+    ) -> memory::Requirements;
+
+    fn bind_buffer_memory(
+        &self,
+        memory: &B::Memory,
+        offset: u32,
+        buffer: &mut B::Buffer,
+    ) -> Result<(), Error>;
     //buffer
     fn create_buffer(
         &self,
         desc: BufferDescriptor,
-    ) -> B::Buffer;
+    ) -> Result<B::Buffer, Error>;
     fn create_pipeline(
         &self,
         desc: PipelineDescriptor<B>,
-    ) -> B::Pipeline;
+    ) -> Result<B::Pipeline, Error>;
     //make pooled
     fn create_cmd_buffer(
         &self
@@ -246,28 +572,30 @@ pub trait RendererDevice<B: Backend> {
     fn allocate_descriptor_set(
         &self,
         desc: &B::DescriptorSetLayout,
-    ) -> B::DescriptorSet;
+    ) -> Result<B::DescriptorSet, Error>;
 
+    ///Returns once the commands finished executing, so the buffers and images they read can be rewritten
+    ///right after. Commands following one that fails are not executed.
     fn execute(
         &self,
         cmd: B::CommandBuffer,
-    );
+    ) -> Result<(), Error>;
 
     fn create_shader_mod(
         &self,
         desc: ShaderModDescriptor,
-    ) -> B::ShaderMod;
+    ) -> Result<B::ShaderMod, Error>;
 
     fn create_descriptor_set_layout(
         &self,
         bindings: &[DescriptorSetLayoutBinding],
-    ) -> B::DescriptorSetLayout;
+    ) -> Result<B::DescriptorSetLayout, Error>;
 
     fn create_pipeline_layout<I>(
         &self,
         desc_layout: &B::DescriptorSetLayout,
         hints: I,
-    ) -> B::PipelineLayout
+    ) -> Result<B::PipelineLayout, Error>
         where
             I: IntoIterator<Item=PipelineLayoutHint>;
 
@@ -280,7 +608,7 @@ pub trait RendererDevice<B: Backend> {
     fn create_render_pass<A>(
         &self,
         attachments: A,// color, depth, stencil attachments, etc.
-    ) -> B::RenderPass
+    ) -> Result<B::RenderPass, Error>
         where
             A: IntoIterator<Item=Attachment>;
 
@@ -288,7 +616,7 @@ pub trait RendererDevice<B: Backend> {
         &self,
         render_pass: &B::RenderPass,
         attachments: A,// color, depth, stencil attachments, etc.
-    ) -> B::Framebuffer
+    ) -> Result<B::Framebuffer, Error>
         where
             A: IntoIterator,
             A::Item: Borrow<B::ImageView>;
@@ -296,27 +624,110 @@ pub trait RendererDevice<B: Backend> {
     fn create_swapchain(
         &self,
         surface: &B::Surface,
-    ) -> (
+    ) -> Result<(
         B::Swapchain,
         Vec<B::Image>
-    );
+    ), Error>;
 
+    ///Fails with `Error::Unsupported` when the device can not sample or render `format`.
     fn create_image(
         &self,
         kind: image::Kind,
-    ) -> B::Image;
+        format: image::Format,
+    ) -> Result<B::Image, Error>;
 
     fn create_image_view(
         &self,
         image: &B::Image,
-    ) -> B::ImageView;
+    ) -> Result<B::ImageView, Error>;
+
+    fn create_sampler(
+        &self,
+        desc: image::SamplerDesc,
+    ) -> Result<B::Sampler, Error>;
 
 
+    fn get_image_requirements(
+        &self,
+        image: &B::Image,
+    ) -> memory::Requirements;
+
+    ///Reads `level` of `image` back after every executed command. Texels are tightly packed, with the rows
+    ///of each layer running from the bottom of what was rendered to its top on every backend. Vulkan stores
+    ///rendered rows from the top and reverses them, so images filled by `copy_buffer_to_image` come back
+    ///upside down there.
+    fn read_pixels(
+        &self,
+        image: &B::Image,
+        level: image::Level,
+    ) -> Result<Vec<u8>, Error>;
+
     fn bind_image_memory(
         &self,
-        mem: &mut B::Memory,
-        img: &B::Image
-    );
+        mem: &B::Memory,
+        offset: u32,
+        img: &mut B::Image
+    ) -> Result<(), Error>;
+}
+
+pub mod memory {
+    use std::ops::BitOr;
+
+    ///Index into `RendererDevice::memory_types`.
+    pub type TypeId = usize;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct Properties(u32);
+
+    impl Properties {
+        pub const DEVICE_LOCAL: Properties = Properties(1);
+        pub const HOST_VISIBLE: Properties = Properties(1 << 1);
+        ///Host writes are visible without `flush_memory`.
+        pub const HOST_COHERENT: Properties = Properties(1 << 2);
+
+        pub fn empty() -> Properties {
+            Properties(0)
+        }
+
+        pub fn contains(self, other: Properties) -> bool {
+            self.0 & other.0 == other.0
+        }
+    }
+
+    impl BitOr for Properties {
+        type Output = Properties;
+
+        fn bitor(self, rhs: Properties) -> Properties {
+            Properties(self.0 | rhs.0)
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct MemoryType {
+        pub properties: Properties,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Requirements {
+        pub size: u32,
+        pub alignment: u32,
+        ///Bit `i` is set when memory type `i` can hold the resource.
+        pub type_mask: u32,
+    }
+
+    impl Requirements {
+        pub fn allows(&self, memory_type: TypeId) -> bool {
+            self.type_mask & (1 << memory_type as u32) != 0
+        }
+
+        ///Whether the resource can be bound at `offset` in memory of `memory_type` and `memory_size`,
+        ///alignments are powers of two.
+        pub fn fits(&self, memory_type: TypeId, memory_size: u32, offset: u32) -> bool {
+            self.allows(memory_type)
+                && offset & (self.alignment.max(1) - 1) == 0
+                && u64::from(offset) + u64::from(self.size) <= u64::from(memory_size)
+        }
+    }
 }
 
 pub mod image {
@@ -328,6 +739,145 @@ pub mod image {
         D1(Size, Level),
         D2(Size, Size, Level),
         D3(Size, Size, Size),
+        ///Six square faces with sides of the given size, stored as layers along `z` in the order +x, -x, +y, -y, +z, -z.
+        Cube(Size, Level),
+    }
+
+    impl Kind {
+        pub fn extent(&self) -> (Size, Size, Size) {
+            match *self {
+                Kind::D1(width, _) => (width, 1, 1),
+                Kind::D2(width, height, _) => (width, height, 1),
+                Kind::D3(width, height, depth) => (width, height, depth),
+                Kind::Cube(size, _) => (size, size, 6),
+            }
+        }
+
+        pub fn levels(&self) -> Level {
+            match *self {
+                Kind::D1(_, levels) | Kind::D2(_, _, levels) | Kind::Cube(_, levels) => levels.max(1),
+                Kind::D3(_, _, _) => 1,
+            }
+        }
+
+        ///Extent of mip `level`, halved per level down to 1. Every level of a cube keeps its six faces.
+        pub fn level_extent(&self, level: Level) -> (Size, Size, Size) {
+            let (width, height, depth) = self.extent();
+            let depth = match self {
+                Kind::Cube(..) => depth,
+                _ => (depth >> level).max(1),
+            };
+            ((width >> level).max(1), (height >> level).max(1), depth)
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum Format {
+        Rgba8,
+        Rgba8Srgb,
+        Rgba16F,
+        R32F,
+        D24S8,
+        D32F,
+        ///BC1 (DXT1) rgba, 8 bytes per 4x4 block.
+        Bc1,
+        ///BC3 (DXT5) rgba, 16 bytes per 4x4 block.
+        Bc3,
+        ///BC4 (RGTC1) red, 8 bytes per 4x4 block.
+        Bc4,
+        ///BC5 (RGTC2) red and green, 16 bytes per 4x4 block.
+        Bc5,
+    }
+
+    impl Format {
+        pub fn is_depth(&self) -> bool {
+            matches!(self, Format::D24S8 | Format::D32F)
+        }
+
+        pub fn has_stencil(&self) -> bool {
+            *self == Format::D24S8
+        }
+
+        pub fn is_compressed(&self) -> bool {
+            self.block().0 > 1
+        }
+
+        ///Side of a block in texels and its size in bytes. Uncompressed formats have single texel blocks.
+        pub fn block(&self) -> (Size, u32) {
+            match self {
+                Format::Rgba8 | Format::Rgba8Srgb | Format::R32F | Format::D24S8 | Format::D32F => (1, 4),
+                Format::Rgba16F => (1, 8),
+                Format::Bc1 | Format::Bc4 => (4, 8),
+                Format::Bc3 | Format::Bc5 => (4, 16),
+            }
+        }
+
+        ///Number of blocks covering `texels` along one axis.
+        pub fn blocks(&self, texels: Size) -> Size {
+            let side = self.block().0;
+            //block sides are powers of two
+            texels / side + Size::from(texels & (side - 1) != 0)
+        }
+
+        ///Size in bytes of a tightly packed region of `extent`, with partial blocks rounded up.
+        pub fn size_of(&self, extent: (Size, Size, Size)) -> u32 {
+            self.blocks(extent.0) * self.blocks(extent.1) * extent.2 * self.block().1
+        }
+    }
+
+    ///Region of a level copied by `CommandBuffer::copy_buffer_to_image` and `copy_image_to_buffer`,
+    ///texels are tightly packed in the image format.
+    #[derive(Debug, Clone)]
+    pub struct BufferImageCopy {
+        pub buffer_offset: u32,
+        pub level: Level,
+        pub offset: (Size, Size, Size),
+        pub extent: (Size, Size, Size),
+    }
+
+    impl BufferImageCopy {
+        ///Whole `level` of an image of `kind` from the start of the buffer.
+        pub fn level(kind: &Kind, level: Level) -> BufferImageCopy {
+            BufferImageCopy {
+                buffer_offset: 0,
+                level,
+                offset: (0, 0, 0),
+                extent: kind.level_extent(level),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Filter {
+        Nearest,
+        Linear,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum WrapMode {
+        Tile,
+        Mirror,
+        Clamp,
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct SamplerDesc {
+        pub min_filter: Filter,
+        pub mag_filter: Filter,
+        ///Filter between levels, `None` samples the base level only.
+        pub mip_filter: Option<Filter>,
+        pub wrap: (WrapMode, WrapMode, WrapMode),
+    }
+
+    impl SamplerDesc {
+        pub fn new(filter: Filter, wrap: WrapMode) -> SamplerDesc {
+            SamplerDesc {
+                min_filter: filter,
+                mag_filter: filter,
+                mip_filter: Some(filter),
+                wrap: (wrap, wrap, wrap),
+            }
+        }
     }
 }
 
@@ -339,10 +889,36 @@ pub trait Swapchain<B: Backend> {
 
 #[derive(Debug)]
 pub struct Attachment {
-    pub layout: AttachmentLayout
+    pub layout: AttachmentLayout,
+    ///Format of the images bound to the attachment.
+    pub format: image::Format,
+    ///What the attachment holds when the pass begins.
+    pub load: LoadOp,
+    ///Whether what the pass rendered is kept once it ends.
+    pub store: StoreOp,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LoadOp {
+    Load,
+    ///Filled with the clear value of the attachment given to `CommandBuffer::begin_render_pass`.
+    Clear,
+    DontCare,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StoreOp {
+    Store,
+    DontCare,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClearValue {
+    Color([f32; 4]),
+    DepthStencil(f32, u32),
+}
+
+#[derive(Debug, Clone)]
 pub enum AttachmentLayout {
     Color,
     Depth,
@@ -351,13 +927,43 @@ pub enum AttachmentLayout {
 
 pub trait CommandBuffer<B: Backend> {
     fn bind_pipeline(&mut self, pipeline: &B::Pipeline);
-    fn bind_render_pass(&self, render_pass: &B::RenderPass, framebuffer: &B::Framebuffer);
+    ///Renders into `framebuffer` until `end_render_pass`, commands outside a pass render to the swapchain.
+    ///`clear_values` are indexed by attachment. Transfers must not be recorded inside a pass.
+    ///Resets the viewport to all of `framebuffer` and disables the scissor.
+    fn begin_render_pass(&mut self, render_pass: &B::RenderPass, framebuffer: &B::Framebuffer, clear_values: &[ClearValue]);
+    ///Resets the viewport to the window's and disables the scissor.
+    fn end_render_pass(&mut self);
     fn bind_vertex_buffer(&mut self, binding: u32, buffer: &B::Buffer);
     fn bind_index_buffer(&mut self, buffer: &B::Buffer);
     fn buffer_data(&mut self, buffer: &B::Buffer, data: &[u8]);
+    fn copy_buffer_to_image(&mut self, buffer: &B::Buffer, image: &B::Image, region: image::BufferImageCopy);
+    fn copy_image_to_buffer(&mut self, image: &B::Image, buffer: &B::Buffer, region: image::BufferImageCopy);
+    ///Fills every level below the base one by downsampling the previous one. Compressed and depth images are not supported.
+    fn generate_mipmaps(&mut self, image: &B::Image);
     fn draw_indexed(&mut self, count: u32, offset: u32, number: u32);
+    ///Like `draw_indexed` with `base_vertex` added to every index. Per-instance attributes are fetched
+    ///starting at `instances.start`.
+    fn draw_indexed_base_vertex(&mut self, count: u32, offset: u32, base_vertex: i32, instances: Range<u32>);
+    ///Draws `draw_count` `DrawIndexedIndirectCommand`s read from `buffer` at `offset`, `stride` bytes apart.
+    ///Backends without multi draw indirect issue them one by one.
+    fn draw_indexed_indirect(&mut self, buffer: &B::Buffer, offset: u32, draw_count: u32, stride: u32);
     fn bind_descriptor_set(&mut self, pipeline_layout: &B::PipelineLayout, desc_set: &B::DescriptorSet);
+    ///Clears colour, depth and stencil of the bound target, only inside the scissor when one is set.
     fn clear_screen(&mut self, color: (f32, f32, f32, f32));
+    ///Maps the draws that follow to `viewport` of the bound target until a render pass begins or ends.
+    ///The window's viewport, which `RendererApi::viewport` sets, is in effect again once the buffer executed.
+    fn set_viewport(&mut self, viewport: Rect);
+    ///Discards fragments outside `scissor` until a render pass begins or ends, `None` keeps every fragment.
+    fn set_scissor(&mut self, scissor: Option<Rect>);
+}
+
+///Region of a framebuffer or the window in pixels, `x` and `y` are its bottom left corner as in OpenGL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
 }
 
 pub trait RendererApi<B: Backend> {
@@ -0,0 +1,24 @@
+use std::sync::{Arc, RwLock};
+
+#[derive(Debug, Clone)]
+pub struct HeadlessBuffer {
+    ///Memory the buffer is bound to, empty until `bind_buffer_memory`.
+    pub(crate) data: Arc<RwLock<Vec<u8>>>,
+    pub(crate) offset: usize,
+    pub(crate) size: usize,
+}
+
+impl HeadlessBuffer {
+    pub fn new(desc: api::BufferDescriptor) -> HeadlessBuffer {
+        HeadlessBuffer {
+            data: Arc::new(RwLock::new(Vec::new())),
+            offset: 0,
+            size: desc.size as usize,
+        }
+    }
+
+    pub(crate) fn bind(&mut self, memory: &crate::memory::HeadlessMemory, offset: u32) {
+        self.data = memory.data.clone();
+        self.offset = offset as usize;
+    }
+}
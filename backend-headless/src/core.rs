@@ -0,0 +1,217 @@
+use core::borrow::Borrow;
+use std::sync::{Arc, Mutex};
+
+use backend_api::{
+    RendererApi,
+    RendererDevice,
+};
+
+use crate::Backend;
+use crate::image::HeadlessImage;
+use crate::raster::{Target, Viewport};
+
+pub(crate) type SharedWindow = Arc<Mutex<Window>>;
+
+///Offscreen replacement of a platform window.
+pub(crate) struct Window {
+    pub(crate) back: Target,
+    pub(crate) front: Target,
+    pub(crate) viewport: Viewport,
+    pub(crate) clear_color: (f32, f32, f32, f32),
+    pub(crate) presented: u64,
+}
+
+impl Window {
+    pub(crate) fn new(width: u32, height: u32) -> SharedWindow {
+        Arc::new(Mutex::new(Window {
+            back: Target::new(width, height),
+            front: Target::new(width, height),
+            viewport: Viewport { x: 0, y: 0, width: width as i32, height: height as i32 },
+            clear_color: (0., 0., 0., 1.),
+            presented: 0,
+        }))
+    }
+
+    pub(crate) fn present(&mut self) {
+        self.front = self.back.clone();
+        self.presented += 1;
+    }
+}
+
+#[derive(Clone)]
+pub struct HeadlessRendererDevice {
+    window: SharedWindow,
+}
+
+impl HeadlessRendererDevice {
+    pub(crate) fn new(window: SharedWindow) -> Self {
+        HeadlessRendererDevice { window }
+    }
+}
+
+impl RendererDevice<Backend> for HeadlessRendererDevice {
+    fn memory_types(&self) -> Vec<api::memory::MemoryType> {
+        crate::memory::memory_types()
+    }
+
+    fn allocate_memory(&self, memory_type: api::memory::TypeId, size: u32) -> Result<<Backend as api::Backend>::Memory, api::Error> {
+        if memory_type != crate::memory::MEMORY_TYPE {
+            return Err(api::Error::Unsupported(format!("Memory type {}", memory_type)));
+        }
+        Ok(crate::memory::HeadlessMemory::allocate(memory_type, size))
+    }
+
+    fn map_memory(&self, memory: &<Backend as api::Backend>::Memory) -> Result<*mut u8, api::Error> {
+        Ok(memory.map_memory())
+    }
+
+    fn flush_memory(&self, memory: &<Backend as api::Backend>::Memory) {
+        memory.flush_memory()
+    }
+
+    fn unmap_memory(&self, memory: &<Backend as api::Backend>::Memory) {
+        memory.unmap_memory()
+    }
+
+    fn get_buffer_requirements(&self, buffer: &<Backend as api::Backend>::Buffer) -> api::memory::Requirements {
+        crate::memory::requirements(buffer.size)
+    }
+
+    fn bind_buffer_memory(&self,
+                          memory: &<Backend as api::Backend>::Memory,
+                          offset: u32,
+                          buffer: &mut <Backend as api::Backend>::Buffer) -> Result<(), api::Error> {
+        if !self.get_buffer_requirements(buffer).fits(memory.memory_type, memory.size(), offset) {
+            return Err(api::Error::InvalidMemoryBinding { offset });
+        }
+        buffer.bind(memory, offset);
+        Ok(())
+    }
+
+    fn create_buffer(&self, desc: api::BufferDescriptor) -> Result<<Backend as api::Backend>::Buffer, api::Error> {
+        Ok(crate::buffer::HeadlessBuffer::new(desc))
+    }
+
+    fn create_pipeline(&self, desc: api::PipelineDescriptor<Backend>) -> Result<<Backend as api::Backend>::Pipeline, api::Error> {
+        Ok(crate::pipeline::HeadlessPipeline::new(desc))
+    }
+
+    fn create_cmd_buffer(&self) -> <Backend as api::Backend>::CommandBuffer {
+        crate::pipeline::HeadlessCommandBuffer::new()
+    }
+
+    fn allocate_descriptor_set(&self, _desc: &<Backend as api::Backend>::DescriptorSetLayout) -> Result<<Backend as api::Backend>::DescriptorSet, api::Error> {
+        Ok(crate::pipeline::HeadlessDescriptorSet::default())
+    }
+
+    fn execute(&self, cmd: <Backend as api::Backend>::CommandBuffer) -> Result<(), api::Error> {
+        cmd.execute(&self.window)
+    }
+
+    fn create_shader_mod(&self, _desc: api::ShaderModDescriptor) -> Result<<Backend as api::Backend>::ShaderMod, api::Error> {
+        Ok(crate::shader_mod::HeadlessShaderMod)
+    }
+
+    fn create_descriptor_set_layout(&self, bindings: &[api::DescriptorSetLayoutBinding]) -> Result<<Backend as api::Backend>::DescriptorSetLayout, api::Error> {
+        Ok(crate::pipeline::HeadlessDescriptorSetLayout::new(bindings))
+    }
+
+    fn create_pipeline_layout<I>(&self, desc_layout: &<Backend as api::Backend>::DescriptorSetLayout, hints: I) -> Result<<Backend as api::Backend>::PipelineLayout, api::Error>
+        where
+            I: IntoIterator<Item=api::PipelineLayoutHint>, {
+        crate::pipeline::HeadlessPipelineLayout::new(desc_layout, hints)
+    }
+
+    fn write_descriptor_set(&self, desc_set_write: api::DescriptorSetWrite<Backend>) {
+        desc_set_write.set.write(desc_set_write.binding, desc_set_write.descriptor);
+    }
+
+    fn create_render_pass<A>(
+        &self, attachments: A,
+    ) -> Result<<Backend as api::Backend>::RenderPass, api::Error>
+        where
+            A: IntoIterator<Item=api::Attachment> {
+        Ok(crate::pipeline::HeadlessRenderPass::new(attachments))
+    }
+
+    fn create_framebuffer<I>(
+        &self,
+        render_pass: &<Backend as api::Backend>::RenderPass,
+        attachments: I,
+    ) -> Result<<Backend as api::Backend>::Framebuffer, api::Error>
+        where
+            I: IntoIterator,
+            I::Item: Borrow<<Backend as api::Backend>::ImageView> {
+        crate::framebuffer::HeadlessFramebuffer::new(render_pass, attachments)
+    }
+
+    fn create_swapchain(
+        &self,
+        surface: &<Backend as api::Backend>::Surface,
+    ) -> Result<(<Backend as api::Backend>::Swapchain, Vec<<Backend as api::Backend>::Image>), api::Error> {
+        Ok((crate::swapchain::HeadlessSwapchain::new(surface), Vec::new()))
+    }
+
+    fn create_image(&self, kind: api::image::Kind, format: api::image::Format) -> Result<<Backend as api::Backend>::Image, api::Error> {
+        Ok(HeadlessImage::new(kind, format))
+    }
+
+    fn create_image_view(&self, image: &<Backend as api::Backend>::Image) -> Result<<Backend as api::Backend>::ImageView, api::Error> {
+        Ok(image.clone())
+    }
+
+    fn create_sampler(&self, _desc: api::image::SamplerDesc) -> Result<<Backend as api::Backend>::Sampler, api::Error> {
+        Ok(crate::image::HeadlessSampler)
+    }
+
+    fn get_image_requirements(&self, image: &<Backend as api::Backend>::Image) -> api::memory::Requirements {
+        crate::memory::requirements(image.size())
+    }
+
+    fn read_pixels(&self, image: &<Backend as api::Backend>::Image, level: api::image::Level) -> Result<Vec<u8>, api::Error> {
+        image.read(level)
+    }
+
+    fn bind_image_memory(
+        &self,
+        mem: &<Backend as api::Backend>::Memory,
+        offset: u32,
+        img: &mut <Backend as api::Backend>::Image,
+    ) -> Result<(), api::Error> {
+        if !self.get_image_requirements(img).fits(mem.memory_type, mem.size(), offset) {
+            return Err(api::Error::InvalidMemoryBinding { offset });
+        }
+        img.bind(mem, offset);
+        Ok(())
+    }
+}
+
+pub struct HeadlessRendererApi {
+    window: SharedWindow,
+}
+
+impl HeadlessRendererApi {
+    pub(crate) fn new(window: SharedWindow) -> HeadlessRendererApi {
+        HeadlessRendererApi { window }
+    }
+}
+
+impl RendererApi<Backend> for HeadlessRendererApi {
+    fn swap_buffer(&mut self) {
+        self.window.lock().unwrap().present()
+    }
+
+    fn clear_color(&self) {
+        let mut window = self.window.lock().unwrap();
+        let color = window.clear_color;
+        window.back.clear(color);
+    }
+
+    fn set_clear_color(&self, r: f32, g: f32, b: f32, a: f32) {
+        self.window.lock().unwrap().clear_color = (r, g, b, a);
+    }
+
+    fn viewport(&self, w: i32, h: i32) {
+        self.window.lock().unwrap().viewport = Viewport { x: 0, y: 0, width: w, height: h };
+    }
+}
@@ -0,0 +1,148 @@
+use std::borrow::Borrow;
+
+use api::image::Format;
+
+use crate::Backend;
+use crate::image::HeadlessImage;
+use crate::raster::Target;
+
+///Rendered through a `Target` of its size with a plane per colour attachment, attachments are loaded
+///into it when the pass begins and stored back when it ends. Every colour attachment receives the fixed
+///function colour, Rgba16F attachments are rendered at 8 bits per channel.
+#[derive(Debug, Clone)]
+pub struct HeadlessFramebuffer {
+    pub(crate) attachments: Vec<(api::AttachmentLayout, HeadlessImage)>,
+    pub(crate) extent: (u32, u32),
+}
+
+impl HeadlessFramebuffer {
+    pub fn new<A, I>(
+        render_pass: &<Backend as api::Backend>::RenderPass,
+        attachments: A,
+    ) -> Result<Self, api::Error>
+        where A: IntoIterator<Item=I>,
+              I: Borrow<HeadlessImage> {
+        let attachments: Vec<(api::AttachmentLayout, HeadlessImage)> = attachments.into_iter()
+            .enumerate()
+            .map(|(i, a)| {
+                let image = a.borrow();
+                let attachment = render_pass.attachments.get(i)
+                    .filter(|attachment| attachment.format == image.format)
+                    .ok_or(api::Error::InvalidAttachment { index: i })?;
+                match image.format {
+                    Format::Rgba8 | Format::Rgba8Srgb | Format::Rgba16F | Format::D24S8 | Format::D32F => {}
+                    format => return Err(api::Error::Unsupported(format!("Rendering to {:?}", format))),
+                }
+                Ok((attachment.layout.clone(), image.clone()))
+            })
+            .collect::<Result<_, api::Error>>()?;
+        if attachments.len() != render_pass.attachments.len() {
+            return Err(api::Error::InvalidAttachment { index: attachments.len().min(render_pass.attachments.len()) });
+        }
+
+        let extent = attachments.first()
+            .map(|(_, image)| image.kind.extent())
+            .ok_or(api::Error::InvalidAttachment { index: 0 })?;
+        if attachments.iter().any(|(_, image)| image.kind.extent() != extent) {
+            return Err(api::Error::IncompleteFramebuffer("Attachments differ in size".to_owned()));
+        }
+        Ok(HeadlessFramebuffer { attachments, extent: (extent.0, extent.1) })
+    }
+
+    pub(crate) fn begin(&self, ops: &[(api::LoadOp, api::StoreOp)], clear_values: &[api::ClearValue]) -> Result<Target, api::Error> {
+        let mut target = Target::with_colors(self.extent.0, self.extent.1, self.planes().filter(Option::is_some).count());
+        for (i, (((_, image), plane), (load, _))) in self.attachments.iter().zip(self.planes()).zip(ops).enumerate() {
+            match (load, plane) {
+                (api::LoadOp::Load, Some(plane)) if image.format == Format::Rgba16F => {
+                    let data = image.data.read().unwrap();
+                    for (c, bytes) in target.colors[plane].iter_mut().zip(data[image.offset..].chunks_exact(2)) {
+                        *c = (from_half(u16::from_le_bytes([bytes[0], bytes[1]])) * 255.).round() as u8;
+                    }
+                }
+                (api::LoadOp::Load, Some(plane)) => {
+                    let data = image.data.read().unwrap();
+                    let color = &mut target.colors[plane];
+                    let len = color.len();
+                    color.copy_from_slice(&data[image.offset..image.offset + len]);
+                }
+                (api::LoadOp::Load, None) => {
+                    let data = image.data.read().unwrap();
+                    for (d, bytes) in target.depth.iter_mut().zip(data[image.offset..].chunks_exact(4)) {
+                        *d = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                    }
+                }
+                (api::LoadOp::Clear, plane) => match (clear_values.get(i), plane) {
+                    (Some(api::ClearValue::Color([r, g, b, a])), Some(plane)) => target.clear_plane(plane, (*r, *g, *b, *a)),
+                    (Some(api::ClearValue::DepthStencil(depth, stencil)), None) => {
+                        target.clear_depth(*depth);
+                        target.clear_stencil(*stencil);
+                    }
+                    (value, _) => return Err(api::Error::InvalidCommand(
+                        format!("Clear value {:?} does not match attachment {}", value, i))),
+                },
+                (api::LoadOp::DontCare, _) => {}
+            }
+        }
+        Ok(target)
+    }
+
+    pub(crate) fn end(&self, ops: &[(api::LoadOp, api::StoreOp)], target: &Target) {
+        for (((_, image), plane), (_, store)) in self.attachments.iter().zip(self.planes()).zip(ops) {
+            if *store == api::StoreOp::DontCare {
+                continue;
+            }
+            let mut data = image.data.write().unwrap();
+            let data = &mut data[image.offset..];
+            match plane {
+                Some(plane) if image.format == Format::Rgba16F => {
+                    for (bytes, c) in data.chunks_exact_mut(2).zip(&target.colors[plane]) {
+                        bytes.copy_from_slice(&to_half(f32::from(*c) / 255.).to_le_bytes());
+                    }
+                }
+                Some(plane) => data[..target.colors[plane].len()].copy_from_slice(&target.colors[plane]),
+                None => for (bytes, d) in data.chunks_exact_mut(4).zip(&target.depth) {
+                    bytes.copy_from_slice(&d.to_le_bytes());
+                },
+            }
+        }
+    }
+
+    ///Colour plane of every attachment, `None` for depth and stencil ones.
+    fn planes(&self) -> impl Iterator<Item=Option<usize>> + '_ {
+        let mut colors = 0;
+        self.attachments.iter().map(move |(layout, _)| match layout {
+            api::AttachmentLayout::Color => {
+                colors += 1;
+                Some(colors - 1)
+            }
+            _ => None,
+        })
+    }
+}
+
+///Rounds to the nearest half, flushing values below its normal range to zero.
+fn to_half(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent <= 0 {
+        sign
+    } else if exponent >= 31 {
+        sign | 0x7c00
+    } else {
+        sign | (((exponent as u32) << 10 | mantissa >> 13) + ((mantissa >> 12) & 1)) as u16
+    }
+}
+
+fn from_half(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1. } else { 1. };
+    let exponent = u32::from((half >> 10) & 0x1f);
+    let mantissa = u32::from(half & 0x3ff);
+    match exponent {
+        0 => sign * mantissa as f32 / (1 << 24) as f32,
+        31 if mantissa == 0 => sign * f32::INFINITY,
+        31 => f32::NAN,
+        _ => f32::from_bits(u32::from(half & 0x8000) << 16 | (exponent + 112) << 23 | mantissa << 13),
+    }
+}
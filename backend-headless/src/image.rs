@@ -0,0 +1,140 @@
+use std::sync::{Arc, RwLock};
+
+use api::image;
+
+use crate::buffer::HeadlessBuffer;
+
+#[derive(Debug, Clone)]
+pub struct HeadlessImage {
+    pub(crate) kind: image::Kind,
+    pub(crate) format: image::Format,
+    ///Memory the texels live in, empty until `bind_image_memory`.
+    pub(crate) data: Arc<RwLock<Vec<u8>>>,
+    pub(crate) offset: usize,
+}
+
+///Textures are not sampled by the fixed function program, only their levels are filled.
+#[derive(Debug)]
+pub struct HeadlessSampler;
+
+impl HeadlessImage {
+    pub fn new(kind: image::Kind, format: image::Format) -> Self {
+        HeadlessImage {
+            data: Arc::new(RwLock::new(Vec::new())),
+            offset: 0,
+            kind,
+            format,
+        }
+    }
+
+    ///Levels are stored one after another, starting with the base level.
+    pub(crate) fn size(&self) -> usize {
+        self.level_offset(self.kind.levels())
+    }
+
+    fn level_offset(&self, level: image::Level) -> usize {
+        (0..level)
+            .map(|l| self.format.size_of(self.kind.level_extent(l)) as usize)
+            .sum()
+    }
+
+    pub(crate) fn bind(&mut self, memory: &crate::memory::HeadlessMemory, offset: u32) {
+        self.data = memory.data.clone();
+        self.offset = offset as usize;
+    }
+
+    ///Copies whole block rows, compressed regions start and end on block boundaries.
+    pub(crate) fn copy_from(&self, buffer: &HeadlessBuffer, region: &image::BufferImageCopy) {
+        let rows = self.block_rows(region);
+        let row = rows.first().map_or(0, |(_, len)| *len);
+        //image and buffer may share one memory, read everything before locking for write
+        let texels = {
+            let data = buffer.data.read().unwrap();
+            let start = buffer.offset + region.buffer_offset as usize;
+            data[start..start + row * rows.len()].to_vec()
+        };
+
+        let mut data = self.data.write().unwrap();
+        for ((start, len), src) in rows.iter().zip(texels.chunks_exact(row)) {
+            data[*start..start + len].copy_from_slice(src);
+        }
+    }
+
+    pub(crate) fn copy_to(&self, buffer: &HeadlessBuffer, region: &image::BufferImageCopy) {
+        let texels: Vec<u8> = {
+            let data = self.data.read().unwrap();
+            self.block_rows(region).iter()
+                .flat_map(|(start, len)| data[*start..start + len].iter().copied())
+                .collect()
+        };
+
+        let mut data = buffer.data.write().unwrap();
+        let start = buffer.offset + region.buffer_offset as usize;
+        data[start..start + texels.len()].copy_from_slice(&texels);
+    }
+
+    ///Whole `level` as stored in memory.
+    pub(crate) fn read(&self, level: image::Level) -> Result<Vec<u8>, api::Error> {
+        let start = self.offset + self.level_offset(level);
+        let len = self.format.size_of(self.kind.level_extent(level)) as usize;
+        self.data.read().unwrap()
+            .get(start..start + len)
+            .map(<[u8]>::to_vec)
+            .ok_or(api::Error::InvalidMemoryBinding { offset: self.offset as u32 })
+    }
+
+    ///Memory start and length of every block row of `region` in buffer order.
+    fn block_rows(&self, region: &image::BufferImageCopy) -> Vec<(usize, usize)> {
+        let (side, block) = self.format.block();
+        let blocks = |texels: u32| self.format.blocks(texels) as usize;
+        let (width, height, _) = self.kind.level_extent(region.level);
+        let row = blocks(region.extent.0) * block as usize;
+        let rows = blocks(region.extent.1);
+
+        let base = self.offset + self.level_offset(region.level);
+        (0..rows * region.extent.2 as usize)
+            .map(|i| {
+                let y = region.offset.1 as usize / side as usize + i % rows;
+                let z = region.offset.2 as usize + i / rows;
+                let x = region.offset.0 as usize / side as usize;
+                (base + ((z * blocks(height) + y) * blocks(width) + x) * block as usize, row)
+            })
+            .collect()
+    }
+
+    ///Box filter of each 2x2 block of the previous level, only 8 bit colour formats are filtered.
+    pub(crate) fn generate_mipmaps(&self) {
+        const TEXEL_SIZE: usize = 4;
+        match self.format {
+            image::Format::Rgba8 | image::Format::Rgba8Srgb => {}
+            _ => return,
+        }
+        let mut data = self.data.write().unwrap();
+        for level in 1..self.kind.levels() {
+            let (src_width, src_height, _) = self.kind.level_extent(level - 1);
+            let (width, height, _) = self.kind.level_extent(level);
+            let src = self.offset + self.level_offset(level - 1);
+            let dst = self.offset + self.level_offset(level);
+            for y in 0..height {
+                for x in 0..width {
+                    let mut sum = [0u32; TEXEL_SIZE];
+                    let mut count = 0;
+                    for (sx, sy) in &[(2 * x, 2 * y), (2 * x + 1, 2 * y), (2 * x, 2 * y + 1), (2 * x + 1, 2 * y + 1)] {
+                        if *sx >= src_width || *sy >= src_height {
+                            continue;
+                        }
+                        let texel = src + ((sy * src_width + sx) as usize) * TEXEL_SIZE;
+                        for (c, s) in sum.iter_mut().enumerate() {
+                            *s += u32::from(data[texel + c]);
+                        }
+                        count += 1;
+                    }
+                    let texel = dst + ((y * width + x) as usize) * TEXEL_SIZE;
+                    for (c, s) in sum.iter().enumerate() {
+                        data[texel + c] = (s / count) as u8;
+                    }
+                }
+            }
+        }
+    }
+}
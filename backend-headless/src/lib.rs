@@ -0,0 +1,560 @@
+//! Windowless backend rasterizing on the CPU.
+//!
+//! Shaders are not compiled: every pipeline runs a fixed function program
+//! mirroring `client/src/test` shaders. The lowest location `Vec3f32` attribute
+//! is the position, the highest location `Vec3f32` attribute is written as colour
+//! and the first `Mat4f32` attribute is the per-instance mvp matrix.
+extern crate backend_api as api;
+
+pub use crate::core::{
+    HeadlessRendererApi as RendererApi,
+    HeadlessRendererDevice as RendererDevice,
+};
+pub use buffer::HeadlessBuffer as Buffer;
+pub use image::{
+    HeadlessImage as Image,
+    HeadlessImage as ImageView,
+};
+pub use pipeline::{
+    HeadlessDescriptorSet as DescriptorSet,
+    HeadlessDescriptorSetLayout as DescriptorSetLayout,
+    HeadlessPipeline as Pipeline,
+    HeadlessPipelineLayout as PipelineLayout,
+};
+pub use platform::{
+    HeadlessImGuiRenderer as ImGuiRenderer,
+    HeadlessPlatformManager as PlatformManager,
+};
+pub use crate::memory::{
+    HeadlessMemory as Memory
+};
+
+mod image;
+mod buffer;
+mod core;
+mod platform;
+mod shader_mod;
+mod memory;
+mod framebuffer;
+mod raster;
+
+mod pipeline;
+mod swapchain;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Backend {}
+
+impl backend_api::Backend for Backend {
+    type RendererApi = core::HeadlessRendererApi;
+    type RendererDevice = core::HeadlessRendererDevice;
+    type PlatformManager = platform::HeadlessPlatformManager;
+    type ImGuiRenderer = platform::HeadlessImGuiRenderer;
+    type Memory = memory::HeadlessMemory;
+    type Buffer = buffer::HeadlessBuffer;
+    type Image = image::HeadlessImage;
+    type ImageView = image::HeadlessImage;
+    type Sampler = image::HeadlessSampler;
+    type Pipeline = pipeline::HeadlessPipeline;
+    type RenderPass = pipeline::HeadlessRenderPass;
+    type CommandBuffer = pipeline::HeadlessCommandBuffer;
+    type ShaderMod = shader_mod::HeadlessShaderMod;
+    type DescriptorSet = pipeline::HeadlessDescriptorSet;
+    type DescriptorSetLayout = pipeline::HeadlessDescriptorSetLayout;
+    type PipelineLayout = pipeline::HeadlessPipelineLayout;
+    type Surface = swapchain::HeadlessSurface;
+    type Swapchain = swapchain::HeadlessSwapchain;
+    type Framebuffer = framebuffer::HeadlessFramebuffer;
+}
+
+#[cfg(test)]
+mod tests {
+    use api::{
+        CommandBuffer,
+        PlatformManager,
+        RendererDevice,
+        Swapchain,
+    };
+
+    use crate::Backend;
+
+    fn upload(device: &crate::RendererDevice, usage: api::Usage, data: &[u8]) -> (crate::Memory, crate::Buffer) {
+        let mut buffer = device.create_buffer(api::BufferDescriptor { size: data.len() as u32, usage }).unwrap();
+        let req = device.get_buffer_requirements(&buffer);
+        let mem = device.allocate_memory(0, req.size).unwrap();
+        device.bind_buffer_memory(&mem, 0, &mut buffer).unwrap();
+        let ptr = device.map_memory(&mem).unwrap();
+        unsafe { std::ptr::copy(data.as_ptr(), ptr, data.len()) };
+        device.unmap_memory(&mem);
+        (mem, buffer)
+    }
+
+    fn as_bytes(floats: &[f32]) -> Vec<u8> {
+        floats.iter().flat_map(|f| f.to_le_bytes().to_vec()).collect()
+    }
+
+    const IDENTITY: [f32; 16] = [
+        1., 0., 0., 0.,
+        0., 1., 0., 0.,
+        0., 0., 1., 0.,
+        0., 0., 0., 1.,
+    ];
+
+    ///Draws one triangle of interleaved position and normal with identity mvp, returns presented pixels.
+    fn render_triangle(width: u32, height: u32, vertices: &[f32]) -> Vec<u8> {
+        render(width, height, vertices, &IDENTITY, |cmd, _| cmd.draw_indexed(3, 0, 1))
+    }
+
+    ///Records `draw` with interleaved position and normal `vertices`, per-instance `mvps` and indices 0, 1, 2.
+    fn render(width: u32, height: u32, vertices: &[f32], mvps: &[f32], draw: impl FnOnce(&mut crate::pipeline::HeadlessCommandBuffer, &crate::RendererDevice)) -> Vec<u8> {
+        render_with(width, height, vertices, mvps, |_| {}, draw)
+    }
+
+    ///`render` with the pipeline's descriptor changed by `state` before it is created.
+    fn render_with(width: u32,
+                   height: u32,
+                   vertices: &[f32],
+                   mvps: &[f32],
+                   state: impl FnOnce(&mut api::PipelineDescriptor<Backend>),
+                   draw: impl FnOnce(&mut crate::pipeline::HeadlessCommandBuffer, &crate::RendererDevice)) -> Vec<u8> {
+        let mut pm = crate::PlatformManager::new(api::WindowConfig { width, height });
+        let (_api, device) = pm.create_renderer();
+
+        let indices: Vec<u8> = [0u32, 1, 2].iter().flat_map(|i| i.to_le_bytes().to_vec()).collect();
+
+        let (_vm, vertex) = upload(&device, api::Usage::Vertex, &as_bytes(vertices));
+        let (_im, instance) = upload(&device, api::Usage::Vertex, &as_bytes(mvps));
+        let (_xm, index) = upload(&device, api::Usage::Index, &indices);
+
+        let desc_set_layout = device.create_descriptor_set_layout(&[]).unwrap();
+        let pipeline_layout = device.create_pipeline_layout(&desc_set_layout, vec![]).unwrap();
+        let shader_set = api::ShaderSet::<Backend> {
+            vertex: device.create_shader_mod(api::ShaderModDescriptor { stype: api::ShaderType::Vertex, source: api::ShaderSource::Glsl(String::new()) }).unwrap(),
+            fragment: device.create_shader_mod(api::ShaderModDescriptor { stype: api::ShaderType::Fragment, source: api::ShaderSource::Glsl(String::new()) }).unwrap(),
+        };
+        let mut desc = api::PipelineDescriptor::new(api::Primitive::Triangles, shader_set, &pipeline_layout);
+        desc.push_vb(api::VertexBufferDescriptor { binding: 0, stride: 24 });
+        desc.push_vb(api::VertexBufferDescriptor { binding: 1, stride: 64 });
+        desc.push_attr(api::AttributeDescriptor { binding: 0, location: 0, data: api::VertexData { offset: 0, data_type: api::DataType::Vec3f32 } });
+        desc.push_attr(api::AttributeDescriptor { binding: 0, location: 2, data: api::VertexData { offset: 12, data_type: api::DataType::Vec3f32 } });
+        desc.push_attr(api::AttributeDescriptor { binding: 1, location: 3, data: api::VertexData { offset: 0, data_type: api::DataType::Mat4f32 } });
+        state(&mut desc);
+        let pipeline = device.create_pipeline(desc).unwrap();
+
+        let mut cmd = device.create_cmd_buffer();
+        cmd.bind_pipeline(&pipeline);
+        cmd.clear_screen((0., 0., 1., 1.));
+        cmd.bind_vertex_buffer(0, &vertex);
+        cmd.bind_vertex_buffer(1, &instance);
+        cmd.bind_index_buffer(&index);
+        draw(&mut cmd, &device);
+        device.execute(cmd).unwrap();
+
+        let surface = pm.create_surface();
+        let (mut swapchain, _) = device.create_swapchain(&surface).unwrap();
+        swapchain.present(0);
+
+        assert_eq!(pm.presented_frames(), 1);
+        let (w, h, pixels) = pm.presented_frame();
+        assert_eq!((w, h), (width, height));
+        pixels
+    }
+
+    #[test]
+    fn draws_front_facing_triangle() {
+        let pixels = render_triangle(4, 4, &[
+            -1., -1., 0., 1., 0., 0.,
+            3., -1., 0., 1., 0., 0.,
+            -1., 3., 0., 1., 0., 0.,
+        ]);
+        assert!(pixels.chunks(4).all(|p| p == [255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn culls_back_facing_triangle() {
+        let pixels = render_triangle(2, 2, &[
+            -1., -1., 0., 0., 1., 0.,
+            -1., 3., 0., 0., 1., 0.,
+            3., -1., 0., 0., 1., 0.,
+        ]);
+        assert!(pixels.chunks(4).all(|p| p == [0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn skips_draws_without_instances_or_pipeline() {
+        let vertices = [
+            -1., -1., 0., 1., 0., 0.,
+            3., -1., 0., 1., 0., 0.,
+            -1., 3., 0., 1., 0., 0.,
+        ];
+        let pixels = render(2, 2, &vertices, &IDENTITY, |cmd, _| cmd.draw_indexed(3, 0, 0));
+        assert!(pixels.chunks(4).all(|p| p == [0, 0, 255, 255]));
+
+        let mut pm = crate::PlatformManager::new(api::WindowConfig { width: 2, height: 2 });
+        let (_api, device) = pm.create_renderer();
+        let mut cmd = device.create_cmd_buffer();
+        cmd.clear_screen((0., 0., 1., 1.));
+        cmd.draw_indexed(3, 0, 1);
+        device.execute(cmd).unwrap();
+        let (mut swapchain, _) = device.create_swapchain(&pm.create_surface()).unwrap();
+        swapchain.present(0);
+        assert!(pm.presented_frame().2.chunks(4).all(|p| p == [0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn fails_draws_reading_unbound_vertex_buffers() {
+        let mut pm = crate::PlatformManager::new(api::WindowConfig { width: 2, height: 2 });
+        let (_api, device) = pm.create_renderer();
+        let indices: Vec<u8> = [0u32, 1, 2].iter().flat_map(|i| i.to_le_bytes().to_vec()).collect();
+        let (_xm, index) = upload(&device, api::Usage::Index, &indices);
+
+        let desc_set_layout = device.create_descriptor_set_layout(&[]).unwrap();
+        let pipeline_layout = device.create_pipeline_layout(&desc_set_layout, vec![]).unwrap();
+        let shader = |stype| device.create_shader_mod(api::ShaderModDescriptor { stype, source: api::ShaderSource::Glsl(String::new()) }).unwrap();
+        let shader_set = api::ShaderSet::<Backend> { vertex: shader(api::ShaderType::Vertex), fragment: shader(api::ShaderType::Fragment) };
+        let mut desc = api::PipelineDescriptor::new(api::Primitive::Triangles, shader_set, &pipeline_layout);
+        desc.push_vb(api::VertexBufferDescriptor { binding: 0, stride: 12 });
+        desc.push_attr(api::AttributeDescriptor { binding: 0, location: 0, data: api::VertexData { offset: 0, data_type: api::DataType::Vec3f32 } });
+        let pipeline = device.create_pipeline(desc).unwrap();
+
+        let mut cmd = device.create_cmd_buffer();
+        cmd.bind_pipeline(&pipeline);
+        cmd.bind_index_buffer(&index);
+        cmd.draw_indexed(3, 0, 1);
+        assert!(matches!(device.execute(cmd), Err(api::Error::InvalidCommand(_))));
+    }
+
+    #[test]
+    fn blends_with_the_cleared_color() {
+        let vertices = [
+            -1., -1., 0., 0.5, 0., 0.,
+            -1., 3., 0., 0.5, 0., 0.,
+            3., -1., 0., 0.5, 0., 0.,
+        ];
+        let additive = |desc: &mut api::PipelineDescriptor<Backend>| {
+            desc.rasterizer.cull_mode = api::CullMode::Front;
+            desc.blend = vec![Some(api::BlendState::ADDITIVE)];
+        };
+        let pixels = render_with(2, 2, &vertices, &IDENTITY, additive, |cmd, _| cmd.draw_indexed(3, 0, 1));
+        assert!(pixels.chunks(4).all(|p| p == [128, 0, 255, 255]));
+    }
+
+    #[test]
+    fn draws_into_viewport_and_clears_inside_scissor() {
+        let pixels = render(4, 2, &[
+            -1., -1., 0., 1., 0., 0.,
+            3., -1., 0., 1., 0., 0.,
+            -1., 3., 0., 1., 0., 0.,
+        ], &IDENTITY, |cmd, _| {
+            cmd.set_viewport(api::Rect { x: 0, y: 0, width: 2, height: 2 });
+            cmd.draw_indexed(3, 0, 1);
+            cmd.set_scissor(Some(api::Rect { x: 3, y: 0, width: 1, height: 2 }));
+            cmd.clear_screen((0., 1., 0., 1.));
+        });
+        for row in pixels.chunks(4 * 4) {
+            assert_eq!(row, [255, 0, 0, 255, 255, 0, 0, 255, 0, 0, 255, 255, 0, 255, 0, 255]);
+        }
+    }
+
+    #[test]
+    fn render_passes_reset_viewport_and_scissor() {
+        let mut offscreen = None;
+        let pixels = render(2, 2, &[
+            -1., -1., 0., 1., 0., 0.,
+            3., -1., 0., 1., 0., 0.,
+            -1., 3., 0., 1., 0., 0.,
+        ], &IDENTITY, |cmd, device| {
+            let mut image = device.create_image(api::image::Kind::D2(2, 2, 1), api::image::Format::Rgba8).unwrap();
+            let mem = device.allocate_memory(0, device.get_image_requirements(&image).size).unwrap();
+            device.bind_image_memory(&mem, 0, &mut image).unwrap();
+            let render_pass = device.create_render_pass(vec![api::Attachment {
+                layout: api::AttachmentLayout::Color,
+                format: api::image::Format::Rgba8,
+                load: api::LoadOp::Clear,
+                store: api::StoreOp::Store,
+            }]).unwrap();
+            let framebuffer = device.create_framebuffer(&render_pass, vec![&image]).unwrap();
+
+            let corner = api::Rect { x: 0, y: 0, width: 1, height: 1 };
+            cmd.set_viewport(corner);
+            cmd.set_scissor(Some(corner));
+            cmd.begin_render_pass(&render_pass, &framebuffer, &[api::ClearValue::Color([0., 0., 1., 1.])]);
+            cmd.draw_indexed(3, 0, 1);
+            cmd.set_viewport(corner);
+            cmd.set_scissor(Some(corner));
+            cmd.end_render_pass();
+            cmd.draw_indexed(3, 0, 1);
+            offscreen = Some((mem, image, framebuffer));
+        });
+        assert!(pixels.chunks(4).all(|p| p == [255, 0, 0, 255]));
+        let (mem, _, _) = offscreen.unwrap();
+        assert!(mem.data.read().unwrap().chunks(4).all(|p| p == [255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn keeps_the_window_viewport_across_buffers() {
+        let mut pm = crate::PlatformManager::new(api::WindowConfig { width: 2, height: 2 });
+        let (_api, device) = pm.create_renderer();
+        let mut cmd = device.create_cmd_buffer();
+        cmd.set_viewport(api::Rect { x: 0, y: 0, width: 1, height: 1 });
+        cmd.set_scissor(Some(api::Rect { x: 0, y: 0, width: 1, height: 1 }));
+        device.execute(cmd).unwrap();
+
+        let mut cmd = device.create_cmd_buffer();
+        cmd.clear_screen((0., 1., 0., 1.));
+        device.execute(cmd).unwrap();
+        let (mut swapchain, _) = device.create_swapchain(&pm.create_surface()).unwrap();
+        swapchain.present(0);
+        assert!(pm.presented_frame().2.chunks(4).all(|p| p == [0, 255, 0, 255]));
+    }
+
+    #[test]
+    fn draws_wireframe_edges() {
+        let wireframe = |desc: &mut api::PipelineDescriptor<Backend>| desc.rasterizer.polygon_mode = api::PolygonMode::Line;
+        let pixels = render_with(4, 4, &[
+            -1., -1., 0., 1., 0., 0.,
+            3., -1., 0., 1., 0., 0.,
+            -1., 3., 0., 1., 0., 0.,
+        ], &IDENTITY, wireframe, |cmd, _| cmd.draw_indexed(3, 0, 1));
+        //the bottom and left edges, the third one passes outside
+        assert_eq!(pixels.chunks(4).filter(|p| *p == [255, 0, 0, 255]).count(), 7);
+        assert_eq!(pixels.chunks(4).filter(|p| *p == [0, 0, 255, 255]).count(), 9);
+    }
+
+    #[test]
+    fn stencil_rejects_fragments_drawn_over() {
+        let vertices = [
+            -1., -1., 0., 0.5, 0., 0.,
+            3., -1., 0., 0.5, 0., 0.,
+            -1., 3., 0., 0.5, 0., 0.,
+        ];
+        let once = |desc: &mut api::PipelineDescriptor<Backend>| {
+            let face = api::StencilFaceState { compare: api::CompareOp::Equal, pass: api::StencilOp::IncrementAndClamp, ..Default::default() };
+            desc.depth_stencil.depth_compare = api::CompareOp::LessOrEqual;
+            desc.depth_stencil.stencil = Some(api::StencilState { front: face, back: face, ..Default::default() });
+            desc.blend = vec![Some(api::BlendState::ADDITIVE)];
+        };
+        let pixels = render_with(2, 2, &vertices, &IDENTITY, once, |cmd, _| {
+            cmd.draw_indexed(3, 0, 1);
+            cmd.draw_indexed(3, 0, 1);
+        });
+        assert!(pixels.chunks(4).all(|p| p == [128, 0, 255, 255]));
+    }
+
+    #[test]
+    fn draws_from_base_vertex_and_first_instance() {
+        //the first instance moves the triangle behind the near plane
+        let mut behind = IDENTITY;
+        behind[14] = -3.;
+        let mvps: Vec<f32> = behind.iter().chain(&IDENTITY).copied().collect();
+        let pixels = render(2, 2, &[
+            -1., -1., 0., 0., 1., 0.,
+            3., -1., 0., 0., 1., 0.,
+            -1., 3., 0., 0., 1., 0.,
+            -1., -1., 0., 1., 0., 0.,
+            3., -1., 0., 1., 0., 0.,
+            -1., 3., 0., 1., 0., 0.,
+        ], &mvps, |cmd, _| cmd.draw_indexed_base_vertex(3, 0, 3, 1..2));
+        assert!(pixels.chunks(4).all(|p| p == [255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn draws_indirect_commands() {
+        let mut behind = IDENTITY;
+        behind[14] = -3.;
+        let mvps: Vec<f32> = behind.iter().chain(&IDENTITY).copied().collect();
+        //second command of stride 24 after 4 bytes of padding, the first one is behind the near plane
+        let args: Vec<u8> = [0u32, 3, 1, 0, 0, 0, 0, 3, 1, 0, 3, 1]
+            .iter()
+            .flat_map(|w| w.to_le_bytes().to_vec())
+            .collect();
+        let mut pm = crate::PlatformManager::new(api::WindowConfig { width: 1, height: 1 });
+        let (_api, device) = pm.create_renderer();
+        let (_am, indirect) = upload(&device, api::Usage::Indirect, &args);
+        let pixels = render(2, 2, &[
+            -1., -1., 0., 0., 1., 0.,
+            3., -1., 0., 0., 1., 0.,
+            -1., 3., 0., 0., 1., 0.,
+            -1., -1., 0., 1., 0., 0.,
+            3., -1., 0., 1., 0., 0.,
+            -1., 3., 0., 1., 0., 0.,
+        ], &mvps, |cmd, _| cmd.draw_indexed_indirect(&indirect, 4, 2, 24));
+        assert!(pixels.chunks(4).all(|p| p == [255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn binds_buffers_at_offsets() {
+        let mut pm = crate::PlatformManager::new(api::WindowConfig { width: 1, height: 1 });
+        let (_api, device) = pm.create_renderer();
+
+        let mem = device.allocate_memory(0, 64).unwrap();
+        let mut first = device.create_buffer(api::BufferDescriptor { size: 16, usage: api::Usage::Vertex }).unwrap();
+        let mut second = device.create_buffer(api::BufferDescriptor { size: 16, usage: api::Usage::Vertex }).unwrap();
+        device.bind_buffer_memory(&mem, 0, &mut first).unwrap();
+        device.bind_buffer_memory(&mem, 16, &mut second).unwrap();
+
+        let mut overflowing = device.create_buffer(api::BufferDescriptor { size: 16, usage: api::Usage::Vertex }).unwrap();
+        assert_eq!(device.bind_buffer_memory(&mem, 56, &mut overflowing), Err(api::Error::InvalidMemoryBinding { offset: 56 }));
+
+        let data: Vec<u8> = (1..=16).collect();
+        let mut cmd = device.create_cmd_buffer();
+        cmd.buffer_data(&second, &data);
+        device.execute(cmd).unwrap();
+
+        let ptr = device.map_memory(&mem).unwrap();
+        let read = unsafe { std::slice::from_raw_parts(ptr, 32) }.to_vec();
+        device.unmap_memory(&mem);
+        assert_eq!(&read[..16], &[0; 16]);
+        assert_eq!(&read[16..], &data[..]);
+    }
+
+    #[test]
+    fn uploads_texture_levels() {
+        let mut pm = crate::PlatformManager::new(api::WindowConfig { width: 1, height: 1 });
+        let (_api, device) = pm.create_renderer();
+
+        let kind = api::image::Kind::D2(2, 2, 2);
+        let mut image = device.create_image(kind.clone(), api::image::Format::Rgba8).unwrap();
+        let req = device.get_image_requirements(&image);
+        assert_eq!(req.size, 5 * 4);
+        let mem = device.allocate_memory(0, req.size).unwrap();
+        device.bind_image_memory(&mem, 0, &mut image).unwrap();
+
+        let texels = [0u8, 0, 0, 255, 100, 0, 0, 255, 0, 200, 0, 255, 0, 0, 40, 255];
+        let (_sm, staging) = upload(&device, api::Usage::TransferSrc, &texels);
+        let mut cmd = device.create_cmd_buffer();
+        cmd.copy_buffer_to_image(&staging, &image, api::image::BufferImageCopy::level(&kind, 0));
+        cmd.generate_mipmaps(&image);
+        device.execute(cmd).unwrap();
+
+        let ptr = device.map_memory(&mem).unwrap();
+        let read = unsafe { std::slice::from_raw_parts(ptr, 20) }.to_vec();
+        device.unmap_memory(&mem);
+        assert_eq!(&read[..16], &texels[..]);
+        assert_eq!(&read[16..], &[25, 50, 10, 255]);
+    }
+
+    #[test]
+    fn sizes_images_by_format() {
+        let mut pm = crate::PlatformManager::new(api::WindowConfig { width: 1, height: 1 });
+        let (_api, device) = pm.create_renderer();
+
+        let size = |kind, format| device.get_image_requirements(&device.create_image(kind, format).unwrap()).size;
+        assert_eq!(size(api::image::Kind::D2(2, 2, 2), api::image::Format::Rgba16F), 40);
+        assert_eq!(size(api::image::Kind::D2(8, 8, 1), api::image::Format::Bc1), 32);
+        assert_eq!(size(api::image::Kind::D2(6, 2, 1), api::image::Format::Bc5), 32);
+        //six faces of 4x4 and 2x2 texels
+        assert_eq!(size(api::image::Kind::Cube(4, 2), api::image::Format::Rgba8), 6 * (64 + 16));
+    }
+
+    #[test]
+    fn clears_and_stores_offscreen_attachments() {
+        let mut pm = crate::PlatformManager::new(api::WindowConfig { width: 1, height: 1 });
+        let (_api, device) = pm.create_renderer();
+
+        let image = |extent, format| {
+            let mut image = device.create_image(api::image::Kind::D2(extent, extent, 1), format).unwrap();
+            let mem = device.allocate_memory(0, device.get_image_requirements(&image).size).unwrap();
+            device.bind_image_memory(&mem, 0, &mut image).unwrap();
+            (mem, image)
+        };
+        let read = |mem: &crate::Memory, len: usize| {
+            let ptr = device.map_memory(mem).unwrap();
+            let read = unsafe { std::slice::from_raw_parts(ptr, len) }.to_vec();
+            device.unmap_memory(mem);
+            read
+        };
+        let (stored_mem, stored) = image(2, api::image::Format::Rgba8);
+        let (discarded_mem, discarded) = image(2, api::image::Format::Rgba8);
+        let (depth_mem, depth) = image(2, api::image::Format::D32F);
+        let (_small_mem, small) = image(1, api::image::Format::D32F);
+
+        let attachment = |layout, format, store| api::Attachment { layout, format, load: api::LoadOp::Clear, store };
+        let render_pass = device.create_render_pass(vec![
+            attachment(api::AttachmentLayout::Color, api::image::Format::Rgba8, api::StoreOp::Store),
+            attachment(api::AttachmentLayout::Color, api::image::Format::Rgba8, api::StoreOp::DontCare),
+            attachment(api::AttachmentLayout::Depth, api::image::Format::D32F, api::StoreOp::Store),
+        ]).unwrap();
+        assert!(matches!(device.create_framebuffer(&render_pass, vec![&stored, &discarded, &small]),
+                         Err(api::Error::IncompleteFramebuffer(_))));
+        let framebuffer = device.create_framebuffer(&render_pass, vec![&stored, &discarded, &depth]).unwrap();
+
+        //clear values are indexed by attachment, the first one does not clear a colour attachment
+        let mut cmd = device.create_cmd_buffer();
+        cmd.begin_render_pass(&render_pass, &framebuffer, &[api::ClearValue::DepthStencil(0.5, 0)]);
+        cmd.end_render_pass();
+        assert!(matches!(device.execute(cmd), Err(api::Error::InvalidCommand(_))));
+
+        let mut cmd = device.create_cmd_buffer();
+        cmd.begin_render_pass(&render_pass, &framebuffer, &[
+            api::ClearValue::Color([1., 0., 0., 1.]),
+            api::ClearValue::Color([0., 1., 0., 1.]),
+            api::ClearValue::DepthStencil(0.5, 0),
+        ]);
+        cmd.end_render_pass();
+        device.execute(cmd).unwrap();
+
+        assert!(read(&stored_mem, 16).chunks(4).all(|p| p == [255, 0, 0, 255]));
+        assert_eq!(read(&discarded_mem, 16), vec![0; 16]);
+        assert_eq!(read(&depth_mem, 16), as_bytes(&[0.5; 4]));
+    }
+
+    #[test]
+    fn stores_half_float_attachments() {
+        let mut pm = crate::PlatformManager::new(api::WindowConfig { width: 1, height: 1 });
+        let (_api, device) = pm.create_renderer();
+        let mut image = device.create_image(api::image::Kind::D2(2, 1, 1), api::image::Format::Rgba16F).unwrap();
+        let mem = device.allocate_memory(0, device.get_image_requirements(&image).size).unwrap();
+        device.bind_image_memory(&mem, 0, &mut image).unwrap();
+
+        let pass = |load| device.create_render_pass(vec![api::Attachment {
+            layout: api::AttachmentLayout::Color,
+            format: api::image::Format::Rgba16F,
+            load,
+            store: api::StoreOp::Store,
+        }]).unwrap();
+        let (clear, load) = (pass(api::LoadOp::Clear), pass(api::LoadOp::Load));
+        let mut cmd = device.create_cmd_buffer();
+        cmd.begin_render_pass(&clear, &device.create_framebuffer(&clear, vec![&image]).unwrap(), &[api::ClearValue::Color([1., 0., 0.5, 1.])]);
+        cmd.end_render_pass();
+        cmd.begin_render_pass(&load, &device.create_framebuffer(&load, vec![&image]).unwrap(), &[]);
+        cmd.end_render_pass();
+        device.execute(cmd).unwrap();
+
+        let ptr = device.map_memory(&mem).unwrap();
+        let read = unsafe { std::slice::from_raw_parts(ptr as *const u16, 8) }.to_vec();
+        device.unmap_memory(&mem);
+        //1, 0, 128 / 255 and 1 as halves
+        assert_eq!(read, vec![0x3c00, 0, 0x3804, 0x3c00, 0x3c00, 0, 0x3804, 0x3c00]);
+    }
+
+    #[test]
+    fn reads_back_image_regions() {
+        let mut pm = crate::PlatformManager::new(api::WindowConfig { width: 1, height: 1 });
+        let (_api, device) = pm.create_renderer();
+
+        let kind = api::image::Kind::D2(2, 2, 1);
+        let mut image = device.create_image(kind.clone(), api::image::Format::Rgba8).unwrap();
+        assert_eq!(device.read_pixels(&image, 0), Err(api::Error::InvalidMemoryBinding { offset: 0 }));
+        let mem = device.allocate_memory(0, device.get_image_requirements(&image).size).unwrap();
+        device.bind_image_memory(&mem, 0, &mut image).unwrap();
+
+        let texels: Vec<u8> = (0..16).collect();
+        let (_sm, staging) = upload(&device, api::Usage::TransferSrc, &texels);
+        let (readback_mem, readback) = upload(&device, api::Usage::TransferDst, &[0; 8]);
+        let mut cmd = device.create_cmd_buffer();
+        cmd.copy_buffer_to_image(&staging, &image, api::image::BufferImageCopy::level(&kind, 0));
+        cmd.copy_image_to_buffer(&image, &readback, api::image::BufferImageCopy {
+            buffer_offset: 0,
+            level: 0,
+            offset: (0, 1, 0),
+            extent: (2, 1, 1),
+        });
+        device.execute(cmd).unwrap();
+
+        assert_eq!(device.read_pixels(&image, 0).unwrap(), texels);
+        let ptr = device.map_memory(&readback_mem).unwrap();
+        let read = unsafe { std::slice::from_raw_parts(ptr, 8) }.to_vec();
+        device.unmap_memory(&readback_mem);
+        assert_eq!(read, &texels[8..]);
+    }
+}
@@ -0,0 +1,47 @@
+use std::sync::{Arc, RwLock};
+
+///Host memory, every resource bound to it reads and writes the block at its offset.
+pub struct HeadlessMemory {
+    pub(crate) memory_type: api::memory::TypeId,
+    pub(crate) data: Arc<RwLock<Vec<u8>>>,
+}
+
+impl HeadlessMemory {
+    pub fn allocate(memory_type: api::memory::TypeId, size: u32) -> Self {
+        HeadlessMemory {
+            memory_type,
+            data: Arc::new(RwLock::new(vec![0; size as usize])),
+        }
+    }
+
+    pub fn size(&self) -> u32 {
+        self.data.read().unwrap().len() as u32
+    }
+
+    pub fn map_memory(&self) -> *mut u8 {
+        //storage is never resized, pointer stays valid while memory is alive
+        self.data.write().unwrap().as_mut_ptr()
+    }
+
+    pub fn unmap_memory(&self) {}
+
+    pub fn flush_memory(&self) {}
+}
+
+///Single type, the CPU is both host and device.
+pub(crate) const MEMORY_TYPE: api::memory::TypeId = 0;
+
+pub(crate) fn memory_types() -> Vec<api::memory::MemoryType> {
+    use api::memory::Properties;
+    vec![api::memory::MemoryType {
+        properties: Properties::DEVICE_LOCAL | Properties::HOST_VISIBLE | Properties::HOST_COHERENT,
+    }]
+}
+
+pub(crate) fn requirements(size: usize) -> api::memory::Requirements {
+    api::memory::Requirements {
+        size: size as u32,
+        alignment: 4,
+        type_mask: 1 << MEMORY_TYPE,
+    }
+}
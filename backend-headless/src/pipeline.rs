@@ -0,0 +1,498 @@
+use std::collections::HashMap;
+use std::mem::size_of;
+use std::ops::Range;
+use std::sync::{Arc, RwLock};
+
+use api::{AttributeDescriptor, PipelineDescriptor, Primitive, VertexBufferDescriptor};
+
+use crate::Backend;
+use crate::buffer::HeadlessBuffer;
+use crate::core::{SharedWindow, Window};
+use crate::framebuffer::HeadlessFramebuffer;
+use crate::image::HeadlessImage;
+use crate::pipeline::HeadlessCommand::{BeginRenderPass, BindDescriptorSet, BindIndexBuffer, BindVertexBuffer, BufferData, ClearScreen, CopyBufferToImage, CopyImageToBuffer, DrawIndexed, DrawIndexedIndirect, EndRenderPass, GenerateMipmaps, PreparePipeline, SetScissor, SetViewport};
+use crate::raster::{State, Target, Vertex, Viewport};
+
+type Binding = u32;
+
+#[derive(Debug)]
+pub struct HeadlessRenderPass {
+    pub(crate) attachments: Vec<api::Attachment>
+}
+
+impl HeadlessRenderPass {
+    pub fn new<A>(attachments: A) -> Self
+        where A: IntoIterator<Item=api::Attachment> {
+        HeadlessRenderPass {
+            attachments: attachments.into_iter().collect()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HeadlessPipeline {
+    primitive: Primitive,
+    buffers: HashMap<Binding, VertexBufferDescriptor>,
+    position: Option<AttributeDescriptor>,
+    color: Option<AttributeDescriptor>,
+    mvp: Option<AttributeDescriptor>,
+    state: State,
+}
+
+impl HeadlessPipeline {
+    pub fn new(desc: PipelineDescriptor<Backend>) -> Self {
+        let mut attributes = desc.vertex_attributes.clone();
+        attributes.sort_by_key(|a| a.location);
+
+        let vec3: Vec<&AttributeDescriptor> = attributes.iter()
+            .filter(|a| a.data.data_type == api::DataType::Vec3f32)
+            .collect();
+
+        HeadlessPipeline {
+            primitive: desc.primitives,
+            buffers: desc.vertex_buffers.iter()
+                .map(|b| (b.binding as Binding, b.clone()))
+                .collect(),
+            position: vec3.first().map(|a| (*a).clone()),
+            color: if vec3.len() > 1 { vec3.last().map(|a| (*a).clone()) } else { None },
+            mvp: attributes.iter()
+                .find(|a| a.data.data_type == api::DataType::Mat4f32)
+                .cloned(),
+            state: State {
+                rasterizer: desc.rasterizer,
+                depth_stencil: desc.depth_stencil,
+                blend: desc.blend,
+            },
+        }
+    }
+
+    fn fetch(&self, bound: &HashMap<Binding, HeadlessBuffer>, vertex: u32, instance: u32) -> Vertex {
+        let position = self.position.as_ref()
+            .map(|a| self.read_floats(bound, a, vertex, 3))
+            .map(|p| [p[0], p[1], p[2], 1.])
+            .unwrap_or([0., 0., 0., 1.]);
+        let color = self.color.as_ref()
+            .map(|a| {
+                let c = self.read_floats(bound, a, vertex, 3);
+                [c[0], c[1], c[2]]
+            })
+            .unwrap_or([1., 1., 1.]);
+        let clip = match &self.mvp {
+            //column major, as uploaded from nalgebra
+            Some(a) => {
+                let m = self.read_floats(bound, a, instance, 16);
+                let mut clip = [0f32; 4];
+                for row in 0..4 {
+                    for col in 0..4 {
+                        clip[row] += m[col * 4 + row] * position[col];
+                    }
+                }
+                clip
+            }
+            None => position,
+        };
+        Vertex { position: clip, color }
+    }
+
+    ///Fails when an attribute the fixed function program reads has no buffer described or bound at its binding.
+    fn check_bound(&self, bound: &HashMap<Binding, HeadlessBuffer>) -> Result<(), api::Error> {
+        for attr in self.position.iter().chain(&self.color).chain(&self.mvp) {
+            let binding = attr.binding as Binding;
+            if !self.buffers.contains_key(&binding) {
+                return Err(api::Error::InvalidCommand(format!("Pipeline describes no vertex buffer at {}", binding)));
+            }
+            if !bound.contains_key(&binding) {
+                return Err(api::Error::InvalidCommand(format!("Vertex buffer is not bound at {}", binding)));
+            }
+        }
+        Ok(())
+    }
+
+    fn read_floats(&self, bound: &HashMap<Binding, HeadlessBuffer>, attr: &AttributeDescriptor, index: u32, count: usize) -> Vec<f32> {
+        let binding = attr.binding as Binding;
+        let stride = self.buffers[&binding].stride;
+        let buffer = &bound[&binding];
+        let data = buffer.data.read().unwrap();
+        let start = buffer.offset + index as usize * stride + attr.data.offset;
+        data[start..start + count * size_of::<f32>()]
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect()
+    }
+
+    fn assemble(&self, indices: &[u32]) -> Primitives {
+        match self.primitive {
+            Primitive::Points => Primitives::Points(indices.to_vec()),
+            Primitive::Lines => Primitives::Lines(indices
+                .chunks_exact(2)
+                .map(|l| [l[0], l[1]])
+                .collect()),
+            Primitive::LinesStrip => Primitives::Lines(indices
+                .windows(2)
+                .map(|l| [l[0], l[1]])
+                .collect()),
+            Primitive::Triangles => Primitives::Triangles(indices
+                .chunks_exact(3)
+                .map(|t| [t[0], t[1], t[2]])
+                .collect()),
+            Primitive::TrianglesStrip => Primitives::Triangles((2..indices.len())
+                .map(|i| if i % 2 == 0 {
+                    [indices[i - 2], indices[i - 1], indices[i]]
+                } else {
+                    [indices[i - 1], indices[i - 2], indices[i]]
+                })
+                .collect()),
+            Primitive::TrianglesFan => Primitives::Triangles((2..indices.len())
+                .map(|i| [indices[0], indices[i - 1], indices[i]])
+                .collect()),
+        }
+    }
+}
+
+///Indices of the vertices of each primitive a draw assembles.
+enum Primitives {
+    Points(Vec<u32>),
+    Lines(Vec<[u32; 2]>),
+    Triangles(Vec<[u32; 3]>),
+}
+
+#[derive(Debug)]
+pub struct HeadlessDescriptorSetLayout {
+    layout: HashMap<u32, api::DescriptorSetLayoutBinding>
+}
+
+impl HeadlessDescriptorSetLayout {
+    pub fn new(bindings: &[api::DescriptorSetLayoutBinding]) -> Self {
+        HeadlessDescriptorSetLayout {
+            layout: bindings.iter().map(|b| (b.binding, b.clone())).collect()
+        }
+    }
+}
+
+///Hints are only checked against the descriptor set layout, the fixed function program reads no uniforms.
+#[derive(Debug)]
+pub struct HeadlessPipelineLayout;
+
+impl HeadlessPipelineLayout {
+    pub fn new<I>(desc_layout: &HeadlessDescriptorSetLayout, hints: I) -> Result<Self, api::Error>
+        where
+            I: IntoIterator<Item=api::PipelineLayoutHint>, {
+        for h in hints {
+            if !desc_layout.layout.contains_key(&h.location) {
+                return Err(api::Error::InvalidUniformBlock {
+                    binding: h.location,
+                    name: match h.hint {
+                        api::LayoutHint::Name(name) => Some(name.to_owned()),
+                    },
+                });
+            }
+        }
+        Ok(HeadlessPipelineLayout)
+    }
+}
+
+///Uniforms and textures are not read by the fixed function program, what was written is only kept to be inspected.
+#[derive(Debug, Default)]
+pub struct HeadlessDescriptorSet {
+    written: RwLock<HashMap<Binding, Written>>,
+}
+
+#[derive(Debug, Clone)]
+enum Written {
+    Buffer(HeadlessBuffer),
+    Image(HeadlessImage),
+}
+
+impl HeadlessDescriptorSet {
+    pub(crate) fn write(&self, binding: Binding, descriptor: api::Descriptor<Backend>) {
+        let written = match descriptor {
+            api::Descriptor::Buffer(buffer) => Written::Buffer(buffer.clone()),
+            api::Descriptor::CombinedImageSampler(image, _) => Written::Image(image.clone()),
+        };
+        self.written.write().unwrap().insert(binding, written);
+    }
+
+    ///Whether `binding` was last written with `buffer`.
+    pub fn holds_buffer(&self, binding: u32, buffer: &HeadlessBuffer) -> bool {
+        match self.written.read().unwrap().get(&binding) {
+            Some(Written::Buffer(written)) => Arc::ptr_eq(&written.data, &buffer.data) && written.offset == buffer.offset,
+            _ => false,
+        }
+    }
+
+    ///Whether `binding` was last written with `image` or a view of it.
+    pub fn holds_image(&self, binding: u32, image: &HeadlessImage) -> bool {
+        match self.written.read().unwrap().get(&binding) {
+            Some(Written::Image(written)) => Arc::ptr_eq(&written.data, &image.data) && written.offset == image.offset,
+            _ => false,
+        }
+    }
+}
+
+///Load and store op of every attachment of a pass.
+type AttachmentOps = [(api::LoadOp, api::StoreOp)];
+
+#[derive(Debug)]
+enum HeadlessCommand {
+    PreparePipeline(HeadlessPipeline),
+    BeginRenderPass(Vec<(api::LoadOp, api::StoreOp)>, HeadlessFramebuffer, Vec<api::ClearValue>),
+    EndRenderPass,
+    BindVertexBuffer(u32, HeadlessBuffer),
+    BindIndexBuffer(HeadlessBuffer),
+    BindDescriptorSet,
+    BufferData(HeadlessBuffer, Vec<u8>),
+    CopyBufferToImage(HeadlessBuffer, HeadlessImage, api::image::BufferImageCopy),
+    CopyImageToBuffer(HeadlessImage, HeadlessBuffer, api::image::BufferImageCopy),
+    GenerateMipmaps(HeadlessImage),
+    ///Index count, first index byte offset, base vertex and instances.
+    DrawIndexed(u32, u32, i32, Range<u32>),
+    ///Arguments buffer, its byte offset, draw count and stride.
+    DrawIndexedIndirect(HeadlessBuffer, u32, u32, u32),
+    ClearScreen((f32, f32, f32, f32)),
+    SetViewport(Viewport),
+    SetScissor(Option<Viewport>),
+}
+
+#[derive(Debug)]
+pub struct HeadlessCommandBuffer {
+    cmds: Vec<HeadlessCommand>
+}
+
+impl HeadlessCommandBuffer {
+    pub(crate) fn new() -> Self {
+        HeadlessCommandBuffer { cmds: Vec::new() }
+    }
+
+    pub(crate) fn execute(&self, window: &SharedWindow) -> Result<(), api::Error> {
+        let mut window = window.lock().unwrap();
+        let executed = self.run(&mut window);
+        window.back.scissor = None;
+        executed
+    }
+
+    fn run(&self, window: &mut Window) -> Result<(), api::Error> {
+        //framebuffer, its ops and the target rendered into while a pass is open
+        let mut pass: Option<(&HeadlessFramebuffer, &AttachmentOps, Target)> = None;
+
+        let mut pipeline: Option<&HeadlessPipeline> = None;
+        let mut vertex_buffers: HashMap<Binding, HeadlessBuffer> = HashMap::new();
+        let mut index_buffer: Option<&HeadlessBuffer> = None;
+        //set by the buffer for the current target, which begins with the framebuffer's or the window's
+        let mut viewport: Option<Viewport> = None;
+
+        for cmd in &self.cmds {
+            match cmd {
+                PreparePipeline(p) => {
+                    pipeline = Some(p);
+                }
+                BeginRenderPass(ops, framebuffer, clear_values) => {
+                    if pass.is_some() {
+                        return Err(api::Error::InvalidCommand("Render pass begun inside another".to_owned()));
+                    }
+                    pass = Some((framebuffer, ops, framebuffer.begin(ops, clear_values)?));
+                    viewport = None;
+                    window.back.scissor = None;
+                }
+                EndRenderPass => {
+                    let (framebuffer, ops, target) = pass.take()
+                        .ok_or_else(|| api::Error::InvalidCommand("Render pass ended without begin".to_owned()))?;
+                    framebuffer.end(ops, &target);
+                    viewport = None;
+                    window.back.scissor = None;
+                }
+                SetViewport(rect) => {
+                    viewport = Some(*rect);
+                }
+                SetScissor(rect) => {
+                    match pass.as_mut() {
+                        Some((_, _, target)) => target.scissor = *rect,
+                        None => window.back.scissor = *rect,
+                    }
+                }
+                BindVertexBuffer(binding, buffer) => {
+                    vertex_buffers.insert(*binding, buffer.clone());
+                }
+                BindIndexBuffer(buffer) => {
+                    index_buffer = Some(buffer);
+                }
+                BindDescriptorSet => {}
+                BufferData(buffer, data) => {
+                    buffer.data.write().unwrap()[buffer.offset..buffer.offset + data.len()].copy_from_slice(data);
+                }
+                CopyBufferToImage(buffer, image, region) => {
+                    image.copy_from(buffer, region);
+                }
+                CopyImageToBuffer(image, buffer, region) => {
+                    image.copy_to(buffer, region);
+                }
+                GenerateMipmaps(image) => {
+                    image.generate_mipmaps();
+                }
+                ClearScreen(color) => {
+                    match pass.as_mut() {
+                        Some((_, _, target)) => target.clear(*color),
+                        None => window.back.clear(*color),
+                    }
+                }
+                DrawIndexed(count, offset, base_vertex, instances) => {
+                    let (pipeline, index_buffer) = match bound(pipeline, index_buffer) {
+                        Some(bound) => bound,
+                        None => continue,
+                    };
+                    if instances.start >= instances.end {
+                        continue;
+                    }
+                    let (target, viewport) = match pass.as_mut() {
+                        Some((_, _, target)) => {
+                            let full = Viewport { x: 0, y: 0, width: target.width as i32, height: target.height as i32 };
+                            (target, viewport.unwrap_or(full))
+                        }
+                        None => (&mut window.back, viewport.unwrap_or(window.viewport)),
+                    };
+                    let args = api::DrawIndexedIndirectCommand {
+                        index_count: *count,
+                        instance_count: instances.end - instances.start,
+                        first_index: offset / size_of::<u32>() as u32,
+                        base_vertex: *base_vertex,
+                        first_instance: instances.start,
+                    };
+                    draw_indexed(pipeline, &vertex_buffers, index_buffer, target, viewport, &args)?;
+                }
+                DrawIndexedIndirect(buffer, offset, count, stride) => {
+                    let (pipeline, index_buffer) = match bound(pipeline, index_buffer) {
+                        Some(bound) => bound,
+                        None => continue,
+                    };
+                    let (target, viewport) = match pass.as_mut() {
+                        Some((_, _, target)) => {
+                            let full = Viewport { x: 0, y: 0, width: target.width as i32, height: target.height as i32 };
+                            (target, viewport.unwrap_or(full))
+                        }
+                        None => (&mut window.back, viewport.unwrap_or(window.viewport)),
+                    };
+                    for i in 0..*count {
+                        let first = buffer.offset + (offset + i * stride) as usize;
+                        let args = api::DrawIndexedIndirectCommand::from_bytes(&buffer.data.read().unwrap()[first..]);
+                        draw_indexed(pipeline, &vertex_buffers, index_buffer, target, viewport, &args)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+///The pipeline and index buffer a draw reads, draws missing either are skipped.
+fn bound<'a>(pipeline: Option<&'a HeadlessPipeline>, index_buffer: Option<&'a HeadlessBuffer>) -> Option<(&'a HeadlessPipeline, &'a HeadlessBuffer)> {
+    Some((pipeline?, index_buffer?))
+}
+
+fn to_viewport(rect: api::Rect) -> Viewport {
+    Viewport { x: rect.x, y: rect.y, width: rect.width, height: rect.height }
+}
+
+///Rasterizes the indices `args` refers to once per instance.
+fn draw_indexed(pipeline: &HeadlessPipeline,
+                vertex_buffers: &HashMap<Binding, HeadlessBuffer>,
+                index_buffer: &HeadlessBuffer,
+                target: &mut Target,
+                viewport: Viewport,
+                args: &api::DrawIndexedIndirectCommand) -> Result<(), api::Error> {
+    pipeline.check_bound(vertex_buffers)?;
+    let indices: Vec<u32> = {
+        let data = index_buffer.data.read().unwrap();
+        let first = index_buffer.offset + args.first_index as usize * size_of::<u32>();
+        data[first..first + args.index_count as usize * size_of::<u32>()]
+            .chunks_exact(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect()
+    };
+    let primitives = pipeline.assemble(&indices);
+    let state = &pipeline.state;
+    for instance in args.first_instance..args.first_instance + args.instance_count {
+        let fetch = |index: u32| pipeline.fetch(vertex_buffers, (index as i32 + args.base_vertex) as u32, instance);
+        match &primitives {
+            Primitives::Points(points) => for p in points {
+                target.draw_point(viewport, state, fetch(*p));
+            },
+            Primitives::Lines(lines) => for l in lines {
+                target.draw_line(viewport, state, [fetch(l[0]), fetch(l[1])]);
+            },
+            Primitives::Triangles(triangles) => for t in triangles {
+                target.draw_triangle(viewport, state, [fetch(t[0]), fetch(t[1]), fetch(t[2])]);
+            },
+        }
+    }
+    Ok(())
+}
+
+
+impl api::CommandBuffer<Backend> for HeadlessCommandBuffer {
+    fn bind_pipeline(&mut self, pipeline: &HeadlessPipeline) {
+        self.cmds.push(PreparePipeline(pipeline.clone()));
+    }
+
+    fn begin_render_pass(&mut self, render_pass: &HeadlessRenderPass, framebuffer: &HeadlessFramebuffer, clear_values: &[api::ClearValue]) {
+        let ops = render_pass.attachments.iter()
+            .map(|a| (a.load, a.store))
+            .collect();
+        self.cmds.push(BeginRenderPass(ops, framebuffer.clone(), clear_values.to_vec()))
+    }
+
+    fn end_render_pass(&mut self) {
+        self.cmds.push(EndRenderPass)
+    }
+
+    fn bind_vertex_buffer(&mut self, binding: u32, buffer: &HeadlessBuffer) {
+        self.cmds.push(BindVertexBuffer(binding, buffer.clone()))
+    }
+
+    fn bind_index_buffer(&mut self, buffer: &HeadlessBuffer) {
+        self.cmds.push(BindIndexBuffer(buffer.clone()))
+    }
+
+    fn buffer_data(&mut self, buffer: &HeadlessBuffer, data: &[u8]) {
+        self.cmds.push(BufferData(buffer.clone(), data.to_vec()))
+    }
+
+    fn copy_buffer_to_image(&mut self, buffer: &HeadlessBuffer, image: &HeadlessImage, region: api::image::BufferImageCopy) {
+        self.cmds.push(CopyBufferToImage(buffer.clone(), image.clone(), region))
+    }
+
+    fn copy_image_to_buffer(&mut self, image: &HeadlessImage, buffer: &HeadlessBuffer, region: api::image::BufferImageCopy) {
+        self.cmds.push(CopyImageToBuffer(image.clone(), buffer.clone(), region))
+    }
+
+    fn generate_mipmaps(&mut self, image: &HeadlessImage) {
+        self.cmds.push(GenerateMipmaps(image.clone()))
+    }
+
+    fn draw_indexed(&mut self, count: u32, offset: u32, number: u32) {
+        self.cmds.push(DrawIndexed(count, offset, 0, 0..number));
+    }
+
+    fn draw_indexed_base_vertex(&mut self, count: u32, offset: u32, base_vertex: i32, instances: Range<u32>) {
+        self.cmds.push(DrawIndexed(count, offset, base_vertex, instances));
+    }
+
+    fn draw_indexed_indirect(&mut self, buffer: &HeadlessBuffer, offset: u32, draw_count: u32, stride: u32) {
+        self.cmds.push(DrawIndexedIndirect(buffer.clone(), offset, draw_count, stride));
+    }
+
+    fn bind_descriptor_set(&mut self, _pipeline_layout: &HeadlessPipelineLayout, _desc_set: &HeadlessDescriptorSet) {
+        //uniforms are not read by the fixed function program
+        self.cmds.push(BindDescriptorSet)
+    }
+
+    fn clear_screen(&mut self, color: (f32, f32, f32, f32)) {
+        self.cmds.push(ClearScreen(color))
+    }
+
+    fn set_viewport(&mut self, viewport: api::Rect) {
+        self.cmds.push(SetViewport(to_viewport(viewport)))
+    }
+
+    fn set_scissor(&mut self, scissor: Option<api::Rect>) {
+        self.cmds.push(SetScissor(scissor.map(to_viewport)))
+    }
+}
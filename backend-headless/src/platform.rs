@@ -0,0 +1,137 @@
+extern crate imgui;
+
+use std::cell::RefCell;
+
+use backend_api::Backend as apiBackend;
+use backend_api::Event;
+use backend_api::ImGuiRenderer;
+use backend_api::PlatformManager;
+use backend_api::WindowConfig;
+
+use crate::Backend;
+use crate::core::{HeadlessRendererApi, HeadlessRendererDevice, SharedWindow, Window};
+use crate::raster::{Target, Viewport};
+
+///Platform time advances by a fixed step per presented frame, keeping runs reproducible.
+pub const FRAME_TIME: f64 = 1. / 60.;
+
+pub struct HeadlessPlatformManager {
+    window: SharedWindow,
+    events: RefCell<Vec<Event>>,
+    frame_limit: Option<u64>,
+}
+
+impl HeadlessPlatformManager {
+    ///Makes `should_close` report true once `frames` frames were presented.
+    pub fn set_frame_limit(&mut self, frames: u64) {
+        self.frame_limit = Some(frames);
+    }
+
+    ///Queues an event for the next `poll_events`.
+    pub fn push_event(&self, event: Event) {
+        self.events.borrow_mut().push(event);
+    }
+
+    ///Resizes the offscreen window and queues the matching resize event.
+    pub fn resize(&self, width: u32, height: u32) {
+        {
+            let mut window = self.window.lock().unwrap();
+            window.back = Target::new(width, height);
+            window.front = Target::new(width, height);
+            window.viewport = Viewport { x: 0, y: 0, width: width as i32, height: height as i32 };
+        }
+        self.push_event(Event::Resize(width as i32, height as i32));
+    }
+
+    pub fn presented_frames(&self) -> u64 {
+        self.window.lock().unwrap().presented
+    }
+
+    ///Width, height and rgba8 pixels of the last presented frame, rows bottom to top.
+    pub fn presented_frame(&self) -> (u32, u32, Vec<u8>) {
+        let window = self.window.lock().unwrap();
+        (window.front.width, window.front.height, window.front.colors[0].clone())
+    }
+}
+
+impl PlatformManager<Backend> for HeadlessPlatformManager {
+    fn new(config: WindowConfig) -> <Backend as apiBackend>::PlatformManager {
+        HeadlessPlatformManager {
+            window: Window::new(config.width, config.height),
+            events: RefCell::new(Vec::new()),
+            frame_limit: None,
+        }
+    }
+
+    fn create_renderer(&mut self)
+                       -> (<Backend as apiBackend>::RendererApi, <Backend as apiBackend>::RendererDevice) {
+        (
+            HeadlessRendererApi::new(self.window.clone()),
+            HeadlessRendererDevice::new(self.window.clone())
+        )
+    }
+
+    fn should_close(&self) -> bool {
+        match self.frame_limit {
+            Some(limit) => self.presented_frames() >= limit,
+            None => false,
+        }
+    }
+
+    fn poll_events(&self) -> Vec<Event> {
+        self.events.borrow_mut().drain(..).collect()
+    }
+
+    fn current_time(&self) -> f64 {
+        self.presented_frames() as f64 * FRAME_TIME
+    }
+
+    fn create_surface(&self) -> <Backend as api::Backend>::Surface {
+        crate::swapchain::HeadlessSurface {
+            window: self.window.clone()
+        }
+    }
+
+    fn imgui_renderer(&mut self, imgui: &mut imgui::Context) -> HeadlessImGuiRenderer {
+        HeadlessImGuiRenderer::new(self.window.clone(), imgui)
+    }
+}
+
+pub struct HeadlessImGuiRenderer {
+    window: SharedWindow,
+}
+
+impl HeadlessImGuiRenderer {
+    fn new(window: SharedWindow, imgui: &mut imgui::Context) -> HeadlessImGuiRenderer {
+        //font atlas has to be built before the first frame
+        match imgui.fonts() {
+            imgui::FontAtlasRefMut::Owned(atlas) => {
+                atlas.build_rgba32_texture();
+            }
+            imgui::FontAtlasRefMut::Shared(mut atlas) => {
+                atlas.build_rgba32_texture();
+            }
+        }
+        HeadlessImGuiRenderer { window }
+    }
+}
+
+impl ImGuiRenderer for HeadlessImGuiRenderer {
+    fn new_frame<'im>(&mut self, imgui: &'im mut imgui::Context) -> imgui::Ui<'im> {
+        let (width, height) = {
+            let window = self.window.lock().unwrap();
+            (window.back.width, window.back.height)
+        };
+        let io = imgui.io_mut();
+        io.display_size = [width as f32, height as f32];
+        io.display_framebuffer_scale = [1., 1.];
+        io.delta_time = FRAME_TIME as f32;
+        imgui.frame()
+    }
+
+    fn render(&self, ui: imgui::Ui) {
+        ui.render();
+    }
+
+    fn handle_events(&mut self, _imgui: &mut imgui::Context) {}
+}
@@ -0,0 +1,419 @@
+///Fixed function state of the pipeline drawing primitives.
+#[derive(Debug, Clone)]
+pub(crate) struct State {
+    pub(crate) rasterizer: api::RasterizerState,
+    pub(crate) depth_stencil: api::DepthStencilState,
+    ///Blend state of each colour plane, planes past its end are replaced.
+    pub(crate) blend: Vec<Option<api::BlendState>>,
+}
+
+///Smallest depth difference of a 24 bit depth attachment, what the constant depth bias is in.
+const DEPTH_UNIT: f32 = 1. / (1 << 24) as f32;
+
+///Clip space vertex with a colour varying.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Vertex {
+    pub(crate) position: [f32; 4],
+    pub(crate) color: [f32; 3],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Viewport {
+    pub(crate) x: i32,
+    pub(crate) y: i32,
+    pub(crate) width: i32,
+    pub(crate) height: i32,
+}
+
+///Colour, depth and stencil planes, rows are stored bottom to top as in OpenGL.
+///Every colour plane receives the same fragments.
+#[derive(Debug, Clone)]
+pub(crate) struct Target {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) colors: Vec<Vec<u8>>,
+    pub(crate) depth: Vec<f32>,
+    ///Only kept for the pass, attachments store their depth alone.
+    pub(crate) stencil: Vec<u8>,
+    ///Fragments and clears outside are discarded, draws are only bounded by their viewport without one.
+    pub(crate) scissor: Option<Viewport>,
+}
+
+impl Target {
+    pub(crate) fn new(width: u32, height: u32) -> Self {
+        Self::with_colors(width, height, 1)
+    }
+
+    pub(crate) fn with_colors(width: u32, height: u32, colors: usize) -> Self {
+        let texels = (width * height) as usize;
+        Target {
+            width,
+            height,
+            colors: vec![vec![0; texels * 4]; colors],
+            depth: vec![1.; texels],
+            stencil: vec![0; texels],
+            scissor: None,
+        }
+    }
+
+    pub(crate) fn clear(&mut self, color: (f32, f32, f32, f32)) {
+        let scissor = match self.scissor {
+            Some(scissor) => scissor,
+            None => {
+                self.clear_color(color);
+                self.clear_depth(1.);
+                self.clear_stencil(0);
+                return;
+            }
+        };
+        let (r, g, b, a) = color;
+        let texel = [to_unorm(r), to_unorm(g), to_unorm(b), to_unorm(a)];
+        let (x0, y0, x1, y1) = self.bounds(scissor);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let index = (y as u32 * self.width + x as u32) as usize;
+                for plane in self.colors.iter_mut() {
+                    plane[index * 4..index * 4 + 4].copy_from_slice(&texel);
+                }
+                self.depth[index] = 1.;
+                self.stencil[index] = 0;
+            }
+        }
+    }
+
+    pub(crate) fn clear_color(&mut self, color: (f32, f32, f32, f32)) {
+        for plane in 0..self.colors.len() {
+            self.clear_plane(plane, color);
+        }
+    }
+
+    pub(crate) fn clear_plane(&mut self, plane: usize, (r, g, b, a): (f32, f32, f32, f32)) {
+        let texel = [to_unorm(r), to_unorm(g), to_unorm(b), to_unorm(a)];
+        for c in self.colors[plane].chunks_exact_mut(4) {
+            c.copy_from_slice(&texel);
+        }
+    }
+
+    pub(crate) fn clear_depth(&mut self, depth: f32) {
+        for d in self.depth.iter_mut() {
+            *d = depth;
+        }
+    }
+
+    pub(crate) fn clear_stencil(&mut self, stencil: u32) {
+        for s in self.stencil.iter_mut() {
+            *s = stencil as u8;
+        }
+    }
+
+    pub(crate) fn draw_triangle(&mut self, viewport: Viewport, state: &State, triangle: [Vertex; 3]) {
+        let clipped = clip_near(&triangle);
+        for i in 1..clipped.len().saturating_sub(1) {
+            self.fill(viewport, state, [clipped[0], clipped[i], clipped[i + 1]]);
+        }
+    }
+
+    pub(crate) fn draw_line(&mut self, viewport: Viewport, state: &State, line: [Vertex; 2]) {
+        if let Some(line) = clip_near_line(&line) {
+            let ends = [to_window(viewport, &line[0]), to_window(viewport, &line[1])];
+            self.line(viewport, state, ends, [line[0].color, line[1].color], 0., true);
+        }
+    }
+
+    pub(crate) fn draw_point(&mut self, viewport: Viewport, state: &State, point: Vertex) {
+        if point.position[2] + point.position[3] >= 0. {
+            self.point(viewport, state, to_window(viewport, &point), point.color, 0., true);
+        }
+    }
+
+    fn fill(&mut self, viewport: Viewport, state: &State, triangle: [Vertex; 3]) {
+        let screen: Vec<[f32; 4]> = triangle.iter().map(|v| to_window(viewport, v)).collect();
+        let (s0, s1, s2) = (screen[0], screen[1], screen[2]);
+
+        let area = edge(s0, s1, s2[0], s2[1]);
+        if area == 0. {
+            return;
+        }
+        let rasterizer = &state.rasterizer;
+        let front_facing = match rasterizer.front_face {
+            api::FrontFace::CounterClockwise => area > 0.,
+            api::FrontFace::Clockwise => area < 0.,
+        };
+        let culled = match rasterizer.cull_mode {
+            api::CullMode::None => false,
+            api::CullMode::Front => front_facing,
+            api::CullMode::Back => !front_facing,
+        };
+        if culled {
+            return;
+        }
+
+        let bias = rasterizer.depth_bias.map_or(0., |bias| {
+            let dz_dx = ((s1[2] - s0[2]) * (s2[1] - s0[1]) - (s2[2] - s0[2]) * (s1[1] - s0[1])) / area;
+            let dz_dy = ((s1[0] - s0[0]) * (s2[2] - s0[2]) - (s2[0] - s0[0]) * (s1[2] - s0[2])) / area;
+            bias.slope * dz_dx.abs().max(dz_dy.abs()) + bias.constant * DEPTH_UNIT
+        });
+        match rasterizer.polygon_mode {
+            api::PolygonMode::Fill => {}
+            api::PolygonMode::Line => {
+                for i in 0..3 {
+                    let j = (i + 1) % 3;
+                    self.line(viewport, state, [screen[i], screen[j]], [triangle[i].color, triangle[j].color], bias, front_facing);
+                }
+                return;
+            }
+            api::PolygonMode::Point => {
+                for i in 0..3 {
+                    self.point(viewport, state, screen[i], triangle[i].color, bias, front_facing);
+                }
+                return;
+            }
+        }
+
+        let (min_x, min_y, max_x, max_y) = self.bounds(viewport);
+        let x0 = (s0[0].min(s1[0]).min(s2[0]).floor() as i32).max(min_x);
+        let y0 = (s0[1].min(s1[1]).min(s2[1]).floor() as i32).max(min_y);
+        let x1 = (s0[0].max(s1[0]).max(s2[0]).ceil() as i32).min(max_x);
+        let y1 = (s0[1].max(s1[1]).max(s2[1]).ceil() as i32).min(max_y);
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                let b0 = edge(s1, s2, px, py) / area;
+                let b1 = edge(s2, s0, px, py) / area;
+                let b2 = edge(s0, s1, px, py) / area;
+                if b0 < 0. || b1 < 0. || b2 < 0. {
+                    continue;
+                }
+
+                let depth = b0 * s0[2] + b1 * s1[2] + b2 * s2[2] + bias;
+                let index = (y as u32 * self.width + x as u32) as usize;
+                let (q0, q1, q2) = (b0 * s0[3], b1 * s1[3], b2 * s2[3]);
+                let q = q0 + q1 + q2;
+                let mut color = [1.; 4];
+                for (c, channel) in color.iter_mut().take(3).enumerate() {
+                    *channel = (q0 * triangle[0].color[c]
+                        + q1 * triangle[1].color[c]
+                        + q2 * triangle[2].color[c]) / q;
+                }
+                self.shade(state, index, depth, color, front_facing);
+            }
+        }
+    }
+
+    ///Steps one fragment per pixel along the major axis of the window space `ends`,
+    ///lines wider than a pixel extend along the minor one.
+    fn line(&mut self, viewport: Viewport, state: &State, ends: [[f32; 4]; 2], colors: [[f32; 3]; 2], bias: f32, front_facing: bool) {
+        let [p0, p1] = ends;
+        let (dx, dy) = (p1[0] - p0[0], p1[1] - p0[1]);
+        let x_major = dx.abs() >= dy.abs();
+        let steps = dx.abs().max(dy.abs()).round().max(1.) as i32;
+        let width = state.rasterizer.line_width.round().max(1.) as i32;
+        for i in 0..steps {
+            let t = (i as f32 + 0.5) / steps as f32;
+            let (x, y) = ((p0[0] + dx * t).floor() as i32, (p0[1] + dy * t).floor() as i32);
+            let depth = p0[2] + (p1[2] - p0[2]) * t + bias;
+            let (q0, q1) = ((1. - t) * p0[3], t * p1[3]);
+            let mut color = [1.; 4];
+            for c in 0..3 {
+                color[c] = (q0 * colors[0][c] + q1 * colors[1][c]) / (q0 + q1);
+            }
+            for offset in (0..width).map(|w| w - (width - 1) / 2) {
+                let (x, y) = if x_major { (x, y + offset) } else { (x + offset, y) };
+                if let Some(index) = self.texel(viewport, x, y) {
+                    self.shade(state, index, depth, color, front_facing);
+                }
+            }
+        }
+    }
+
+    fn point(&mut self, viewport: Viewport, state: &State, position: [f32; 4], color: [f32; 3], bias: f32, front_facing: bool) {
+        if let Some(index) = self.texel(viewport, position[0].floor() as i32, position[1].floor() as i32) {
+            self.shade(state, index, position[2] + bias, [color[0], color[1], color[2], 1.], front_facing);
+        }
+    }
+
+    ///Columns and rows from the first to past the last covered by both `viewport` and the scissor, inside the target.
+    fn bounds(&self, viewport: Viewport) -> (i32, i32, i32, i32) {
+        let mut bounds = (viewport.x.max(0),
+                          viewport.y.max(0),
+                          (viewport.x + viewport.width).min(self.width as i32),
+                          (viewport.y + viewport.height).min(self.height as i32));
+        if let Some(scissor) = self.scissor {
+            bounds = (bounds.0.max(scissor.x),
+                      bounds.1.max(scissor.y),
+                      bounds.2.min(scissor.x + scissor.width),
+                      bounds.3.min(scissor.y + scissor.height));
+        }
+        bounds
+    }
+
+    ///Index of the texel at `x`, `y` unless it is outside the viewport, the scissor or the target.
+    fn texel(&self, viewport: Viewport, x: i32, y: i32) -> Option<usize> {
+        let (x0, y0, x1, y1) = self.bounds(viewport);
+        let inside = x >= x0 && x < x1 && y >= y0 && y < y1;
+        if inside { Some((y as u32 * self.width + x as u32) as usize) } else { None }
+    }
+
+    ///Runs the stencil and depth tests of a fragment at `index`, then writes what passes them into every colour plane.
+    fn shade(&mut self, state: &State, index: usize, depth: f32, color: [f32; 4], front_facing: bool) {
+        if depth < 0. {
+            return;
+        }
+        let depth_stencil = &state.depth_stencil;
+        let stencil = depth_stencil.stencil
+            .map(|stencil| (stencil, if front_facing { stencil.front } else { stencil.back }));
+        if let Some((stencil, face)) = stencil {
+            let masked = |value: u32| value & stencil.read_mask;
+            if !passes(face.compare, masked(stencil.reference), masked(u32::from(self.stencil[index]))) {
+                self.write_stencil(index, &stencil, face.fail);
+                return;
+            }
+        }
+        if !passes(depth_stencil.depth_compare, depth, self.depth[index]) {
+            if let Some((stencil, face)) = stencil {
+                self.write_stencil(index, &stencil, face.depth_fail);
+            }
+            return;
+        }
+        if let Some((stencil, face)) = stencil {
+            self.write_stencil(index, &stencil, face.pass);
+        }
+        if depth_stencil.depth_write {
+            self.depth[index] = depth;
+        }
+
+        for (i, plane) in self.colors.iter_mut().enumerate() {
+            let texel = &mut plane[index * 4..index * 4 + 4];
+            let color = match state.blend.get(i).copied().flatten() {
+                Some(blend) => self::blend(&blend, color, [0, 1, 2, 3].map(|c| f32::from(texel[c]) / 255.)),
+                None => color,
+            };
+            for c in 0..4 {
+                texel[c] = to_unorm(color[c]);
+            }
+        }
+    }
+
+    fn write_stencil(&mut self, index: usize, stencil: &api::StencilState, op: api::StencilOp) {
+        let stored = self.stencil[index];
+        let value = match op {
+            api::StencilOp::Keep => return,
+            api::StencilOp::Zero => 0,
+            api::StencilOp::Replace => stencil.reference as u8,
+            api::StencilOp::IncrementAndClamp => stored.saturating_add(1),
+            api::StencilOp::DecrementAndClamp => stored.saturating_sub(1),
+            api::StencilOp::Invert => !stored,
+            api::StencilOp::IncrementAndWrap => stored.wrapping_add(1),
+            api::StencilOp::DecrementAndWrap => stored.wrapping_sub(1),
+        };
+        let mask = stencil.write_mask as u8;
+        self.stencil[index] = (stored & !mask) | (value & mask);
+    }
+}
+
+///Window space x, y and depth of `v` along with 1/w for perspective correction.
+fn to_window(viewport: Viewport, v: &Vertex) -> [f32; 4] {
+    let inv_w = 1. / v.position[3];
+    let ndc = [v.position[0] * inv_w, v.position[1] * inv_w, v.position[2] * inv_w];
+    [
+        viewport.x as f32 + (ndc[0] + 1.) * 0.5 * viewport.width as f32,
+        viewport.y as f32 + (ndc[1] + 1.) * 0.5 * viewport.height as f32,
+        (ndc[2] + 1.) * 0.5,
+        inv_w,
+    ]
+}
+
+///Fragment `src` combined with the stored `dst`, both rgba from 0 to 1.
+fn blend(state: &api::BlendState, src: [f32; 4], dst: [f32; 4]) -> [f32; 4] {
+    let factor = |factor: api::BlendFactor, c: usize| match factor {
+        api::BlendFactor::Zero => 0.,
+        api::BlendFactor::One => 1.,
+        api::BlendFactor::SrcColor => src[c],
+        api::BlendFactor::OneMinusSrcColor => 1. - src[c],
+        api::BlendFactor::DstColor => dst[c],
+        api::BlendFactor::OneMinusDstColor => 1. - dst[c],
+        api::BlendFactor::SrcAlpha => src[3],
+        api::BlendFactor::OneMinusSrcAlpha => 1. - src[3],
+        api::BlendFactor::DstAlpha => dst[3],
+        api::BlendFactor::OneMinusDstAlpha => 1. - dst[3],
+    };
+    let mut blended = [0.; 4];
+    for c in 0..4 {
+        let (src_factor, dst_factor, op) = match c {
+            3 => (state.src_alpha, state.dst_alpha, state.alpha_op),
+            _ => (state.src_color, state.dst_color, state.color_op),
+        };
+        let (s, d) = (src[c] * factor(src_factor, c), dst[c] * factor(dst_factor, c));
+        blended[c] = match op {
+            api::BlendOp::Add => s + d,
+            api::BlendOp::Subtract => s - d,
+            api::BlendOp::ReverseSubtract => d - s,
+            api::BlendOp::Min => src[c].min(dst[c]),
+            api::BlendOp::Max => src[c].max(dst[c]),
+        };
+    }
+    blended
+}
+
+fn passes<T: PartialOrd>(op: api::CompareOp, value: T, stored: T) -> bool {
+    match op {
+        api::CompareOp::Never => false,
+        api::CompareOp::Less => value < stored,
+        api::CompareOp::Equal => value == stored,
+        api::CompareOp::LessOrEqual => value <= stored,
+        api::CompareOp::Greater => value > stored,
+        api::CompareOp::NotEqual => value != stored,
+        api::CompareOp::GreaterOrEqual => value >= stored,
+        api::CompareOp::Always => true,
+    }
+}
+
+fn edge(a: [f32; 4], b: [f32; 4], x: f32, y: f32) -> f32 {
+    (b[0] - a[0]) * (y - a[1]) - (b[1] - a[1]) * (x - a[0])
+}
+
+fn to_unorm(value: f32) -> u8 {
+    (value.clamp(0., 1.) * 255.).round() as u8
+}
+
+///Sutherland-Hodgman against the near plane (z >= -w), other planes are handled by the viewport scissor.
+fn clip_near(triangle: &[Vertex; 3]) -> Vec<Vertex> {
+    let distance = |v: &Vertex| v.position[2] + v.position[3];
+    let mut out = Vec::with_capacity(4);
+    for i in 0..3 {
+        let current = triangle[i];
+        let next = triangle[(i + 1) % 3];
+        let (dc, dn) = (distance(&current), distance(&next));
+        if dc >= 0. {
+            out.push(current);
+        }
+        if (dc >= 0.) != (dn >= 0.) {
+            let t = dc / (dc - dn);
+            out.push(lerp(&current, &next, t));
+        }
+    }
+    out
+}
+
+fn clip_near_line(line: &[Vertex; 2]) -> Option<[Vertex; 2]> {
+    let (d0, d1) = (line[0].position[2] + line[0].position[3], line[1].position[2] + line[1].position[3]);
+    let t = d0 / (d0 - d1);
+    match (d0 >= 0., d1 >= 0.) {
+        (true, true) => Some(*line),
+        (true, false) => Some([line[0], lerp(&line[0], &line[1], t)]),
+        (false, true) => Some([lerp(&line[0], &line[1], t), line[1]]),
+        (false, false) => None,
+    }
+}
+
+fn lerp(a: &Vertex, b: &Vertex, t: f32) -> Vertex {
+    let mut v = *a;
+    for i in 0..4 {
+        v.position[i] = a.position[i] + (b.position[i] - a.position[i]) * t;
+    }
+    for i in 0..3 {
+        v.color[i] = a.color[i] + (b.color[i] - a.color[i]) * t;
+    }
+    v
+}
@@ -0,0 +1,3 @@
+///Sources are dropped, the fixed function program stands in for every shader.
+#[derive(Debug)]
+pub struct HeadlessShaderMod;
@@ -0,0 +1,24 @@
+use crate::Backend;
+use crate::core::SharedWindow;
+
+pub struct HeadlessSurface {
+    pub(crate) window: SharedWindow,
+}
+
+pub struct HeadlessSwapchain {
+    window: SharedWindow,
+}
+
+impl HeadlessSwapchain {
+    pub fn new(surface: &HeadlessSurface) -> Self {
+        HeadlessSwapchain {
+            window: surface.window.clone()
+        }
+    }
+}
+
+impl api::Swapchain<Backend> for HeadlessSwapchain {
+    fn present(&mut self, _frame_index: u32) {
+        self.window.lock().unwrap().present()
+    }
+}
@@ -1,56 +1,39 @@
-use std::rc::Rc;
+use crate::memory::OpenGlMemory;
 
 #[derive(Debug, Clone)]
 pub struct OpenGlBuffer {
+    ///Buffer object of the memory the buffer is bound to, 0 until `bind_buffer_memory`.
     pub(crate)id: u32,
+    pub(crate)offset: u32,
     pub(crate)target: u32,
-    pub(crate)usage: u32,
     pub(crate)size: u32,
 }
 
 impl OpenGlBuffer {
-    pub fn new(gl: &gl::Gl, desc: api::BufferDescriptor) -> OpenGlBuffer {
-        unsafe {
-            let id = Self::generate(gl);
-            let buffer = OpenGlBuffer { id, target: to_gl_buffer_type(&desc.usage), usage: gl::STATIC_DRAW, size: desc.size };
-            buffer.bind(gl);
-            Self::buffer_empty(gl, &buffer);
-            buffer
-        }
+    pub fn new(desc: api::BufferDescriptor) -> OpenGlBuffer {
+        OpenGlBuffer { id: 0, offset: 0, target: to_gl_buffer_type(&desc.usage), size: desc.size }
     }
 
-    pub unsafe fn generate(gl: &gl::Gl) -> u32 {
-        let mut id: gl::types::GLuint = 0;
-        gl.GenBuffers(1, &mut id);
-        id
+    pub fn bind_memory(&mut self, memory: &OpenGlMemory, offset: u32) {
+        self.id = memory.id;
+        self.offset = offset;
     }
 
     pub unsafe fn bind(&self, gl: &gl::Gl) {
         gl.BindBuffer(self.target, self.id);
     }
 
-    pub unsafe fn buffer_empty(gl: &gl::Gl, buffer: &OpenGlBuffer) {
-        gl.BufferData(buffer.target, buffer.size as isize, std::ptr::null(), buffer.usage);
+    pub(crate) unsafe fn sub_data(&self, gl: &gl::Gl, data: &[u8]) {
+        self.bind(gl);
+        gl.BufferSubData(self.target, self.offset as isize, data.len() as isize, data.as_ptr() as *const _);
     }
 
-    pub fn mapper(gl: &gl::Gl, buffer: &OpenGlBuffer) -> *mut u8 {
-        unsafe { buffer.bind(&gl) };
-        unsafe {
-            gl.MapBufferRange(buffer.target, 0, buffer.size as isize,
-                              gl::MAP_WRITE_BIT
-                                  | gl::MAP_READ_BIT
-                                  | gl::MAP_FLUSH_EXPLICIT_BIT) as *mut u8
-        }
-    }
-
-    pub fn unmap(gl: &gl::Gl, buffer: &OpenGlBuffer) {
-        unsafe { buffer.bind(&gl) };
-        unsafe { gl.UnmapBuffer(buffer.target); };
-    }
-
-    pub fn flush(gl: &gl::Gl, buffer: &OpenGlBuffer) {
-        unsafe { buffer.bind(&gl) };
-        unsafe { gl.FlushMappedBufferRange(buffer.target, 0, buffer.size as isize); };
+    ///Reads `size` bytes from `offset` of the buffer back to the host.
+    pub(crate) unsafe fn get_sub_data(&self, gl: &gl::Gl, offset: u32, size: u32) -> Vec<u8> {
+        let mut data = vec![0u8; size as usize];
+        gl.BindBuffer(gl::COPY_READ_BUFFER, self.id);
+        gl.GetBufferSubData(gl::COPY_READ_BUFFER, (self.offset + offset) as isize, size as isize, data.as_mut_ptr() as *mut _);
+        data
     }
 }
 
@@ -58,6 +41,9 @@ pub(crate) fn to_gl_buffer_type(u: &api::Usage) -> u32 {
     match u {
         api::Usage::Vertex => gl::ARRAY_BUFFER,
         api::Usage::Index => gl::ELEMENT_ARRAY_BUFFER,
-        api::Usage::Uniform => gl::UNIFORM_BUFFER
+        api::Usage::Uniform => gl::UNIFORM_BUFFER,
+        api::Usage::TransferSrc => gl::PIXEL_UNPACK_BUFFER,
+        api::Usage::TransferDst => gl::PIXEL_PACK_BUFFER,
+        api::Usage::Indirect => gl::DRAW_INDIRECT_BUFFER,
     }
 }
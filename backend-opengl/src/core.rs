@@ -1,5 +1,7 @@
 use core::borrow::Borrow;
 use std::{fs, path::Path, rc::Rc, sync::mpsc::Receiver};
+use std::ffi::CStr;
+use std::os::raw::c_char;
 use std::os::raw::c_void;
 
 use backend_api::{
@@ -10,25 +12,44 @@ use backend_api::{
 
 use crate::Backend;
 use crate::Backend as MyBackend;
-use crate::image::{OpenGlImage, OpenGlImageView};
+use crate::image::{OpenGlImage, OpenGlImageView, OpenGlSampler};
+use crate::pipeline::OpenGlDescriptor;
 
 #[derive(Clone)]
 pub struct OpenGLRendererDevice {
     gl_api: Rc<gl::Gl>,
+    uniform_alignment: u32,
+    ///`GL_EXT_texture_compression_s3tc` is present, BC1 and BC3 images can be created.
+    s3tc: bool,
 }
 
 impl OpenGLRendererDevice {
     pub fn new(gl_api: Rc<gl::Gl>) -> Self {
-        OpenGLRendererDevice { gl_api }
+        let mut uniform_alignment = 0;
+        let mut extensions = 0;
+        let s3tc = unsafe {
+            gl_api.GetIntegerv(gl::UNIFORM_BUFFER_OFFSET_ALIGNMENT, &mut uniform_alignment);
+            gl_api.GetIntegerv(gl::NUM_EXTENSIONS, &mut extensions);
+            //cubes are filtered across the edges of their faces, as Vulkan always does
+            gl_api.Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
+            (0..extensions.max(0) as u32)
+                .map(|i| CStr::from_ptr(gl_api.GetStringi(gl::EXTENSIONS, i) as *const c_char))
+                .any(|name| name.to_bytes() == b"GL_EXT_texture_compression_s3tc")
+        };
+        OpenGLRendererDevice { gl_api, uniform_alignment: uniform_alignment.max(1) as u32, s3tc }
     }
 }
 
 impl RendererDevice<Backend> for OpenGLRendererDevice {
-    fn allocate_memory(&self, size: u32) -> <Backend as api::Backend>::Memory {
-        crate::memory::OpenGlMemory::allocate(size)
+    fn memory_types(&self) -> Vec<api::memory::MemoryType> {
+        crate::memory::memory_types()
+    }
+
+    fn allocate_memory(&self, memory_type: api::memory::TypeId, size: u32) -> Result<<Backend as api::Backend>::Memory, api::Error> {
+        crate::memory::OpenGlMemory::allocate(&self.gl_api, memory_type, size)
     }
 
-    fn map_memory(&self, memory: &<Backend as api::Backend>::Memory) -> *mut u8 {
+    fn map_memory(&self, memory: &<Backend as api::Backend>::Memory) -> Result<*mut u8, api::Error> {
         memory.map_memory(&self.gl_api)
     }
 
@@ -40,71 +61,85 @@ impl RendererDevice<Backend> for OpenGLRendererDevice {
         memory.unmap_memory(&self.gl_api)
     }
 
+    fn get_buffer_requirements(&self, buffer: &<Backend as api::Backend>::Buffer) -> api::memory::Requirements {
+        api::memory::Requirements {
+            size: buffer.size,
+            alignment: if buffer.target == gl::UNIFORM_BUFFER { self.uniform_alignment } else { 4 },
+            type_mask: 1 << crate::memory::DEVICE_LOCAL | 1 << crate::memory::HOST_VISIBLE,
+        }
+    }
+
     fn bind_buffer_memory(&self,
-                          memory: &mut <Backend as api::Backend>::Memory,
-                          buffer: &<Backend as api::Backend>::Buffer) {
-        memory.bind_buffer(buffer)
+                          memory: &<Backend as api::Backend>::Memory,
+                          offset: u32,
+                          buffer: &mut <Backend as api::Backend>::Buffer) -> Result<(), api::Error> {
+        if !self.get_buffer_requirements(buffer).fits(memory.memory_type, memory.size, offset) {
+            return Err(api::Error::InvalidMemoryBinding { offset });
+        }
+        buffer.bind_memory(memory, offset);
+        Ok(())
     }
 
-    fn create_buffer(&self, desc: api::BufferDescriptor) -> <Backend as api::Backend>::Buffer {
-        crate::buffer_v2::OpenGlBuffer::new(&self.gl_api, desc)
+    fn create_buffer(&self, desc: api::BufferDescriptor) -> Result<<Backend as api::Backend>::Buffer, api::Error> {
+        Ok(crate::buffer_v2::OpenGlBuffer::new(desc))
     }
 
-    fn create_pipeline(&self, desc: api::PipelineDescriptor<Backend>) -> <Backend as api::Backend>::Pipeline {
-        unsafe {
-            crate::pipeline::OpenGlPipeline::new(&self.gl_api, desc)
-                .expect("err")
-        }
+    fn create_pipeline(&self, desc: api::PipelineDescriptor<Backend>) -> Result<<Backend as api::Backend>::Pipeline, api::Error> {
+        unsafe { crate::pipeline::OpenGlPipeline::new(&self.gl_api, desc) }
     }
 
     fn create_cmd_buffer(&self) -> <Backend as api::Backend>::CommandBuffer {
         crate::pipeline::OpenGlCommandBuffer::new()
     }
 
-    fn allocate_descriptor_set(&self, desc: &<Backend as api::Backend>::DescriptorSetLayout) -> <Backend as api::Backend>::DescriptorSet {
-        crate::pipeline::OpenGlDescriptorSet {}
+    fn allocate_descriptor_set(&self, desc: &<Backend as api::Backend>::DescriptorSetLayout) -> Result<<Backend as api::Backend>::DescriptorSet, api::Error> {
+        Ok(crate::pipeline::OpenGlDescriptorSet::default())
     }
 
-    fn execute(&self, mut cmd: <Backend as api::Backend>::CommandBuffer) {
+    fn execute(&self, mut cmd: <Backend as api::Backend>::CommandBuffer) -> Result<(), api::Error> {
         unsafe { cmd.execute(&self.gl_api); };
+        Ok(())
     }
 
-    fn create_shader_mod(&self, desc: api::ShaderModDescriptor) -> <Backend as api::Backend>::ShaderMod {
+    fn create_shader_mod(&self, desc: api::ShaderModDescriptor) -> Result<<Backend as api::Backend>::ShaderMod, api::Error> {
         crate::shader_mod::OpenGlShaderMod::new(&self.gl_api, desc)
-            .expect("err")
     }
 
-    fn create_descriptor_set_layout(&self, bindings: &[api::DescriptorSetLayoutBinding]) -> <Backend as api::Backend>::DescriptorSetLayout {
-        crate::pipeline::OpenGlDescriptorSetLayout::new(bindings)
+    fn create_descriptor_set_layout(&self, bindings: &[api::DescriptorSetLayoutBinding]) -> Result<<Backend as api::Backend>::DescriptorSetLayout, api::Error> {
+        Ok(crate::pipeline::OpenGlDescriptorSetLayout::new(bindings))
     }
 
-    fn create_pipeline_layout<I>(&self, desc_layout: &<Backend as api::Backend>::DescriptorSetLayout, hints: I) -> <Backend as api::Backend>::PipelineLayout
+    fn create_pipeline_layout<I>(&self, desc_layout: &<Backend as api::Backend>::DescriptorSetLayout, hints: I) -> Result<<Backend as api::Backend>::PipelineLayout, api::Error>
         where
             I: IntoIterator<Item=api::PipelineLayoutHint>, {
         crate::pipeline::OpenGlPipelineLayout::new(desc_layout, hints)
     }
 
     fn write_descriptor_set(&self, desc_set_write: api::DescriptorSetWrite<Backend>) {
-        match &desc_set_write.descriptor {
-            api::Descriptor::Buffer(buffer) => {
-                unsafe { self.gl_api.BindBufferBase(buffer.target, desc_set_write.binding, buffer.id); };
+        let descriptor = match &desc_set_write.descriptor {
+            api::Descriptor::Buffer(buffer) => OpenGlDescriptor::Buffer((*buffer).clone()),
+            api::Descriptor::CombinedImageSampler(view, sampler) => {
+                OpenGlDescriptor::CombinedImageSampler((*view).clone(), (*sampler).clone())
             }
         };
+        //applied right away as well for sets that are never bound
+        unsafe { descriptor.apply(&self.gl_api, desc_set_write.binding) };
+        desc_set_write.set.write(desc_set_write.binding, descriptor);
     }
 
     fn create_render_pass<A>(
         &self, attachments: A,
-    ) -> <Backend as api::Backend>::RenderPass
+    ) -> Result<<Backend as api::Backend>::RenderPass, api::Error>
         where
             A: IntoIterator<Item=api::Attachment> {
-        crate::pipeline::OpenGlRenderPass::new(attachments)
+        Ok(crate::pipeline::OpenGlRenderPass::new(attachments))
     }
 
     fn create_framebuffer<I>(
         &self,
         render_pass: &<Backend as api::Backend>::RenderPass,
         attachments: I,
-    ) -> <Backend as api::Backend>::Framebuffer
+    ) -> Result<<Backend as api::Backend>::Framebuffer, api::Error>
         where
             I: IntoIterator,
             I::Item: Borrow<<Backend as api::Backend>::ImageView> {
@@ -114,24 +149,54 @@ impl RendererDevice<Backend> for OpenGLRendererDevice {
     fn create_swapchain(
         &self,
         surface: &<Backend as api::Backend>::Surface,
-    ) -> (<Backend as api::Backend>::Swapchain, Vec<<Backend as api::Backend>::Image>) {
-        (crate::swapchain::OpenGlSwapchain::new(surface), Vec::new())
+    ) -> Result<(<Backend as api::Backend>::Swapchain, Vec<<Backend as api::Backend>::Image>), api::Error> {
+        Ok((crate::swapchain::OpenGlSwapchain::new(surface), Vec::new()))
+    }
+
+    fn create_image(&self, kind: api::image::Kind, format: api::image::Format) -> Result<<Backend as api::Backend>::Image, api::Error> {
+        use api::image::{Format, Kind};
+        if format.is_compressed() {
+            if let Kind::D1(..) | Kind::D3(..) = kind {
+                return Err(api::Error::Unsupported(format!("{:?} image of {:?}", format, kind)));
+            }
+        }
+        if let (Format::Bc1, false) | (Format::Bc3, false) = (format, self.s3tc) {
+            return Err(api::Error::Unsupported(format!("{:?} without GL_EXT_texture_compression_s3tc", format)));
+        }
+        Ok(unsafe { OpenGlImage::new(&self.gl_api, kind, format) })
+    }
+
+    fn create_image_view(&self, image: &<Backend as api::Backend>::Image) -> Result<<Backend as api::Backend>::ImageView, api::Error> {
+        Ok(image.clone())
     }
 
-    fn create_image(&self, kind: api::image::Kind) -> <Backend as api::Backend>::Image {
-        unsafe { OpenGlImage::new(&self.gl_api, kind) }
+    fn create_sampler(&self, desc: api::image::SamplerDesc) -> Result<<Backend as api::Backend>::Sampler, api::Error> {
+        Ok(unsafe { OpenGlSampler::new(&self.gl_api, &desc) })
+    }
+
+    fn get_image_requirements(&self, image: &<Backend as api::Backend>::Image) -> api::memory::Requirements {
+        api::memory::Requirements {
+            size: image.size(),
+            alignment: 4,
+            type_mask: 1 << crate::memory::DEVICE_LOCAL,
+        }
     }
 
-    fn create_image_view(&self, image: &<Backend as api::Backend>::Image) -> <Backend as api::Backend>::ImageView {
-        image.clone()
+    fn read_pixels(&self, image: &<Backend as api::Backend>::Image, level: api::image::Level) -> Result<Vec<u8>, api::Error> {
+        Ok(unsafe { image.read(&self.gl_api, level) })
     }
 
     fn bind_image_memory(
         &self, mem:
-        &mut <Backend as api::Backend>::Memory,
-        img: &<Backend as api::Backend>::Image
-    ) {
-        mem.bind_image(img)
+        &<Backend as api::Backend>::Memory,
+        offset: u32,
+        img: &mut <Backend as api::Backend>::Image
+    ) -> Result<(), api::Error> {
+        if !self.get_image_requirements(img).fits(mem.memory_type, mem.size, offset) {
+            return Err(api::Error::InvalidMemoryBinding { offset });
+        }
+        unsafe { img.allocate_storage(&self.gl_api) };
+        Ok(())
     }
 }
 
@@ -175,4 +240,52 @@ impl RendererApi<Backend> for OpenGLRendererApi {
     fn set_clear_color(&self, r: f32, g: f32, b: f32, a: f32) {
         unsafe { self.gl_api.ClearColor(r, g, b, a); }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use api::{CommandBuffer, PlatformManager};
+
+    use super::*;
+
+    #[test]
+    #[ignore] //needs a display with OpenGL 3.3
+    fn restores_the_window_viewport_after_passes_and_buffers() {
+        let mut pm = crate::PlatformManager::new(api::WindowConfig { width: 8, height: 8 });
+        let (renderer, device) = pm.create_renderer();
+        renderer.viewport(8, 8);
+        let state = || unsafe {
+            let mut viewport = [0; 4];
+            device.gl_api.GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+            (viewport, device.gl_api.IsEnabled(gl::SCISSOR_TEST) == gl::TRUE)
+        };
+
+        let mut image = device.create_image(api::image::Kind::D2(2, 2, 1), api::image::Format::Rgba8).unwrap();
+        let mem = device.allocate_memory(0, device.get_image_requirements(&image).size).unwrap();
+        device.bind_image_memory(&mem, 0, &mut image).unwrap();
+        let render_pass = device.create_render_pass(vec![api::Attachment {
+            layout: api::AttachmentLayout::Color,
+            format: api::image::Format::Rgba8,
+            load: api::LoadOp::Load,
+            store: api::StoreOp::Store,
+        }]).unwrap();
+        let framebuffer = device.create_framebuffer(&render_pass, vec![&image]).unwrap();
+
+        let corner = api::Rect { x: 1, y: 1, width: 1, height: 1 };
+        let mut cmd = device.create_cmd_buffer();
+        cmd.set_viewport(corner);
+        cmd.set_scissor(Some(corner));
+        cmd.begin_render_pass(&render_pass, &framebuffer, &[]);
+        cmd.set_viewport(corner);
+        cmd.set_scissor(Some(corner));
+        cmd.end_render_pass();
+        device.execute(cmd).unwrap();
+        assert_eq!(state(), ([0, 0, 8, 8], false));
+
+        //a viewport set outside of passes lasts until the end of its buffer
+        let mut cmd = device.create_cmd_buffer();
+        cmd.set_viewport(corner);
+        cmd.set_scissor(Some(corner));
+        device.execute(cmd).unwrap();
+        assert_eq!(state(), ([0, 0, 8, 8], false));
+    }
+}
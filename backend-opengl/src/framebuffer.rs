@@ -5,9 +5,12 @@ use gl::Gl;
 use crate::Backend;
 use crate::image::OpenGlImage;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct OpenGlFramebuffer {
-    id: u32
+    id: u32,
+    pub(crate) extent: (u32, u32),
+    ///Draw buffer index of each attachment, `None` for depth and stencil ones.
+    pub(crate) draw_buffers: Vec<Option<u32>>,
 }
 
 impl OpenGlFramebuffer {
@@ -16,54 +19,74 @@ impl OpenGlFramebuffer {
         render_pass: &<Backend as api::Backend>::RenderPass,
         attachments: A,
     )
-        -> Self
+        -> Result<Self, api::Error>
         where A: IntoIterator<Item=I>,
               I: Borrow<OpenGlImage> {
-        let fb = OpenGlFramebuffer {
-            id: Self::generate(gl)
-        };
+        let images: Vec<I> = attachments.into_iter().collect();
+        for (i, a) in images.iter().enumerate() {
+            render_pass.attachments.get(i)
+                .filter(|attachment| attachment.format == a.borrow().format)
+                .ok_or(api::Error::InvalidAttachment { index: i })?;
+        }
+        if images.len() != render_pass.attachments.len() {
+            return Err(api::Error::InvalidAttachment { index: images.len().min(render_pass.attachments.len()) });
+        }
+        let extent = images.first()
+            .map(|a| a.borrow().kind.extent())
+            .ok_or(api::Error::InvalidAttachment { index: 0 })?;
+        if images.iter().any(|a| a.borrow().kind.extent() != extent) {
+            return Err(api::Error::IncompleteFramebuffer("Attachments differ in size".to_owned()));
+        }
 
-        for (i, a) in attachments.into_iter().enumerate() {
+        let mut fb = OpenGlFramebuffer {
+            id: Self::generate(gl),
+            extent: (extent.0, extent.1),
+            draw_buffers: Vec::with_capacity(images.len()),
+        };
+        let mut colors = 0;
+        fb.bind(gl);
+        for (a, attachment) in images.iter().zip(&render_pass.attachments) {
             let img: &OpenGlImage = a.borrow();
-            println!("{:?}", img);
-            println!("{:?}", render_pass.attachments.get(i));
-            fb.attach(gl, render_pass.attachments.get(i).unwrap(), img);
+            let point = match attachment.layout {
+                api::AttachmentLayout::Color => gl::COLOR_ATTACHMENT0 + colors,
+                api::AttachmentLayout::Depth if img.format.has_stencil() => gl::DEPTH_STENCIL_ATTACHMENT,
+                api::AttachmentLayout::Depth => gl::DEPTH_ATTACHMENT,
+                api::AttachmentLayout::Stencil => gl::STENCIL_ATTACHMENT,
+            };
+            if let api::AttachmentLayout::Color = attachment.layout {
+                fb.draw_buffers.push(Some(colors));
+                colors += 1;
+            } else {
+                fb.draw_buffers.push(None);
+            }
+            gl.FramebufferTexture2D(gl::FRAMEBUFFER, point, gl::TEXTURE_2D, img.id, 0);
+        }
+
+        let buffers: Vec<u32> = (0..colors).map(|i| gl::COLOR_ATTACHMENT0 + i).collect();
+        if buffers.is_empty() {
+            gl.DrawBuffer(gl::NONE);
+        } else {
+            gl.DrawBuffers(buffers.len() as i32, buffers.as_ptr());
+        }
+        let status = gl.CheckFramebufferStatus(gl::FRAMEBUFFER);
+        fb.unbind(gl);
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(api::Error::IncompleteFramebuffer(format!("Status 0x{:X}", status)));
         }
-//        panic!();
-        fb
+        Ok(fb)
     }
 
-    unsafe fn bind(&self, gl: &Gl) {
+    pub(crate) unsafe fn bind(&self, gl: &Gl) {
         gl.BindFramebuffer(gl::FRAMEBUFFER, self.id)
     }
 
-    unsafe fn unbind(&self, gl: &Gl) {
+    pub(crate) unsafe fn unbind(&self, gl: &Gl) {
         gl.BindFramebuffer(gl::FRAMEBUFFER, 0)
     }
 
-    unsafe fn attach(&self, gl: &Gl, attachment: &api::Attachment, img: &OpenGlImage) {
-        //glGetIntegerv(GL_DRAW_FRAMEBUFFER_BINDING, &drawFboId);
-        //glGetIntegerv(GL_READ_FRAMEBUFFER_BINDING, &readFboId);
-        self.bind(gl);
-        gl.FramebufferTexture2D(
-            gl::FRAMEBUFFER,
-            match attachment.layout {
-                api::AttachmentLayout::Color => gl::COLOR_ATTACHMENT0,
-                api::AttachmentLayout::Depth => gl::DEPTH_ATTACHMENT,
-                api::AttachmentLayout::Stencil => gl::STENCIL_ATTACHMENT,
-            },
-            gl::TEXTURE_2D,
-            img.id,
-            0
-        );
-        self.unbind(gl)
-    }
-
-
     unsafe fn generate(gl: &gl::Gl) -> u32 {
         let mut id: gl::types::GLuint = 0;
         gl.GenFramebuffers(1, &mut id);
         id
     }
 }
-
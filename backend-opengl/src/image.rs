@@ -3,51 +3,190 @@ use std::os::raw::c_void;
 use api::image;
 use gl::Gl;
 
+use crate::buffer_v2::OpenGlBuffer;
+
+///S3TC formats of `GL_EXT_texture_compression_s3tc`, missing from the core profile bindings.
+const COMPRESSED_RGBA_S3TC_DXT1_EXT: u32 = 0x83F1;
+const COMPRESSED_RGBA_S3TC_DXT5_EXT: u32 = 0x83F3;
+
 #[derive(Debug, Clone)]
 pub struct OpenGlImage {
     pub(crate)id: u32,
-    kind: image::Kind,
+    pub(crate) kind: image::Kind,
+    pub(crate) format: image::Format,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct OpenGlSampler {
     id: u32,
 }
 
 
 impl OpenGlImage {
-    pub unsafe fn new(gl: &Gl, kind: image::Kind) -> Self {
-        OpenGlImage { id: Self::generate(gl), kind }
+    pub unsafe fn new(gl: &Gl, kind: image::Kind, format: image::Format) -> Self {
+        OpenGlImage { id: Self::generate(gl), kind, format }
     }
 
-    pub unsafe fn bind_mem(&self, gl: &Gl, data: &[u8]) {
+    fn target(&self) -> u32 {
         match self.kind {
-            image::Kind::D1(_, _) => unreachable!(),
-            image::Kind::D2(width, heigth, level) =>
-                Self::bind_d2(gl, self.id, width, heigth, level, data),
-            image::Kind::D3(_, _, _) => unreachable!(),
+            image::Kind::D1(_, _) => gl::TEXTURE_1D,
+            image::Kind::D2(_, _, _) => gl::TEXTURE_2D,
+            image::Kind::D3(_, _, _) => gl::TEXTURE_3D,
+            image::Kind::Cube(_, _) => gl::TEXTURE_CUBE_MAP,
+        }
+    }
+
+    ///Target of each of the `faces` of a cube, or the image's target for the other kinds.
+    fn face_targets(&self, faces: std::ops::Range<u32>) -> Vec<u32> {
+        match self.target() {
+            gl::TEXTURE_CUBE_MAP => faces.map(|face| gl::TEXTURE_CUBE_MAP_POSITIVE_X + face).collect(),
+            target => vec![target],
+        }
+    }
+
+    ///Texels are read from `buffer` bound as the pixel unpack buffer.
+    pub(crate) unsafe fn copy_from(&self, gl: &Gl, buffer: &OpenGlBuffer, region: &image::BufferImageCopy) {
+        let target = self.target();
+        let level = i32::from(region.level);
+        let (x, y, z) = (region.offset.0 as i32, region.offset.1 as i32, region.offset.2 as i32);
+        let (width, height, depth) = (region.extent.0 as i32, region.extent.1 as i32, region.extent.2 as i32);
+        let pixels = (buffer.offset + region.buffer_offset) as usize as *const c_void;
+        let (internal, format, data_type) = to_gl_format(self.format);
+
+        gl.BindBuffer(gl::PIXEL_UNPACK_BUFFER, buffer.id);
+        gl.BindTexture(target, self.id);
+        //faces of a cube are uploaded one at a time from consecutive layers of the region
+        let layer = self.format.size_of((region.extent.0, region.extent.1, 1)) as usize;
+        let faces = self.face_targets(region.offset.2..region.offset.2 + region.extent.2);
+        for (i, &face) in faces.iter().enumerate() {
+            let pixels = (pixels as usize + layer * i) as *const c_void;
+            if self.format.is_compressed() {
+                let size = match face {
+                    gl::TEXTURE_3D => self.format.size_of(region.extent) as i32,
+                    _ => layer as i32,
+                };
+                match face {
+                    gl::TEXTURE_3D => gl.CompressedTexSubImage3D(face, level, x, y, z, width, height, depth, internal, size, pixels),
+                    _ => gl.CompressedTexSubImage2D(face, level, x, y, width, height, internal, size, pixels),
+                }
+            } else {
+                match face {
+                    gl::TEXTURE_1D => gl.TexSubImage1D(face, level, x, width, format, data_type, pixels),
+                    gl::TEXTURE_3D => gl.TexSubImage3D(face, level, x, y, z, width, height, depth, format, data_type, pixels),
+                    _ => gl.TexSubImage2D(face, level, x, y, width, height, format, data_type, pixels),
+                }
+            }
         }
+        gl.BindTexture(target, 0);
+        gl.BindBuffer(gl::PIXEL_UNPACK_BUFFER, 0);
     }
 
-    pub unsafe fn bind_d2(gl: &Gl, id: u32, width: u32, height: u32, level: u16, data: &[u8]) {
-        gl.BindTexture(gl::TEXTURE_2D, id);
+    ///Texels are written to `buffer` bound as the pixel pack buffer. Whole levels are read from the texture,
+    ///regions only from uncompressed 2D images attached to a temporary read framebuffer.
+    pub(crate) unsafe fn copy_to(&self, gl: &Gl, buffer: &OpenGlBuffer, region: &image::BufferImageCopy) {
+        let pixels = (buffer.offset + region.buffer_offset) as usize as *mut c_void;
+        gl.BindBuffer(gl::PIXEL_PACK_BUFFER, buffer.id);
+        if region.offset == (0, 0, 0) && region.extent == self.kind.level_extent(region.level) {
+            self.read_level(gl, region.level, pixels);
+        } else {
+            assert!(self.target() == gl::TEXTURE_2D && !self.format.is_compressed(),
+                    "Regions of {:?} {:?} can not be read back", self.kind, self.format);
+            let (_, format, data_type) = to_gl_format(self.format);
+            let attachment = match (self.format.is_depth(), self.format.has_stencil()) {
+                (false, _) => gl::COLOR_ATTACHMENT0,
+                (true, false) => gl::DEPTH_ATTACHMENT,
+                (true, true) => gl::DEPTH_STENCIL_ATTACHMENT,
+            };
+            let mut fbo = 0;
+            gl.GenFramebuffers(1, &mut fbo);
+            gl.BindFramebuffer(gl::READ_FRAMEBUFFER, fbo);
+            gl.FramebufferTexture2D(gl::READ_FRAMEBUFFER, attachment, gl::TEXTURE_2D, self.id, i32::from(region.level));
+            gl.PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl.ReadPixels(region.offset.0 as i32, region.offset.1 as i32,
+                          region.extent.0 as i32, region.extent.1 as i32,
+                          format, data_type, pixels);
+            gl.BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+            gl.DeleteFramebuffers(1, &fbo);
+        }
+        gl.BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+    }
 
-        //TODO: Configurable
-        gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-        gl.TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+    ///Whole `level` into host memory.
+    pub(crate) unsafe fn read(&self, gl: &Gl, level: image::Level) -> Vec<u8> {
+        let mut texels = vec![0u8; self.format.size_of(self.kind.level_extent(level)) as usize];
+        self.read_level(gl, level, texels.as_mut_ptr() as *mut c_void);
+        texels
+    }
 
-        gl.TexImage2D(gl::TEXTURE_2D,
-                      level as i32,
-                      gl::RGBA as i32,
-                      width as i32,
-                      height as i32,
-                      0,
-                      gl::RGBA,
-                      gl::UNSIGNED_INT,
-                      data.as_ptr() as *const c_void,
-        );
+    unsafe fn read_level(&self, gl: &Gl, level: image::Level, pixels: *mut c_void) {
+        let target = self.target();
+        let (_, format, data_type) = to_gl_format(self.format);
+        let (width, height, faces) = self.kind.level_extent(level);
+        let layer = self.format.size_of((width, height, 1)) as usize;
+        gl.BindTexture(target, self.id);
+        gl.PixelStorei(gl::PACK_ALIGNMENT, 1);
+        for (i, face) in self.face_targets(0..faces).into_iter().enumerate() {
+            let pixels = (pixels as usize + layer * i) as *mut c_void;
+            if self.format.is_compressed() {
+                gl.GetCompressedTexImage(face, i32::from(level), pixels);
+            } else {
+                gl.GetTexImage(face, i32::from(level), format, data_type, pixels);
+            }
+        }
+        gl.BindTexture(target, 0);
+    }
+
+    pub(crate) unsafe fn generate_mipmaps(&self, gl: &Gl) {
+        let target = self.target();
+        gl.BindTexture(target, self.id);
+        gl.GenerateMipmap(target);
+        gl.BindTexture(target, 0);
+    }
 
-        gl.BindTexture(gl::TEXTURE_2D, 0);
+    pub(crate) unsafe fn bind_unit(&self, gl: &Gl, unit: u32) {
+        gl.ActiveTexture(gl::TEXTURE0 + unit);
+        gl.BindTexture(self.target(), self.id);
+    }
+
+    ///Bytes of every level.
+    pub(crate) fn size(&self) -> u32 {
+        (0..self.kind.levels())
+            .map(|level| self.format.size_of(self.kind.level_extent(level)))
+            .sum()
+    }
+
+    ///Textures are not backed by the bound memory, storage of every level is allocated by GL here.
+    pub(crate) unsafe fn allocate_storage(&self, gl: &Gl) {
+        let target = self.target();
+        let (internal, format, data_type) = to_gl_format(self.format);
+        let null = std::ptr::null();
+
+        gl.BindTexture(target, self.id);
+        for level in 0..self.kind.levels() {
+            let extent = self.kind.level_extent(level);
+            let (width, height, depth) = (extent.0 as i32, extent.1 as i32, extent.2 as i32);
+            let level = i32::from(level);
+            for face in self.face_targets(0..6) {
+                if self.format.is_compressed() {
+                    let size = match face {
+                        gl::TEXTURE_3D => self.format.size_of(extent) as i32,
+                        _ => self.format.size_of((extent.0, extent.1, 1)) as i32,
+                    };
+                    match face {
+                        gl::TEXTURE_3D => gl.CompressedTexImage3D(face, level, internal, width, height, depth, 0, size, null),
+                        _ => gl.CompressedTexImage2D(face, level, internal, width, height, 0, size, null),
+                    }
+                } else {
+                    match face {
+                        gl::TEXTURE_1D => gl.TexImage1D(face, level, internal as i32, width, 0, format, data_type, null),
+                        gl::TEXTURE_3D => gl.TexImage3D(face, level, internal as i32, width, height, depth, 0, format, data_type, null),
+                        _ => gl.TexImage2D(face, level, internal as i32, width, height, 0, format, data_type, null),
+                    }
+                }
+            }
+        }
+        gl.TexParameteri(target, gl::TEXTURE_MAX_LEVEL, i32::from(self.kind.levels()) - 1);
+        gl.BindTexture(target, 0);
     }
 
     unsafe fn generate(gl: &gl::Gl) -> u32 {
@@ -59,8 +198,59 @@ impl OpenGlImage {
 
 
 impl OpenGlSampler {
-    pub fn new(id: u32, gl: &Gl) -> Self {
-        OpenGlSampler { id: 0 }
+    pub unsafe fn new(gl: &Gl, desc: &image::SamplerDesc) -> Self {
+        let mut id = 0;
+        gl.GenSamplers(1, &mut id);
+
+        let min_filter = match (desc.min_filter, desc.mip_filter) {
+            (image::Filter::Nearest, None) => gl::NEAREST,
+            (image::Filter::Linear, None) => gl::LINEAR,
+            (image::Filter::Nearest, Some(image::Filter::Nearest)) => gl::NEAREST_MIPMAP_NEAREST,
+            (image::Filter::Nearest, Some(image::Filter::Linear)) => gl::NEAREST_MIPMAP_LINEAR,
+            (image::Filter::Linear, Some(image::Filter::Nearest)) => gl::LINEAR_MIPMAP_NEAREST,
+            (image::Filter::Linear, Some(image::Filter::Linear)) => gl::LINEAR_MIPMAP_LINEAR,
+        };
+        gl.SamplerParameteri(id, gl::TEXTURE_MIN_FILTER, min_filter as i32);
+        gl.SamplerParameteri(id, gl::TEXTURE_MAG_FILTER, to_gl_filter(desc.mag_filter) as i32);
+        gl.SamplerParameteri(id, gl::TEXTURE_WRAP_S, to_gl_wrap(desc.wrap.0) as i32);
+        gl.SamplerParameteri(id, gl::TEXTURE_WRAP_T, to_gl_wrap(desc.wrap.1) as i32);
+        gl.SamplerParameteri(id, gl::TEXTURE_WRAP_R, to_gl_wrap(desc.wrap.2) as i32);
+        OpenGlSampler { id }
+    }
+
+    pub(crate) unsafe fn bind_unit(&self, gl: &Gl, unit: u32) {
+        gl.BindSampler(unit, self.id);
+    }
+}
+
+///Internal format, pixel format and pixel type of uploads.
+pub(crate) fn to_gl_format(format: image::Format) -> (u32, u32, u32) {
+    match format {
+        image::Format::Rgba8 => (gl::RGBA8, gl::RGBA, gl::UNSIGNED_BYTE),
+        image::Format::Rgba8Srgb => (gl::SRGB8_ALPHA8, gl::RGBA, gl::UNSIGNED_BYTE),
+        image::Format::Rgba16F => (gl::RGBA16F, gl::RGBA, gl::HALF_FLOAT),
+        image::Format::R32F => (gl::R32F, gl::RED, gl::FLOAT),
+        image::Format::D24S8 => (gl::DEPTH24_STENCIL8, gl::DEPTH_STENCIL, gl::UNSIGNED_INT_24_8),
+        image::Format::D32F => (gl::DEPTH_COMPONENT32F, gl::DEPTH_COMPONENT, gl::FLOAT),
+        image::Format::Bc1 => (COMPRESSED_RGBA_S3TC_DXT1_EXT, gl::RGBA, gl::UNSIGNED_BYTE),
+        image::Format::Bc3 => (COMPRESSED_RGBA_S3TC_DXT5_EXT, gl::RGBA, gl::UNSIGNED_BYTE),
+        image::Format::Bc4 => (gl::COMPRESSED_RED_RGTC1, gl::RED, gl::UNSIGNED_BYTE),
+        image::Format::Bc5 => (gl::COMPRESSED_RG_RGTC2, gl::RG, gl::UNSIGNED_BYTE),
+    }
+}
+
+fn to_gl_filter(filter: image::Filter) -> u32 {
+    match filter {
+        image::Filter::Nearest => gl::NEAREST,
+        image::Filter::Linear => gl::LINEAR,
+    }
+}
+
+fn to_gl_wrap(wrap: image::WrapMode) -> u32 {
+    match wrap {
+        image::WrapMode::Tile => gl::REPEAT,
+        image::WrapMode::Mirror => gl::MIRRORED_REPEAT,
+        image::WrapMode::Clamp => gl::CLAMP_TO_EDGE,
     }
 }
 
@@ -172,6 +172,7 @@ impl Renderer {
             let last_enable_cull_face = gl.IsEnabled(gl::CULL_FACE) == gl::TRUE;
             let last_enable_depth_test = gl.IsEnabled(gl::DEPTH_TEST) == gl::TRUE;
             let last_enable_scissor_test = gl.IsEnabled(gl::SCISSOR_TEST) == gl::TRUE;
+            let last_enable_stencil_test = gl.IsEnabled(gl::STENCIL_TEST) == gl::TRUE;
 
 
             gl.Enable(gl::BLEND);
@@ -179,6 +180,7 @@ impl Renderer {
             gl.BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
             gl.Disable(gl::CULL_FACE);
             gl.Disable(gl::DEPTH_TEST);
+            gl.Disable(gl::STENCIL_TEST);
             gl.Enable(gl::SCISSOR_TEST);
             gl.PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
 
@@ -278,6 +280,7 @@ impl Renderer {
             if last_enable_cull_face { gl.Enable(gl::CULL_FACE) } else { gl.Disable(gl::CULL_FACE) };
             if last_enable_depth_test { gl.Enable(gl::DEPTH_TEST) } else { gl.Disable(gl::DEPTH_TEST) };
             if last_enable_scissor_test { gl.Enable(gl::SCISSOR_TEST) } else { gl.Disable(gl::SCISSOR_TEST) };
+            if last_enable_stencil_test { gl.Enable(gl::STENCIL_TEST) } else { gl.Disable(gl::STENCIL_TEST) };
             gl.PolygonMode(gl::FRONT_AND_BACK, last_polygon_mode[0] as _);
             gl.Viewport(last_viewport[0] as _, last_viewport[1] as _, last_viewport[2] as _, last_viewport[3] as _);
             gl.Scissor(last_scissor_box[0] as _, last_scissor_box[1] as _, last_scissor_box[2] as _,  last_scissor_box[3] as _);
@@ -1,60 +1,86 @@
-use std::sync::Arc;
+use std::sync::Mutex;
 
 use gl::Gl;
 
-use crate::buffer_v2::OpenGlBuffer;
-use crate::image::OpenGlImage;
-
+///Memory is one buffer object, buffers bound to it are ranges of that object.
+///Textures keep their own storage, the range reserved for them only accounts for their size.
 pub struct OpenGlMemory {
-    capacity: u32,
-    pub(crate) kind: Kind,
-    storage: Option<Arc<*mut u8>>,
+    pub(crate) id: u32,
+    pub(crate) memory_type: api::memory::TypeId,
+    pub(crate) size: u32,
+    ///Map count and the pointer returned by the first map.
+    mapped: Mutex<(u32, usize)>,
 }
 
 unsafe impl Send for OpenGlMemory {}
 
 unsafe impl Sync for OpenGlMemory {}
 
-impl OpenGlMemory {
-    pub fn allocate(capacity: u32) -> Self {
-        OpenGlMemory { capacity, kind: Kind::Unbinded(false), storage: None }
-    }
+pub(crate) const DEVICE_LOCAL: api::memory::TypeId = 0;
+pub(crate) const HOST_VISIBLE: api::memory::TypeId = 1;
 
-    pub fn bind_image(&mut self, image: &OpenGlImage) {
-        self.kind = Kind::Texture(image.clone())
-    }
+pub(crate) fn memory_types() -> Vec<api::memory::MemoryType> {
+    use api::memory::Properties;
+    vec![
+        api::memory::MemoryType { properties: Properties::DEVICE_LOCAL },
+        api::memory::MemoryType { properties: Properties::HOST_VISIBLE | Properties::HOST_COHERENT },
+    ]
+}
 
-    pub fn bind_buffer(&mut self, buffer: &OpenGlBuffer) {
-        self.kind = Kind::Buffer(buffer.clone());
-    }
-    pub fn map_memory(&self, gl: &Gl) -> *mut u8 {
-        match &self.kind {
-            Kind::Unbinded(allocated) => unimplemented!(),
-            Kind::Buffer(buffer) => crate::buffer_v2::OpenGlBuffer::mapper(gl, buffer),
-            Kind::Texture(_) => unimplemented!()
+impl OpenGlMemory {
+    pub fn allocate(gl: &Gl, memory_type: api::memory::TypeId, size: u32) -> Result<Self, api::Error> {
+        let usage = match memory_type {
+            DEVICE_LOCAL => gl::STATIC_DRAW,
+            HOST_VISIBLE => gl::DYNAMIC_DRAW,
+            _ => return Err(api::Error::Unsupported(format!("Memory type {}", memory_type))),
+        };
+        unsafe {
+            let mut id = 0;
+            gl.GenBuffers(1, &mut id);
+            gl.BindBuffer(gl::COPY_WRITE_BUFFER, id);
+            gl.BufferData(gl::COPY_WRITE_BUFFER, size as isize, std::ptr::null(), usage);
+            if gl.GetError() == gl::OUT_OF_MEMORY {
+                gl.DeleteBuffers(1, &id);
+                return Err(api::Error::OutOfMemory);
+            }
+            Ok(OpenGlMemory { id, memory_type, size, mapped: Mutex::new((0, 0)) })
         }
     }
 
-    pub fn unmap_memory(&self, gl: &Gl) {
-        match &self.kind {
-            Kind::Unbinded(allocated) => unimplemented!(),
-            Kind::Buffer(buffer) => crate::buffer_v2::OpenGlBuffer::unmap(gl, buffer),
-            Kind::Texture(_) => unimplemented!()
+    pub fn map_memory(&self, gl: &Gl) -> Result<*mut u8, api::Error> {
+        if self.memory_type != HOST_VISIBLE {
+            return Err(api::Error::Unsupported("Mapping memory which is not host visible".to_owned()));
+        }
+        let mut mapped = self.mapped.lock().unwrap();
+        if mapped.0 == 0 {
+            let ptr = unsafe {
+                gl.BindBuffer(gl::COPY_WRITE_BUFFER, self.id);
+                gl.MapBufferRange(gl::COPY_WRITE_BUFFER, 0, self.size as isize,
+                                  gl::MAP_WRITE_BIT | gl::MAP_READ_BIT) as *mut u8
+            };
+            if ptr.is_null() {
+                return Err(api::Error::Backend(format!("Memory {} mapping failed", self.id)));
+            }
+            mapped.1 = ptr as usize;
         }
+        mapped.0 += 1;
+        Ok(mapped.1 as *mut u8)
     }
 
-    pub fn flush_memory(&self, gl: &Gl) {
-        match &self.kind {
-            Kind::Unbinded(allocated) => unimplemented!(),
-            Kind::Buffer(buffer) => crate::buffer_v2::OpenGlBuffer::flush(gl, buffer),
-            Kind::Texture(_) => unimplemented!()
+    pub fn unmap_memory(&self, gl: &Gl) {
+        let mut mapped = self.mapped.lock().unwrap();
+        if mapped.0 == 0 {
+            return;
+        }
+        mapped.0 -= 1;
+        if mapped.0 == 0 {
+            unsafe {
+                gl.BindBuffer(gl::COPY_WRITE_BUFFER, self.id);
+                gl.UnmapBuffer(gl::COPY_WRITE_BUFFER);
+            }
         }
     }
-}
-
 
-pub enum Kind {
-    Unbinded(bool),
-    Buffer(OpenGlBuffer),
-    Texture(OpenGlImage),
-}
\ No newline at end of file
+    ///Host visible memory is coherent, writes land on unmap.
+    pub fn flush_memory(&self, _gl: &Gl) {}
+}
@@ -2,7 +2,9 @@ use core::borrow::Borrow;
 use std::collections::HashMap;
 use std::ffi::c_void;
 use std::fmt;
+use std::sync::{Arc, RwLock};
 use std::mem::size_of;
+use std::ops::Range;
 use std::os::raw::c_char;
 
 use itertools::Itertools;
@@ -15,7 +17,8 @@ use gl::Gl;
 
 use crate::Backend;
 use crate::buffer_v2::OpenGlBuffer;
-use crate::pipeline::OpenGlCommand::{BindDescriptorSet, BindIndexBuffer, BindVertexBuffer, ClearScreen, DrawIndexed, DrawIndexedInstanced, PreparePipeline};
+use crate::image::{OpenGlImage, OpenGlSampler};
+use crate::pipeline::OpenGlCommand::{BeginRenderPass, BindDescriptorSet, BindIndexBuffer, BindVertexBuffer, BufferData, ClearScreen, CopyBufferToImage, CopyImageToBuffer, DrawIndexed, DrawIndexedBaseVertex, DrawIndexedIndirect, DrawIndexedInstanced, EndRenderPass, GenerateMipmaps, PreparePipeline, SetScissor, SetViewport};
 use crate::framebuffer::OpenGlFramebuffer;
 
 type GlPrimitive = gl::types::GLenum;
@@ -50,56 +53,138 @@ pub struct OpenGlPipeline {
     vao_id: VaoId,
     program_id: ProgramId,
     primitive: GlPrimitive,
+    rasterizer: api::RasterizerState,
+    depth_stencil: api::DepthStencilState,
+    blend: Vec<Option<api::BlendState>>,
     layout: Vec<(VertexBufferDescriptor, Vec<AttributeDescriptor>)>,
 
-    binding_cache: HashMap<Binding, BufferId>,
+    bound: HashMap<Binding, OpenGlBuffer>,
+    ///Buffer and byte offset the attributes of a binding point at.
+    binding_cache: HashMap<Binding, (BufferId, usize)>,
     index_buffer_cache: BufferId,
+    ///Byte offset of the bound index buffer in its memory.
+    index_offset: u32,
 
     ubo_index: HashMap<Binding, UboIndex>,
 }
 
 impl OpenGlPipeline {
-    pub unsafe fn new(gl: &Gl, desc: PipelineDescriptor<Backend>) -> Result<Self, String> {
+    pub unsafe fn new(gl: &Gl, desc: PipelineDescriptor<Backend>) -> Result<Self, api::Error> {
+        //GL 3.3 enables blending per draw buffer, but its factors and equations are shared by all of them
+        let mut states = desc.blend.iter().flatten();
+        if let Some(first) = states.next() {
+            if states.any(|state| state != first) {
+                return Err(api::Error::Unsupported("Attachments blending with different states".to_owned()));
+            }
+        }
+        let mut layout = Vec::with_capacity(desc.vertex_buffers.len());
+        for (bind, attrs) in &desc.vertex_attributes.iter().group_by(|d| d.binding) {
+            let attrs: Vec<AttributeDescriptor> = attrs.cloned().collect();
+            match desc.vertex_buffers.iter().find(|buff| buff.binding == bind as u8) {
+                Some(buff) => layout.insert(bind as usize, (buff.clone(), attrs)),
+                None => return Err(api::Error::MissingAttribute { location: attrs[0].location }),
+            }
+        }
         let program_id = create_program(&gl, &desc)?;
+        let ubo_index = match ubo_bindings(&gl, program_id, &desc.layout)
+            .and_then(|ubo_index| bind_sampler_units(gl, program_id, desc.layout).map(|_| ubo_index)) {
+            Ok(ubo_index) => ubo_index,
+            Err(e) => {
+                gl.DeleteProgram(program_id);
+                return Err(e);
+            }
+        };
         Ok(OpenGlPipeline {
             vao_id: gen_vao(gl),
             program_id: program_id,
             primitive: match desc.primitives {
+                Primitive::Points => gl::POINTS,
+                Primitive::Lines => gl::LINES,
+                Primitive::LinesStrip => gl::LINE_STRIP,
                 Primitive::Triangles => gl::TRIANGLES,
                 Primitive::TrianglesFan => gl::TRIANGLE_FAN,
                 Primitive::TrianglesStrip => gl::TRIANGLE_STRIP,
-                //TODO:[POSSIBLE_WTF]
-                Primitive::Quads => gl::TRIANGLES,
-            },
-            layout: {
-                let mut layout = Vec::with_capacity(desc.vertex_buffers.len());
-                'main: for (bind, attr) in &desc.vertex_attributes.into_iter().group_by(|d| d.binding) {
-                    for buff in desc.vertex_buffers.clone() {
-                        if buff.binding == bind as u8 {
-                            layout.insert(bind as usize, (buff.clone(), attr
-                                .map(|c| c.clone()).collect::<Vec<AttributeDescriptor>>()));
-                            continue 'main;
-                        }
-                    }
-                    //TODO:ok
-                    panic!("Not found binding {}", bind);
-                }
-                println!("{:?}", layout);
-                //TODO:ok
-                layout
             },
+            rasterizer: desc.rasterizer,
+            depth_stencil: desc.depth_stencil,
+            blend: desc.blend,
+            layout,
+            bound: HashMap::new(),
             binding_cache: HashMap::new(),
             index_buffer_cache: 0,
-            ubo_index: ubo_bindings(&gl, program_id, &desc.layout),
+            index_offset: 0,
+            ubo_index,
         })
     }
 
     pub unsafe fn prepare(&self, gl: &Gl) {
         gl.BindVertexArray(self.vao_id);
         gl.UseProgram(self.program_id);
-        gl.Enable(gl::CULL_FACE);
-        gl.CullFace(gl::BACK);
-        gl.Enable(gl::DEPTH_TEST)
+        self.prepare_rasterizer(gl);
+        self.prepare_depth_stencil(gl);
+
+        //disabling blending disables it for every draw buffer, those blending enable it again
+        gl.Disable(gl::BLEND);
+        if let Some(blend) = self.blend.iter().flatten().next() {
+            gl.BlendFuncSeparate(to_gl_blend_factor(blend.src_color),
+                                 to_gl_blend_factor(blend.dst_color),
+                                 to_gl_blend_factor(blend.src_alpha),
+                                 to_gl_blend_factor(blend.dst_alpha));
+            gl.BlendEquationSeparate(to_gl_blend_op(blend.color_op), to_gl_blend_op(blend.alpha_op));
+        }
+        for (i, _) in self.blend.iter().enumerate().filter(|(_, blend)| blend.is_some()) {
+            gl.Enablei(gl::BLEND, i as u32);
+        }
+    }
+
+    unsafe fn prepare_rasterizer(&self, gl: &Gl) {
+        let rasterizer = &self.rasterizer;
+        match rasterizer.cull_mode {
+            api::CullMode::None => gl.Disable(gl::CULL_FACE),
+            mode => {
+                gl.Enable(gl::CULL_FACE);
+                gl.CullFace(if mode == api::CullMode::Front { gl::FRONT } else { gl::BACK });
+            }
+        }
+        gl.FrontFace(match rasterizer.front_face {
+            api::FrontFace::CounterClockwise => gl::CCW,
+            api::FrontFace::Clockwise => gl::CW,
+        });
+        let (mode, offset) = match rasterizer.polygon_mode {
+            api::PolygonMode::Fill => (gl::FILL, gl::POLYGON_OFFSET_FILL),
+            api::PolygonMode::Line => (gl::LINE, gl::POLYGON_OFFSET_LINE),
+            api::PolygonMode::Point => (gl::POINT, gl::POLYGON_OFFSET_POINT),
+        };
+        gl.PolygonMode(gl::FRONT_AND_BACK, mode);
+        for cap in [gl::POLYGON_OFFSET_FILL, gl::POLYGON_OFFSET_LINE, gl::POLYGON_OFFSET_POINT] {
+            gl.Disable(cap);
+        }
+        if let Some(bias) = rasterizer.depth_bias {
+            gl.Enable(offset);
+            gl.PolygonOffset(bias.slope, bias.constant);
+        }
+        gl.LineWidth(rasterizer.line_width);
+    }
+
+    unsafe fn prepare_depth_stencil(&self, gl: &Gl) {
+        let depth_stencil = &self.depth_stencil;
+        gl.Enable(gl::DEPTH_TEST);
+        gl.DepthFunc(to_gl_compare(depth_stencil.depth_compare));
+        gl.DepthMask(if depth_stencil.depth_write { gl::TRUE } else { gl::FALSE });
+        match depth_stencil.stencil {
+            Some(stencil) => {
+                gl.Enable(gl::STENCIL_TEST);
+                for (face, state) in [(gl::FRONT, stencil.front), (gl::BACK, stencil.back)] {
+                    gl.StencilFuncSeparate(face, to_gl_compare(state.compare), stencil.reference as i32, stencil.read_mask);
+                    gl.StencilOpSeparate(face,
+                                         to_gl_stencil_op(state.fail),
+                                         to_gl_stencil_op(state.depth_fail),
+                                         to_gl_stencil_op(state.pass));
+                }
+                gl.StencilMask(stencil.write_mask);
+            }
+            None => gl.Disable(gl::STENCIL_TEST),
+        }
     }
 
     pub unsafe fn bind_index(&mut self, buffer: &OpenGlBuffer, gl: &Gl) {
@@ -107,6 +192,7 @@ impl OpenGlPipeline {
             buffer.bind(gl);
             self.index_buffer_cache = buffer.id;
         }
+        self.index_offset = buffer.offset;
     }
 
     pub unsafe fn bind_descriptors(&mut self, gl: &Gl, bindings: &Vec<u32>) {
@@ -118,37 +204,58 @@ impl OpenGlPipeline {
     }
 
     pub unsafe fn bind_buffer(&mut self, binding: u32, buffer: &OpenGlBuffer, gl: &Gl) {
+        self.bound.insert(binding, buffer.clone());
+        self.point_attributes(binding, 0, gl);
+    }
+
+    ///GL 3.3 has no base instance, per-instance attributes are pointed `first` elements into their buffers instead.
+    pub(crate) unsafe fn first_instance(&mut self, first: u32, gl: &Gl) {
+        let instanced: Vec<Binding> = self.bound.keys()
+            .filter(|binding| matches!(self.layout.get(**binding as usize), Some((_, attrs)) if per_instance(attrs)))
+            .copied()
+            .collect();
+        for binding in instanced {
+            self.point_attributes(binding, first, gl);
+        }
+    }
+
+    unsafe fn point_attributes(&mut self, binding: u32, first_instance: u32, gl: &Gl) {
         let (buff, attrs) = self.layout
             .get(binding as usize)
             .unwrap();
+        let buffer = self.bound.get(&binding).unwrap();
+        let base = buffer.offset as usize + if per_instance(attrs) { first_instance as usize * buff.stride } else { 0 };
+        if self.binding_cache.get(&binding) == Some(&(buffer.id, base)) {
+            return;
+        }
 
-        if !self.binding_cache.contains_key(&binding) {
-            buffer.bind(gl);
-            for attr in attrs {
-                match attr.data.data_type {
-                    api::DataType::Vec3f32 => {
-                        OpenGlPipeline::vertex_pointer(gl, buff, attr, attr.data.offset, attr.location);
-                    }
-                    api::DataType::Vec2f32 => {
-                        OpenGlPipeline::vertex_pointer(gl, buff, attr, attr.data.offset, attr.location);
-                    }
-                    api::DataType::Mat4f32 => {
-                        OpenGlPipeline::vertex_pointer(gl, buff, attr, attr.data.offset + size_of::<[f32; 4]>() * 0, attr.location + 0);
-                        OpenGlPipeline::vertex_pointer(gl, buff, attr, attr.data.offset + size_of::<[f32; 4]>() * 1, attr.location + 1);
-                        OpenGlPipeline::vertex_pointer(gl, buff, attr, attr.data.offset + size_of::<[f32; 4]>() * 2, attr.location + 2);
-                        OpenGlPipeline::vertex_pointer(gl, buff, attr, attr.data.offset + size_of::<[f32; 4]>() * 3, attr.location + 3);
-                        gl.VertexAttribDivisor(attr.location + 0, 1);
-                        gl.VertexAttribDivisor(attr.location + 1, 1);
-                        gl.VertexAttribDivisor(attr.location + 2, 1);
-                        gl.VertexAttribDivisor(attr.location + 3, 1);
-                    }
-                };
+        buffer.bind(gl);
+        for attr in attrs {
+            match attr.data.data_type {
+                api::DataType::Float32 => {
+                    OpenGlPipeline::vertex_pointer(gl, buff, attr, base + attr.data.offset, attr.location);
+                    gl.VertexAttribDivisor(attr.location, if per_instance(attrs) { 1 } else { 0 });
+                }
+                api::DataType::Vec3f32 => {
+                    OpenGlPipeline::vertex_pointer(gl, buff, attr, base + attr.data.offset, attr.location);
+                }
+                api::DataType::Vec2f32 => {
+                    OpenGlPipeline::vertex_pointer(gl, buff, attr, base + attr.data.offset, attr.location);
+                }
+                api::DataType::Mat4f32 => {
+                    OpenGlPipeline::vertex_pointer(gl, buff, attr, base + attr.data.offset + size_of::<[f32; 4]>() * 0, attr.location + 0);
+                    OpenGlPipeline::vertex_pointer(gl, buff, attr, base + attr.data.offset + size_of::<[f32; 4]>() * 1, attr.location + 1);
+                    OpenGlPipeline::vertex_pointer(gl, buff, attr, base + attr.data.offset + size_of::<[f32; 4]>() * 2, attr.location + 2);
+                    OpenGlPipeline::vertex_pointer(gl, buff, attr, base + attr.data.offset + size_of::<[f32; 4]>() * 3, attr.location + 3);
+                    gl.VertexAttribDivisor(attr.location + 0, 1);
+                    gl.VertexAttribDivisor(attr.location + 1, 1);
+                    gl.VertexAttribDivisor(attr.location + 2, 1);
+                    gl.VertexAttribDivisor(attr.location + 3, 1);
+                }
             };
+        };
 
-            self.binding_cache.insert(binding, buffer.id);
-        } else {
-            println!("{:?}", self.binding_cache);
-        }
+        self.binding_cache.insert(binding, (buffer.id, base));
     }
 
     unsafe fn vertex_pointer(
@@ -160,11 +267,13 @@ impl OpenGlPipeline {
     ) {
         gl.VertexAttribPointer(location.into(),
                                match attr.data.data_type {
+                                   api::DataType::Float32 => 1,
                                    api::DataType::Vec3f32 => 3,
                                    api::DataType::Vec2f32 => 2,
                                    api::DataType::Mat4f32 => 4,
                                },
                                match attr.data.data_type {
+                                   api::DataType::Float32 => gl::FLOAT,
                                    api::DataType::Vec3f32 => gl::FLOAT,
                                    api::DataType::Vec2f32 => gl::FLOAT,
                                    api::DataType::Mat4f32 => gl::FLOAT,
@@ -176,9 +285,68 @@ impl OpenGlPipeline {
     }
 }
 
-unsafe fn ubo_bindings(gl: &Gl, program: ProgramId, pipeline_layout: &OpenGlPipelineLayout) -> HashMap<Binding, UboIndex> {
+fn to_gl_compare(op: api::CompareOp) -> gl::types::GLenum {
+    match op {
+        api::CompareOp::Never => gl::NEVER,
+        api::CompareOp::Less => gl::LESS,
+        api::CompareOp::Equal => gl::EQUAL,
+        api::CompareOp::LessOrEqual => gl::LEQUAL,
+        api::CompareOp::Greater => gl::GREATER,
+        api::CompareOp::NotEqual => gl::NOTEQUAL,
+        api::CompareOp::GreaterOrEqual => gl::GEQUAL,
+        api::CompareOp::Always => gl::ALWAYS,
+    }
+}
+
+fn to_gl_stencil_op(op: api::StencilOp) -> gl::types::GLenum {
+    match op {
+        api::StencilOp::Keep => gl::KEEP,
+        api::StencilOp::Zero => gl::ZERO,
+        api::StencilOp::Replace => gl::REPLACE,
+        api::StencilOp::IncrementAndClamp => gl::INCR,
+        api::StencilOp::DecrementAndClamp => gl::DECR,
+        api::StencilOp::Invert => gl::INVERT,
+        api::StencilOp::IncrementAndWrap => gl::INCR_WRAP,
+        api::StencilOp::DecrementAndWrap => gl::DECR_WRAP,
+    }
+}
+
+fn to_gl_blend_factor(factor: api::BlendFactor) -> gl::types::GLenum {
+    match factor {
+        api::BlendFactor::Zero => gl::ZERO,
+        api::BlendFactor::One => gl::ONE,
+        api::BlendFactor::SrcColor => gl::SRC_COLOR,
+        api::BlendFactor::OneMinusSrcColor => gl::ONE_MINUS_SRC_COLOR,
+        api::BlendFactor::DstColor => gl::DST_COLOR,
+        api::BlendFactor::OneMinusDstColor => gl::ONE_MINUS_DST_COLOR,
+        api::BlendFactor::SrcAlpha => gl::SRC_ALPHA,
+        api::BlendFactor::OneMinusSrcAlpha => gl::ONE_MINUS_SRC_ALPHA,
+        api::BlendFactor::DstAlpha => gl::DST_ALPHA,
+        api::BlendFactor::OneMinusDstAlpha => gl::ONE_MINUS_DST_ALPHA,
+    }
+}
+
+fn to_gl_blend_op(op: api::BlendOp) -> gl::types::GLenum {
+    match op {
+        api::BlendOp::Add => gl::FUNC_ADD,
+        api::BlendOp::Subtract => gl::FUNC_SUBTRACT,
+        api::BlendOp::ReverseSubtract => gl::FUNC_REVERSE_SUBTRACT,
+        api::BlendOp::Min => gl::MIN,
+        api::BlendOp::Max => gl::MAX,
+    }
+}
+
+///Bindings holding a matrix are advanced per instance, everything else per vertex.
+fn per_instance(attrs: &[AttributeDescriptor]) -> bool {
+    attrs.iter().any(|attr| attr.data.data_type == api::DataType::Mat4f32)
+}
+
+unsafe fn ubo_bindings(gl: &Gl, program: ProgramId, pipeline_layout: &OpenGlPipelineLayout) -> Result<HashMap<Binding, UboIndex>, api::Error> {
     let mut indexes = HashMap::new();
     for (binding, (desc, hint)) in &pipeline_layout.layout {
+        if let api::DescriptorType::Sampler = desc.desc {
+            continue;
+        }
         let index = if hint.is_some() {
             match hint.as_ref().unwrap().hint {
                 api::LayoutHint::Name(string) => {
@@ -191,26 +359,55 @@ unsafe fn ubo_bindings(gl: &Gl, program: ProgramId, pipeline_layout: &OpenGlPipe
             *binding
         };
         if index == gl::INVALID_INDEX {
-            panic!("Invalid index binding:{:?}, resolved:{:?}", binding, index)
+            return Err(api::Error::InvalidUniformBlock {
+                binding: *binding,
+                name: hint.as_ref().map(|h| match h.hint {
+                    api::LayoutHint::Name(name) => name.to_owned(),
+                }),
+            });
         }
         indexes.insert(*binding, index);
     }
-    indexes
+    Ok(indexes)
+}
+
+///Texture unit of a sampler is its binding, samplers are found by their name hint.
+unsafe fn bind_sampler_units(gl: &Gl, program: ProgramId, pipeline_layout: &OpenGlPipelineLayout) -> Result<(), api::Error> {
+    gl.UseProgram(program);
+    for (binding, (desc, hint)) in &pipeline_layout.layout {
+        if let (api::DescriptorType::Sampler, Some(hint)) = (&desc.desc, hint) {
+            let api::LayoutHint::Name(name) = hint.hint;
+            let string = name.to_owned() + "\0";
+            let location = gl.GetUniformLocation(program, string.as_str().as_ptr() as *const c_char);
+            if location < 0 {
+                gl.UseProgram(0);
+                return Err(api::Error::InvalidUniformBlock { binding: *binding, name: Some(name.to_owned()) });
+            }
+            gl.Uniform1i(location, *binding as i32);
+        }
+    }
+    gl.UseProgram(0);
+    Ok(())
 }
 
-unsafe fn create_program(gl: &Gl, desc: &PipelineDescriptor<Backend>) -> Result<ProgramId, String> {
+unsafe fn create_program(gl: &Gl, desc: &PipelineDescriptor<Backend>) -> Result<ProgramId, api::Error> {
     let program = gl.CreateProgram();
     gl.AttachShader(program, desc.shader_set.vertex.id);
     gl.AttachShader(program, desc.shader_set.fragment.id);
     gl.LinkProgram(program);
     gl.DetachShader(program, desc.shader_set.vertex.id);
     gl.DetachShader(program, desc.shader_set.fragment.id);
-    validate_program(gl, program)?;
-    validate_attrs(gl, program, desc)
+    let linked = validate_program(gl, program)
+        .map_err(api::Error::Link)
+        .and_then(|program| validate_attrs(gl, program, desc));
+    if linked.is_err() {
+        gl.DeleteProgram(program);
+    }
+    linked
 }
 
 unsafe fn validate_attrs(gl: &Gl, id: ProgramId, desc: &PipelineDescriptor<Backend>)
-                         -> Result<ProgramId, String> {
+                         -> Result<ProgramId, api::Error> {
     let (mut len, mut name) = {
         let mut len: gl::types::GLint = 0;
         gl.GetProgramiv(id, gl::ACTIVE_ATTRIBUTE_MAX_LENGTH, &mut len);
@@ -244,24 +441,29 @@ unsafe fn validate_attrs(gl: &Gl, id: ProgramId, desc: &PipelineDescriptor<Backe
 
     for attr in &desc.vertex_attributes {
         let gl_attr = get_attr(attr, &gl_attrs)?;
-        assert_eq!(gl_attr.0, attr.location as i32);
-        assert_eq!(gl_attr.1, match attr.data.data_type {
+        let expected = match attr.data.data_type {
+            api::DataType::Float32 => gl::FLOAT,
             api::DataType::Vec3f32 => gl::FLOAT_VEC3,
             api::DataType::Vec2f32 => gl::FLOAT_VEC2,
             api::DataType::Mat4f32 => gl::FLOAT_MAT4,
-        });
-        assert_eq!(gl_attr.2, 1);
+        };
+        if gl_attr.1 != expected || gl_attr.2 != 1 {
+            return Err(api::Error::AttributeTypeMismatch {
+                location: attr.location,
+                expected: attr.data.data_type.clone(),
+            });
+        }
     }
     Ok(id)
 }
 
-fn get_attr(attr: &AttributeDescriptor, gl_attrs: &Vec<(i32, u32, i32)>) -> Result<(i32, u32, i32), String> {
+fn get_attr(attr: &AttributeDescriptor, gl_attrs: &Vec<(i32, u32, i32)>) -> Result<(i32, u32, i32), api::Error> {
     for gl_attr in gl_attrs {
         if gl_attr.0 == attr.location as i32 {
             return Ok(*gl_attr);
         }
     }
-    Err(format!("Not found attr {:?}", attr))
+    Err(api::Error::MissingAttribute { location: attr.location })
 }
 
 fn validate_program(gl: &Gl, id: ProgramId) -> Result<ProgramId, String> {
@@ -319,7 +521,7 @@ pub struct OpenGlPipelineLayout {
 }
 
 impl OpenGlPipelineLayout {
-    pub fn new<I>(desc_layout: &<Backend as api::Backend>::DescriptorSetLayout, hints: I) -> Self
+    pub fn new<I>(desc_layout: &<Backend as api::Backend>::DescriptorSetLayout, hints: I) -> Result<Self, api::Error>
         where
             I: IntoIterator<Item=api::PipelineLayoutHint>, {
         let mut map: HashMap<u32, (api::DescriptorSetLayoutBinding, Option<api::PipelineLayoutHint>)>
@@ -330,15 +532,57 @@ impl OpenGlPipelineLayout {
 
 
         for h in hints {
-            let (desc, hint) = map.get(&h.location).unwrap();
+            let (desc, hint) = map.get(&h.location)
+                .ok_or_else(|| api::Error::InvalidUniformBlock {
+                    binding: h.location,
+                    name: match h.hint {
+                        api::LayoutHint::Name(name) => Some(name.to_owned()),
+                    },
+                })?;
             map.insert(h.location, (desc.clone(), Some(h)));
         }
-        OpenGlPipelineLayout { layout: map }
+        Ok(OpenGlPipelineLayout { layout: map })
     }
 }
 
-#[derive(Debug)]
-pub struct OpenGlDescriptorSet {}
+///Descriptor of a set, applied to the context whenever the set is bound.
+#[derive(Debug, Clone)]
+pub(crate) enum OpenGlDescriptor {
+    Buffer(OpenGlBuffer),
+    CombinedImageSampler(OpenGlImage, OpenGlSampler),
+}
+
+impl OpenGlDescriptor {
+    pub(crate) unsafe fn apply(&self, gl: &Gl, binding: Binding) {
+        match self {
+            OpenGlDescriptor::Buffer(buffer) => {
+                gl.BindBufferRange(buffer.target, binding, buffer.id, buffer.offset as isize, buffer.size as isize);
+            }
+            OpenGlDescriptor::CombinedImageSampler(view, sampler) => {
+                view.bind_unit(gl, binding);
+                sampler.bind_unit(gl, binding);
+            }
+        }
+    }
+}
+
+///Descriptors written so far, shared by the commands binding the set.
+#[derive(Debug, Clone, Default)]
+pub struct OpenGlDescriptorSet {
+    writes: Arc<RwLock<HashMap<Binding, OpenGlDescriptor>>>,
+}
+
+impl OpenGlDescriptorSet {
+    pub(crate) fn write(&self, binding: Binding, descriptor: OpenGlDescriptor) {
+        self.writes.write().unwrap().insert(binding, descriptor);
+    }
+
+    unsafe fn bind(&self, gl: &Gl) {
+        for (binding, descriptor) in self.writes.read().unwrap().iter() {
+            descriptor.apply(gl, *binding);
+        }
+    }
+}
 
 const DESCRIPTORS_LIMIT: usize = 10;
 
@@ -347,10 +591,24 @@ enum OpenGlCommand {
     PreparePipeline(OpenGlPipeline),
     BindVertexBuffer(u32, OpenGlBuffer),
     BindIndexBuffer(OpenGlBuffer),
-    BindDescriptorSet(Vec<u32>),
+    ///Uniform block bindings of the pipeline layout and the set.
+    BindDescriptorSet(Vec<u32>, OpenGlDescriptorSet),
+    BufferData(OpenGlBuffer, Vec<u8>),
+    CopyBufferToImage(OpenGlBuffer, OpenGlImage, api::image::BufferImageCopy),
+    CopyImageToBuffer(OpenGlImage, OpenGlBuffer, api::image::BufferImageCopy),
+    GenerateMipmaps(OpenGlImage),
     DrawIndexed(u32, u32),
     DrawIndexedInstanced(u32, u32, u32),
+    ///Index count, first index byte offset, base vertex and instances.
+    DrawIndexedBaseVertex(u32, u32, i32, Range<u32>),
+    ///Arguments buffer, its byte offset, draw count and stride.
+    DrawIndexedIndirect(OpenGlBuffer, u32, u32, u32),
     ClearScreen((f32, f32, f32, f32)),
+    ///Framebuffer with the load op of every attachment and their clear values.
+    BeginRenderPass(OpenGlFramebuffer, Vec<api::LoadOp>, Vec<api::ClearValue>),
+    EndRenderPass,
+    SetViewport(api::Rect),
+    SetScissor(Option<api::Rect>),
 }
 
 #[derive(Debug)]
@@ -366,6 +624,10 @@ impl OpenGlCommandBuffer {
     pub(crate) unsafe fn execute(&mut self, gl: &Gl) {
         //TODO: Make not like an idiot
         let mut pipeline: Option<&mut OpenGlPipeline> = None;
+        //window viewport to restore once a render pass ends and after the buffer, which may change it
+        let mut window = [0; 4];
+        gl.GetIntegerv(gl::VIEWPORT, window.as_mut_ptr());
+        let mut in_pass = false;
         for cmd in &mut self.cmds {
             match cmd {
                 PreparePipeline(p) => {
@@ -380,34 +642,138 @@ impl OpenGlCommandBuffer {
                     pipeline.as_mut().unwrap()
                         .bind_index(buffer, gl);
                 }
+                BufferData(buffer, data) => {
+                    buffer.sub_data(gl, data);
+                }
+                CopyBufferToImage(buffer, image, region) => {
+                    image.copy_from(gl, buffer, region);
+                }
+                CopyImageToBuffer(image, buffer, region) => {
+                    image.copy_to(gl, buffer, region);
+                }
+                GenerateMipmaps(image) => {
+                    image.generate_mipmaps(gl);
+                }
                 DrawIndexed(count, offset) => {
+                    let pipeline = pipeline.as_mut().unwrap();
+                    pipeline.first_instance(0, gl);
                     gl.DrawElements(
-                        pipeline.as_ref().unwrap().primitive,
+                        pipeline.primitive,
                         *count as i32,
                         gl::UNSIGNED_INT,
-                        *offset as *const c_void,
+                        (pipeline.index_offset + *offset) as usize as *const c_void,
                     );
                 }
                 ClearScreen((r, g, b, a)) => {
+                    //depth and stencil are only cleared where they may be written, which the last pipeline may have masked
+                    gl.DepthMask(gl::TRUE);
+                    gl.StencilMask(!0);
                     gl.ClearColor(*r, *g, *b, *a);
-                    gl.Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+                    gl.ClearStencil(0);
+                    gl.Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT);
+                }
+                BeginRenderPass(framebuffer, loads, clear_values) => {
+                    assert!(!in_pass, "Render pass begun inside another");
+                    in_pass = true;
+                    framebuffer.bind(gl);
+                    gl.Viewport(0, 0, framebuffer.extent.0 as i32, framebuffer.extent.1 as i32);
+                    gl.Disable(gl::SCISSOR_TEST);
+                    clear_attachments(gl, framebuffer, loads, clear_values);
+                }
+                EndRenderPass => {
+                    assert!(in_pass, "Render pass ended without begin");
+                    in_pass = false;
+                    gl.BindFramebuffer(gl::FRAMEBUFFER, 0);
+                    let [x, y, width, height] = window;
+                    gl.Viewport(x, y, width, height);
+                    gl.Disable(gl::SCISSOR_TEST);
                 }
-                BindDescriptorSet(bindings) => {
+                SetViewport(rect) => {
+                    gl.Viewport(rect.x, rect.y, rect.width, rect.height);
+                }
+                SetScissor(Some(rect)) => {
+                    gl.Enable(gl::SCISSOR_TEST);
+                    gl.Scissor(rect.x, rect.y, rect.width, rect.height);
+                }
+                SetScissor(None) => {
+                    gl.Disable(gl::SCISSOR_TEST);
+                }
+                BindDescriptorSet(bindings, set) => {
                     pipeline.as_mut()
                         .unwrap()
                         .bind_descriptors(gl, bindings);
+                    set.bind(gl);
                 }
                 OpenGlCommand::DrawIndexedInstanced(count, offset, number) => {
+                    let pipeline = pipeline.as_mut().unwrap();
+                    pipeline.first_instance(0, gl);
                     gl.DrawElementsInstanced(
-                        pipeline.as_ref().unwrap().primitive,
+                        pipeline.primitive,
                         *count as i32,
                         gl::UNSIGNED_INT,
-                        *offset as *const c_void,
+                        (pipeline.index_offset + *offset) as usize as *const c_void,
                         *number as i32,
                     )
                 }
+                DrawIndexedBaseVertex(count, offset, base_vertex, instances) => {
+                    let pipeline = pipeline.as_mut().unwrap();
+                    pipeline.first_instance(instances.start, gl);
+                    gl.DrawElementsInstancedBaseVertex(
+                        pipeline.primitive,
+                        *count as i32,
+                        gl::UNSIGNED_INT,
+                        (pipeline.index_offset + *offset) as usize as *const c_void,
+                        (instances.end - instances.start) as i32,
+                        *base_vertex,
+                    )
+                }
+                DrawIndexedIndirect(buffer, offset, count, stride) => {
+                    let pipeline = pipeline.as_mut().unwrap();
+                    //the commands' first index can not account for the offset of the bound index buffer
+                    if gl.MultiDrawElementsIndirect.is_loaded() && pipeline.index_offset == 0 {
+                        pipeline.first_instance(0, gl);
+                        buffer.bind(gl);
+                        gl.MultiDrawElementsIndirect(
+                            pipeline.primitive,
+                            gl::UNSIGNED_INT,
+                            (buffer.offset + *offset) as usize as *const c_void,
+                            *count as i32,
+                            *stride as i32,
+                        );
+                    } else {
+                        for i in 0..*count {
+                            let args = api::DrawIndexedIndirectCommand::from_bytes(&buffer.get_sub_data(gl, *offset + i * *stride, size_of::<api::DrawIndexedIndirectCommand>() as u32));
+                            let indices = (pipeline.index_offset + args.first_index * size_of::<u32>() as u32) as usize as *const c_void;
+                            if gl.DrawElementsInstancedBaseVertexBaseInstance.is_loaded() {
+                                pipeline.first_instance(0, gl);
+                                gl.DrawElementsInstancedBaseVertexBaseInstance(
+                                    pipeline.primitive,
+                                    args.index_count as i32,
+                                    gl::UNSIGNED_INT,
+                                    indices,
+                                    args.instance_count as i32,
+                                    args.base_vertex,
+                                    args.first_instance,
+                                );
+                            } else {
+                                pipeline.first_instance(args.first_instance, gl);
+                                gl.DrawElementsInstancedBaseVertex(
+                                    pipeline.primitive,
+                                    args.index_count as i32,
+                                    gl::UNSIGNED_INT,
+                                    indices,
+                                    args.instance_count as i32,
+                                    args.base_vertex,
+                                );
+                            }
+                        }
+                    }
+                }
             }
         }
+        let [x, y, width, height] = window;
+        gl.Viewport(x, y, width, height);
+        gl.Disable(gl::SCISSOR_TEST);
     }
 }
 
@@ -416,12 +782,15 @@ impl api::CommandBuffer<Backend> for OpenGlCommandBuffer {
         self.cmds.push(PreparePipeline(pipeline.clone()));
     }
 
-    fn bind_render_pass(
-        &self,
-        render_pass: &OpenGlRenderPass,
-        framebuffer: &OpenGlFramebuffer,
-    ) {
-//        unimplemented!()
+    fn begin_render_pass(&mut self, render_pass: &OpenGlRenderPass, framebuffer: &OpenGlFramebuffer, clear_values: &[api::ClearValue]) {
+        let loads = render_pass.attachments.iter()
+            .map(|a| a.load)
+            .collect();
+        self.cmds.push(BeginRenderPass(framebuffer.clone(), loads, clear_values.to_vec()))
+    }
+
+    fn end_render_pass(&mut self) {
+        self.cmds.push(EndRenderPass)
     }
 
     fn bind_vertex_buffer(&mut self, binding: u32, buffer: &<Backend as api::Backend>::Buffer) {
@@ -433,7 +802,25 @@ impl api::CommandBuffer<Backend> for OpenGlCommandBuffer {
     }
 
     fn buffer_data(&mut self, buffer: &<Backend as api::Backend>::Buffer, data: &[u8]) {
-        unimplemented!()
+        self.cmds.push(BufferData(buffer.clone(), data.to_vec()))
+    }
+
+    fn copy_buffer_to_image(&mut self,
+                            buffer: &<Backend as api::Backend>::Buffer,
+                            image: &<Backend as api::Backend>::Image,
+                            region: api::image::BufferImageCopy) {
+        self.cmds.push(CopyBufferToImage(buffer.clone(), image.clone(), region))
+    }
+
+    fn copy_image_to_buffer(&mut self,
+                            image: &<Backend as api::Backend>::Image,
+                            buffer: &<Backend as api::Backend>::Buffer,
+                            region: api::image::BufferImageCopy) {
+        self.cmds.push(CopyImageToBuffer(image.clone(), buffer.clone(), region))
+    }
+
+    fn generate_mipmaps(&mut self, image: &<Backend as api::Backend>::Image) {
+        self.cmds.push(GenerateMipmaps(image.clone()))
     }
 
     fn draw_indexed(&mut self, count: u32, offset: u32, number: u32) {
@@ -444,20 +831,61 @@ impl api::CommandBuffer<Backend> for OpenGlCommandBuffer {
         }
     }
 
+    fn draw_indexed_base_vertex(&mut self, count: u32, offset: u32, base_vertex: i32, instances: Range<u32>) {
+        self.cmds.push(DrawIndexedBaseVertex(count, offset, base_vertex, instances));
+    }
+
+    fn draw_indexed_indirect(&mut self, buffer: &<Backend as api::Backend>::Buffer, offset: u32, draw_count: u32, stride: u32) {
+        self.cmds.push(DrawIndexedIndirect(buffer.clone(), offset, draw_count, stride));
+    }
+
     fn bind_descriptor_set(&mut self, pipeline_layout: &<Backend as api::Backend>::PipelineLayout, desc_set: &<Backend as api::Backend>::DescriptorSet) {
         let bindings: Vec<u32> = (&pipeline_layout.layout)
             .into_iter()
+            .filter(|(_, (desc, _))| match desc.desc {
+                api::DescriptorType::UniformBuffer => true,
+                api::DescriptorType::Sampler => false,
+            })
             .map(|(binding, _)| *binding)
             .collect();
-        self.cmds.push(BindDescriptorSet(bindings))
+        self.cmds.push(BindDescriptorSet(bindings, desc_set.clone()))
     }
 
     fn clear_screen(&mut self, color: (f32, f32, f32, f32)) {
         self.cmds.push(ClearScreen(color))
     }
+
+    fn set_viewport(&mut self, viewport: api::Rect) {
+        self.cmds.push(SetViewport(viewport))
+    }
+
+    fn set_scissor(&mut self, scissor: Option<api::Rect>) {
+        self.cmds.push(SetScissor(scissor))
+    }
 }
 
 
+///Store ops are not applied, everything rendered is kept.
+unsafe fn clear_attachments(gl: &Gl, framebuffer: &OpenGlFramebuffer, loads: &[api::LoadOp], clear_values: &[api::ClearValue]) {
+    for (i, (draw_buffer, load)) in framebuffer.draw_buffers.iter().zip(loads).enumerate() {
+        if *load != api::LoadOp::Clear {
+            continue;
+        }
+        match (clear_values.get(i), draw_buffer) {
+            (Some(api::ClearValue::Color(color)), Some(draw_buffer)) => {
+                gl.ClearBufferfv(gl::COLOR, *draw_buffer as i32, color.as_ptr());
+            }
+            (Some(api::ClearValue::DepthStencil(depth, stencil)), None) => {
+                gl.DepthMask(gl::TRUE);
+                gl.StencilMask(!0);
+                gl.ClearBufferfi(gl::DEPTH_STENCIL, 0, *depth, *stencil as i32);
+            }
+            //attachments without a matching clear value are left as they were
+            _ => {}
+        }
+    }
+}
+
 fn to_gl_str(src: &str) -> *const c_char {
     let string = src.to_owned() + "\0";
     string.as_str().as_ptr() as *const c_char
@@ -105,11 +105,11 @@ impl PlatformManager<Backend> for GlfwPlatformManager {
             //mapping
             let e = match event {
                 glfw::WindowEvent::FramebufferSize(w, h) => Event::Resize(w, h),
-                glfw::WindowEvent::Key(key, code, action, modfifer) => {
+                glfw::WindowEvent::Key(key, _, action, modfifer) => {
                     match action {
-                        glfw::Action::Release => Event::Key(code as u32, api::Action::Release),
-                        glfw::Action::Press => Event::Key(code as u32, api::Action::Press),
-                        glfw::Action::Repeat => Event::Key(code as u32, api::Action::Repeat),
+                        glfw::Action::Release => Event::Key(key as u32, api::Action::Release),
+                        glfw::Action::Press => Event::Key(key as u32, api::Action::Press),
+                        glfw::Action::Repeat => Event::Key(key as u32, api::Action::Repeat),
                     }
                 }
                 _ => {
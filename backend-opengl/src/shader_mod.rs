@@ -12,12 +12,16 @@ pub struct OpenGlShaderMod {
 }
 
 impl OpenGlShaderMod {
-    pub fn new(gl: &Gl, desc: api::ShaderModDescriptor) -> Result<Self, String> {
+    pub fn new(gl: &Gl, desc: api::ShaderModDescriptor) -> Result<Self, api::Error> {
         let kind = match desc.stype {
             api::ShaderType::Vertex => gl::VERTEX_SHADER,
             api::ShaderType::Fragment => gl::FRAGMENT_SHADER,
         };
-        let id = shader_from_src(gl, &desc.source, kind)?;
+        let id = match &desc.source {
+            api::ShaderSource::Glsl(source) => shader_from_src(gl, source, kind)
+                .map_err(|log| api::Error::ShaderCompile { stage: desc.stype.clone(), log })?,
+            api::ShaderSource::SpirV(_) => return Err(api::Error::Unsupported("SPIR-V modules in OpenGL 3.3".to_owned())),
+        };
 
         Ok(OpenGlShaderMod { id, kind })
     }
@@ -36,7 +40,11 @@ fn shader_from_src(gl: &Gl, src: &str, kind: gl::types::GLenum) -> Result<u32, S
                         std::ptr::null());
         gl.CompileShader(shader_id);
     };
-    validate_shader(gl, shader_id)
+    let validated = validate_shader(gl, shader_id);
+    if validated.is_err() {
+        unsafe { gl.DeleteShader(shader_id) };
+    }
+    validated
 }
 
 fn validate_shader(gl: &Gl, shader_id: u32) -> Result<u32, String> {
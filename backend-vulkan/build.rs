@@ -0,0 +1,10 @@
+use std::env;
+
+fn main() {
+    //the glslang archives bevy-glsl-to-spirv links are C++ built without their runtime
+    match env::var("CARGO_CFG_TARGET_OS").as_ref().map(String::as_str) {
+        Ok("linux") => println!("cargo:rustc-link-lib=stdc++"),
+        Ok("macos") => println!("cargo:rustc-link-lib=c++"),
+        _ => {}
+    }
+}
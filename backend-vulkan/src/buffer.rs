@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use crate::core::{RawDevice, to_error};
+
+#[derive(Debug)]
+pub struct VulkanBuffer {
+    raw: Arc<RawDevice>,
+    pub(crate) buffer: vk::Buffer,
+}
+
+impl VulkanBuffer {
+    pub(crate) fn new(raw: Arc<RawDevice>, desc: api::BufferDescriptor) -> Result<VulkanBuffer, api::Error> {
+        let buffer = unsafe {
+            raw.device.create_buffer(&vk::BufferCreateInfo::builder()
+                .size(u64::from(desc.size))
+                .usage(to_vk_usage(&desc.usage)
+                    | vk::BufferUsageFlags::TRANSFER_SRC
+                    | vk::BufferUsageFlags::TRANSFER_DST)
+                .sharing_mode(vk::SharingMode::EXCLUSIVE), None)
+                .map_err(to_error)?
+        };
+        Ok(VulkanBuffer { raw, buffer })
+    }
+
+    pub(crate) fn requirements(&self) -> api::memory::Requirements {
+        crate::memory::to_requirements(unsafe { self.raw.device.get_buffer_memory_requirements(self.buffer) })
+    }
+}
+
+impl Drop for VulkanBuffer {
+    fn drop(&mut self) {
+        unsafe { self.raw.device.destroy_buffer(self.buffer, None) }
+    }
+}
+
+pub(crate) fn to_vk_usage(u: &api::Usage) -> vk::BufferUsageFlags {
+    match u {
+        api::Usage::Vertex => vk::BufferUsageFlags::VERTEX_BUFFER,
+        api::Usage::Index => vk::BufferUsageFlags::INDEX_BUFFER,
+        api::Usage::Uniform => vk::BufferUsageFlags::UNIFORM_BUFFER,
+        api::Usage::TransferSrc => vk::BufferUsageFlags::TRANSFER_SRC,
+        api::Usage::TransferDst => vk::BufferUsageFlags::TRANSFER_DST,
+        api::Usage::Indirect => vk::BufferUsageFlags::INDIRECT_BUFFER,
+    }
+}
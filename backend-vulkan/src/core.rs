@@ -0,0 +1,559 @@
+use core::borrow::Borrow;
+use std::ffi::CStr;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use ash::extensions::khr;
+use ash::version::{DeviceV1_0, EntryV1_0, InstanceV1_0};
+use ash::vk;
+
+use backend_api::{
+    RendererApi,
+    RendererDevice,
+};
+
+use crate::Backend;
+use crate::swapchain::SharedDisplay;
+
+///Sets of the first descriptor pool, each pool chained once the previous ones are exhausted holds twice as many.
+const POOL_SETS: u32 = 64;
+///Pools stop growing once they hold `POOL_SETS << POOL_GROWTH` sets.
+const POOL_GROWTH: usize = 6;
+
+///Instance, logical device and the pools every resource of the backend is created from.
+pub(crate) struct RawDevice {
+    _entry: ash::Entry,
+    pub(crate) instance: ash::Instance,
+    pub(crate) physical: vk::PhysicalDevice,
+    pub(crate) device: ash::Device,
+    pub(crate) queue: vk::Queue,
+    pub(crate) memory_properties: vk::PhysicalDeviceMemoryProperties,
+    pub(crate) depth_format: vk::Format,
+    ///Several draws per `vkCmdDrawIndexedIndirect`, issued one at a time otherwise.
+    pub(crate) multi_draw_indirect: bool,
+    pub(crate) surface_loader: khr::Surface,
+    ///Surface of the window, swapchains render offscreen without one.
+    pub(crate) surface: Option<vk::SurfaceKHR>,
+    ///Format of the images of swapchains, rgba8 when offscreen.
+    pub(crate) window_format: vk::SurfaceFormatKHR,
+    ///Pass of the window: one colour attachment of `window_format` and one depth attachment.
+    pub(crate) render_pass: vk::RenderPass,
+    pub(crate) render_pass_layout: u32,
+    ///Colour and depth formats of the passes seen so far, pipelines are built once per layout of pass.
+    pass_layouts: Mutex<Vec<Vec<(bool, vk::Format)>>>,
+    command_pool: Mutex<vk::CommandPool>,
+    descriptor_pools: Mutex<Vec<vk::DescriptorPool>>,
+}
+
+impl RawDevice {
+    ///Device presenting to the surface of `window`, or rendering offscreen without one.
+    pub(crate) fn new(window: Option<&glfw::Window>) -> Result<Arc<RawDevice>, String> {
+        unsafe {
+            let entry = ash::Entry::new().map_err(|e| format!("Vulkan loader: {}", e))?;
+
+            let app_name = CStr::from_bytes_with_nul_unchecked(b"rx_engine\0");
+            let app_info = vk::ApplicationInfo::builder()
+                .application_name(app_name)
+                .engine_name(app_name)
+                //1.1 for the negative viewport height
+                .api_version(vk_make_version!(1, 1, 0));
+            let extensions = match window {
+                Some(_) => crate::platform::required_extensions()?,
+                None => Vec::new(),
+            };
+            let instance = entry
+                .create_instance(&vk::InstanceCreateInfo::builder()
+                    .application_info(&app_info)
+                    .enabled_extension_names(&extensions), None)
+                .map_err(|e| format!("Instance creation: {}", e))?;
+            let surface_loader = khr::Surface::new(&entry, &instance);
+            let surface = match window {
+                Some(window) => Some(crate::platform::create_window_surface(&instance, window)
+                    .map_err(|e| format!("Surface creation: {}", e))?),
+                None => None,
+            };
+
+            let presents = |p: vk::PhysicalDevice, family: u32| match surface {
+                Some(surface) => surface_loader.get_physical_device_surface_support(p, family, surface),
+                None => true,
+            };
+            let (physical, queue_family) = instance.enumerate_physical_devices()
+                .map_err(|e| format!("Physical devices: {}", e))?
+                .into_iter()
+                .filter_map(|p| instance.get_physical_device_queue_family_properties(p)
+                    .iter()
+                    .enumerate()
+                    .position(|(family, q)| q.queue_flags.contains(vk::QueueFlags::GRAPHICS) && presents(p, family as u32))
+                    .map(|family| (p, family as u32)))
+                .next()
+                .ok_or_else(|| "No device with a graphics queue presenting to the window".to_owned())?;
+            let window_format = match surface {
+                Some(surface) => choose_window_format(&surface_loader
+                    .get_physical_device_surface_formats(physical, surface)
+                    .map_err(|e| format!("Surface formats: {}", e))?),
+                None => vk::SurfaceFormatKHR { format: crate::image::COLOR_FORMAT, color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR },
+            };
+
+            let priorities = [1.];
+            let queue_info = [vk::DeviceQueueCreateInfo::builder()
+                .queue_family_index(queue_family)
+                .queue_priorities(&priorities)
+                .build()];
+            let supported = instance.get_physical_device_features(physical);
+            let features = vk::PhysicalDeviceFeatures::builder()
+                .multi_draw_indirect(supported.multi_draw_indirect == vk::TRUE)
+                .draw_indirect_first_instance(supported.draw_indirect_first_instance == vk::TRUE);
+            let device_extensions = match surface {
+                Some(_) => vec![khr::Swapchain::name().as_ptr()],
+                None => Vec::new(),
+            };
+            let device = instance
+                .create_device(physical, &vk::DeviceCreateInfo::builder()
+                    .queue_create_infos(&queue_info)
+                    .enabled_extension_names(&device_extensions)
+                    .enabled_features(&features), None)
+                .map_err(|e| format!("Device creation: {}", e))?;
+            let queue = device.get_device_queue(queue_family, 0);
+
+            let depth_format = [vk::Format::D32_SFLOAT, vk::Format::X8_D24_UNORM_PACK32, vk::Format::D16_UNORM]
+                .iter()
+                .cloned()
+                .find(|f| instance.get_physical_device_format_properties(physical, *f)
+                    .optimal_tiling_features
+                    .contains(vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT))
+                .ok_or_else(|| "No supported depth format".to_owned())?;
+
+            let command_pool = device.create_command_pool(&vk::CommandPoolCreateInfo::builder()
+                .flags(vk::CommandPoolCreateFlags::TRANSIENT)
+                .queue_family_index(queue_family), None)
+                .map_err(|e| format!("Command pool: {}", e))?;
+
+            let descriptor_pool = create_descriptor_pool(&device, POOL_SETS)
+                .map_err(|e| format!("Descriptor pool: {}", e))?;
+
+            let window_layout = [
+                (api::AttachmentLayout::Color, window_format.format),
+                (api::AttachmentLayout::Depth, depth_format),
+            ];
+            let render_pass = crate::pipeline::raw_render_pass(&device, &window_layout, &[(api::LoadOp::Load, api::StoreOp::Store); 2])
+                .map_err(|e| format!("Render pass: {}", e))?;
+
+            Ok(Arc::new(RawDevice {
+                _entry: entry,
+                memory_properties: instance.get_physical_device_memory_properties(physical),
+                instance,
+                physical,
+                device,
+                queue,
+                depth_format,
+                multi_draw_indirect: supported.multi_draw_indirect == vk::TRUE,
+                surface_loader,
+                surface,
+                window_format,
+                render_pass,
+                render_pass_layout: 0,
+                pass_layouts: Mutex::new(vec![to_pass_layout(&window_layout)]),
+                command_pool: Mutex::new(command_pool),
+                descriptor_pools: Mutex::new(vec![descriptor_pool]),
+            }))
+        }
+    }
+
+    ///Index of a memory type allowed by `type_bits`, preferring one with all of `flags`.
+    pub(crate) fn memory_type(&self, type_bits: u32, flags: vk::MemoryPropertyFlags) -> Result<u32, api::Error> {
+        let types = &self.memory_properties.memory_types[..self.memory_properties.memory_type_count as usize];
+        let allowed = |i: &usize| type_bits & (1 << *i as u32) != 0;
+        (0..types.len())
+            .filter(allowed)
+            .find(|i| types[*i].property_flags.contains(flags))
+            .or_else(|| (0..types.len()).find(allowed))
+            .map(|i| i as u32)
+            .ok_or_else(|| api::Error::Unsupported("No memory type for resource".to_owned()))
+    }
+
+    ///Index of the layout of a pass with `attachments`, equal for compatible passes.
+    pub(crate) fn pass_layout(&self, attachments: &[(api::AttachmentLayout, vk::Format)]) -> u32 {
+        let layout = to_pass_layout(attachments);
+        let mut layouts = self.pass_layouts.lock().unwrap();
+        match layouts.iter().position(|known| *known == layout) {
+            Some(i) => i as u32,
+            None => {
+                layouts.push(layout);
+                layouts.len() as u32 - 1
+            }
+        }
+    }
+
+    ///Colour attachments of passes of `layout`.
+    pub(crate) fn pass_colors(&self, layout: u32) -> u32 {
+        self.pass_layouts.lock().unwrap()[layout as usize].iter()
+            .filter(|(color, _)| *color)
+            .count() as u32
+    }
+
+    ///Allocates a set of `layout` from the first pool with room for it, chaining a larger pool when none has.
+    ///Returns the set along with the pool it is freed to.
+    pub(crate) fn allocate_descriptor_set(&self, layout: vk::DescriptorSetLayout) -> Result<(vk::DescriptorPool, vk::DescriptorSet), api::Error> {
+        let mut pools = self.descriptor_pools.lock().unwrap();
+        let set_layouts = [layout];
+        let allocate = |pool: vk::DescriptorPool| unsafe {
+            self.device.allocate_descriptor_sets(&vk::DescriptorSetAllocateInfo::builder()
+                .descriptor_pool(pool)
+                .set_layouts(&set_layouts))
+                .map(|sets| (pool, sets[0]))
+        };
+        for pool in pools.iter().rev() {
+            match allocate(*pool) {
+                Ok(allocated) => return Ok(allocated),
+                Err(vk::Result::ERROR_OUT_OF_POOL_MEMORY) | Err(vk::Result::ERROR_FRAGMENTED_POOL) => {}
+                Err(e) => return Err(to_error(e)),
+            }
+        }
+        let pool = unsafe { create_descriptor_pool(&self.device, POOL_SETS << pools.len().min(POOL_GROWTH)) }
+            .map_err(to_error)?;
+        pools.push(pool);
+        allocate(pool).map_err(to_error)
+    }
+
+    pub(crate) fn free_descriptor_set(&self, pool: vk::DescriptorPool, set: vk::DescriptorSet) {
+        //pools are externally synchronised, as when allocating
+        let _pools = self.descriptor_pools.lock().unwrap();
+        unsafe { self.device.free_descriptor_sets(pool, &[set]) }
+    }
+
+    ///Records a primary command buffer with `record`, submits it and waits for completion.
+    ///Nothing is submitted when `record` fails.
+    pub(crate) fn submit<F>(&self, record: F) -> Result<(), api::Error>
+        where F: FnOnce(vk::CommandBuffer) -> Result<(), api::Error> {
+        let pool = self.command_pool.lock().unwrap();
+        unsafe {
+            let cbs = self.device.allocate_command_buffers(&vk::CommandBufferAllocateInfo::builder()
+                .command_pool(*pool)
+                .level(vk::CommandBufferLevel::PRIMARY)
+                .command_buffer_count(1))
+                .map_err(to_error)?;
+            let submitted = self.record_and_wait(cbs[0], record);
+            self.device.free_command_buffers(*pool, &cbs);
+            submitted
+        }
+    }
+
+    unsafe fn record_and_wait<F>(&self, cb: vk::CommandBuffer, record: F) -> Result<(), api::Error>
+        where F: FnOnce(vk::CommandBuffer) -> Result<(), api::Error> {
+        self.device.begin_command_buffer(cb, &vk::CommandBufferBeginInfo::builder()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT))
+            .map_err(to_error)?;
+
+        record(cb)?;
+
+        self.device.end_command_buffer(cb).map_err(to_error)?;
+        let fence = self.device.create_fence(&vk::FenceCreateInfo::default(), None)
+            .map_err(to_error)?;
+        let cbs = [cb];
+        let waited = self.device.queue_submit(self.queue, &[vk::SubmitInfo::builder().command_buffers(&cbs).build()], fence)
+            .and_then(|_| self.device.wait_for_fences(&[fence], true, u64::MAX))
+            .map_err(to_error);
+        self.device.destroy_fence(fence, None);
+        waited
+    }
+}
+
+///Rgba8 or bgra8 when the surface supports them, which the window's pass stores its colour as.
+fn choose_window_format(formats: &[vk::SurfaceFormatKHR]) -> vk::SurfaceFormatKHR {
+    let srgb = vk::ColorSpaceKHR::SRGB_NONLINEAR;
+    match formats {
+        //no preferred format
+        [vk::SurfaceFormatKHR { format: vk::Format::UNDEFINED, .. }] =>
+            vk::SurfaceFormatKHR { format: crate::image::COLOR_FORMAT, color_space: srgb },
+        _ => [crate::image::COLOR_FORMAT, vk::Format::B8G8R8A8_UNORM].iter()
+            .filter_map(|wanted| formats.iter().find(|f| f.format == *wanted && f.color_space == srgb))
+            .chain(formats.first())
+            .copied()
+            .next()
+            .unwrap_or(vk::SurfaceFormatKHR { format: crate::image::COLOR_FORMAT, color_space: srgb }),
+    }
+}
+
+fn to_pass_layout(attachments: &[(api::AttachmentLayout, vk::Format)]) -> Vec<(bool, vk::Format)> {
+    attachments.iter()
+        .map(|(layout, format)| (matches!(layout, api::AttachmentLayout::Color), *format))
+        .collect()
+}
+
+///Pool of `sets` sets with a uniform buffer and four samplers each on average.
+unsafe fn create_descriptor_pool(device: &ash::Device, sets: u32) -> Result<vk::DescriptorPool, vk::Result> {
+    let pool_sizes = [
+        vk::DescriptorPoolSize { ty: vk::DescriptorType::UNIFORM_BUFFER, descriptor_count: sets },
+        vk::DescriptorPoolSize { ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER, descriptor_count: sets * 4 },
+    ];
+    device.create_descriptor_pool(&vk::DescriptorPoolCreateInfo::builder()
+        .flags(vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET)
+        .max_sets(sets)
+        .pool_sizes(&pool_sizes), None)
+}
+
+///Maps the errors a creation call can fail with, allocation failures are reported as `OutOfMemory`.
+pub(crate) fn to_error(e: vk::Result) -> api::Error {
+    match e {
+        vk::Result::ERROR_OUT_OF_HOST_MEMORY
+        | vk::Result::ERROR_OUT_OF_DEVICE_MEMORY
+        | vk::Result::ERROR_OUT_OF_POOL_MEMORY
+        | vk::Result::ERROR_FRAGMENTED_POOL => api::Error::OutOfMemory,
+        e => api::Error::Backend(format!("{}", e)),
+    }
+}
+
+impl fmt::Debug for RawDevice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RawDevice")
+            .field("physical", &self.physical)
+            .field("depth_format", &self.depth_format)
+            .finish()
+    }
+}
+
+impl Drop for RawDevice {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.device_wait_idle().ok();
+            self.device.destroy_render_pass(self.render_pass, None);
+            for pool in self.descriptor_pools.get_mut().unwrap().drain(..) {
+                self.device.destroy_descriptor_pool(pool, None);
+            }
+            self.device.destroy_command_pool(*self.command_pool.lock().unwrap(), None);
+            self.device.destroy_device(None);
+            if let Some(surface) = self.surface {
+                self.surface_loader.destroy_surface(surface, None);
+            }
+            self.instance.destroy_instance(None);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct VulkanRendererDevice {
+    raw: Arc<RawDevice>,
+    display: SharedDisplay,
+}
+
+impl VulkanRendererDevice {
+    pub(crate) fn new(raw: Arc<RawDevice>, display: SharedDisplay) -> Self {
+        VulkanRendererDevice { raw, display }
+    }
+}
+
+impl RendererDevice<Backend> for VulkanRendererDevice {
+    fn memory_types(&self) -> Vec<api::memory::MemoryType> {
+        let properties = &self.raw.memory_properties;
+        properties.memory_types[..properties.memory_type_count as usize].iter()
+            .map(|t| {
+                let mut properties = api::memory::Properties::empty();
+                for (vk_flag, flag) in &[
+                    (vk::MemoryPropertyFlags::DEVICE_LOCAL, api::memory::Properties::DEVICE_LOCAL),
+                    (vk::MemoryPropertyFlags::HOST_VISIBLE, api::memory::Properties::HOST_VISIBLE),
+                    (vk::MemoryPropertyFlags::HOST_COHERENT, api::memory::Properties::HOST_COHERENT),
+                ] {
+                    if t.property_flags.contains(*vk_flag) {
+                        properties = properties | *flag;
+                    }
+                }
+                api::memory::MemoryType { properties }
+            })
+            .collect()
+    }
+
+    fn allocate_memory(&self, memory_type: api::memory::TypeId, size: u32) -> Result<<Backend as api::Backend>::Memory, api::Error> {
+        crate::memory::VulkanMemory::allocate(self.raw.clone(), memory_type, size)
+    }
+
+    fn map_memory(&self, memory: &<Backend as api::Backend>::Memory) -> Result<*mut u8, api::Error> {
+        memory.map_memory()
+    }
+
+    fn flush_memory(&self, memory: &<Backend as api::Backend>::Memory) {
+        memory.flush_memory()
+    }
+
+    fn unmap_memory(&self, memory: &<Backend as api::Backend>::Memory) {
+        memory.unmap_memory()
+    }
+
+    fn get_buffer_requirements(&self, buffer: &<Backend as api::Backend>::Buffer) -> api::memory::Requirements {
+        buffer.requirements()
+    }
+
+    fn bind_buffer_memory(&self,
+                          memory: &<Backend as api::Backend>::Memory,
+                          offset: u32,
+                          buffer: &mut <Backend as api::Backend>::Buffer) -> Result<(), api::Error> {
+        memory.bind_buffer(buffer, offset)
+    }
+
+    fn create_buffer(&self, desc: api::BufferDescriptor) -> Result<<Backend as api::Backend>::Buffer, api::Error> {
+        crate::buffer::VulkanBuffer::new(self.raw.clone(), desc)
+    }
+
+    fn create_pipeline(&self, desc: api::PipelineDescriptor<Backend>) -> Result<<Backend as api::Backend>::Pipeline, api::Error> {
+        crate::pipeline::VulkanPipeline::new(self.raw.clone(), desc)
+    }
+
+    fn create_cmd_buffer(&self) -> <Backend as api::Backend>::CommandBuffer {
+        crate::pipeline::VulkanCommandBuffer::new()
+    }
+
+    fn allocate_descriptor_set(&self, desc: &<Backend as api::Backend>::DescriptorSetLayout) -> Result<<Backend as api::Backend>::DescriptorSet, api::Error> {
+        crate::pipeline::VulkanDescriptorSet::allocate(self.raw.clone(), desc)
+    }
+
+    fn execute(&self, cmd: <Backend as api::Backend>::CommandBuffer) -> Result<(), api::Error> {
+        cmd.execute(&self.raw, &self.display)
+    }
+
+    fn create_shader_mod(&self, desc: api::ShaderModDescriptor) -> Result<<Backend as api::Backend>::ShaderMod, api::Error> {
+        crate::shader_mod::VulkanShaderMod::new(self.raw.clone(), desc)
+    }
+
+    fn create_descriptor_set_layout(&self, bindings: &[api::DescriptorSetLayoutBinding]) -> Result<<Backend as api::Backend>::DescriptorSetLayout, api::Error> {
+        crate::pipeline::VulkanDescriptorSetLayout::new(self.raw.clone(), bindings)
+    }
+
+    fn create_pipeline_layout<I>(&self, desc_layout: &<Backend as api::Backend>::DescriptorSetLayout, hints: I) -> Result<<Backend as api::Backend>::PipelineLayout, api::Error>
+        where
+            I: IntoIterator<Item=api::PipelineLayoutHint>, {
+        crate::pipeline::VulkanPipelineLayout::new(self.raw.clone(), desc_layout, hints)
+    }
+
+    fn write_descriptor_set(&self, desc_set_write: api::DescriptorSetWrite<Backend>) {
+        desc_set_write.set.write(desc_set_write.binding, &desc_set_write.descriptor)
+    }
+
+    fn create_render_pass<A>(
+        &self, attachments: A,
+    ) -> Result<<Backend as api::Backend>::RenderPass, api::Error>
+        where
+            A: IntoIterator<Item=api::Attachment> {
+        crate::pipeline::VulkanRenderPass::new(self.raw.clone(), attachments)
+    }
+
+    fn create_framebuffer<I>(
+        &self,
+        render_pass: &<Backend as api::Backend>::RenderPass,
+        attachments: I,
+    ) -> Result<<Backend as api::Backend>::Framebuffer, api::Error>
+        where
+            I: IntoIterator,
+            I::Item: Borrow<<Backend as api::Backend>::ImageView> {
+        crate::framebuffer::VulkanFramebuffer::new(self.raw.clone(), render_pass, attachments)
+    }
+
+    fn create_swapchain(
+        &self,
+        surface: &<Backend as api::Backend>::Surface,
+    ) -> Result<(<Backend as api::Backend>::Swapchain, Vec<<Backend as api::Backend>::Image>), api::Error> {
+        Ok((crate::swapchain::VulkanSwapchain::new(self.raw.clone(), surface)?, Vec::new()))
+    }
+
+    fn create_image(&self, kind: api::image::Kind, format: api::image::Format) -> Result<<Backend as api::Backend>::Image, api::Error> {
+        crate::image::VulkanImage::new(self.raw.clone(), kind, format)
+    }
+
+    fn create_image_view(&self, image: &<Backend as api::Backend>::Image) -> Result<<Backend as api::Backend>::ImageView, api::Error> {
+        crate::image::VulkanImageView::new(self.raw.clone(), image)
+    }
+
+    fn create_sampler(&self, desc: api::image::SamplerDesc) -> Result<<Backend as api::Backend>::Sampler, api::Error> {
+        crate::image::VulkanSampler::new(self.raw.clone(), &desc)
+    }
+
+    fn get_image_requirements(&self, image: &<Backend as api::Backend>::Image) -> api::memory::Requirements {
+        image.requirements()
+    }
+
+    ///Copies through a host visible buffer. The flipped viewport stores rendered rows from the top, so the rows of
+    ///every layer are reversed to start at the bottom like the other backends.
+    fn read_pixels(&self, image: &<Backend as api::Backend>::Image, level: api::image::Level) -> Result<Vec<u8>, api::Error> {
+        use api::CommandBuffer;
+
+        let format = crate::image::from_vk_format(image.format)
+            .ok_or_else(|| api::Error::Unsupported(format!("Reading {:?} images", image.format)))?;
+        let size = format.size_of(image.kind.level_extent(level));
+        let mut buffer = crate::buffer::VulkanBuffer::new(self.raw.clone(), api::BufferDescriptor {
+            size,
+            usage: api::Usage::TransferDst,
+        })?;
+        let req = buffer.requirements();
+        let memory_type = self.raw.memory_type(req.type_mask, vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT)?;
+        let memory = crate::memory::VulkanMemory::allocate(self.raw.clone(), memory_type as usize, req.size)?;
+        memory.bind_buffer(&mut buffer, 0)?;
+
+        let mut cmd = self.create_cmd_buffer();
+        cmd.copy_image_to_buffer(image, &buffer, api::image::BufferImageCopy::level(&image.kind, level));
+        self.execute(cmd)?;
+
+        let ptr = memory.map_memory()?;
+        let texels = unsafe { std::slice::from_raw_parts(ptr, size as usize) };
+        let (width, height, _) = image.kind.level_extent(level);
+        let row = (format.blocks(width) * format.block().1) as usize;
+        let rows = format.blocks(height) as usize;
+        let flipped = texels.chunks_exact(row * rows)
+            .flat_map(|layer| layer.chunks_exact(row).rev().flatten().copied())
+            .collect();
+        memory.unmap_memory();
+        Ok(flipped)
+    }
+
+    fn bind_image_memory(
+        &self,
+        mem: &<Backend as api::Backend>::Memory,
+        offset: u32,
+        img: &mut <Backend as api::Backend>::Image,
+    ) -> Result<(), api::Error> {
+        mem.bind_image(img, offset)
+    }
+}
+
+pub struct VulkanRendererApi {
+    raw: Arc<RawDevice>,
+    display: SharedDisplay,
+}
+
+impl VulkanRendererApi {
+    pub(crate) fn new(raw: Arc<RawDevice>, display: SharedDisplay) -> VulkanRendererApi {
+        VulkanRendererApi { raw, display }
+    }
+}
+
+impl RendererApi<Backend> for VulkanRendererApi {
+    ///Frames are handed over by `Swapchain::present`.
+    fn swap_buffer(&mut self) {}
+
+    fn clear_color(&self) {
+        let display = self.display.lock().unwrap();
+        let target = match &display.target {
+            Some(target) => target,
+            None => return,
+        };
+        let (r, g, b, a) = display.clear_color;
+        let cleared = self.raw.submit(|cb| unsafe {
+            self.raw.device.cmd_clear_color_image(
+                cb,
+                target.color,
+                vk::ImageLayout::GENERAL,
+                &vk::ClearColorValue { float32: [r, g, b, a] },
+                &[crate::image::color_range()],
+            );
+            Ok(())
+        });
+        if let Err(e) = cleared {
+            eprintln!("Window not cleared: {}", e);
+        }
+    }
+
+    fn set_clear_color(&self, r: f32, g: f32, b: f32, a: f32) {
+        self.display.lock().unwrap().clear_color = (r, g, b, a);
+    }
+
+    fn viewport(&self, w: i32, h: i32) {
+        self.display.lock().unwrap().viewport = (0, 0, w, h);
+    }
+}
@@ -0,0 +1,93 @@
+use std::borrow::Borrow;
+use std::sync::Arc;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use crate::core::{RawDevice, to_error};
+use crate::image::VulkanImageView;
+use crate::pipeline::VulkanRenderPass;
+
+///Everything needed to begin a pass instance on a framebuffer.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PassTarget {
+    pub(crate) pass: vk::RenderPass,
+    pub(crate) framebuffer: vk::Framebuffer,
+    pub(crate) extent: vk::Extent2D,
+    pub(crate) colors: u32,
+    pub(crate) depth: Option<vk::ImageAspectFlags>,
+    ///Shared by compatible passes, see `RawDevice::pass_layout`.
+    pub(crate) layout: u32,
+}
+
+#[derive(Debug)]
+pub struct VulkanFramebuffer {
+    raw: Arc<RawDevice>,
+    pub(crate) target: PassTarget,
+}
+
+impl VulkanFramebuffer {
+    pub(crate) fn new<A, I>(
+        raw: Arc<RawDevice>,
+        render_pass: &VulkanRenderPass,
+        attachments: A,
+    ) -> Result<Self, api::Error>
+        where A: IntoIterator<Item=I>,
+              I: Borrow<VulkanImageView> {
+        let views: Vec<(vk::ImageView, vk::Format, vk::Extent2D)> = attachments.into_iter()
+            .map(|a| {
+                let view: &VulkanImageView = a.borrow();
+                (view.view, view.format, view.extent)
+            })
+            .collect();
+        if views.len() != render_pass.attachments.len() {
+            return Err(api::Error::InvalidAttachment { index: views.len().min(render_pass.attachments.len()) });
+        }
+        if let Some(index) = views.iter()
+            .zip(&render_pass.attachments)
+            .position(|((_, format, _), (_, expected))| format != expected) {
+            return Err(api::Error::InvalidAttachment { index });
+        }
+        let extent = views.first()
+            .map(|(_, _, extent)| *extent)
+            .ok_or(api::Error::InvalidAttachment { index: 0 })?;
+        Self::from_raw(raw, render_pass.pass, &render_pass.attachments, views.iter().map(|(view, _, _)| *view), extent)
+    }
+
+    pub(crate) fn from_raw<I>(
+        raw: Arc<RawDevice>,
+        pass: vk::RenderPass,
+        layout: &[(api::AttachmentLayout, vk::Format)],
+        views: I,
+        extent: vk::Extent2D,
+    ) -> Result<Self, api::Error>
+        where I: IntoIterator<Item=vk::ImageView> {
+        let views: Vec<vk::ImageView> = views.into_iter().collect();
+        let framebuffer = unsafe {
+            raw.device.create_framebuffer(&vk::FramebufferCreateInfo::builder()
+                .render_pass(pass)
+                .attachments(&views)
+                .width(extent.width)
+                .height(extent.height)
+                .layers(1), None)
+                .map_err(to_error)?
+        };
+        let colors = layout.iter()
+            .filter(|(l, _)| matches!(l, api::AttachmentLayout::Color))
+            .count() as u32;
+        let depth = layout.iter()
+            .find(|(l, _)| !matches!(l, api::AttachmentLayout::Color))
+            .map(|(_, format)| crate::image::aspect(*format));
+        let layout = raw.pass_layout(layout);
+        Ok(VulkanFramebuffer {
+            raw,
+            target: PassTarget { pass, framebuffer, extent, colors, depth, layout },
+        })
+    }
+}
+
+impl Drop for VulkanFramebuffer {
+    fn drop(&mut self) {
+        unsafe { self.raw.device.destroy_framebuffer(self.target.framebuffer, None) }
+    }
+}
@@ -0,0 +1,282 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use ash::version::{DeviceV1_0, InstanceV1_0};
+use ash::vk;
+
+use api::image;
+
+use crate::core::{RawDevice, to_error};
+
+pub(crate) const COLOR_FORMAT: vk::Format = vk::Format::R8G8B8A8_UNORM;
+
+#[derive(Debug)]
+pub struct VulkanImage {
+    raw: Arc<RawDevice>,
+    pub(crate) image: vk::Image,
+    pub(crate) kind: image::Kind,
+    pub(crate) format: vk::Format,
+    pub(crate) aspect: vk::ImageAspectFlags,
+}
+
+impl VulkanImage {
+    ///Image usable in every way the device supports for `format` with optimal tiling.
+    pub(crate) fn new(raw: Arc<RawDevice>, kind: image::Kind, format: image::Format) -> Result<Self, api::Error> {
+        let vk_format = to_vk_format(format);
+        let features = unsafe {
+            raw.instance.get_physical_device_format_properties(raw.physical, vk_format).optimal_tiling_features
+        };
+        if !features.contains(vk::FormatFeatureFlags::SAMPLED_IMAGE) {
+            return Err(api::Error::Unsupported(format!("{:?} images", format)));
+        }
+        let usage = [
+            (vk::FormatFeatureFlags::SAMPLED_IMAGE, vk::ImageUsageFlags::SAMPLED),
+            (vk::FormatFeatureFlags::COLOR_ATTACHMENT, vk::ImageUsageFlags::COLOR_ATTACHMENT),
+            (vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT, vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT),
+            (vk::FormatFeatureFlags::TRANSFER_SRC, vk::ImageUsageFlags::TRANSFER_SRC),
+            (vk::FormatFeatureFlags::TRANSFER_DST, vk::ImageUsageFlags::TRANSFER_DST),
+        ].iter()
+            .filter(|(feature, _)| features.contains(*feature))
+            .fold(vk::ImageUsageFlags::empty(), |usage, (_, u)| usage | *u);
+        Self::with_format(raw, kind, vk_format, usage)
+    }
+
+    pub(crate) fn with_format(raw: Arc<RawDevice>, kind: image::Kind, format: vk::Format, usage: vk::ImageUsageFlags) -> Result<Self, api::Error> {
+        let (image_type, extent, levels) = match kind {
+            image::Kind::D1(width, levels) => (vk::ImageType::TYPE_1D, (width, 1, 1), levels),
+            image::Kind::D2(width, height, levels) => (vk::ImageType::TYPE_2D, (width, height, 1), levels),
+            image::Kind::D3(width, height, depth) => (vk::ImageType::TYPE_3D, (width, height, depth), 1),
+            image::Kind::Cube(size, levels) => (vk::ImageType::TYPE_2D, (size, size, 1), levels),
+        };
+        let flags = match kind {
+            image::Kind::Cube(..) => vk::ImageCreateFlags::CUBE_COMPATIBLE,
+            _ => vk::ImageCreateFlags::empty(),
+        };
+        let aspect = aspect(format);
+        let image = unsafe {
+            raw.device.create_image(&vk::ImageCreateInfo::builder()
+                .flags(flags)
+                .image_type(image_type)
+                .format(format)
+                .extent(vk::Extent3D { width: extent.0, height: extent.1, depth: extent.2 })
+                .mip_levels(u32::from(levels.max(1)))
+                .array_layers(array_layers(&kind))
+                .samples(vk::SampleCountFlags::TYPE_1)
+                .tiling(vk::ImageTiling::OPTIMAL)
+                .usage(usage)
+                .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                .initial_layout(vk::ImageLayout::UNDEFINED), None)
+                .map_err(to_error)?
+        };
+        Ok(VulkanImage { raw, image, kind, format, aspect })
+    }
+
+    pub(crate) fn extent(&self) -> vk::Extent2D {
+        match self.kind {
+            image::Kind::D1(width, _) => vk::Extent2D { width, height: 1 },
+            image::Kind::D2(width, height, _) => vk::Extent2D { width, height },
+            image::Kind::D3(width, height, _) => vk::Extent2D { width, height },
+            image::Kind::Cube(size, _) => vk::Extent2D { width: size, height: size },
+        }
+    }
+
+    pub(crate) fn requirements(&self) -> api::memory::Requirements {
+        crate::memory::to_requirements(unsafe { self.raw.device.get_image_memory_requirements(self.image) })
+    }
+
+    ///Every image lives in `GENERAL` layout once it has memory, so passes and copies never transition.
+    pub(crate) fn init_layout(&self) -> Result<(), api::Error> {
+        let barrier = vk::ImageMemoryBarrier::builder()
+            .old_layout(vk::ImageLayout::UNDEFINED)
+            .new_layout(vk::ImageLayout::GENERAL)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(self.image)
+            .subresource_range(range(self.aspect))
+            .dst_access_mask(vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE)
+            .build();
+        self.raw.submit(|cb| unsafe {
+            self.raw.device.cmd_pipeline_barrier(
+                cb,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::ALL_COMMANDS,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier],
+            );
+            Ok(())
+        })
+    }
+}
+
+impl Drop for VulkanImage {
+    fn drop(&mut self) {
+        unsafe { self.raw.device.destroy_image(self.image, None) }
+    }
+}
+
+#[derive(Debug)]
+pub struct VulkanImageView {
+    raw: Arc<RawDevice>,
+    pub(crate) view: vk::ImageView,
+    pub(crate) format: vk::Format,
+    pub(crate) extent: vk::Extent2D,
+}
+
+impl VulkanImageView {
+    pub(crate) fn new(raw: Arc<RawDevice>, image: &VulkanImage) -> Result<Self, api::Error> {
+        let view_type = match image.kind {
+            image::Kind::D1(_, _) => vk::ImageViewType::TYPE_1D,
+            image::Kind::D2(_, _, _) => vk::ImageViewType::TYPE_2D,
+            image::Kind::D3(_, _, _) => vk::ImageViewType::TYPE_3D,
+            image::Kind::Cube(_, _) => vk::ImageViewType::CUBE,
+        };
+        let view = unsafe {
+            raw.device.create_image_view(&vk::ImageViewCreateInfo::builder()
+                .image(image.image)
+                .view_type(view_type)
+                .format(image.format)
+                .subresource_range(range(image.aspect)), None)
+                .map_err(to_error)?
+        };
+        Ok(VulkanImageView { raw, view, format: image.format, extent: image.extent() })
+    }
+
+    ///View of a colour image owned elsewhere, such as by a window swapchain.
+    pub(crate) fn from_raw(raw: Arc<RawDevice>, image: vk::Image, format: vk::Format, extent: vk::Extent2D) -> Result<Self, api::Error> {
+        let view = unsafe {
+            raw.device.create_image_view(&vk::ImageViewCreateInfo::builder()
+                .image(image)
+                .view_type(vk::ImageViewType::TYPE_2D)
+                .format(format)
+                .subresource_range(color_range()), None)
+                .map_err(to_error)?
+        };
+        Ok(VulkanImageView { raw, view, format, extent })
+    }
+}
+
+impl Drop for VulkanImageView {
+    fn drop(&mut self) {
+        unsafe { self.raw.device.destroy_image_view(self.view, None) }
+    }
+}
+
+#[derive(Debug)]
+pub struct VulkanSampler {
+    raw: Arc<RawDevice>,
+    pub(crate) sampler: vk::Sampler,
+}
+
+impl VulkanSampler {
+    pub(crate) fn new(raw: Arc<RawDevice>, desc: &image::SamplerDesc) -> Result<Self, api::Error> {
+        let (mipmap_mode, max_lod) = match desc.mip_filter {
+            Some(image::Filter::Nearest) => (vk::SamplerMipmapMode::NEAREST, vk::LOD_CLAMP_NONE),
+            Some(image::Filter::Linear) => (vk::SamplerMipmapMode::LINEAR, vk::LOD_CLAMP_NONE),
+            None => (vk::SamplerMipmapMode::NEAREST, 0.),
+        };
+        let sampler = unsafe {
+            raw.device.create_sampler(&vk::SamplerCreateInfo::builder()
+                .min_filter(to_vk_filter(desc.min_filter))
+                .mag_filter(to_vk_filter(desc.mag_filter))
+                .mipmap_mode(mipmap_mode)
+                .address_mode_u(to_vk_wrap(desc.wrap.0))
+                .address_mode_v(to_vk_wrap(desc.wrap.1))
+                .address_mode_w(to_vk_wrap(desc.wrap.2))
+                .min_lod(0.)
+                .max_lod(max_lod), None)
+                .map_err(to_error)?
+        };
+        Ok(VulkanSampler { raw, sampler })
+    }
+}
+
+impl Drop for VulkanSampler {
+    fn drop(&mut self) {
+        unsafe { self.raw.device.destroy_sampler(self.sampler, None) }
+    }
+}
+
+pub(crate) fn to_vk_format(format: image::Format) -> vk::Format {
+    match format {
+        image::Format::Rgba8 => vk::Format::R8G8B8A8_UNORM,
+        image::Format::Rgba8Srgb => vk::Format::R8G8B8A8_SRGB,
+        image::Format::Rgba16F => vk::Format::R16G16B16A16_SFLOAT,
+        image::Format::R32F => vk::Format::R32_SFLOAT,
+        image::Format::D24S8 => vk::Format::D24_UNORM_S8_UINT,
+        image::Format::D32F => vk::Format::D32_SFLOAT,
+        image::Format::Bc1 => vk::Format::BC1_RGBA_UNORM_BLOCK,
+        image::Format::Bc3 => vk::Format::BC3_UNORM_BLOCK,
+        image::Format::Bc4 => vk::Format::BC4_UNORM_BLOCK,
+        image::Format::Bc5 => vk::Format::BC5_UNORM_BLOCK,
+    }
+}
+
+///Inverse of `to_vk_format`, `None` for formats only used internally.
+pub(crate) fn from_vk_format(format: vk::Format) -> Option<image::Format> {
+    [
+        image::Format::Rgba8, image::Format::Rgba8Srgb, image::Format::Rgba16F, image::Format::R32F,
+        image::Format::D24S8, image::Format::D32F,
+        image::Format::Bc1, image::Format::Bc3, image::Format::Bc4, image::Format::Bc5,
+    ].iter()
+        .copied()
+        .find(|f| to_vk_format(*f) == format)
+}
+
+fn to_vk_filter(filter: image::Filter) -> vk::Filter {
+    match filter {
+        image::Filter::Nearest => vk::Filter::NEAREST,
+        image::Filter::Linear => vk::Filter::LINEAR,
+    }
+}
+
+fn to_vk_wrap(wrap: image::WrapMode) -> vk::SamplerAddressMode {
+    match wrap {
+        image::WrapMode::Tile => vk::SamplerAddressMode::REPEAT,
+        image::WrapMode::Mirror => vk::SamplerAddressMode::MIRRORED_REPEAT,
+        image::WrapMode::Clamp => vk::SamplerAddressMode::CLAMP_TO_EDGE,
+    }
+}
+
+///Faces of a cube are array layers, every other kind has one.
+pub(crate) fn array_layers(kind: &image::Kind) -> u32 {
+    match kind {
+        image::Kind::Cube(..) => 6,
+        _ => 1,
+    }
+}
+
+pub(crate) fn layers(aspect: vk::ImageAspectFlags, level: image::Level, layers: Range<u32>) -> vk::ImageSubresourceLayers {
+    vk::ImageSubresourceLayers {
+        aspect_mask: aspect,
+        mip_level: u32::from(level),
+        base_array_layer: layers.start,
+        layer_count: layers.end - layers.start,
+    }
+}
+
+pub(crate) fn color_range() -> vk::ImageSubresourceRange {
+    range(vk::ImageAspectFlags::COLOR)
+}
+
+fn range(aspect: vk::ImageAspectFlags) -> vk::ImageSubresourceRange {
+    vk::ImageSubresourceRange {
+        aspect_mask: aspect,
+        base_mip_level: 0,
+        level_count: vk::REMAINING_MIP_LEVELS,
+        base_array_layer: 0,
+        layer_count: vk::REMAINING_ARRAY_LAYERS,
+    }
+}
+
+pub(crate) fn aspect(format: vk::Format) -> vk::ImageAspectFlags {
+    match format {
+        vk::Format::D32_SFLOAT | vk::Format::X8_D24_UNORM_PACK32 | vk::Format::D16_UNORM =>
+            vk::ImageAspectFlags::DEPTH,
+        vk::Format::D24_UNORM_S8_UINT | vk::Format::D32_SFLOAT_S8_UINT =>
+            vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL,
+        vk::Format::S8_UINT => vk::ImageAspectFlags::STENCIL,
+        _ => vk::ImageAspectFlags::COLOR,
+    }
+}
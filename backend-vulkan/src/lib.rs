@@ -1,7 +1,175 @@
+//! Vulkan backend built on `ash`.
+//!
+//! GLSL shader modules are compiled to SPIR-V when a pipeline is built with them, with the
+//! bindings named by the hints of its layout. Swapchains present to a GLFW window and are
+//! recreated as it is resized, those of `PlatformManager::headless` render into offscreen images
+//! and `present` rotates them. The viewport is flipped so winding and orientation match the
+//! OpenGL backend, and `read_pixels` reverses the rows it reads to match as well. Clip space
+//! depth follows Vulkan (0..1).
+#[macro_use]
+extern crate ash;
+extern crate backend_api as api;
+
+pub use crate::core::{
+    VulkanRendererApi as RendererApi,
+    VulkanRendererDevice as RendererDevice,
+};
+pub use buffer::VulkanBuffer as Buffer;
+pub use image::{
+    VulkanImage as Image,
+    VulkanImageView as ImageView,
+};
+pub use pipeline::{
+    VulkanDescriptorSet as DescriptorSet,
+    VulkanDescriptorSetLayout as DescriptorSetLayout,
+    VulkanPipeline as Pipeline,
+    VulkanPipelineLayout as PipelineLayout,
+};
+pub use platform::{
+    VulkanImGuiRenderer as ImGuiRenderer,
+    VulkanPlatformManager as PlatformManager,
+};
+pub use crate::memory::{
+    VulkanMemory as Memory
+};
+
+mod image;
+mod buffer;
+mod core;
+mod platform;
+mod shader_mod;
+mod memory;
+mod framebuffer;
+
+mod pipeline;
+mod swapchain;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Backend {}
+
+impl backend_api::Backend for Backend {
+    type RendererApi = core::VulkanRendererApi;
+    type RendererDevice = core::VulkanRendererDevice;
+    type PlatformManager = platform::VulkanPlatformManager;
+    type ImGuiRenderer = platform::VulkanImGuiRenderer;
+    type Memory = memory::VulkanMemory;
+    type Buffer = buffer::VulkanBuffer;
+    type Image = image::VulkanImage;
+    type ImageView = image::VulkanImageView;
+    type Sampler = image::VulkanSampler;
+    type Pipeline = pipeline::VulkanPipeline;
+    type RenderPass = pipeline::VulkanRenderPass;
+    type CommandBuffer = pipeline::VulkanCommandBuffer;
+    type ShaderMod = shader_mod::VulkanShaderMod;
+    type DescriptorSet = pipeline::VulkanDescriptorSet;
+    type DescriptorSetLayout = pipeline::VulkanDescriptorSetLayout;
+    type PipelineLayout = pipeline::VulkanPipelineLayout;
+    type Surface = swapchain::VulkanSurface;
+    type Swapchain = swapchain::VulkanSwapchain;
+    type Framebuffer = framebuffer::VulkanFramebuffer;
+}
+
 #[cfg(test)]
 mod tests {
+    use api::{
+        CommandBuffer,
+        PlatformManager,
+        RendererDevice,
+        Swapchain,
+    };
+
+    #[test]
+    #[ignore] //needs a Vulkan loader and device, such as lavapipe
+    fn maps_buffer_memory() {
+        let mut pm = crate::PlatformManager::headless(api::WindowConfig { width: 4, height: 4 }).unwrap();
+        let (_api, device) = pm.create_renderer();
+
+        let data: Vec<u8> = (0..64).collect();
+        let mut buffer = device.create_buffer(api::BufferDescriptor { size: data.len() as u32, usage: api::Usage::Uniform }).unwrap();
+        let req = device.get_buffer_requirements(&buffer);
+        let host = api::memory::Properties::HOST_VISIBLE | api::memory::Properties::HOST_COHERENT;
+        let memory_type = device.memory_types().iter()
+            .enumerate()
+            .position(|(i, t)| req.allows(i) && t.properties.contains(host))
+            .expect("No host visible memory");
+        let mem = device.allocate_memory(memory_type, req.size).unwrap();
+        device.bind_buffer_memory(&mem, 0, &mut buffer).unwrap();
+
+        let ptr = device.map_memory(&mem).unwrap();
+        unsafe { std::ptr::copy(data.as_ptr(), ptr, data.len()) };
+        device.flush_memory(&mem);
+        device.unmap_memory(&mem);
+
+        let ptr = device.map_memory(&mem).unwrap();
+        let read = unsafe { std::slice::from_raw_parts(ptr, data.len()) }.to_vec();
+        device.unmap_memory(&mem);
+        assert_eq!(read, data);
+    }
+
+    #[test]
+    #[ignore] //needs a Vulkan loader and device, such as lavapipe
+    fn chains_descriptor_pools() {
+        let mut pm = crate::PlatformManager::headless(api::WindowConfig { width: 4, height: 4 }).unwrap();
+        let (_api, device) = pm.create_renderer();
+        let layout = device.create_descriptor_set_layout(&[
+            api::DescriptorSetLayoutBinding { binding: 0, desc: api::DescriptorType::UniformBuffer },
+            api::DescriptorSetLayoutBinding { binding: 1, desc: api::DescriptorType::Sampler },
+        ]).unwrap();
+
+        let sets: Vec<crate::DescriptorSet> = (0..1000)
+            .map(|_| device.allocate_descriptor_set(&layout).unwrap())
+            .collect();
+        drop(sets);
+        device.allocate_descriptor_set(&layout).unwrap();
+    }
+
+    #[test]
+    fn compiles_glsl_with_the_bindings_of_its_layout() {
+        let read = |name: &str| std::fs::read_to_string(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/../client/src/test/").to_owned() + name).unwrap();
+        let (vertex, fragment) = (read("skybox_vert.glsl"), read("skybox_frag.glsl"));
+        let varyings = vec!["out_Direction".to_owned()];
+        let bindings = [("Sky", 0), ("u_environment", 1)];
+
+        let vertex_code = crate::shader_mod::compile(&vertex, ash::vk::ShaderStageFlags::VERTEX, &bindings, &varyings);
+        assert!(vertex_code.is_ok(), "{:?}", vertex_code);
+        let fragment_code = crate::shader_mod::compile(&fragment, ash::vk::ShaderStageFlags::FRAGMENT, &bindings, &varyings);
+        assert!(fragment_code.is_ok(), "{:?}", fragment_code);
+        assert!(crate::shader_mod::compile(&fragment, ash::vk::ShaderStageFlags::FRAGMENT, &bindings[..1], &varyings).is_err());
+    }
+
+    #[test]
+    #[ignore] //needs a Vulkan loader and device, such as lavapipe
+    fn clears_and_presents() {
+        let mut pm = crate::PlatformManager::headless(api::WindowConfig { width: 4, height: 4 }).unwrap();
+        let (_api, device) = pm.create_renderer();
+        let surface = pm.create_surface();
+        let (mut swapchain, _) = device.create_swapchain(&surface).unwrap();
+
+        for frame in 0..3 {
+            let mut cmd = device.create_cmd_buffer();
+            cmd.clear_screen((0., 0., 1., 1.));
+            device.execute(cmd).unwrap();
+            swapchain.present(frame);
+        }
+        assert_eq!(pm.presented_frames(), 3);
+    }
+
     #[test]
-    fn it_works() {
-        assert_eq!(2 + 2, 4);
+    #[ignore] //needs a Vulkan loader and device, and a display to open a window on
+    fn presents_to_a_window() {
+        let mut pm = crate::PlatformManager::new(api::WindowConfig { width: 64, height: 64 });
+        let (_api, device) = pm.create_renderer();
+        let surface = pm.create_surface();
+        let (mut swapchain, _) = device.create_swapchain(&surface).unwrap();
+
+        for frame in 0..3 {
+            pm.poll_events();
+            let mut cmd = device.create_cmd_buffer();
+            cmd.clear_screen((0., 0., 1., 1.));
+            device.execute(cmd).unwrap();
+            swapchain.present(frame);
+        }
+        assert_eq!(pm.presented_frames(), 3);
     }
 }
@@ -0,0 +1,110 @@
+use std::sync::{Arc, Mutex};
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use crate::buffer::VulkanBuffer;
+use crate::core::{RawDevice, to_error};
+use crate::image::VulkanImage;
+
+pub struct VulkanMemory {
+    raw: Arc<RawDevice>,
+    pub(crate) memory: vk::DeviceMemory,
+    pub(crate) memory_type: api::memory::TypeId,
+    pub(crate) size: u32,
+    ///Map count and the pointer returned by the first map.
+    mapped: Mutex<(u32, usize)>,
+}
+
+impl VulkanMemory {
+    pub(crate) fn allocate(raw: Arc<RawDevice>, memory_type: api::memory::TypeId, size: u32) -> Result<Self, api::Error> {
+        if memory_type >= raw.memory_properties.memory_type_count as usize {
+            return Err(api::Error::Unsupported(format!("Memory type {}", memory_type)));
+        }
+        let memory = unsafe {
+            raw.device.allocate_memory(&vk::MemoryAllocateInfo::builder()
+                .allocation_size(u64::from(size))
+                .memory_type_index(memory_type as u32), None)
+                .map_err(to_error)?
+        };
+        Ok(VulkanMemory { raw, memory, memory_type, size, mapped: Mutex::new((0, 0)) })
+    }
+
+    ///Device local memory holding only `image`, bound at its start.
+    pub(crate) fn dedicated(raw: Arc<RawDevice>, image: &mut VulkanImage) -> Result<Self, api::Error> {
+        let req = image.requirements();
+        let memory_type = raw.memory_type(req.type_mask, vk::MemoryPropertyFlags::DEVICE_LOCAL)?;
+        let memory = VulkanMemory::allocate(raw, memory_type as usize, req.size)?;
+        memory.bind_image(image, 0)?;
+        Ok(memory)
+    }
+
+    pub fn bind_image(&self, image: &mut VulkanImage, offset: u32) -> Result<(), api::Error> {
+        if !image.requirements().fits(self.memory_type, self.size, offset) {
+            return Err(api::Error::InvalidMemoryBinding { offset });
+        }
+        unsafe {
+            self.raw.device.bind_image_memory(image.image, self.memory, u64::from(offset))
+                .map_err(to_error)?;
+        }
+        image.init_layout()
+    }
+
+    pub fn bind_buffer(&self, buffer: &mut VulkanBuffer, offset: u32) -> Result<(), api::Error> {
+        if !buffer.requirements().fits(self.memory_type, self.size, offset) {
+            return Err(api::Error::InvalidMemoryBinding { offset });
+        }
+        unsafe {
+            self.raw.device.bind_buffer_memory(buffer.buffer, self.memory, u64::from(offset))
+                .map_err(to_error)
+        }
+    }
+
+    pub fn map_memory(&self) -> Result<*mut u8, api::Error> {
+        let mut mapped = self.mapped.lock().unwrap();
+        if mapped.0 == 0 {
+            let ptr = unsafe {
+                self.raw.device.map_memory(self.memory, 0, vk::WHOLE_SIZE, vk::MemoryMapFlags::empty())
+                    .map_err(to_error)?
+            };
+            mapped.1 = ptr as usize;
+        }
+        mapped.0 += 1;
+        Ok(mapped.1 as *mut u8)
+    }
+
+    pub fn unmap_memory(&self) {
+        let mut mapped = self.mapped.lock().unwrap();
+        if mapped.0 == 0 {
+            return;
+        }
+        mapped.0 -= 1;
+        if mapped.0 == 0 {
+            unsafe { self.raw.device.unmap_memory(self.memory) }
+        }
+    }
+
+    pub fn flush_memory(&self) {
+        unsafe {
+            self.raw.device.flush_mapped_memory_ranges(&[vk::MappedMemoryRange::builder()
+                .memory(self.memory)
+                .size(vk::WHOLE_SIZE)
+                .build()])
+                .expect("Memory flush")
+        }
+    }
+}
+
+impl Drop for VulkanMemory {
+    fn drop(&mut self) {
+        unsafe { self.raw.device.free_memory(self.memory, None) }
+    }
+}
+
+pub(crate) fn to_requirements(req: vk::MemoryRequirements) -> api::memory::Requirements {
+    api::memory::Requirements {
+        size: req.size as u32,
+        alignment: req.alignment as u32,
+        type_mask: req.memory_type_bits,
+    }
+}
@@ -0,0 +1,945 @@
+use std::ffi::CStr;
+use std::mem::size_of;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use api::{PipelineDescriptor, Primitive};
+
+use crate::Backend;
+use crate::buffer::VulkanBuffer;
+use crate::core::{RawDevice, to_error};
+use crate::framebuffer::{PassTarget, VulkanFramebuffer};
+use crate::image::VulkanImage;
+use crate::pipeline::VulkanCommand::{BeginRenderPass, BindDescriptorSet, BindIndexBuffer, BindPipeline, BindVertexBuffer, BufferData, ClearScreen, CopyBufferToImage, CopyImageToBuffer, DrawIndexed, DrawIndexedIndirect, EndRenderPass, GenerateMipmaps, SetScissor, SetViewport};
+use crate::swapchain::SharedDisplay;
+
+///Largest payload of a single `vkCmdUpdateBuffer`.
+const UPDATE_LIMIT: usize = 65536;
+
+#[derive(Debug)]
+pub struct VulkanRenderPass {
+    raw: Arc<RawDevice>,
+    pub(crate) pass: vk::RenderPass,
+    pub(crate) attachments: Vec<(api::AttachmentLayout, vk::Format)>,
+    pub(crate) layout: u32,
+}
+
+impl VulkanRenderPass {
+    pub(crate) fn new<A>(raw: Arc<RawDevice>, attachments: A) -> Result<Self, api::Error>
+        where A: IntoIterator<Item=api::Attachment> {
+        let (attachments, ops): (Vec<(api::AttachmentLayout, vk::Format)>, Vec<(api::LoadOp, api::StoreOp)>) = attachments.into_iter()
+            .map(|a| ((a.layout, crate::image::to_vk_format(a.format)), (a.load, a.store)))
+            .unzip();
+        let pass = raw_render_pass(&raw.device, &attachments, &ops)?;
+        let layout = raw.pass_layout(&attachments);
+        Ok(VulkanRenderPass { raw, pass, attachments, layout })
+    }
+}
+
+impl Drop for VulkanRenderPass {
+    fn drop(&mut self) {
+        unsafe { self.raw.device.destroy_render_pass(self.pass, None) }
+    }
+}
+
+///Single subpass with the load and store `ops` of each attachment, all kept in `GENERAL` layout.
+pub(crate) fn raw_render_pass(device: &ash::Device,
+                              attachments: &[(api::AttachmentLayout, vk::Format)],
+                              ops: &[(api::LoadOp, api::StoreOp)]) -> Result<vk::RenderPass, api::Error> {
+    let descriptions: Vec<vk::AttachmentDescription> = attachments.iter()
+        .zip(ops)
+        .map(|((_, format), (load, store))| {
+            let load = match load {
+                api::LoadOp::Load => vk::AttachmentLoadOp::LOAD,
+                api::LoadOp::Clear => vk::AttachmentLoadOp::CLEAR,
+                api::LoadOp::DontCare => vk::AttachmentLoadOp::DONT_CARE,
+            };
+            let store = match store {
+                api::StoreOp::Store => vk::AttachmentStoreOp::STORE,
+                api::StoreOp::DontCare => vk::AttachmentStoreOp::DONT_CARE,
+            };
+            vk::AttachmentDescription::builder()
+                .format(*format)
+                .samples(vk::SampleCountFlags::TYPE_1)
+                .load_op(load)
+                .store_op(store)
+                .stencil_load_op(load)
+                .stencil_store_op(store)
+                .initial_layout(vk::ImageLayout::GENERAL)
+                .final_layout(vk::ImageLayout::GENERAL)
+                .build()
+        })
+        .collect();
+    let reference = |i: usize| vk::AttachmentReference { attachment: i as u32, layout: vk::ImageLayout::GENERAL };
+    let colors: Vec<vk::AttachmentReference> = attachments.iter()
+        .enumerate()
+        .filter(|(_, (layout, _))| matches!(layout, api::AttachmentLayout::Color))
+        .map(|(i, _)| reference(i))
+        .collect();
+    let depth = attachments.iter()
+        .position(|(layout, _)| !matches!(layout, api::AttachmentLayout::Color))
+        .map(reference);
+
+    let mut subpass = vk::SubpassDescription::builder()
+        .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+        .color_attachments(&colors);
+    if let Some(depth) = depth.as_ref() {
+        subpass = subpass.depth_stencil_attachment(depth);
+    }
+    let subpasses = [subpass.build()];
+
+    let access = vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE;
+    let dependencies = [
+        vk::SubpassDependency {
+            src_subpass: vk::SUBPASS_EXTERNAL,
+            dst_subpass: 0,
+            src_stage_mask: vk::PipelineStageFlags::ALL_COMMANDS,
+            dst_stage_mask: vk::PipelineStageFlags::ALL_GRAPHICS,
+            src_access_mask: access,
+            dst_access_mask: access,
+            dependency_flags: vk::DependencyFlags::empty(),
+        },
+        vk::SubpassDependency {
+            src_subpass: 0,
+            dst_subpass: vk::SUBPASS_EXTERNAL,
+            src_stage_mask: vk::PipelineStageFlags::ALL_GRAPHICS,
+            dst_stage_mask: vk::PipelineStageFlags::ALL_COMMANDS,
+            src_access_mask: access,
+            dst_access_mask: access,
+            dependency_flags: vk::DependencyFlags::empty(),
+        },
+    ];
+
+    unsafe {
+        device.create_render_pass(&vk::RenderPassCreateInfo::builder()
+            .attachments(&descriptions)
+            .subpasses(&subpasses)
+            .dependencies(&dependencies), None)
+            .map_err(to_error)
+    }
+}
+
+///Built up front against the pass of its descriptor and again for every other layout of pass it is drawn in.
+#[derive(Debug)]
+pub struct VulkanPipeline {
+    pub(crate) state: Arc<PipelineState>,
+}
+
+///What a variant of a pipeline is built from, kept alive by the command buffers binding it.
+#[derive(Debug)]
+pub(crate) struct PipelineState {
+    raw: Arc<RawDevice>,
+    shader_set: api::ShaderSet<Backend>,
+    bindings: Vec<vk::VertexInputBindingDescription>,
+    attributes: Vec<vk::VertexInputAttributeDescription>,
+    topology: vk::PrimitiveTopology,
+    rasterizer: api::RasterizerState,
+    depth_stencil: api::DepthStencilState,
+    blend: Vec<Option<api::BlendState>>,
+    layout: vk::PipelineLayout,
+    ///Built pipelines by the layout of their pass.
+    variants: Mutex<Vec<(u32, vk::Pipeline)>>,
+}
+
+impl VulkanPipeline {
+    pub(crate) fn new(raw: Arc<RawDevice>, desc: PipelineDescriptor<Backend>) -> Result<Self, api::Error> {
+        let bindings: Vec<vk::VertexInputBindingDescription> = desc.vertex_buffers.iter()
+            .map(|b| vk::VertexInputBindingDescription {
+                binding: u32::from(b.binding),
+                stride: b.stride as u32,
+                //matrices are per instance, as with the OpenGL divisor
+                input_rate: if desc.vertex_attributes.iter()
+                    .any(|a| a.binding == u16::from(b.binding) && a.data.data_type == api::DataType::Mat4f32) {
+                    vk::VertexInputRate::INSTANCE
+                } else {
+                    vk::VertexInputRate::VERTEX
+                },
+            })
+            .collect();
+        let attributes: Vec<vk::VertexInputAttributeDescription> = desc.vertex_attributes.iter()
+            .flat_map(|a| {
+                let (format, columns) = match a.data.data_type {
+                    api::DataType::Float32 => (vk::Format::R32_SFLOAT, 1),
+                    api::DataType::Vec3f32 => (vk::Format::R32G32B32_SFLOAT, 1),
+                    api::DataType::Vec2f32 => (vk::Format::R32G32_SFLOAT, 1),
+                    api::DataType::Mat4f32 => (vk::Format::R32G32B32A32_SFLOAT, 4),
+                };
+                (0..columns).map(move |c| vk::VertexInputAttributeDescription {
+                    location: a.location + c,
+                    binding: u32::from(a.binding),
+                    format,
+                    offset: (a.data.offset + size_of::<[f32; 4]>() * c as usize) as u32,
+                })
+            })
+            .collect();
+        let topology = match desc.primitives {
+            Primitive::Points => vk::PrimitiveTopology::POINT_LIST,
+            Primitive::Lines => vk::PrimitiveTopology::LINE_LIST,
+            Primitive::LinesStrip => vk::PrimitiveTopology::LINE_STRIP,
+            Primitive::Triangles => vk::PrimitiveTopology::TRIANGLE_LIST,
+            Primitive::TrianglesFan => vk::PrimitiveTopology::TRIANGLE_FAN,
+            Primitive::TrianglesStrip => vk::PrimitiveTopology::TRIANGLE_STRIP,
+        };
+        //the window's pass unless the descriptor names another
+        let (pass, pass_layout) = match desc.render_pass {
+            Some(render_pass) => (render_pass.pass, render_pass.layout),
+            None => (raw.render_pass, raw.render_pass_layout),
+        };
+        //GLSL is compiled now that the bindings of the layout are known
+        let varyings = desc.shader_set.vertex.varyings();
+        let shader_set = api::ShaderSet {
+            vertex: desc.shader_set.vertex.for_layout(&desc.layout.bindings, &varyings)?,
+            fragment: desc.shader_set.fragment.for_layout(&desc.layout.bindings, &varyings)?,
+        };
+        let state = PipelineState {
+            raw: raw.clone(),
+            shader_set,
+            bindings,
+            attributes,
+            topology,
+            rasterizer: desc.rasterizer,
+            depth_stencil: desc.depth_stencil,
+            blend: desc.blend,
+            layout: desc.layout.layout,
+            variants: Mutex::new(Vec::new()),
+        };
+        state.variant(pass, pass_layout)?;
+        Ok(VulkanPipeline { state: Arc::new(state) })
+    }
+}
+
+impl PipelineState {
+    ///Pipeline for passes of `pass_layout`, built against `pass` the first time such a pass is used.
+    pub(crate) fn variant(&self, pass: vk::RenderPass, pass_layout: u32) -> Result<vk::Pipeline, api::Error> {
+        let mut variants = self.variants.lock().unwrap();
+        if let Some((_, pipeline)) = variants.iter().find(|(layout, _)| *layout == pass_layout) {
+            return Ok(*pipeline);
+        }
+        let pipeline = self.build(pass, self.raw.pass_colors(pass_layout))?;
+        variants.push((pass_layout, pipeline));
+        Ok(pipeline)
+    }
+
+    fn build(&self, pass: vk::RenderPass, colors: u32) -> Result<vk::Pipeline, api::Error> {
+        let entry = unsafe { CStr::from_bytes_with_nul_unchecked(b"main\0") };
+        let stages = [&self.shader_set.vertex, &self.shader_set.fragment].iter()
+            .map(|m| vk::PipelineShaderStageCreateInfo::builder()
+                .stage(m.stage)
+                .module(m.module())
+                .name(entry)
+                .build())
+            .collect::<Vec<vk::PipelineShaderStageCreateInfo>>();
+        let vertex_input = vk::PipelineVertexInputStateCreateInfo::builder()
+            .vertex_binding_descriptions(&self.bindings)
+            .vertex_attribute_descriptions(&self.attributes);
+        let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::builder()
+            .topology(self.topology);
+        let viewport = vk::PipelineViewportStateCreateInfo::builder()
+            .viewport_count(1)
+            .scissor_count(1);
+        let rasterizer = &self.rasterizer;
+        let bias = rasterizer.depth_bias.unwrap_or(api::DepthBias { constant: 0., slope: 0. });
+        let rasterization = vk::PipelineRasterizationStateCreateInfo::builder()
+            .polygon_mode(match rasterizer.polygon_mode {
+                api::PolygonMode::Fill => vk::PolygonMode::FILL,
+                api::PolygonMode::Line => vk::PolygonMode::LINE,
+                api::PolygonMode::Point => vk::PolygonMode::POINT,
+            })
+            .cull_mode(match rasterizer.cull_mode {
+                api::CullMode::None => vk::CullModeFlags::NONE,
+                api::CullMode::Front => vk::CullModeFlags::FRONT,
+                api::CullMode::Back => vk::CullModeFlags::BACK,
+            })
+            .front_face(match rasterizer.front_face {
+                api::FrontFace::CounterClockwise => vk::FrontFace::COUNTER_CLOCKWISE,
+                api::FrontFace::Clockwise => vk::FrontFace::CLOCKWISE,
+            })
+            .depth_bias_enable(rasterizer.depth_bias.is_some())
+            .depth_bias_constant_factor(bias.constant)
+            .depth_bias_slope_factor(bias.slope)
+            .line_width(rasterizer.line_width);
+        let multisample = vk::PipelineMultisampleStateCreateInfo::builder()
+            .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+        let stencil = self.depth_stencil.stencil;
+        let stencil_face = |face: fn(&api::StencilState) -> api::StencilFaceState| match stencil {
+            Some(state) => vk::StencilOpState {
+                fail_op: to_vk_stencil_op(face(&state).fail),
+                pass_op: to_vk_stencil_op(face(&state).pass),
+                depth_fail_op: to_vk_stencil_op(face(&state).depth_fail),
+                compare_op: to_vk_compare(face(&state).compare),
+                compare_mask: state.read_mask,
+                write_mask: state.write_mask,
+                reference: state.reference,
+            },
+            None => vk::StencilOpState::default(),
+        };
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::builder()
+            .depth_test_enable(true)
+            .depth_write_enable(self.depth_stencil.depth_write)
+            .depth_compare_op(to_vk_compare(self.depth_stencil.depth_compare))
+            .stencil_test_enable(stencil.is_some())
+            .front(stencil_face(|state| state.front))
+            .back(stencil_face(|state| state.back));
+        //one state per colour attachment of the pass, those without one are not blended
+        let blend_attachments: Vec<vk::PipelineColorBlendAttachmentState> = self.blend.iter()
+            .copied()
+            .chain(std::iter::repeat(None))
+            .take(colors as usize)
+            .map(|state| {
+                let attachment = vk::PipelineColorBlendAttachmentState::builder()
+                    .color_write_mask(vk::ColorComponentFlags::all());
+                match state {
+                    Some(state) => attachment
+                        .blend_enable(true)
+                        .src_color_blend_factor(to_vk_blend_factor(state.src_color))
+                        .dst_color_blend_factor(to_vk_blend_factor(state.dst_color))
+                        .color_blend_op(to_vk_blend_op(state.color_op))
+                        .src_alpha_blend_factor(to_vk_blend_factor(state.src_alpha))
+                        .dst_alpha_blend_factor(to_vk_blend_factor(state.dst_alpha))
+                        .alpha_blend_op(to_vk_blend_op(state.alpha_op))
+                        .build(),
+                    None => attachment.build(),
+                }
+            })
+            .collect();
+        let blend = vk::PipelineColorBlendStateCreateInfo::builder()
+            .attachments(&blend_attachments);
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic = vk::PipelineDynamicStateCreateInfo::builder()
+            .dynamic_states(&dynamic_states);
+
+        let info = vk::GraphicsPipelineCreateInfo::builder()
+            .stages(&stages)
+            .vertex_input_state(&vertex_input)
+            .input_assembly_state(&input_assembly)
+            .viewport_state(&viewport)
+            .rasterization_state(&rasterization)
+            .multisample_state(&multisample)
+            .depth_stencil_state(&depth_stencil)
+            .color_blend_state(&blend)
+            .dynamic_state(&dynamic)
+            .layout(self.layout)
+            .render_pass(pass)
+            .subpass(0)
+            .build();
+        unsafe {
+            self.raw.device.create_graphics_pipelines(vk::PipelineCache::null(), &[info], None)
+                .map(|pipelines| pipelines[0])
+                .map_err(|(_, e)| to_error(e))
+        }
+    }
+}
+
+impl Drop for PipelineState {
+    fn drop(&mut self) {
+        for (_, pipeline) in self.variants.get_mut().unwrap().drain(..) {
+            unsafe { self.raw.device.destroy_pipeline(pipeline, None) }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct VulkanDescriptorSetLayout {
+    raw: Arc<RawDevice>,
+    pub(crate) layout: vk::DescriptorSetLayout,
+}
+
+impl VulkanDescriptorSetLayout {
+    pub(crate) fn new(raw: Arc<RawDevice>, bindings: &[api::DescriptorSetLayoutBinding]) -> Result<Self, api::Error> {
+        let bindings: Vec<vk::DescriptorSetLayoutBinding> = bindings.iter()
+            .map(|b| vk::DescriptorSetLayoutBinding::builder()
+                .binding(b.binding)
+                .descriptor_type(to_vk_descriptor(&b.desc))
+                .descriptor_count(1)
+                .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT)
+                .build())
+            .collect();
+        let layout = unsafe {
+            raw.device.create_descriptor_set_layout(&vk::DescriptorSetLayoutCreateInfo::builder()
+                .bindings(&bindings), None)
+                .map_err(to_error)?
+        };
+        Ok(VulkanDescriptorSetLayout { raw, layout })
+    }
+}
+
+impl Drop for VulkanDescriptorSetLayout {
+    fn drop(&mut self) {
+        unsafe { self.raw.device.destroy_descriptor_set_layout(self.layout, None) }
+    }
+}
+
+fn to_vk_compare(op: api::CompareOp) -> vk::CompareOp {
+    match op {
+        api::CompareOp::Never => vk::CompareOp::NEVER,
+        api::CompareOp::Less => vk::CompareOp::LESS,
+        api::CompareOp::Equal => vk::CompareOp::EQUAL,
+        api::CompareOp::LessOrEqual => vk::CompareOp::LESS_OR_EQUAL,
+        api::CompareOp::Greater => vk::CompareOp::GREATER,
+        api::CompareOp::NotEqual => vk::CompareOp::NOT_EQUAL,
+        api::CompareOp::GreaterOrEqual => vk::CompareOp::GREATER_OR_EQUAL,
+        api::CompareOp::Always => vk::CompareOp::ALWAYS,
+    }
+}
+
+fn to_vk_stencil_op(op: api::StencilOp) -> vk::StencilOp {
+    match op {
+        api::StencilOp::Keep => vk::StencilOp::KEEP,
+        api::StencilOp::Zero => vk::StencilOp::ZERO,
+        api::StencilOp::Replace => vk::StencilOp::REPLACE,
+        api::StencilOp::IncrementAndClamp => vk::StencilOp::INCREMENT_AND_CLAMP,
+        api::StencilOp::DecrementAndClamp => vk::StencilOp::DECREMENT_AND_CLAMP,
+        api::StencilOp::Invert => vk::StencilOp::INVERT,
+        api::StencilOp::IncrementAndWrap => vk::StencilOp::INCREMENT_AND_WRAP,
+        api::StencilOp::DecrementAndWrap => vk::StencilOp::DECREMENT_AND_WRAP,
+    }
+}
+
+fn to_vk_blend_factor(factor: api::BlendFactor) -> vk::BlendFactor {
+    match factor {
+        api::BlendFactor::Zero => vk::BlendFactor::ZERO,
+        api::BlendFactor::One => vk::BlendFactor::ONE,
+        api::BlendFactor::SrcColor => vk::BlendFactor::SRC_COLOR,
+        api::BlendFactor::OneMinusSrcColor => vk::BlendFactor::ONE_MINUS_SRC_COLOR,
+        api::BlendFactor::DstColor => vk::BlendFactor::DST_COLOR,
+        api::BlendFactor::OneMinusDstColor => vk::BlendFactor::ONE_MINUS_DST_COLOR,
+        api::BlendFactor::SrcAlpha => vk::BlendFactor::SRC_ALPHA,
+        api::BlendFactor::OneMinusSrcAlpha => vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+        api::BlendFactor::DstAlpha => vk::BlendFactor::DST_ALPHA,
+        api::BlendFactor::OneMinusDstAlpha => vk::BlendFactor::ONE_MINUS_DST_ALPHA,
+    }
+}
+
+fn to_vk_blend_op(op: api::BlendOp) -> vk::BlendOp {
+    match op {
+        api::BlendOp::Add => vk::BlendOp::ADD,
+        api::BlendOp::Subtract => vk::BlendOp::SUBTRACT,
+        api::BlendOp::ReverseSubtract => vk::BlendOp::REVERSE_SUBTRACT,
+        api::BlendOp::Min => vk::BlendOp::MIN,
+        api::BlendOp::Max => vk::BlendOp::MAX,
+    }
+}
+
+fn to_vk_descriptor(desc: &api::DescriptorType) -> vk::DescriptorType {
+    match desc {
+        api::DescriptorType::UniformBuffer => vk::DescriptorType::UNIFORM_BUFFER,
+        api::DescriptorType::Sampler => vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+    }
+}
+
+#[derive(Debug)]
+pub struct VulkanPipelineLayout {
+    raw: Arc<RawDevice>,
+    pub(crate) layout: vk::PipelineLayout,
+    ///Binding of each name hint, what GLSL shaders are compiled with.
+    pub(crate) bindings: Vec<(&'static str, u32)>,
+}
+
+impl VulkanPipelineLayout {
+    pub(crate) fn new<I>(raw: Arc<RawDevice>, desc_layout: &VulkanDescriptorSetLayout, hints: I) -> Result<Self, api::Error>
+        where I: IntoIterator<Item=api::PipelineLayoutHint> {
+        let bindings = hints.into_iter()
+            .map(|h| match h.hint {
+                api::LayoutHint::Name(name) => (name, h.location),
+            })
+            .collect();
+        let set_layouts = [desc_layout.layout];
+        let layout = unsafe {
+            raw.device.create_pipeline_layout(&vk::PipelineLayoutCreateInfo::builder()
+                .set_layouts(&set_layouts), None)
+                .map_err(to_error)?
+        };
+        Ok(VulkanPipelineLayout { raw, layout, bindings })
+    }
+}
+
+impl Drop for VulkanPipelineLayout {
+    fn drop(&mut self) {
+        unsafe { self.raw.device.destroy_pipeline_layout(self.layout, None) }
+    }
+}
+
+#[derive(Debug)]
+pub struct VulkanDescriptorSet {
+    raw: Arc<RawDevice>,
+    pool: vk::DescriptorPool,
+    pub(crate) set: vk::DescriptorSet,
+}
+
+impl VulkanDescriptorSet {
+    pub(crate) fn allocate(raw: Arc<RawDevice>, desc_layout: &VulkanDescriptorSetLayout) -> Result<Self, api::Error> {
+        let (pool, set) = raw.allocate_descriptor_set(desc_layout.layout)?;
+        Ok(VulkanDescriptorSet { raw, pool, set })
+    }
+
+    pub(crate) fn write(&self, binding: u32, descriptor: &api::Descriptor<Backend>) {
+        match descriptor {
+            api::Descriptor::Buffer(buffer) => {
+                let info = [vk::DescriptorBufferInfo {
+                    buffer: buffer.buffer,
+                    offset: 0,
+                    range: vk::WHOLE_SIZE,
+                }];
+                let write = vk::WriteDescriptorSet::builder()
+                    .dst_set(self.set)
+                    .dst_binding(binding)
+                    .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+                    .buffer_info(&info)
+                    .build();
+                unsafe { self.raw.device.update_descriptor_sets(&[write], &[]) }
+            }
+            api::Descriptor::CombinedImageSampler(view, sampler) => {
+                let info = [vk::DescriptorImageInfo {
+                    sampler: sampler.sampler,
+                    image_view: view.view,
+                    image_layout: vk::ImageLayout::GENERAL,
+                }];
+                let write = vk::WriteDescriptorSet::builder()
+                    .dst_set(self.set)
+                    .dst_binding(binding)
+                    .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .image_info(&info)
+                    .build();
+                unsafe { self.raw.device.update_descriptor_sets(&[write], &[]) }
+            }
+        }
+    }
+}
+
+impl Drop for VulkanDescriptorSet {
+    fn drop(&mut self) {
+        self.raw.free_descriptor_set(self.pool, self.set)
+    }
+}
+
+#[derive(Debug)]
+enum VulkanCommand {
+    BindPipeline(Arc<PipelineState>),
+    BeginRenderPass(PassTarget, Vec<api::ClearValue>),
+    EndRenderPass,
+    BindVertexBuffer(u32, vk::Buffer),
+    BindIndexBuffer(vk::Buffer),
+    BindDescriptorSet(vk::PipelineLayout, vk::DescriptorSet),
+    BufferData(vk::Buffer, Vec<u8>),
+    CopyBufferToImage(vk::Buffer, vk::Image, vk::BufferImageCopy),
+    CopyImageToBuffer(vk::Image, vk::Buffer, vk::BufferImageCopy),
+    GenerateMipmaps(vk::Image, api::image::Kind),
+    ///Index count, first index byte offset, base vertex and instances.
+    DrawIndexed(u32, u32, i32, Range<u32>),
+    ///Buffer, offset, draw count and stride.
+    DrawIndexedIndirect(vk::Buffer, u32, u32, u32),
+    ClearScreen((f32, f32, f32, f32)),
+    SetViewport(api::Rect),
+    SetScissor(Option<api::Rect>),
+}
+
+///Records commands, outside of an explicitly begun render pass the pass instances of the display
+///are opened on replay around draws and clears.
+#[derive(Debug)]
+pub struct VulkanCommandBuffer {
+    cmds: Mutex<Vec<VulkanCommand>>
+}
+
+impl VulkanCommandBuffer {
+    pub(crate) fn new() -> Self {
+        VulkanCommandBuffer { cmds: Mutex::new(Vec::new()) }
+    }
+
+    fn push(&mut self, cmd: VulkanCommand) {
+        self.cmds.get_mut().unwrap().push(cmd)
+    }
+
+    pub(crate) fn execute(self, raw: &RawDevice, display: &SharedDisplay) -> Result<(), api::Error> {
+        let cmds = self.cmds.into_inner().unwrap();
+        let display = display.lock().unwrap();
+        let device = &raw.device;
+
+        raw.submit(|cb| unsafe {
+            let mut target = display.target.as_ref().map(|t| t.pass);
+            let mut on_display = true;
+            let mut in_pass = false;
+            //begun by `begin_render_pass`, not closed implicitly
+            let mut explicit = false;
+            //set by the buffer for the current target, pass instances opened on replay apply them again
+            let mut viewport: Option<api::Rect> = None;
+            let mut scissor: Option<api::Rect> = None;
+            let display_viewport = |on_display: bool| on_display.then(|| {
+                let (x, y, width, height) = display.viewport;
+                api::Rect { x, y, width, height }
+            });
+            //bound by the buffer, draws bind its variant for the pass they are in
+            let mut pipeline: Option<&PipelineState> = None;
+            let mut bound: Option<vk::Pipeline> = None;
+
+            for cmd in &cmds {
+                match cmd {
+                    BindPipeline(state) => {
+                        pipeline = Some(state);
+                    }
+                    BeginRenderPass(pass, clear_values) => {
+                        assert!(!explicit, "Render pass begun inside another");
+                        if in_pass {
+                            device.cmd_end_render_pass(cb);
+                        }
+                        let clear_values: Vec<vk::ClearValue> = clear_values.iter().map(to_vk_clear).collect();
+                        viewport = None;
+                        scissor = None;
+                        begin(raw, cb, pass, None, None, &clear_values);
+                        target = Some(*pass);
+                        on_display = false;
+                        in_pass = true;
+                        explicit = true;
+                    }
+                    EndRenderPass => {
+                        assert!(explicit, "Render pass ended without begin");
+                        device.cmd_end_render_pass(cb);
+                        target = display.target.as_ref().map(|t| t.pass);
+                        on_display = true;
+                        in_pass = false;
+                        explicit = false;
+                        viewport = None;
+                        scissor = None;
+                    }
+                    SetViewport(rect) => {
+                        viewport = Some(*rect);
+                        if let (true, Some(pass)) = (in_pass, target) {
+                            set_viewport(raw, cb, pass.extent, *rect);
+                        }
+                    }
+                    SetScissor(rect) => {
+                        scissor = *rect;
+                        if let (true, Some(pass)) = (in_pass, target) {
+                            set_scissor(raw, cb, pass.extent, scissor);
+                        }
+                    }
+                    BindVertexBuffer(binding, buffer) => {
+                        device.cmd_bind_vertex_buffers(cb, *binding, &[*buffer], &[0]);
+                    }
+                    BindIndexBuffer(buffer) => {
+                        device.cmd_bind_index_buffer(cb, *buffer, 0, vk::IndexType::UINT32);
+                    }
+                    BindDescriptorSet(layout, set) => {
+                        device.cmd_bind_descriptor_sets(cb, vk::PipelineBindPoint::GRAPHICS, *layout, 0, &[*set], &[]);
+                    }
+                    BufferData(buffer, data) => {
+                        //transfers are not allowed inside a pass instance
+                        assert!(!explicit, "Transfer inside a render pass");
+                        if in_pass {
+                            device.cmd_end_render_pass(cb);
+                            in_pass = false;
+                        }
+                        for (i, chunk) in data.chunks(UPDATE_LIMIT).enumerate() {
+                            device.cmd_update_buffer(cb, *buffer, (i * UPDATE_LIMIT) as u64, chunk);
+                        }
+                        let barrier = vk::MemoryBarrier::builder()
+                            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                            .dst_access_mask(vk::AccessFlags::VERTEX_ATTRIBUTE_READ
+                                | vk::AccessFlags::INDEX_READ
+                                | vk::AccessFlags::UNIFORM_READ
+                                | vk::AccessFlags::INDIRECT_COMMAND_READ)
+                            .build();
+                        device.cmd_pipeline_barrier(cb,
+                                                    vk::PipelineStageFlags::TRANSFER,
+                                                    vk::PipelineStageFlags::DRAW_INDIRECT | vk::PipelineStageFlags::VERTEX_INPUT | vk::PipelineStageFlags::VERTEX_SHADER | vk::PipelineStageFlags::FRAGMENT_SHADER,
+                                                    vk::DependencyFlags::empty(),
+                                                    &[barrier], &[], &[]);
+                    }
+                    CopyBufferToImage(buffer, image, region) => {
+                        assert!(!explicit, "Transfer inside a render pass");
+                        if in_pass {
+                            device.cmd_end_render_pass(cb);
+                            in_pass = false;
+                        }
+                        device.cmd_copy_buffer_to_image(cb, *buffer, *image, vk::ImageLayout::GENERAL, &[*region]);
+                        transfer_barrier(raw, cb, vk::AccessFlags::TRANSFER_READ | vk::AccessFlags::SHADER_READ);
+                    }
+                    CopyImageToBuffer(image, buffer, region) => {
+                        assert!(!explicit, "Transfer inside a render pass");
+                        if in_pass {
+                            device.cmd_end_render_pass(cb);
+                            in_pass = false;
+                        }
+                        device.cmd_copy_image_to_buffer(cb, *image, vk::ImageLayout::GENERAL, *buffer, &[*region]);
+                        let barrier = vk::MemoryBarrier::builder()
+                            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                            .dst_access_mask(vk::AccessFlags::HOST_READ)
+                            .build();
+                        device.cmd_pipeline_barrier(cb,
+                                                    vk::PipelineStageFlags::TRANSFER,
+                                                    vk::PipelineStageFlags::HOST,
+                                                    vk::DependencyFlags::empty(),
+                                                    &[barrier], &[], &[]);
+                    }
+                    GenerateMipmaps(image, kind) => {
+                        assert!(!explicit, "Transfer inside a render pass");
+                        if in_pass {
+                            device.cmd_end_render_pass(cb);
+                            in_pass = false;
+                        }
+                        //faces of a cube are blitted together as layers rather than depth
+                        let layers = crate::image::array_layers(kind);
+                        for level in 1..kind.levels() {
+                            let corner = |(width, height, depth): (u32, u32, u32)| vk::Offset3D {
+                                x: width as i32,
+                                y: height as i32,
+                                z: (depth / layers) as i32,
+                            };
+                            let origin = vk::Offset3D { x: 0, y: 0, z: 0 };
+                            device.cmd_blit_image(cb, *image, vk::ImageLayout::GENERAL, *image, vk::ImageLayout::GENERAL, &[vk::ImageBlit {
+                                src_subresource: crate::image::layers(vk::ImageAspectFlags::COLOR, level - 1, 0..layers),
+                                src_offsets: [origin, corner(kind.level_extent(level - 1))],
+                                dst_subresource: crate::image::layers(vk::ImageAspectFlags::COLOR, level, 0..layers),
+                                dst_offsets: [origin, corner(kind.level_extent(level))],
+                            }], vk::Filter::LINEAR);
+                            //next level reads the one just written
+                            transfer_barrier(raw, cb, vk::AccessFlags::TRANSFER_READ | vk::AccessFlags::SHADER_READ);
+                        }
+                    }
+                    DrawIndexed(count, offset, base_vertex, instances) => {
+                        let pass = target.ok_or_else(|| api::Error::InvalidCommand("Draw without swapchain or bound render pass".to_owned()))?;
+                        if !in_pass {
+                            begin(raw, cb, &pass, viewport.or_else(|| display_viewport(on_display)), scissor, &[]);
+                            in_pass = true;
+                        }
+                        if !bind_variant(raw, cb, pipeline, &pass, &mut bound) {
+                            continue;
+                        }
+                        device.cmd_draw_indexed(cb, *count, instances.end - instances.start, offset / size_of::<u32>() as u32, *base_vertex, instances.start);
+                    }
+                    DrawIndexedIndirect(buffer, offset, count, stride) => {
+                        let pass = target.ok_or_else(|| api::Error::InvalidCommand("Draw without swapchain or bound render pass".to_owned()))?;
+                        if !in_pass {
+                            begin(raw, cb, &pass, viewport.or_else(|| display_viewport(on_display)), scissor, &[]);
+                            in_pass = true;
+                        }
+                        if !bind_variant(raw, cb, pipeline, &pass, &mut bound) {
+                            continue;
+                        }
+                        if raw.multi_draw_indirect {
+                            device.cmd_draw_indexed_indirect(cb, *buffer, u64::from(*offset), *count, *stride);
+                        } else {
+                            for i in 0..*count {
+                                device.cmd_draw_indexed_indirect(cb, *buffer, u64::from(offset + i * stride), 1, *stride);
+                            }
+                        }
+                    }
+                    ClearScreen((r, g, b, a)) => {
+                        let pass = target.ok_or_else(|| api::Error::InvalidCommand("Clear without swapchain or bound render pass".to_owned()))?;
+                        if !in_pass {
+                            begin(raw, cb, &pass, viewport.or_else(|| display_viewport(on_display)), scissor, &[]);
+                            in_pass = true;
+                        }
+                        let mut attachments: Vec<vk::ClearAttachment> = (0..pass.colors)
+                            .map(|i| vk::ClearAttachment {
+                                aspect_mask: vk::ImageAspectFlags::COLOR,
+                                color_attachment: i,
+                                clear_value: vk::ClearValue { color: vk::ClearColorValue { float32: [*r, *g, *b, *a] } },
+                            })
+                            .collect();
+                        if let Some(aspect) = pass.depth {
+                            attachments.push(vk::ClearAttachment {
+                                aspect_mask: aspect,
+                                color_attachment: 0,
+                                clear_value: vk::ClearValue {
+                                    depth_stencil: vk::ClearDepthStencilValue { depth: 1., stencil: 0 }
+                                },
+                            });
+                        }
+                        device.cmd_clear_attachments(cb, &attachments, &[vk::ClearRect {
+                            rect: to_vk_rect(pass.extent, scissor),
+                            base_array_layer: 0,
+                            layer_count: 1,
+                        }]);
+                    }
+                }
+            }
+            if in_pass {
+                device.cmd_end_render_pass(cb);
+            }
+            Ok(())
+        })
+    }
+}
+
+fn to_vk_copy(image: &VulkanImage, region: &api::image::BufferImageCopy) -> vk::BufferImageCopy {
+    //only one aspect of depth stencil images is copied at once
+    let aspect = if image.aspect.contains(vk::ImageAspectFlags::DEPTH) { vk::ImageAspectFlags::DEPTH } else { image.aspect };
+    //the faces of a cube along z are its array layers
+    let (layers, z, depth) = match image.kind {
+        api::image::Kind::Cube(..) => (region.offset.2..region.offset.2 + region.extent.2, 0, 1),
+        _ => (0..1, region.offset.2, region.extent.2),
+    };
+    vk::BufferImageCopy {
+        buffer_offset: u64::from(region.buffer_offset),
+        buffer_row_length: 0,
+        buffer_image_height: 0,
+        image_subresource: crate::image::layers(aspect, region.level, layers),
+        image_offset: vk::Offset3D { x: region.offset.0 as i32, y: region.offset.1 as i32, z: z as i32 },
+        image_extent: vk::Extent3D { width: region.extent.0, height: region.extent.1, depth },
+    }
+}
+
+///Makes transfer writes visible to `access` of later transfers and shaders.
+unsafe fn transfer_barrier(raw: &RawDevice, cb: vk::CommandBuffer, access: vk::AccessFlags) {
+    let barrier = vk::MemoryBarrier::builder()
+        .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+        .dst_access_mask(access)
+        .build();
+    raw.device.cmd_pipeline_barrier(cb,
+                                    vk::PipelineStageFlags::TRANSFER,
+                                    vk::PipelineStageFlags::TRANSFER | vk::PipelineStageFlags::VERTEX_SHADER | vk::PipelineStageFlags::FRAGMENT_SHADER,
+                                    vk::DependencyFlags::empty(),
+                                    &[barrier], &[], &[]);
+}
+
+fn to_vk_clear(value: &api::ClearValue) -> vk::ClearValue {
+    match value {
+        api::ClearValue::Color(color) => vk::ClearValue { color: vk::ClearColorValue { float32: *color } },
+        api::ClearValue::DepthStencil(depth, stencil) => vk::ClearValue {
+            depth_stencil: vk::ClearDepthStencilValue { depth: *depth, stencil: *stencil }
+        },
+    }
+}
+
+///Binds the variant of `pipeline` for `pass` unless it already is, false when the draw has to be skipped.
+unsafe fn bind_variant(raw: &RawDevice,
+                       cb: vk::CommandBuffer,
+                       pipeline: Option<&PipelineState>,
+                       pass: &PassTarget,
+                       bound: &mut Option<vk::Pipeline>) -> bool {
+    let variant = match pipeline.map(|pipeline| pipeline.variant(pass.pass, pass.layout)) {
+        Some(Ok(variant)) => variant,
+        Some(Err(e)) => {
+            eprintln!("Draw skipped: pipeline not built for the pass, {}", e);
+            return false;
+        }
+        None => {
+            eprintln!("Draw skipped: no pipeline bound");
+            return false;
+        }
+    };
+    if *bound != Some(variant) {
+        raw.device.cmd_bind_pipeline(cb, vk::PipelineBindPoint::GRAPHICS, variant);
+        *bound = Some(variant);
+    }
+    true
+}
+
+///Opens a pass instance and sets the viewport, the whole framebuffer without one, and the scissor.
+unsafe fn begin(raw: &RawDevice,
+                cb: vk::CommandBuffer,
+                pass: &PassTarget,
+                viewport: Option<api::Rect>,
+                scissor: Option<api::Rect>,
+                clear_values: &[vk::ClearValue]) {
+    raw.device.cmd_begin_render_pass(cb, &vk::RenderPassBeginInfo::builder()
+        .render_pass(pass.pass)
+        .framebuffer(pass.framebuffer)
+        .render_area(to_vk_rect(pass.extent, None))
+        .clear_values(clear_values), vk::SubpassContents::INLINE);
+
+    let viewport = viewport
+        .unwrap_or(api::Rect { x: 0, y: 0, width: pass.extent.width as i32, height: pass.extent.height as i32 });
+    set_viewport(raw, cb, pass.extent, viewport);
+    set_scissor(raw, cb, pass.extent, scissor);
+}
+
+///Sets the OpenGL style `viewport`, y flipped so winding matches.
+unsafe fn set_viewport(raw: &RawDevice, cb: vk::CommandBuffer, extent: vk::Extent2D, viewport: api::Rect) {
+    raw.device.cmd_set_viewport(cb, 0, &[vk::Viewport {
+        x: viewport.x as f32,
+        y: (extent.height as i32 - viewport.y) as f32,
+        width: viewport.width as f32,
+        height: -viewport.height as f32,
+        min_depth: 0.,
+        max_depth: 1.,
+    }]);
+}
+
+unsafe fn set_scissor(raw: &RawDevice, cb: vk::CommandBuffer, extent: vk::Extent2D, scissor: Option<api::Rect>) {
+    raw.device.cmd_set_scissor(cb, 0, &[to_vk_rect(extent, scissor)]);
+}
+
+///Framebuffer region of the bottom left based `rect`, clamped to `extent`, or all of it.
+fn to_vk_rect(extent: vk::Extent2D, rect: Option<api::Rect>) -> vk::Rect2D {
+    let (width, height) = (extent.width as i32, extent.height as i32);
+    let rect = rect.unwrap_or(api::Rect { x: 0, y: 0, width, height });
+    let (x0, x1) = (rect.x.clamp(0, width), (rect.x + rect.width).clamp(0, width));
+    //rows start at the top
+    let (y0, y1) = ((height - rect.y - rect.height).clamp(0, height), (height - rect.y).clamp(0, height));
+    vk::Rect2D {
+        offset: vk::Offset2D { x: x0, y: y0 },
+        extent: vk::Extent2D { width: (x1 - x0) as u32, height: (y1 - y0) as u32 },
+    }
+}
+
+impl api::CommandBuffer<Backend> for VulkanCommandBuffer {
+    fn bind_pipeline(&mut self, pipeline: &VulkanPipeline) {
+        self.push(BindPipeline(pipeline.state.clone()));
+    }
+
+    fn begin_render_pass(&mut self, render_pass: &VulkanRenderPass, framebuffer: &VulkanFramebuffer, clear_values: &[api::ClearValue]) {
+        //any pass compatible with the one the framebuffer was created for, such as one loading rather than clearing
+        let target = PassTarget { pass: render_pass.pass, ..framebuffer.target };
+        self.push(BeginRenderPass(target, clear_values.to_vec()))
+    }
+
+    fn end_render_pass(&mut self) {
+        self.push(EndRenderPass)
+    }
+
+    fn bind_vertex_buffer(&mut self, binding: u32, buffer: &VulkanBuffer) {
+        self.push(BindVertexBuffer(binding, buffer.buffer))
+    }
+
+    fn bind_index_buffer(&mut self, buffer: &VulkanBuffer) {
+        self.push(BindIndexBuffer(buffer.buffer))
+    }
+
+    fn buffer_data(&mut self, buffer: &VulkanBuffer, data: &[u8]) {
+        self.push(BufferData(buffer.buffer, data.to_vec()))
+    }
+
+    fn copy_buffer_to_image(&mut self, buffer: &VulkanBuffer, image: &VulkanImage, region: api::image::BufferImageCopy) {
+        self.push(CopyBufferToImage(buffer.buffer, image.image, to_vk_copy(image, &region)))
+    }
+
+    fn copy_image_to_buffer(&mut self, image: &VulkanImage, buffer: &VulkanBuffer, region: api::image::BufferImageCopy) {
+        self.push(CopyImageToBuffer(image.image, buffer.buffer, to_vk_copy(image, &region)))
+    }
+
+    fn generate_mipmaps(&mut self, image: &VulkanImage) {
+        self.push(GenerateMipmaps(image.image, image.kind.clone()))
+    }
+
+    fn draw_indexed(&mut self, count: u32, offset: u32, number: u32) {
+        self.push(DrawIndexed(count, offset, 0, 0..number.max(1)));
+    }
+
+    fn draw_indexed_base_vertex(&mut self, count: u32, offset: u32, base_vertex: i32, instances: Range<u32>) {
+        self.push(DrawIndexed(count, offset, base_vertex, instances));
+    }
+
+    fn draw_indexed_indirect(&mut self, buffer: &VulkanBuffer, offset: u32, draw_count: u32, stride: u32) {
+        self.push(DrawIndexedIndirect(buffer.buffer, offset, draw_count, stride));
+    }
+
+    fn bind_descriptor_set(&mut self, pipeline_layout: &VulkanPipelineLayout, desc_set: &VulkanDescriptorSet) {
+        self.push(BindDescriptorSet(pipeline_layout.layout, desc_set.set))
+    }
+
+    fn clear_screen(&mut self, color: (f32, f32, f32, f32)) {
+        self.push(ClearScreen(color))
+    }
+
+    fn set_viewport(&mut self, viewport: api::Rect) {
+        self.push(SetViewport(viewport))
+    }
+
+    fn set_scissor(&mut self, scissor: Option<api::Rect>) {
+        self.push(SetScissor(scissor))
+    }
+}
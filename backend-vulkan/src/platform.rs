@@ -0,0 +1,217 @@
+extern crate imgui;
+
+use std::cell::RefCell;
+use std::os::raw::c_char;
+use std::sync::Arc;
+use std::sync::mpsc::Receiver;
+use std::time::Instant;
+
+use ash::version::InstanceV1_0;
+use ash::vk;
+
+use backend_api::Backend as apiBackend;
+use backend_api::Event;
+use backend_api::ImGuiRenderer;
+use backend_api::PlatformManager;
+use backend_api::WindowConfig;
+
+use crate::Backend;
+use crate::core::{RawDevice, VulkanRendererApi, VulkanRendererDevice};
+use crate::swapchain::{Display, SharedDisplay};
+
+extern "C" {
+    fn glfwGetRequiredInstanceExtensions(count: *mut u32) -> *const *const c_char;
+    fn glfwCreateWindowSurface(instance: vk::Instance,
+                               window: *mut glfw::ffi::GLFWwindow,
+                               allocator: *const vk::AllocationCallbacks,
+                               surface: *mut vk::SurfaceKHR) -> vk::Result;
+}
+
+///Instance extensions GLFW creates window surfaces with.
+pub(crate) fn required_extensions() -> Result<Vec<*const c_char>, String> {
+    let mut count = 0;
+    let names = unsafe { glfwGetRequiredInstanceExtensions(&mut count) };
+    if names.is_null() {
+        return Err("GLFW can not create Vulkan surfaces".to_owned());
+    }
+    Ok(unsafe { std::slice::from_raw_parts(names, count as usize) }.to_vec())
+}
+
+pub(crate) fn create_window_surface(instance: &ash::Instance, window: &glfw::Window) -> Result<vk::SurfaceKHR, vk::Result> {
+    let mut surface = vk::SurfaceKHR::null();
+    match unsafe { glfwCreateWindowSurface(instance.handle(), window.window_ptr(), std::ptr::null(), &mut surface) } {
+        vk::Result::SUCCESS => Ok(surface),
+        e => Err(e),
+    }
+}
+
+///GLFW window without a client API, presented to through the surface of the device.
+struct Window {
+    window: RefCell<glfw::Window>,
+    events: Receiver<(f64, glfw::WindowEvent)>,
+    glfw: RefCell<glfw::Glfw>,
+}
+
+pub struct VulkanPlatformManager {
+    raw: Arc<RawDevice>,
+    display: SharedDisplay,
+    window: Option<Window>,
+    start: Instant,
+    frame_limit: Option<u64>,
+}
+
+impl VulkanPlatformManager {
+    ///Same as `PlatformManager::new`, reporting a missing loader, device or window instead of panicking.
+    pub fn try_new(config: WindowConfig) -> Result<Self, String> {
+        let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS).map_err(|e| format!("GLFW: {:?}", e))?;
+        glfw.window_hint(glfw::WindowHint::ClientApi(glfw::ClientApiHint::NoApi));
+        let (mut window, events) = glfw.create_window(config.width, config.height, "Hello this is window", glfw::WindowMode::Windowed)
+            .ok_or_else(|| "Failed to create GLFW window".to_owned())?;
+        window.set_framebuffer_size_polling(true);
+        window.set_key_polling(true);
+
+        let raw = RawDevice::new(Some(&window))?;
+        let (width, height) = window.get_framebuffer_size();
+        Ok(VulkanPlatformManager {
+            raw,
+            display: Display::new(width as u32, height as u32),
+            window: Some(Window { window: RefCell::new(window), events, glfw: RefCell::new(glfw) }),
+            start: Instant::now(),
+            frame_limit: None,
+        })
+    }
+
+    ///Without a window, swapchains rotate rendering between offscreen images of the size of `config`.
+    pub fn headless(config: WindowConfig) -> Result<Self, String> {
+        Ok(VulkanPlatformManager {
+            raw: RawDevice::new(None)?,
+            display: Display::new(config.width, config.height),
+            window: None,
+            start: Instant::now(),
+            frame_limit: None,
+        })
+    }
+
+    ///Makes `should_close` report true once `frames` frames were presented.
+    pub fn set_frame_limit(&mut self, frames: u64) {
+        self.frame_limit = Some(frames);
+    }
+
+    pub fn presented_frames(&self) -> u64 {
+        self.display.lock().unwrap().presented
+    }
+}
+
+impl PlatformManager<Backend> for VulkanPlatformManager {
+    fn new(config: WindowConfig) -> <Backend as apiBackend>::PlatformManager {
+        Self::try_new(config).unwrap_or_else(|e| panic!("Vulkan is not available: {}", e))
+    }
+
+    fn create_renderer(&mut self)
+                       -> (<Backend as apiBackend>::RendererApi, <Backend as apiBackend>::RendererDevice) {
+        (
+            VulkanRendererApi::new(self.raw.clone(), self.display.clone()),
+            VulkanRendererDevice::new(self.raw.clone(), self.display.clone())
+        )
+    }
+
+    fn should_close(&self) -> bool {
+        if let Some(window) = &self.window {
+            if window.window.borrow().should_close() {
+                return true;
+            }
+        }
+        match self.frame_limit {
+            Some(limit) => self.presented_frames() >= limit,
+            None => false,
+        }
+    }
+
+    fn poll_events(&self) -> Vec<Event> {
+        let window = match &self.window {
+            Some(window) => window,
+            None => return Vec::new(),
+        };
+        window.glfw.borrow_mut().poll_events();
+        let mut events = Vec::new();
+        for (_, event) in glfw::flush_messages(&window.events) {
+            match event {
+                glfw::WindowEvent::FramebufferSize(w, h) => {
+                    //swapchains are recreated at this size when they next present
+                    self.display.lock().unwrap().extent = vk::Extent2D { width: w as u32, height: h as u32 };
+                    events.push(Event::Resize(w, h));
+                }
+                glfw::WindowEvent::Key(key, _, action, _) => {
+                    if let (glfw::Key::Escape, glfw::Action::Press) = (key, action) {
+                        window.window.borrow_mut().set_should_close(true)
+                    }
+                    let action = match action {
+                        glfw::Action::Release => api::Action::Release,
+                        glfw::Action::Press => api::Action::Press,
+                        glfw::Action::Repeat => api::Action::Repeat,
+                    };
+                    events.push(Event::Key(key as u32, action));
+                }
+                _ => {}
+            }
+        }
+        events
+    }
+
+    fn current_time(&self) -> f64 {
+        let elapsed = self.start.elapsed();
+        elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) / 1_000_000_000.0
+    }
+
+    fn create_surface(&self) -> <Backend as api::Backend>::Surface {
+        crate::swapchain::VulkanSurface {
+            display: self.display.clone()
+        }
+    }
+
+    fn imgui_renderer(&mut self, imgui: &mut imgui::Context) -> VulkanImGuiRenderer {
+        VulkanImGuiRenderer::new(self.display.clone(), imgui)
+    }
+}
+
+///Drives imgui frames, draw data is not rendered yet.
+pub struct VulkanImGuiRenderer {
+    display: SharedDisplay,
+    last_frame: Instant,
+}
+
+impl VulkanImGuiRenderer {
+    fn new(display: SharedDisplay, imgui: &mut imgui::Context) -> VulkanImGuiRenderer {
+        //font atlas has to be built before the first frame
+        match imgui.fonts() {
+            imgui::FontAtlasRefMut::Owned(atlas) => {
+                atlas.build_rgba32_texture();
+            }
+            imgui::FontAtlasRefMut::Shared(mut atlas) => {
+                atlas.build_rgba32_texture();
+            }
+        }
+        VulkanImGuiRenderer { display, last_frame: Instant::now() }
+    }
+}
+
+impl ImGuiRenderer for VulkanImGuiRenderer {
+    fn new_frame<'im>(&mut self, imgui: &'im mut imgui::Context) -> imgui::Ui<'im> {
+        let extent = self.display.lock().unwrap().extent;
+        let now = Instant::now();
+        let delta = now - self.last_frame;
+        self.last_frame = now;
+
+        let io = imgui.io_mut();
+        io.display_size = [extent.width as f32, extent.height as f32];
+        io.display_framebuffer_scale = [1., 1.];
+        io.delta_time = delta.as_secs() as f32 + delta.subsec_nanos() as f32 / 1_000_000_000.0;
+        imgui.frame()
+    }
+
+    fn render(&self, ui: imgui::Ui) {
+        ui.render();
+    }
+
+    fn handle_events(&mut self, _imgui: &mut imgui::Context) {}
+}
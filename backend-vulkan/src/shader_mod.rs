@@ -0,0 +1,166 @@
+use std::sync::Arc;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use crate::core::{RawDevice, to_error};
+
+///GLSL is kept as source until a pipeline knows the bindings of its layout.
+#[derive(Debug)]
+pub struct VulkanShaderMod {
+    raw: Arc<RawDevice>,
+    pub(crate) stage: vk::ShaderStageFlags,
+    code: ShaderCode,
+}
+
+#[derive(Debug)]
+enum ShaderCode {
+    Module(vk::ShaderModule),
+    Glsl(String),
+}
+
+impl VulkanShaderMod {
+    pub(crate) fn new(raw: Arc<RawDevice>, desc: api::ShaderModDescriptor) -> Result<Self, api::Error> {
+        let stage = match desc.stype {
+            api::ShaderType::Vertex => vk::ShaderStageFlags::VERTEX,
+            api::ShaderType::Fragment => vk::ShaderStageFlags::FRAGMENT,
+        };
+        let code = match desc.source {
+            api::ShaderSource::SpirV(code) => ShaderCode::Module(create_module(&raw, &code)?),
+            api::ShaderSource::Glsl(source) => ShaderCode::Glsl(source),
+        };
+        Ok(VulkanShaderMod { raw, stage, code })
+    }
+
+    ///Names of the outputs of a GLSL vertex shader, in the order their locations are given.
+    pub(crate) fn varyings(&self) -> Vec<String> {
+        match &self.code {
+            ShaderCode::Glsl(source) => source.lines()
+                .filter_map(declaration)
+                .filter(|d| d.storage == "out")
+                .map(|d| d.name.to_owned())
+                .collect(),
+            ShaderCode::Module(_) => Vec::new(),
+        }
+    }
+
+    ///Module to build pipelines with, compiled from GLSL with the `bindings` of the layout.
+    pub(crate) fn for_layout(self, bindings: &[(&str, u32)], varyings: &[String]) -> Result<Self, api::Error> {
+        let source = match &self.code {
+            ShaderCode::Glsl(source) => source,
+            ShaderCode::Module(_) => return Ok(self),
+        };
+        let code = compile(source, self.stage, bindings, varyings)?;
+        let module = create_module(&self.raw, &code)?;
+        Ok(VulkanShaderMod { raw: self.raw.clone(), stage: self.stage, code: ShaderCode::Module(module) })
+    }
+
+    pub(crate) fn module(&self) -> vk::ShaderModule {
+        match self.code {
+            ShaderCode::Module(module) => module,
+            ShaderCode::Glsl(_) => vk::ShaderModule::null(),
+        }
+    }
+}
+
+impl Drop for VulkanShaderMod {
+    fn drop(&mut self) {
+        if let ShaderCode::Module(module) = self.code {
+            unsafe { self.raw.device.destroy_shader_module(module, None) }
+        }
+    }
+}
+
+fn create_module(raw: &RawDevice, code: &[u32]) -> Result<vk::ShaderModule, api::Error> {
+    unsafe {
+        raw.device.create_shader_module(&vk::ShaderModuleCreateInfo::builder().code(code), None)
+            .map_err(to_error)
+    }
+}
+
+///Compiles OpenGL GLSL to SPIR-V. Uniform blocks and samplers take the binding of their name
+///in set 0, outputs of the vertex stage and inputs of the fragment stage the position of their
+///name in `varyings`, and other inputs and outputs the next free location.
+pub(crate) fn compile(source: &str,
+                      stage: vk::ShaderStageFlags,
+                      bindings: &[(&str, u32)],
+                      varyings: &[String]) -> Result<Vec<u32>, api::Error> {
+    let (shader_type, varying) = if stage == vk::ShaderStageFlags::VERTEX {
+        (bevy_glsl_to_spirv::ShaderType::Vertex, "out")
+    } else {
+        (bevy_glsl_to_spirv::ShaderType::Fragment, "in")
+    };
+    let mut next_location = 0;
+    let mut vulkan = String::with_capacity(source.len());
+    for line in source.lines() {
+        let decl = match declaration(line) {
+            Some(decl) => decl,
+            None if line.trim_start().starts_with("#version") => {
+                vulkan.push_str("#version 450\n");
+                continue;
+            }
+            None => {
+                vulkan.push_str(line);
+                vulkan.push('\n');
+                continue;
+            }
+        };
+        let added = if decl.storage == "uniform" {
+            let binding = bindings.iter()
+                .find(|(name, _)| *name == decl.name)
+                .map(|(_, binding)| *binding)
+                .ok_or_else(|| api::Error::Unsupported(format!("Uniform {} without a layout hint", decl.name)))?;
+            format!("set = 0, binding = {}", binding)
+        } else if decl.qualifiers.contains("location") {
+            String::new()
+        } else if decl.storage == varying {
+            let location = varyings.iter()
+                .position(|name| name == decl.name)
+                .ok_or_else(|| api::Error::Unsupported(format!("Varying {} not written by the vertex shader", decl.name)))?;
+            format!("location = {}", location)
+        } else {
+            next_location += 1;
+            format!("location = {}", next_location - 1)
+        };
+        let qualifiers = match (decl.qualifiers.is_empty(), added.is_empty()) {
+            (true, _) => added,
+            (false, true) => decl.qualifiers.to_owned(),
+            (false, false) => format!("{}, {}", decl.qualifiers, added),
+        };
+        vulkan.push_str(&format!("layout ({}) {}\n", qualifiers, decl.rest));
+    }
+    bevy_glsl_to_spirv::compile(&vulkan, shader_type, None)
+        .map_err(api::Error::Unsupported)
+}
+
+///Global `in`, `out` or `uniform` declaration.
+struct Declaration<'a> {
+    ///Inside `layout (...)`, empty without one.
+    qualifiers: &'a str,
+    storage: &'a str,
+    name: &'a str,
+    ///The line after its layout.
+    rest: &'a str,
+}
+
+fn declaration(line: &str) -> Option<Declaration<'_>> {
+    let line = line.trim();
+    let (qualifiers, rest) = if line.starts_with("layout") {
+        let open = line.find('(')?;
+        let close = line.find(')')?;
+        (line[open + 1..close].trim(), line[close + 1..].trim_start())
+    } else {
+        ("", line)
+    };
+    let mut tokens = rest.split_whitespace()
+        .skip_while(|t| ["flat", "smooth", "noperspective"].contains(t));
+    let storage = tokens.next().filter(|t| ["in", "out", "uniform"].contains(t))?;
+    let ty = tokens.next()?;
+    //blocks are named before their brace, other declarations after their type
+    let name = match tokens.next() {
+        Some(name) if name != "{" => name,
+        _ => ty,
+    };
+    let name = name.trim_end_matches(&[';', '{'][..]);
+    Some(Declaration { qualifiers, storage, name, rest })
+}
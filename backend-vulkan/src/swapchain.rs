@@ -0,0 +1,328 @@
+use std::sync::{Arc, Mutex};
+
+use ash::extensions::khr;
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use crate::Backend;
+use crate::core::{RawDevice, to_error};
+use crate::framebuffer::{PassTarget, VulkanFramebuffer};
+use crate::image::{VulkanImage, VulkanImageView};
+use crate::memory::VulkanMemory;
+
+///Offscreen images rendering rotates between without a window.
+const IMAGE_COUNT: usize = 2;
+
+pub(crate) type SharedDisplay = Arc<Mutex<Display>>;
+
+///The window, or its offscreen replacement, rendered to outside of explicit render passes.
+pub(crate) struct Display {
+    ///Updated as the window is resized, swapchains are recreated to match.
+    pub(crate) extent: vk::Extent2D,
+    ///Image the next frame is rendered into, set once a swapchain exists.
+    pub(crate) target: Option<FrameTarget>,
+    pub(crate) viewport: (i32, i32, i32, i32),
+    pub(crate) clear_color: (f32, f32, f32, f32),
+    pub(crate) presented: u64,
+}
+
+impl Display {
+    pub(crate) fn new(width: u32, height: u32) -> SharedDisplay {
+        Arc::new(Mutex::new(Display {
+            extent: vk::Extent2D { width, height },
+            target: None,
+            viewport: (0, 0, width as i32, height as i32),
+            clear_color: (0., 0., 0., 1.),
+            presented: 0,
+        }))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct FrameTarget {
+    pub(crate) pass: PassTarget,
+    pub(crate) color: vk::Image,
+}
+
+pub struct VulkanSurface {
+    pub(crate) display: SharedDisplay,
+}
+
+///Colour image along with its memory when the swapchain does not get it from the window.
+type ColorImage = (vk::Image, Option<(VulkanImage, VulkanMemory)>);
+
+struct SwapchainImage {
+    framebuffer: VulkanFramebuffer,
+    _view: VulkanImageView,
+    color: vk::Image,
+    _offscreen: Option<(VulkanImage, VulkanMemory)>,
+}
+
+///`VK_KHR_swapchain` of the window surface. Images are acquired with the fence and presented
+///once every submission rendering them was waited on, so no semaphores are needed.
+struct WindowChain {
+    loader: khr::Swapchain,
+    swapchain: vk::SwapchainKHR,
+    fence: vk::Fence,
+}
+
+///Presents to the window of the device, or rotates rendering between offscreen images without one.
+pub struct VulkanSwapchain {
+    raw: Arc<RawDevice>,
+    display: SharedDisplay,
+    window: Option<WindowChain>,
+    images: Vec<SwapchainImage>,
+    _depth: Option<(VulkanImageView, VulkanImage, VulkanMemory)>,
+    extent: vk::Extent2D,
+    current: usize,
+}
+
+impl VulkanSwapchain {
+    pub(crate) fn new(raw: Arc<RawDevice>, surface: &VulkanSurface) -> Result<Self, api::Error> {
+        let window = match raw.surface {
+            Some(_) => Some(WindowChain {
+                loader: khr::Swapchain::new(&raw.instance, &raw.device),
+                swapchain: vk::SwapchainKHR::null(),
+                fence: unsafe { raw.device.create_fence(&vk::FenceCreateInfo::default(), None).map_err(to_error)? },
+            }),
+            None => None,
+        };
+        let mut swapchain = VulkanSwapchain {
+            raw,
+            display: surface.display.clone(),
+            window,
+            images: Vec::new(),
+            _depth: None,
+            extent: vk::Extent2D { width: 0, height: 0 },
+            current: 0,
+        };
+        swapchain.build()?;
+        swapchain.acquire()?;
+        Ok(swapchain)
+    }
+
+    ///Creates the images at the extent of the display, replacing those built before.
+    fn build(&mut self) -> Result<(), api::Error> {
+        let raw = self.raw.clone();
+        self.display.lock().unwrap().target = None;
+        self.images.clear();
+        let wanted = self.display.lock().unwrap().extent;
+        let (extent, colors) = match (self.window.as_mut(), raw.surface) {
+            (Some(window), Some(surface)) => window.recreate(&raw, surface, wanted)?,
+            _ => (wanted, offscreen_images(&raw, wanted)?),
+        };
+
+        let kind = api::image::Kind::D2(extent.width, extent.height, 1);
+        let mut depth = VulkanImage::with_format(raw.clone(), kind, raw.depth_format,
+                                                 vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT)?;
+        let depth_memory = VulkanMemory::dedicated(raw.clone(), &mut depth)?;
+        let depth_view = VulkanImageView::new(raw.clone(), &depth)?;
+
+        let format = raw.window_format.format;
+        let layout = [
+            (api::AttachmentLayout::Color, format),
+            (api::AttachmentLayout::Depth, raw.depth_format),
+        ];
+        self.images = colors.into_iter()
+            .map(|(color, offscreen)| {
+                let view = VulkanImageView::from_raw(raw.clone(), color, format, extent)?;
+                let framebuffer = VulkanFramebuffer::from_raw(
+                    raw.clone(), raw.render_pass, &layout,
+                    vec![view.view, depth_view.view], extent,
+                )?;
+                Ok(SwapchainImage { framebuffer, _view: view, color, _offscreen: offscreen })
+            })
+            .collect::<Result<_, api::Error>>()?;
+        self._depth = Some((depth_view, depth, depth_memory));
+        self.extent = extent;
+        self.current = 0;
+        self.display.lock().unwrap().extent = extent;
+        Ok(())
+    }
+
+    ///Makes the next image the target of the display, waiting for the window to release one.
+    fn acquire(&mut self) -> Result<(), api::Error> {
+        while let Some(window) = &self.window {
+            match window.acquire(&self.raw)? {
+                Some(index) => {
+                    self.current = index as usize;
+                    //contents are not kept between frames
+                    transition(&self.raw, self.images[self.current].color,
+                               vk::ImageLayout::UNDEFINED, vk::ImageLayout::GENERAL)?;
+                    break;
+                }
+                None => self.build()?,
+            }
+        }
+        let image = &self.images[self.current];
+        self.display.lock().unwrap().target = Some(FrameTarget {
+            pass: image.framebuffer.target,
+            color: image.color,
+        });
+        Ok(())
+    }
+
+    fn next_frame(&mut self) -> Result<(), api::Error> {
+        let window = match &self.window {
+            Some(window) => window,
+            None => {
+                //every submission is waited on, the image is complete here
+                self.current = (self.current + 1) % self.images.len();
+                return self.acquire();
+            }
+        };
+        let wanted = self.display.lock().unwrap().extent;
+        //minimised, frames go on into the acquired image until the window has an area again
+        if wanted.width == 0 || wanted.height == 0 {
+            return Ok(());
+        }
+        let stale = window.present(&self.raw, self.images[self.current].color, self.current as u32)?;
+        if stale || (wanted.width, wanted.height) != (self.extent.width, self.extent.height) {
+            self.build()?;
+        }
+        self.acquire()
+    }
+}
+
+impl WindowChain {
+    ///Replaces the swapchain with one of the current size of `surface`, `wanted` when the surface
+    ///takes the size of its swapchain.
+    fn recreate(&mut self, raw: &RawDevice, surface: vk::SurfaceKHR, wanted: vk::Extent2D) -> Result<(vk::Extent2D, Vec<ColorImage>), api::Error> {
+        unsafe {
+            let caps = raw.surface_loader.get_physical_device_surface_capabilities(raw.physical, surface)
+                .map_err(to_error)?;
+            let extent = if caps.current_extent.width == u32::MAX {
+                vk::Extent2D {
+                    width: wanted.width.clamp(caps.min_image_extent.width, caps.max_image_extent.width),
+                    height: wanted.height.clamp(caps.min_image_extent.height, caps.max_image_extent.height),
+                }
+            } else {
+                caps.current_extent
+            };
+            //one more than the minimum, so one can be acquired while the window shows another
+            let image_count = match caps.max_image_count {
+                0 => caps.min_image_count + 1,
+                max => (caps.min_image_count + 1).min(max),
+            };
+            let composite_alpha = [
+                vk::CompositeAlphaFlagsKHR::OPAQUE,
+                vk::CompositeAlphaFlagsKHR::INHERIT,
+                vk::CompositeAlphaFlagsKHR::PRE_MULTIPLIED,
+                vk::CompositeAlphaFlagsKHR::POST_MULTIPLIED,
+            ].iter()
+                .copied()
+                .find(|mode| caps.supported_composite_alpha.contains(*mode))
+                .unwrap_or(vk::CompositeAlphaFlagsKHR::OPAQUE);
+
+            let old = self.swapchain;
+            self.swapchain = self.loader.create_swapchain(&vk::SwapchainCreateInfoKHR::builder()
+                .surface(surface)
+                .min_image_count(image_count)
+                .image_format(raw.window_format.format)
+                .image_color_space(raw.window_format.color_space)
+                .image_extent(extent)
+                .image_array_layers(1)
+                //cleared with transfers by `RendererApi::clear_color`
+                .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_DST)
+                .image_sharing_mode(vk::SharingMode::EXCLUSIVE)
+                .pre_transform(caps.current_transform)
+                .composite_alpha(composite_alpha)
+                .present_mode(vk::PresentModeKHR::FIFO)
+                .clipped(true)
+                .old_swapchain(old), None)
+                .map_err(to_error)?;
+            if old != vk::SwapchainKHR::null() {
+                self.loader.destroy_swapchain(old, None);
+            }
+            let images = self.loader.get_swapchain_images(self.swapchain).map_err(to_error)?;
+            Ok((extent, images.into_iter().map(|image| (image, None)).collect()))
+        }
+    }
+
+    ///Index of the next image, `None` when the swapchain is out of date.
+    fn acquire(&self, raw: &RawDevice) -> Result<Option<u32>, api::Error> {
+        unsafe {
+            let index = match self.loader.acquire_next_image(self.swapchain, u64::MAX, vk::Semaphore::null(), self.fence) {
+                Ok((index, _)) => index,
+                Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => return Ok(None),
+                Err(e) => return Err(to_error(e)),
+            };
+            raw.device.wait_for_fences(&[self.fence], true, u64::MAX).map_err(to_error)?;
+            raw.device.reset_fences(&[self.fence]).map_err(to_error)?;
+            Ok(Some(index))
+        }
+    }
+
+    ///Whether the swapchain no longer matches the surface and has to be recreated.
+    fn present(&self, raw: &RawDevice, image: vk::Image, index: u32) -> Result<bool, api::Error> {
+        transition(raw, image, vk::ImageLayout::GENERAL, vk::ImageLayout::PRESENT_SRC_KHR)?;
+        let swapchains = [self.swapchain];
+        let indices = [index];
+        let presented = unsafe {
+            self.loader.queue_present(raw.queue, &vk::PresentInfoKHR::builder()
+                .swapchains(&swapchains)
+                .image_indices(&indices))
+        };
+        match presented {
+            Ok(suboptimal) => Ok(suboptimal),
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => Ok(true),
+            Err(e) => Err(to_error(e)),
+        }
+    }
+}
+
+fn offscreen_images(raw: &Arc<RawDevice>, extent: vk::Extent2D) -> Result<Vec<ColorImage>, api::Error> {
+    let kind = api::image::Kind::D2(extent.width, extent.height, 1);
+    (0..IMAGE_COUNT)
+        .map(|_| {
+            let mut color = VulkanImage::new(raw.clone(), kind.clone(), api::image::Format::Rgba8)?;
+            let memory = VulkanMemory::dedicated(raw.clone(), &mut color)?;
+            Ok((color.image, Some((color, memory))))
+        })
+        .collect()
+}
+
+///Moves the colour `image` from layout `from` to `to` once the work submitted before is done.
+fn transition(raw: &RawDevice, image: vk::Image, from: vk::ImageLayout, to: vk::ImageLayout) -> Result<(), api::Error> {
+    let barrier = vk::ImageMemoryBarrier::builder()
+        .src_access_mask(vk::AccessFlags::MEMORY_WRITE)
+        .dst_access_mask(vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE)
+        .old_layout(from)
+        .new_layout(to)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .image(image)
+        .subresource_range(crate::image::color_range())
+        .build();
+    raw.submit(|cb| unsafe {
+        raw.device.cmd_pipeline_barrier(cb,
+                                        vk::PipelineStageFlags::ALL_COMMANDS,
+                                        vk::PipelineStageFlags::ALL_COMMANDS,
+                                        vk::DependencyFlags::empty(),
+                                        &[], &[], &[barrier]);
+        Ok(())
+    })
+}
+
+impl Drop for VulkanSwapchain {
+    fn drop(&mut self) {
+        self.display.lock().unwrap().target = None;
+        //views and framebuffers go before the swapchain owning their images
+        self.images.clear();
+        if let Some(window) = &self.window {
+            unsafe {
+                window.loader.destroy_swapchain(window.swapchain, None);
+                self.raw.device.destroy_fence(window.fence, None);
+            }
+        }
+    }
+}
+
+impl api::Swapchain<Backend> for VulkanSwapchain {
+    fn present(&mut self, _frame_index: u32) {
+        self.display.lock().unwrap().presented += 1;
+        if let Err(e) = self.next_frame() {
+            eprintln!("Frame not presented: {}", e);
+        }
+    }
+}
@@ -5,8 +5,8 @@ use specs::{Builder, Join, Read, ReadStorage, WorldExt, Write, WriteStorage};
 use rx_engine::{
     backend,
     ecs::{
-        ActiveCamera,
-        components::{Camera, Position, Render, Rotation, Transformation, Velocity},
+        ActiveEnvironment,
+        components::{Camera, DirectionalLight, PointLight, Position, Render, Rotation, Transformation, Velocity},
         DeltaTime,
         InputEvent,
         InputEventsRead,
@@ -24,6 +24,8 @@ use rx_engine::{
         WindowConfig,
     },
     loader::Loader,
+    material::{Material, PBR_PIPELINE, TRANSPARENT_PIPELINE},
+    post::PostPass,
     Matrix4f,
     rand::{Rng, RngCore},
     specs,
@@ -99,12 +101,12 @@ impl<'a> rx_engine::specs::System<'a> for ControlsSystem {
         for e in &platform.0 {
             if let Event::Key(code, action) = e {
                 let event = match code {
-                    65 => InputEvent::Up(action_to_type(action)),       //space
-                    54 => InputEvent::Down(action_to_type(action)),     //C
-                    38 => InputEvent::Left(action_to_type(action)),     //A
-                    40 => InputEvent::Right(action_to_type(action)),    //D
-                    25 => InputEvent::Forward(action_to_type(action)),  //W
-                    39 => InputEvent::Backward(action_to_type(action)), //S
+                    32 => InputEvent::Up(action_to_type(action)),       //space
+                    67 => InputEvent::Down(action_to_type(action)),     //C
+                    65 => InputEvent::Left(action_to_type(action)),     //A
+                    68 => InputEvent::Right(action_to_type(action)),    //D
+                    87 => InputEvent::Forward(action_to_type(action)),  //W
+                    83 => InputEvent::Backward(action_to_type(action)), //S
                     _ => InputEvent::None
                 };
 
@@ -116,10 +118,12 @@ impl<'a> rx_engine::specs::System<'a> for ControlsSystem {
     }
 }
 
-fn main() {
-    let mut engine: rx_engine::run::RxEngine = rx_engine::run::build_engine(
+fn main() -> Result<(), rx_engine::api::Error> {
+    let mut engine: rx_engine::run::RxEngine<backend::Backend> = rx_engine::run::build_engine(
         WindowConfig { width: 600, height: 400 },
         EcsLayerBuilder::new(Box::new(|mut w, d, ctx| {
+            //the default sky as background
+            w.insert(ActiveEnvironment(Some(0)));
             w.create_entity()
                 .with(Camera::default())
                 .with(Position {
@@ -138,6 +142,78 @@ fn main() {
                     z: 0.0,
                 })
                 .build();
+            //minimap looking down on the scene from the top right corner
+            w.create_entity()
+                .with(Camera {
+                    priority: 1,
+                    viewport: [0.7, 0.7, 0.25, 0.25],
+                    casts_shadows: false,
+                    ..Camera::default()
+                })
+                .with(Position {
+                    x: 0.0,
+                    y: 150.0,
+                    z: 0.0,
+                })
+                .with(Rotation {
+                    x: -90.0,
+                    y: 0.0,
+                    z: 0.0,
+                })
+                .build();
+
+            w.create_entity()
+                .with(DirectionalLight {
+                    direction: [-1., -1., -1.],
+                    color: [1., 1., 1.],
+                    intensity: 0.8,
+                    casts_shadows: true,
+                })
+                .build();
+            w.create_entity()
+                .with(PointLight {
+                    color: [1., 0.8, 0.6],
+                    intensity: 40.,
+                    range: 50.,
+                })
+                .with(Position {
+                    x: 10.,
+                    y: 10.,
+                    z: 30.,
+                })
+                .build();
+
+            let meshes: Vec<u32> = ["sphere.obj", "torus.obj", "tetrahedron.obj"].iter()
+                .map(|file| ctx.renderer.load_mesh(&ctx.renderer_device, &relative_to_current_path(&["client", "resources", file]))
+                    .unwrap_or_else(|e| panic!("Loading {} failed: {}", file, e)))
+                .collect();
+
+            let materials: Vec<u32> = vec![
+                Material { base_color: [1., 0.6, 0.6, 1.], ..Material::default() },
+                Material { base_color: [0.6, 1., 0.6, 1.], ..Material::default() },
+                //gold
+                Material { pipeline: PBR_PIPELINE, base_color: [1., 0.78, 0.34, 1.], metallic: 1., roughness: 0.3, ..Material::default() },
+                //glass
+                Material { pipeline: TRANSPARENT_PIPELINE, base_color: [0.6, 0.8, 1., 0.4], ..Material::default() },
+            ].into_iter()
+                .map(|material| ctx.renderer.add_material(&ctx.renderer_device, material)
+                    .unwrap_or_else(|e| panic!("Adding material failed: {}", e)))
+                .collect();
+
+            //bloom at half resolution, then ACES tone mapping, gamma and anti-aliasing
+            let post = |file: &str| PostPass::new(relative_to_current_path(&["client", "src", "test", "post", file]));
+            for pass in vec![
+                PostPass { scale: 0.5, params: [1., 0., 0., 0.], ..post("bright.glsl") },
+                PostPass { scale: 0.5, params: [1., 0., 0., 0.], ..post("blur.glsl") },
+                PostPass { scale: 0.5, params: [0., 1., 0., 0.], ..post("blur.glsl") },
+                PostPass { params: [0.6, 0., 0., 0.], reads_scene: true, ..post("bloom.glsl") },
+                PostPass { params: [1., 1., 0., 0.], ..post("tonemap.glsl") },
+                PostPass { params: [2.2, 0., 0., 0.], ..post("gamma.glsl") },
+                post("fxaa.glsl"),
+            ] {
+                ctx.renderer.add_post_pass(&ctx.renderer_device, pass)
+                    .unwrap_or_else(|e| panic!("Adding post-process pass failed: {}", e));
+            }
 
             let mut rng = rx_engine::rand::thread_rng();
 
@@ -177,8 +253,8 @@ fn main() {
                         z: rng.gen::<f32>() * 20.,
                     })
                     .with(Render {
-                        va: 0,
-                        material: 0,
+                        va: meshes[i % meshes.len()],
+                        material: materials[i / meshes.len() % materials.len()],
                     })
                     .build();
             }
@@ -188,10 +264,11 @@ fn main() {
             let d = d.with(CameraMoveSystem, "cam_mov_sys", &[]);
             return (w, d);
         })),
-    );
+    )?;
     dbg!();
 
 
-    engine.run();
-    println!("Bye!")
+    engine.run()?;
+    println!("Bye!");
+    Ok(())
 }
@@ -1,8 +1,15 @@
 #version 330 core
-in vec4 out_Color;
+in vec3 out_Position;
+in vec3 out_Normal;
+in vec2 out_Uv;
+flat in int out_Material;
+in float out_Depth;
 out vec4 Color;
 
-uniform vec3 color_r;
+uniform sampler2D u_texture;
+uniform sampler2D u_shadow0;
+uniform sampler2D u_shadow1;
+uniform sampler2D u_shadow2;
 
 layout (std140) uniform Matricies {
     mat4 view;
@@ -10,6 +17,110 @@ layout (std140) uniform Matricies {
     mat4 trans;
 } matrix;
 
+struct Material {
+    vec4 base_color;
+    float roughness;
+    float metallic;
+    vec4 emissive;
+};
+
+layout (std140) uniform Materials {
+    Material data[256];
+} materials;
+
+struct DirectionalLight {
+    vec4 direction; //w is 1 when it casts shadows
+    vec4 color;
+};
+
+struct PointLight {
+    vec4 position; //w is the range
+    vec4 color;
+};
+
+struct SpotLight {
+    vec4 position;  //w is the range
+    vec4 direction; //w is the cosine of the outer angle
+    vec4 color;     //w is the cosine of the inner angle
+};
+
+layout (std140) uniform Lights {
+    vec4 eye;
+    ivec4 counts;
+    DirectionalLight directional[16];
+    PointLight point[16];
+    SpotLight spot[16];
+    mat4 cascades[3];
+    vec4 splits; //view space depth each cascade ends at
+} lights;
+
+const vec3 AMBIENT = vec3(0.05);
+
+//Blinn-Phong of a light arriving from `to_light`
+vec3 shade(vec3 normal, vec3 to_eye, vec3 to_light, vec3 color, vec3 albedo, float shininess) {
+    float diffuse = max(dot(normal, to_light), 0.0);
+    vec3 half_way = normalize(to_light + to_eye);
+    float specular = diffuse > 0.0 ? pow(max(dot(normal, half_way), 0.0), shininess) : 0.0;
+    return color * (albedo * diffuse + vec3(specular));
+}
+
+float shadow_depth(int cascade, vec2 uv) {
+    if (cascade == 0) return textureLod(u_shadow0, uv, 0.0).r;
+    if (cascade == 1) return textureLod(u_shadow1, uv, 0.0).r;
+    return textureLod(u_shadow2, uv, 0.0).r;
+}
+
+//Fraction of a 3x3 texel neighbourhood of the cascade covering `out_Depth` that is lit
+float shadow(vec3 normal, vec3 to_light) {
+    if (out_Depth >= lights.splits.z) return 1.0;
+    int cascade = out_Depth < lights.splits.x ? 0 : out_Depth < lights.splits.y ? 1 : 2;
+    vec4 clip = lights.cascades[cascade] * vec4(out_Position, 1.0);
+    vec3 coords = clip.xyz / clip.w * 0.5 + 0.5;
+    if (any(lessThan(coords, vec3(0.0))) || any(greaterThan(coords, vec3(1.0)))) return 1.0;
+
+    float bias = max(0.005 * (1.0 - dot(normal, to_light)), 0.0005);
+    vec2 texel = 1.0 / vec2(textureSize(u_shadow0, 0));
+    float lit = 0.0;
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            lit += coords.z - bias > shadow_depth(cascade, coords.xy + vec2(x, y) * texel) ? 0.0 : 1.0;
+        }
+    }
+    return lit / 9.0;
+}
+
+float falloff(float distance, float range) {
+    float x = clamp(1.0 - pow(distance / range, 4.0), 0.0, 1.0);
+    return x * x / (distance * distance + 1.0);
+}
+
 void main(){
-    Color = vec4(out_Color.x, out_Color.y, out_Color.z, 1.0f);
+    Material material = materials.data[out_Material];
+    vec3 albedo = (material.base_color * texture(u_texture, out_Uv)).rgb;
+    float shininess = exp2(10.0 * (1.0 - material.roughness) + 1.0);
+
+    vec3 normal = normalize(out_Normal);
+    vec3 to_eye = normalize(lights.eye.xyz - out_Position);
+
+    vec3 color = AMBIENT * albedo;
+    for (int i = 0; i < lights.counts.x; i++) {
+        DirectionalLight light = lights.directional[i];
+        vec3 to_light = normalize(-light.direction.xyz);
+        float lit = light.direction.w > 0.5 ? shadow(normal, to_light) : 1.0;
+        color += lit * shade(normal, to_eye, to_light, light.color.rgb, albedo, shininess);
+    }
+    for (int i = 0; i < lights.counts.y; i++) {
+        PointLight light = lights.point[i];
+        vec3 to_light = light.position.xyz - out_Position;
+        float attenuation = falloff(length(to_light), light.position.w);
+        color += attenuation * shade(normal, to_eye, normalize(to_light), light.color.rgb, albedo, shininess);
+    }
+    for (int i = 0; i < lights.counts.z; i++) {
+        SpotLight light = lights.spot[i];
+        vec3 to_light = light.position.xyz - out_Position;
+        float cone = smoothstep(light.direction.w, light.color.w, dot(normalize(-to_light), normalize(light.direction.xyz)));
+        float attenuation = cone * falloff(length(to_light), light.position.w);
+        color += attenuation * shade(normal, to_eye, normalize(to_light), light.color.rgb, albedo, shininess);
+    }
+    Color = vec4(color, material.base_color.a);
 }
\ No newline at end of file
@@ -0,0 +1,195 @@
+#version 330 core
+in vec3 out_Position;
+in vec3 out_Normal;
+in vec2 out_Uv;
+flat in int out_Material;
+in float out_Depth;
+out vec4 Color;
+
+uniform sampler2D u_texture;
+uniform sampler2D u_normal;
+uniform sampler2D u_metallic_roughness;
+uniform sampler2D u_occlusion;
+uniform sampler2D u_emissive;
+uniform sampler2D u_shadow0;
+uniform sampler2D u_shadow1;
+uniform sampler2D u_shadow2;
+//equirectangular, see `ibl.rs`
+uniform sampler2D u_irradiance;
+uniform sampler2D u_prefiltered;
+uniform sampler2D u_brdf;
+
+layout (std140) uniform Matricies {
+    mat4 view;
+    mat4 projection;
+    mat4 trans;
+} matrix;
+
+struct Material {
+    vec4 base_color;
+    float roughness;
+    float metallic;
+    vec4 emissive;
+};
+
+layout (std140) uniform Materials {
+    Material data[256];
+} materials;
+
+struct DirectionalLight {
+    vec4 direction; //w is 1 when it casts shadows
+    vec4 color;
+};
+
+struct PointLight {
+    vec4 position; //w is the range
+    vec4 color;
+};
+
+struct SpotLight {
+    vec4 position;  //w is the range
+    vec4 direction; //w is the cosine of the outer angle
+    vec4 color;     //w is the cosine of the inner angle
+};
+
+layout (std140) uniform Lights {
+    vec4 eye;
+    ivec4 counts;
+    DirectionalLight directional[16];
+    PointLight point[16];
+    SpotLight spot[16];
+    mat4 cascades[3];
+    vec4 splits; //view space depth each cascade ends at
+} lights;
+
+const float PI = 3.14159265;
+//PREFILTERED_LEVELS - 1
+const float MAX_LOD = 4.0;
+
+vec2 equirect(vec3 direction) {
+    vec3 d = normalize(direction);
+    return vec2(atan(d.z, d.x) / (2.0 * PI) + 0.5, acos(clamp(d.y, -1.0, 1.0)) / PI);
+}
+
+float distribution_ggx(float n_dot_h, float roughness) {
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+float geometry_smith(float n_dot_v, float n_dot_l, float roughness) {
+    float k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    return n_dot_v / (n_dot_v * (1.0 - k) + k) * n_dot_l / (n_dot_l * (1.0 - k) + k);
+}
+
+vec3 fresnel_schlick(float cos_theta, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(1.0 - cos_theta, 5.0);
+}
+
+vec3 fresnel_schlick_roughness(float cos_theta, vec3 f0, float roughness) {
+    return f0 + (max(vec3(1.0 - roughness), f0) - f0) * pow(1.0 - cos_theta, 5.0);
+}
+
+//Cook-Torrance of a light arriving from `to_light`
+vec3 shade(vec3 normal, vec3 to_eye, vec3 to_light, vec3 radiance, vec3 albedo, float metallic, float roughness, vec3 f0) {
+    vec3 half_way = normalize(to_light + to_eye);
+    float n_dot_l = max(dot(normal, to_light), 0.0);
+    float n_dot_v = max(dot(normal, to_eye), 1e-4);
+    vec3 f = fresnel_schlick(max(dot(half_way, to_eye), 0.0), f0);
+    vec3 specular = distribution_ggx(max(dot(normal, half_way), 0.0), roughness)
+        * geometry_smith(n_dot_v, n_dot_l, roughness) * f / (4.0 * n_dot_v * n_dot_l + 1e-4);
+    vec3 diffuse = (1.0 - f) * (1.0 - metallic) * albedo / PI;
+    return (diffuse + specular) * radiance * n_dot_l;
+}
+
+float shadow_depth(int cascade, vec2 uv) {
+    if (cascade == 0) return textureLod(u_shadow0, uv, 0.0).r;
+    if (cascade == 1) return textureLod(u_shadow1, uv, 0.0).r;
+    return textureLod(u_shadow2, uv, 0.0).r;
+}
+
+//Fraction of a 3x3 texel neighbourhood of the cascade covering `out_Depth` that is lit
+float shadow(vec3 normal, vec3 to_light) {
+    if (out_Depth >= lights.splits.z) return 1.0;
+    int cascade = out_Depth < lights.splits.x ? 0 : out_Depth < lights.splits.y ? 1 : 2;
+    vec4 clip = lights.cascades[cascade] * vec4(out_Position, 1.0);
+    vec3 coords = clip.xyz / clip.w * 0.5 + 0.5;
+    if (any(lessThan(coords, vec3(0.0))) || any(greaterThan(coords, vec3(1.0)))) return 1.0;
+
+    float bias = max(0.005 * (1.0 - dot(normal, to_light)), 0.0005);
+    vec2 texel = 1.0 / vec2(textureSize(u_shadow0, 0));
+    float lit = 0.0;
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            lit += coords.z - bias > shadow_depth(cascade, coords.xy + vec2(x, y) * texel) ? 0.0 : 1.0;
+        }
+    }
+    return lit / 9.0;
+}
+
+float falloff(float distance, float range) {
+    float x = clamp(1.0 - pow(distance / range, 4.0), 0.0, 1.0);
+    return x * x / (distance * distance + 1.0);
+}
+
+//Perturbs `normal` by the normal map, with a tangent frame from the derivatives of the position and uv
+vec3 mapped_normal(vec3 normal) {
+    vec3 dp1 = dFdx(out_Position);
+    vec3 dp2 = dFdy(out_Position);
+    vec2 duv1 = dFdx(out_Uv);
+    vec2 duv2 = dFdy(out_Uv);
+    vec3 dp2perp = cross(dp2, normal);
+    vec3 dp1perp = cross(normal, dp1);
+    vec3 tangent = dp2perp * duv1.x + dp1perp * duv2.x;
+    vec3 bitangent = dp2perp * duv1.y + dp1perp * duv2.y;
+    float scale = inversesqrt(max(max(dot(tangent, tangent), dot(bitangent, bitangent)), 1e-12));
+    vec3 mapped = texture(u_normal, out_Uv).xyz * 2.0 - 1.0;
+    return normalize(mat3(tangent * scale, bitangent * scale, normal) * mapped);
+}
+
+void main(){
+    Material material = materials.data[out_Material];
+    vec4 base_color = material.base_color * texture(u_texture, out_Uv);
+    vec3 albedo = base_color.rgb;
+    vec4 metallic_roughness = texture(u_metallic_roughness, out_Uv);
+    float roughness = clamp(material.roughness * metallic_roughness.g, 0.04, 1.0);
+    float metallic = clamp(material.metallic * metallic_roughness.b, 0.0, 1.0);
+    float occlusion = texture(u_occlusion, out_Uv).r;
+    vec3 emissive = material.emissive.rgb * texture(u_emissive, out_Uv).rgb;
+    vec3 f0 = mix(vec3(0.04), albedo, metallic);
+
+    vec3 normal = mapped_normal(normalize(out_Normal));
+    vec3 to_eye = normalize(lights.eye.xyz - out_Position);
+
+    vec3 color = vec3(0.0);
+    for (int i = 0; i < lights.counts.x; i++) {
+        DirectionalLight light = lights.directional[i];
+        vec3 to_light = normalize(-light.direction.xyz);
+        float lit = light.direction.w > 0.5 ? shadow(normal, to_light) : 1.0;
+        color += lit * shade(normal, to_eye, to_light, light.color.rgb, albedo, metallic, roughness, f0);
+    }
+    for (int i = 0; i < lights.counts.y; i++) {
+        PointLight light = lights.point[i];
+        vec3 to_light = light.position.xyz - out_Position;
+        float attenuation = falloff(length(to_light), light.position.w);
+        color += attenuation * shade(normal, to_eye, normalize(to_light), light.color.rgb, albedo, metallic, roughness, f0);
+    }
+    for (int i = 0; i < lights.counts.z; i++) {
+        SpotLight light = lights.spot[i];
+        vec3 to_light = light.position.xyz - out_Position;
+        float cone = smoothstep(light.direction.w, light.color.w, dot(normalize(-to_light), normalize(light.direction.xyz)));
+        float attenuation = cone * falloff(length(to_light), light.position.w);
+        color += attenuation * shade(normal, to_eye, normalize(to_light), light.color.rgb, albedo, metallic, roughness, f0);
+    }
+
+    //split sum image based lighting
+    float n_dot_v = max(dot(normal, to_eye), 0.0);
+    vec3 f = fresnel_schlick_roughness(n_dot_v, f0, roughness);
+    vec3 diffuse = (1.0 - f) * (1.0 - metallic) * albedo * textureLod(u_irradiance, equirect(normal), 0.0).rgb;
+    vec3 prefiltered = textureLod(u_prefiltered, equirect(reflect(-to_eye, normal)), roughness * MAX_LOD).rgb;
+    vec2 brdf = textureLod(u_brdf, vec2(n_dot_v, roughness), 0.0).rg;
+    color += (diffuse + prefiltered * (f * brdf.x + brdf.y)) * occlusion;
+
+    Color = vec4(color + emissive, base_color.a);
+}
\ No newline at end of file
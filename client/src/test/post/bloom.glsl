@@ -0,0 +1,18 @@
+#version 330 core
+in vec2 out_Uv;
+out vec4 Color;
+
+uniform sampler2D u_input;
+
+layout (std140) uniform Post {
+    vec4 params;
+    vec4 texel; //size of a texel of u_input, then its extent
+} post;
+
+uniform sampler2D u_scene;
+
+//Adds the blurred bright parts scaled by params.x back onto the scene
+void main() {
+    vec3 scene = texture(u_scene, out_Uv).rgb;
+    Color = vec4(scene + texture(u_input, out_Uv).rgb * post.params.x, 1.0);
+}
\ No newline at end of file
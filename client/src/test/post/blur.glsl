@@ -0,0 +1,23 @@
+#version 330 core
+in vec2 out_Uv;
+out vec4 Color;
+
+uniform sampler2D u_input;
+
+layout (std140) uniform Post {
+    vec4 params;
+    vec4 texel; //size of a texel of u_input, then its extent
+} post;
+
+const float WEIGHTS[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+//Gaussian blur along the direction in params.xy, run twice for both axes
+void main() {
+    vec2 step = post.params.xy * post.texel.xy;
+    vec3 color = texture(u_input, out_Uv).rgb * WEIGHTS[0];
+    for (int i = 1; i < 5; i++) {
+        color += texture(u_input, out_Uv + step * float(i)).rgb * WEIGHTS[i];
+        color += texture(u_input, out_Uv - step * float(i)).rgb * WEIGHTS[i];
+    }
+    Color = vec4(color, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,17 @@
+#version 330 core
+in vec2 out_Uv;
+out vec4 Color;
+
+uniform sampler2D u_input;
+
+layout (std140) uniform Post {
+    vec4 params;
+    vec4 texel; //size of a texel of u_input, then its extent
+} post;
+
+//Keeps what is brighter than the threshold in params.x, what blooms
+void main() {
+    vec3 color = texture(u_input, out_Uv).rgb;
+    float brightness = max(color.r, max(color.g, color.b));
+    Color = vec4(color * max(brightness - post.params.x, 0.0) / max(brightness, 1e-4), 1.0);
+}
\ No newline at end of file
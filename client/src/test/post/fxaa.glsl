@@ -0,0 +1,47 @@
+#version 330 core
+in vec2 out_Uv;
+out vec4 Color;
+
+uniform sampler2D u_input;
+
+layout (std140) uniform Post {
+    vec4 params;
+    vec4 texel; //size of a texel of u_input, then its extent
+} post;
+
+const float SPAN_MAX = 8.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+const float REDUCE_MIN = 1.0 / 128.0;
+
+float luma(vec3 color) {
+    return dot(color, vec3(0.299, 0.587, 0.114));
+}
+
+//Fast approximate anti-aliasing of tone mapped colour, blurring along the edges found in luma
+void main() {
+    vec2 texel = post.texel.xy;
+    vec3 nw = texture(u_input, out_Uv + vec2(-1.0, -1.0) * texel).rgb;
+    vec3 ne = texture(u_input, out_Uv + vec2(1.0, -1.0) * texel).rgb;
+    vec3 sw = texture(u_input, out_Uv + vec2(-1.0, 1.0) * texel).rgb;
+    vec3 se = texture(u_input, out_Uv + vec2(1.0, 1.0) * texel).rgb;
+    vec3 m = texture(u_input, out_Uv).rgb;
+    float luma_nw = luma(nw);
+    float luma_ne = luma(ne);
+    float luma_sw = luma(sw);
+    float luma_se = luma(se);
+    float luma_m = luma(m);
+    float luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    float luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    vec2 direction = vec2(-((luma_nw + luma_ne) - (luma_sw + luma_se)), (luma_nw + luma_sw) - (luma_ne + luma_se));
+    float reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float scale = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+    direction = clamp(direction * scale, vec2(-SPAN_MAX), vec2(SPAN_MAX)) * texel;
+
+    vec3 a = 0.5 * (texture(u_input, out_Uv + direction * (1.0 / 3.0 - 0.5)).rgb
+        + texture(u_input, out_Uv + direction * (2.0 / 3.0 - 0.5)).rgb);
+    vec3 b = a * 0.5 + 0.25 * (texture(u_input, out_Uv - direction * 0.5).rgb
+        + texture(u_input, out_Uv + direction * 0.5).rgb);
+    float luma_b = luma(b);
+    Color = vec4(luma_b < luma_min || luma_b > luma_max ? a : b, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,16 @@
+#version 330 core
+in vec2 out_Uv;
+out vec4 Color;
+
+uniform sampler2D u_input;
+
+layout (std140) uniform Post {
+    vec4 params;
+    vec4 texel; //size of a texel of u_input, then its extent
+} post;
+
+//Encodes linear colour with the gamma in params.x
+void main() {
+    vec3 color = texture(u_input, out_Uv).rgb;
+    Color = vec4(pow(color, vec3(1.0 / post.params.x)), 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,29 @@
+#version 330 core
+in vec2 out_Uv;
+out vec4 Color;
+
+uniform sampler2D u_input;
+
+layout (std140) uniform Post {
+    vec4 params;
+    vec4 texel; //size of a texel of u_input, then its extent
+} post;
+
+vec3 aces(vec3 x) {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+//Maps HDR colour scaled by the exposure in params.x to [0, 1] with the operator in params.y:
+//0 is Reinhard, 1 ACES filmic and 2 clamps
+void main() {
+    vec3 color = texture(u_input, out_Uv).rgb * post.params.x;
+    int operator = int(post.params.y + 0.5);
+    if (operator == 0) {
+        color = color / (color + 1.0);
+    } else if (operator == 1) {
+        color = aces(color);
+    } else {
+        color = clamp(color, 0.0, 1.0);
+    }
+    Color = vec4(color, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,9 @@
+#version 330 core
+layout (location = 0) in vec3 position;
+
+out vec2 out_Uv;
+
+void main() {
+    gl_Position = vec4(position.xy, 0.0, 1.0);
+    out_Uv = position.xy * 0.5 + 0.5;
+}
\ No newline at end of file
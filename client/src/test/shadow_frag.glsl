@@ -0,0 +1,5 @@
+#version 330 core
+
+//depth only, the depth test writes the map
+void main() {
+}
\ No newline at end of file
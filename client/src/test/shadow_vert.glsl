@@ -0,0 +1,12 @@
+#version 330 core
+layout (location = 0) in vec3 position;
+//instanced
+layout (location = 8) in mat4 model;
+
+layout (std140) uniform Shadow {
+    mat4 light_view_projection;
+} shadow;
+
+void main() {
+    gl_Position = shadow.light_view_projection * model * vec4(position, 1.0);
+}
\ No newline at end of file
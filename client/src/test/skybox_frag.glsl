@@ -0,0 +1,9 @@
+#version 330 core
+in vec3 out_Direction;
+out vec4 Color;
+
+uniform samplerCube u_environment;
+
+void main() {
+    Color = vec4(texture(u_environment, normalize(out_Direction)).rgb, 1.0);
+}
\ No newline at end of file
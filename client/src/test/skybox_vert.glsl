@@ -0,0 +1,15 @@
+#version 330 core
+layout (location = 0) in vec3 position;
+
+layout (std140) uniform Sky {
+    mat4 inverse_view_projection;
+} sky;
+
+out vec3 out_Direction;
+
+void main() {
+    //on the far plane, behind everything drawn before
+    gl_Position = vec4(position.xy, 1.0, 1.0);
+    vec4 far = sky.inverse_view_projection * vec4(position.xy, 1.0, 1.0);
+    out_Direction = far.xyz / far.w;
+}
\ No newline at end of file
@@ -4,13 +4,14 @@ layout (location = 1) in vec2 uv;
 layout (location = 2) in vec3 normal;
 //instanced
 layout (location = 3) in mat4 mvp;
+layout (location = 7) in float material_id;
+layout (location = 8) in mat4 model;
 
-out vec4 out_Color;
-
-//uniform mat4 r_transformation;
-//uniform mat4 r_view;
-//uniform mat4 r_projection;
-//uniform mat4 r_vp;
+out vec3 out_Position;
+out vec3 out_Normal;
+out vec2 out_Uv;
+flat out int out_Material;
+out float out_Depth;
 
 layout (std140) uniform Matricies {
     mat4 view;
@@ -20,5 +21,10 @@ layout (std140) uniform Matricies {
 
 void main() {
     gl_Position = mvp * vec4(position, 1.0);
-    out_Color = vec4(normal.xyz, uv.x);
+    vec4 world = model * vec4(position, 1.0);
+    out_Position = world.xyz;
+    out_Depth = -(matrix.view * world).z;
+    out_Normal = mat3(transpose(inverse(model))) * normal;
+    out_Uv = uv;
+    out_Material = int(material_id + 0.5);
 }
\ No newline at end of file
@@ -0,0 +1,226 @@
+use api::memory::{MemoryType, Properties, Requirements, TypeId};
+use api::RendererDevice;
+
+///Size in bytes of a memory block. Resources larger than that get a block of their own.
+pub const BLOCK_SIZE: u32 = 16 * 1024 * 1024;
+
+///Range of a block a resource is bound to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Allocation {
+    block: usize,
+    pub offset: u32,
+    pub size: u32,
+}
+
+struct Block<B: api::Backend> {
+    memory: B::Memory,
+    memory_type: TypeId,
+    free: FreeList,
+}
+
+///Binds many buffers and images into few `Memory` blocks, one set of blocks per memory type.
+pub struct Allocator<B: api::Backend> {
+    types: Vec<MemoryType>,
+    block_size: u32,
+    blocks: Vec<Block<B>>,
+}
+
+impl<B: api::Backend> Allocator<B> {
+    pub fn new(device: &B::RendererDevice, block_size: u32) -> Self {
+        Allocator {
+            types: device.memory_types(),
+            block_size,
+            blocks: Vec::new(),
+        }
+    }
+
+    pub fn bind_buffer(&mut self,
+                       device: &B::RendererDevice,
+                       buffer: &mut B::Buffer,
+                       properties: Properties) -> Result<Allocation, api::Error> {
+        let allocation = self.allocate(device, device.get_buffer_requirements(buffer), properties)?;
+        let memory = &self.blocks[allocation.block].memory;
+        if let Err(e) = device.bind_buffer_memory(memory, allocation.offset, buffer) {
+            self.free(allocation);
+            return Err(e);
+        }
+        Ok(allocation)
+    }
+
+    pub fn bind_image(&mut self,
+                      device: &B::RendererDevice,
+                      image: &mut B::Image,
+                      properties: Properties) -> Result<Allocation, api::Error> {
+        let allocation = self.allocate(device, device.get_image_requirements(image), properties)?;
+        let memory = &self.blocks[allocation.block].memory;
+        if let Err(e) = device.bind_image_memory(memory, allocation.offset, image) {
+            self.free(allocation);
+            return Err(e);
+        }
+        Ok(allocation)
+    }
+
+    ///Returns the range to its block, the resource bound to it must not be used anymore.
+    pub fn free(&mut self, allocation: Allocation) {
+        self.blocks[allocation.block].free.release(allocation.offset, allocation.size);
+    }
+
+    pub fn memory(&self, allocation: &Allocation) -> &B::Memory {
+        &self.blocks[allocation.block].memory
+    }
+
+    ///Pointer to the start of the allocation, blocks can be mapped for several allocations at once.
+    pub fn map(&self, device: &B::RendererDevice, allocation: &Allocation) -> Result<*mut u8, api::Error> {
+        let ptr = device.map_memory(self.memory(allocation))?;
+        Ok(unsafe { ptr.add(allocation.offset as usize) })
+    }
+
+    pub fn unmap(&self, device: &B::RendererDevice, allocation: &Allocation) {
+        device.unmap_memory(self.memory(allocation))
+    }
+
+    ///Bytes of device memory held by all blocks, whether resources are bound to them or not.
+    pub fn reserved(&self) -> u64 {
+        self.blocks.iter().map(|b| u64::from(b.free.size)).sum()
+    }
+
+    ///Bytes of the blocks bound to resources, without the padding their alignment leaves between them.
+    pub fn used(&self) -> u64 {
+        self.blocks.iter().map(|b| u64::from(b.free.used())).sum()
+    }
+
+    fn allocate(&mut self,
+                device: &B::RendererDevice,
+                req: Requirements,
+                properties: Properties) -> Result<Allocation, api::Error> {
+        let memory_type = (0..self.types.len())
+            .find(|t| req.allows(*t) && self.types[*t].properties.contains(properties))
+            .ok_or_else(|| api::Error::Unsupported(format!("No memory type with {:?}", properties)))?;
+
+        for (i, block) in self.blocks.iter_mut().enumerate() {
+            if block.memory_type != memory_type {
+                continue;
+            }
+            if let Some(offset) = block.free.take(req.size, req.alignment) {
+                return Ok(Allocation { block: i, offset, size: req.size });
+            }
+        }
+
+        let size = self.block_size.max(req.size);
+        let mut free = FreeList::new(size);
+        let offset = free.take(req.size, req.alignment).expect("Resource does not fit a new block");
+        self.blocks.push(Block {
+            memory: device.allocate_memory(memory_type, size)?,
+            memory_type,
+            free,
+        });
+        Ok(Allocation { block: self.blocks.len() - 1, offset, size: req.size })
+    }
+}
+
+///Unused ranges of a block, sorted by offset and never adjacent.
+struct FreeList {
+    size: u32,
+    ranges: Vec<(u32, u32)>,
+}
+
+impl FreeList {
+    fn new(size: u32) -> Self {
+        FreeList { size, ranges: vec![(0, size)] }
+    }
+
+    ///First fit, alignment is a power of two.
+    fn take(&mut self, size: u32, alignment: u32) -> Option<u32> {
+        let mask = alignment.max(1) - 1;
+        let (i, offset) = self.ranges.iter()
+            .enumerate()
+            .filter_map(|(i, &(start, len))| {
+                let offset = (start + mask) & !mask;
+                if u64::from(offset) + u64::from(size) <= u64::from(start) + u64::from(len) {
+                    Some((i, offset))
+                } else {
+                    None
+                }
+            })
+            .next()?;
+
+        let (start, len) = self.ranges.remove(i);
+        let end = start + len;
+        if end > offset + size {
+            self.ranges.insert(i, (offset + size, end - offset - size));
+        }
+        if offset > start {
+            self.ranges.insert(i, (start, offset - start));
+        }
+        Some(offset)
+    }
+
+    fn used(&self) -> u32 {
+        self.size - self.ranges.iter().map(|&(_, len)| len).sum::<u32>()
+    }
+
+    fn release(&mut self, offset: u32, size: u32) {
+        let i = self.ranges.iter()
+            .position(|&(start, _)| start > offset)
+            .unwrap_or(self.ranges.len());
+        self.ranges.insert(i, (offset, size));
+
+        if i + 1 < self.ranges.len() && offset + size == self.ranges[i + 1].0 {
+            let (_, next) = self.ranges.remove(i + 1);
+            self.ranges[i].1 += next;
+        }
+        if i > 0 && self.ranges[i - 1].0 + self.ranges[i - 1].1 == offset {
+            let (_, merged) = self.ranges.remove(i);
+            self.ranges[i - 1].1 += merged;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_aligned_ranges() {
+        let mut free = FreeList::new(256);
+        assert_eq!(free.take(10, 4), Some(0));
+        assert_eq!(free.take(16, 64), Some(64));
+        assert_eq!(free.take(40, 4), Some(12));
+        assert_eq!(free.ranges, vec![(10, 2), (52, 12), (80, 176)]);
+        assert_eq!(free.take(512, 4), None);
+    }
+
+    #[test]
+    fn merges_released_ranges() {
+        let mut free = FreeList::new(64);
+        let a = free.take(16, 1).unwrap();
+        let b = free.take(16, 1).unwrap();
+        let c = free.take(16, 1).unwrap();
+        free.release(a, 16);
+        free.release(c, 16);
+        assert_eq!(free.ranges, vec![(0, 16), (32, 32)]);
+        free.release(b, 16);
+        assert_eq!(free.ranges, vec![(0, 64)]);
+    }
+
+    #[cfg(feature = "headless")]
+    #[test]
+    fn reserves_whole_blocks_and_uses_what_is_bound() {
+        use api::PlatformManager;
+
+        let mut pm = headless::PlatformManager::new(api::WindowConfig { width: 8, height: 8 });
+        let (_api, device) = pm.create_renderer();
+        let mut allocator = Allocator::<headless::Backend>::new(&device, 1024);
+        let buffer = |size| device.create_buffer(api::BufferDescriptor { size, usage: api::Usage::Uniform }).unwrap();
+        let (mut small, mut large) = (buffer(100), buffer(4096));
+
+        let a = allocator.bind_buffer(&device, &mut small, Properties::HOST_VISIBLE).unwrap();
+        assert_eq!((allocator.reserved(), allocator.used()), (1024, 100));
+        let b = allocator.bind_buffer(&device, &mut large, Properties::HOST_VISIBLE).unwrap();
+        assert_eq!((allocator.reserved(), allocator.used()), (1024 + 4096, 4196));
+
+        allocator.free(a);
+        allocator.free(b);
+        assert_eq!((allocator.reserved(), allocator.used()), (1024 + 4096, 0));
+    }
+}
@@ -0,0 +1,148 @@
+use std::ops::Range;
+
+use crate::Matrix4f;
+use crate::mesh::MeshId;
+
+///Pipeline, material and mesh a draw binds. Instances with equal keys are drawn together.
+///Ordered so that batches sharing a pipeline and then a material are adjacent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BatchKey {
+    pub pipeline: u32,
+    pub material: u32,
+    pub mesh: MeshId,
+}
+
+impl BatchKey {
+    ///Whether both keys bind the same pipeline and material, only their meshes may differ.
+    pub fn shares_state(&self, other: &BatchKey) -> bool {
+        (self.pipeline, self.material) == (other.pipeline, other.material)
+    }
+}
+
+///One instanced draw, `instances` is its range of the instance buffer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Batch {
+    pub key: BatchKey,
+    pub instances: Range<u32>,
+}
+
+///Counters of the last processed frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameStats {
+    pub draws: u32,
+    ///Indirect submissions the draws were issued with.
+    pub calls: u32,
+    ///Instances drawn. Instances outside the camera frustum are counted in `culled` instead.
+    pub instances: u32,
+    pub culled: u32,
+}
+
+///Buckets the instances submitted during a frame by their key.
+pub struct Batcher<T = Matrix4f> {
+    instances: Vec<(BatchKey, T)>,
+    ///Instances blending over what is behind them, with their distance from the camera.
+    transparent: Vec<(BatchKey, f32, T)>,
+}
+
+impl<T> Default for Batcher<T> {
+    fn default() -> Self {
+        Batcher { instances: Vec::new(), transparent: Vec::new() }
+    }
+}
+
+impl<T> Batcher<T> {
+    pub fn push(&mut self, key: BatchKey, instance: T) {
+        self.instances.push((key, instance));
+    }
+
+    ///Pushes an instance to be drawn after the opaque ones, ordered from the furthest `distance` to the nearest.
+    pub fn push_transparent(&mut self, key: BatchKey, distance: f32, instance: T) {
+        self.transparent.push((key, distance, instance));
+    }
+
+    ///Sorts the pushed instances by key, keeping submission order inside a batch, and returns the batches
+    ///along with the instances laid out in the order the batch ranges refer to.
+    ///Transparent instances follow back to front, only adjacent ones with equal keys share a batch.
+    pub fn build(&mut self) -> (Vec<Batch>, impl ExactSizeIterator<Item=T> + '_) {
+        self.instances.sort_by_key(|(key, _)| *key);
+        self.transparent.sort_by(|a, b| b.1.total_cmp(&a.1));
+        self.instances.extend(self.transparent.drain(..).map(|(key, _, instance)| (key, instance)));
+
+        let mut batches: Vec<Batch> = Vec::new();
+        for (i, (key, _)) in self.instances.iter().enumerate() {
+            let i = i as u32;
+            match batches.last_mut() {
+                Some(batch) if batch.key == *key => batch.instances.end = i + 1,
+                _ => batches.push(Batch { key: *key, instances: i..i + 1 }),
+            }
+        }
+        (batches, self.instances.drain(..).map(|(_, instance)| instance))
+    }
+}
+
+///Ranges of adjacent `batches` sharing a pipeline and a material, each drawable with one indirect call.
+pub fn runs(batches: &[Batch]) -> Vec<Range<u32>> {
+    let mut runs: Vec<Range<u32>> = Vec::new();
+    for (i, batch) in batches.iter().enumerate() {
+        let i = i as u32;
+        match runs.last_mut() {
+            Some(run) if batches[run.start as usize].key.shares_state(&batch.key) => run.end = i + 1,
+            _ => runs.push(i..i + 1),
+        }
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_instances_by_key() {
+        let key = |material, mesh| BatchKey { pipeline: 0, material, mesh };
+        let instance = |n: f32| Matrix4f::identity() * n;
+
+        let mut batcher = Batcher::default();
+        batcher.push(key(0, 2), instance(1.));
+        batcher.push(key(1, 0), instance(2.));
+        batcher.push(key(0, 2), instance(3.));
+        batcher.push(key(0, 1), instance(4.));
+
+        let (batches, instances) = batcher.build();
+        let instances: Vec<f32> = instances.map(|m| m[0]).collect();
+        assert_eq!(batches, vec![
+            Batch { key: key(0, 1), instances: 0..1 },
+            Batch { key: key(0, 2), instances: 1..3 },
+            Batch { key: key(1, 0), instances: 3..4 },
+        ]);
+        assert_eq!(instances, vec![4., 1., 3., 2.]);
+        assert_eq!(runs(&batches), vec![0..2, 2..3]);
+
+        let (batches, mut instances) = batcher.build();
+        assert!(batches.is_empty());
+        assert!(instances.next().is_none());
+    }
+
+    #[test]
+    fn draws_transparent_instances_back_to_front() {
+        let key = |pipeline, mesh| BatchKey { pipeline, material: 0, mesh };
+        let instance = |n: f32| Matrix4f::identity() * n;
+
+        let mut batcher = Batcher::default();
+        batcher.push_transparent(key(1, 0), 2., instance(1.));
+        batcher.push(key(0, 0), instance(2.));
+        batcher.push_transparent(key(1, 1), 3., instance(3.));
+        batcher.push_transparent(key(1, 0), 5., instance(4.));
+        batcher.push_transparent(key(1, 0), 4., instance(5.));
+
+        let (batches, instances) = batcher.build();
+        let instances: Vec<f32> = instances.map(|m| m[0]).collect();
+        assert_eq!(batches, vec![
+            Batch { key: key(0, 0), instances: 0..1 },
+            Batch { key: key(1, 0), instances: 1..3 },
+            Batch { key: key(1, 1), instances: 3..4 },
+            Batch { key: key(1, 0), instances: 4..5 },
+        ]);
+        assert_eq!(instances, vec![2., 4., 5., 3., 1.]);
+    }
+}
@@ -0,0 +1,21 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+///Writes tightly packed RGBA8 `pixels` stored bottom to top as a PNG.
+pub fn write_png(path: &Path, (width, height): (u32, u32), pixels: &[u8]) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    encoder.set_color(png::ColorType::RGBA);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&flip_rows(width, pixels))?;
+    Ok(())
+}
+
+///Reverses the row order of RGBA8 `pixels`, PNG stores the top row first.
+pub fn flip_rows(width: u32, pixels: &[u8]) -> Vec<u8> {
+    pixels.chunks_exact(width as usize * 4)
+        .rev()
+        .flatten()
+        .copied()
+        .collect()
+}
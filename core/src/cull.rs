@@ -0,0 +1,57 @@
+use crate::Matrix4f;
+
+///Axis aligned box enclosing a mesh in its model space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: glm::Vec3,
+    pub max: glm::Vec3,
+}
+
+impl Aabb {
+    ///Box of tightly packed xyz `positions`, empty ones enclose the origin.
+    pub fn from_positions(positions: &[f32]) -> Aabb {
+        let mut points = positions.chunks_exact(3).map(|p| glm::vec3(p[0], p[1], p[2]));
+        let first = points.next().unwrap_or_else(glm::zero);
+        points.fold(Aabb { min: first, max: first }, |aabb, p| Aabb {
+            min: glm::min2(&aabb.min, &p),
+            max: glm::max2(&aabb.max, &p),
+        })
+    }
+
+    fn corner(&self, i: usize) -> glm::Vec4 {
+        let pick = |bit: usize, axis: usize| if i & bit == 0 { self.min[axis] } else { self.max[axis] };
+        glm::vec4(pick(1, 0), pick(2, 1), pick(4, 2), 1.)
+    }
+
+    ///Whether the box may be seen through `mvp`, false only when all its corners are outside one clip plane.
+    ///Conservative near large boxes crossing several planes, which are drawn anyway.
+    pub fn visible(&self, mvp: &Matrix4f) -> bool {
+        let corners: Vec<glm::Vec4> = (0..8).map(|i| mvp * self.corner(i)).collect();
+        (0..3).all(|axis| {
+            !corners.iter().all(|c| c[axis] < -c.w) && !corners.iter().all(|c| c[axis] > c.w)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn culls_boxes_outside_the_frustum() {
+        let unit = Aabb::from_positions(&[-1., -1., -1., 1., 1., 1., 0., 0., 0.]);
+        assert_eq!(unit, Aabb { min: glm::vec3(-1., -1., -1.), max: glm::vec3(1., 1., 1.) });
+
+        let vp = glm::perspective(1., glm::half_pi(), 0.1, 100.)
+            * glm::look_at(&glm::vec3(0., 0., 10.), &glm::vec3(0., 0., 0.), &glm::vec3(0., 1., 0.));
+        let at = |x: f32, y: f32, z: f32| vp * glm::translation(&glm::vec3(x, y, z));
+        assert!(unit.visible(&at(0., 0., 0.)));
+        //partially inside
+        assert!(unit.visible(&at(10.5, 0., 0.)));
+        assert!(!unit.visible(&at(13., 0., 0.)));
+        assert!(!unit.visible(&at(0., -13., 0.)));
+        //behind the camera and beyond the far plane
+        assert!(!unit.visible(&at(0., 0., 20.)));
+        assert!(!unit.visible(&at(0., 0., -200.)));
+    }
+}
@@ -6,11 +6,10 @@ use specs::Read;
 use specs::ReadStorage;
 use specs::WriteStorage;
 
-use crate::backend::{PlatformManager, RendererDevice};
-use crate::ecs::{ActiveCamera, DeltaTime, InputEventsRead, InputEventsWrite, PlatformEvents};
-use crate::ecs::components::{Camera, Position, Render, Rotation, Transformation, Velocity};
-use crate::ecs::system::{CameraSystem, MoveSystem, TransformationSystem};
-use crate::api::Event;
+use crate::ecs::{ActiveCameras, ActiveEnvironment, DeltaTime, InputEventsRead, InputEventsWrite, PlatformEvents, SceneLights};
+use crate::ecs::components::{Camera, DirectionalLight, PointLight, Position, Render, Rotation, SpotLight, Transformation, Velocity};
+use crate::ecs::system::{CameraSystem, LightSystem, MoveSystem, TransformationSystem};
+use crate::api::{Backend, Event};
 use crate::render::DrawIndexed;
 use crate::render::Renderer;
 use crate::run::{EngineContext, FrameContext, Layer, LayerBuilder};
@@ -51,7 +50,7 @@ pub struct EcsLayer<'a> {
 }
 
 impl<'a> EcsLayer<'a> {
-    pub fn new(sender: Sender<DrawIndexed>, init: &EcsInit<'a>, ctx: &mut EngineContext) -> Self {
+    pub fn new<B: Backend>(sender: Sender<DrawIndexed>, init: &EcsInit<'a, B>, ctx: &mut EngineContext<B>) -> Self {
         let mut world: specs::World = specs::WorldExt::new();
         world.register::<Position>();
         world.register::<Rotation>();
@@ -59,12 +58,17 @@ impl<'a> EcsLayer<'a> {
         world.register::<Camera>();
         world.register::<Render>();
         world.register::<Velocity>();
+        world.register::<DirectionalLight>();
+        world.register::<PointLight>();
+        world.register::<SpotLight>();
 
         world.insert(DeltaTime(0f64));
         world.insert(PlatformEvents(Vec::new()));
         world.insert(InputEventsRead(Vec::new()));
         world.insert(InputEventsWrite(Vec::new()));
-        world.insert(ActiveCamera::default());
+        world.insert(ActiveCameras::default());
+        world.insert(ActiveEnvironment::default());
+        world.insert(SceneLights::default());
 
         let render_system: RenderSystem = RenderSystem::new(sender);
         let dispatcher = specs::DispatcherBuilder::new()
@@ -72,9 +76,10 @@ impl<'a> EcsLayer<'a> {
             .with(TransformationSystem, "tsm_system", &[])
             .with(MoveSystem, "move_system", &[])
             .with(CameraSystem, "camera_system", &[])
+            .with(LightSystem, "light_system", &["move_system"])
             .with_thread_local(render_system);
 
-        let ctx: &mut EngineContext = ctx;
+        let ctx: &mut EngineContext<B> = ctx;
         let (world, dispatcher) = init(world, dispatcher, ctx);
         let dispatcher = dispatcher.build();
 
@@ -84,29 +89,29 @@ impl<'a> EcsLayer<'a> {
 }
 
 
-pub type EcsInit<'a> = Box<fn(specs::World, specs::DispatcherBuilder<'a, 'a>, ctx: &mut EngineContext) -> (specs::World, specs::DispatcherBuilder<'a, 'a>)>;
+pub type EcsInit<'a, B> = Box<fn(specs::World, specs::DispatcherBuilder<'a, 'a>, ctx: &mut EngineContext<B>) -> (specs::World, specs::DispatcherBuilder<'a, 'a>)>;
 
-pub struct EcsLayerBuilder<'a> {
-    ecs_builder_fn: EcsInit<'a>
+pub struct EcsLayerBuilder<'a, B: Backend> {
+    ecs_builder_fn: EcsInit<'a, B>
 }
 
-impl<'a> EcsLayerBuilder<'a> {
-    pub fn new(ecs_builder_fn: EcsInit<'a>) -> EcsLayerBuilder<'a> {
+impl<'a, B: Backend> EcsLayerBuilder<'a, B> {
+    pub fn new(ecs_builder_fn: EcsInit<'a, B>) -> EcsLayerBuilder<'a, B> {
         EcsLayerBuilder { ecs_builder_fn }
     }
 }
 
-impl<'a> Default for EcsLayerBuilder<'a> {
+impl<'a, B: Backend> Default for EcsLayerBuilder<'a, B> {
     fn default() -> Self {
-        let f: EcsInit<'a> = Box::new(|world, dispatcher, ctx| {
+        let f: EcsInit<'a, B> = Box::new(|world, dispatcher, ctx| {
             return (world, dispatcher);
         });
         EcsLayerBuilder { ecs_builder_fn: f }
     }
 }
 
-impl<'l> LayerBuilder<'l> for EcsLayerBuilder<'l> {
-    fn build(&self, ctx: &mut EngineContext) -> Box<dyn Layer + 'l> {
+impl<'l, B: Backend> LayerBuilder<'l, B> for EcsLayerBuilder<'l, B> {
+    fn build(&self, ctx: &mut EngineContext<B>) -> Box<dyn Layer<B> + 'l> {
         Box::new(
             EcsLayer::new(ctx.renderer.get_submitter(),
                           &self.ecs_builder_fn,
@@ -114,8 +119,8 @@ impl<'l> LayerBuilder<'l> for EcsLayerBuilder<'l> {
     }
 }
 
-impl<'a> Layer for EcsLayer<'a> {
-    fn on_update(&mut self, frame: &mut FrameContext, ctx: &mut EngineContext) {
+impl<'a, B: Backend> Layer<B> for EcsLayer<'a> {
+    fn on_update(&mut self, frame: &mut FrameContext, ctx: &mut EngineContext<B>) {
         {
             let mut delta_resource = self.world.write_resource::<DeltaTime>();
             let mut events_resource = self.world.write_resource::<PlatformEvents>();
@@ -135,9 +140,11 @@ impl<'a> Layer for EcsLayer<'a> {
         self.dispatcher.dispatch(&self.world);
 
         {
-            let mut camera = self.world.read_resource::<ActiveCamera>();
-            frame.frame.set_view_matrix(camera.view_mtx);
-            frame.frame.set_projection_matrix(camera.proj_mtx);
+            let cameras = self.world.read_resource::<ActiveCameras>();
+            frame.frame.set_cameras(cameras.0.clone());
+            let lights = self.world.read_resource::<SceneLights>();
+            frame.frame.set_lights(lights.0);
+            frame.frame.set_environment(self.world.read_resource::<ActiveEnvironment>().0);
         }
     }
 }
\ No newline at end of file
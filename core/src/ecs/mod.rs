@@ -4,7 +4,9 @@ use specs::System;
 
 use crate::ecs::components::Camera;
 use crate::api;
-use crate::Matrix4f;
+use crate::ibl::EnvironmentId;
+use crate::light::LightsData;
+use crate::render::CameraView;
 
 pub mod layer;
 mod system;
@@ -38,25 +40,24 @@ pub enum InputType {
     End,None
 }
 
-pub struct ActiveCamera {
-    pub view_mtx: Matrix4f,
-    pub proj_mtx: Matrix4f,
-}
+///Views of the active cameras collected by `CameraSystem`, each draws the scene once.
+#[derive(Default)]
+pub struct ActiveCameras(pub Vec<CameraView>);
 
-impl Default for ActiveCamera {
-    fn default() -> Self {
-        Self {
-            view_mtx: glm::identity(),
-            proj_mtx: glm::identity(),
-        }
-    }
-}
+///Environment added with `Renderer::add_environment` the scene is lit by and drawn in front of,
+///`None` lights it by the default sky in front of the clear color.
+#[derive(Default)]
+pub struct ActiveEnvironment(pub Option<EnvironmentId>);
+
+///Lights of the scene packed by `LightSystem`, uploaded with the frame.
+#[derive(Default)]
+pub struct SceneLights(pub LightsData);
 
 //components
 pub mod components {
     use na::Matrix4;
     use specs::{Component, VecStorage};
-    use crate::ecs::ActiveCamera;
+    use crate::render::CameraTarget;
 
     #[derive(Component, Debug)]
     #[storage(VecStorage)]
@@ -103,16 +104,31 @@ pub mod components {
 
         pub view: Matrix4<f32>,
         pub projection: Matrix4<f32>,
+        ///Cameras draw in increasing priority, over what the ones before them drew to the same target.
+        pub priority: i32,
+        ///Left, bottom, width and height of the area drawn into, as fractions of the target.
+        pub viewport: [f32; 4],
+        ///Window cameras follow its aspect ratio on resize, those of images keep the one they were created with.
+        pub target: CameraTarget,
+        ///Inactive cameras do not draw.
+        pub active: bool,
+        ///Shadow maps are fit to the highest priority active camera casting shadows.
+        pub casts_shadows: bool,
     }
 
     impl Camera {
-        fn default_with_aspect(aspect_ratio: f32) -> Self {
+        pub fn default_with_aspect(aspect_ratio: f32) -> Self {
             Self {
                 fov: 45.,
                 view: glm::identity(),
                 projection: glm::perspective(
                     aspect_ratio, glm::radians(&glm::vec1(45.)).x,
                     0.1, 1000., ),
+                priority: 0,
+                viewport: [0., 0., 1., 1.],
+                target: CameraTarget::Window,
+                active: true,
+                casts_shadows: true,
             }
         }
     }
@@ -123,6 +139,39 @@ pub mod components {
         }
     }
 
+    ///Light shining everywhere along `direction`, such as the sun.
+    #[derive(Component, Debug)]
+    #[storage(VecStorage)]
+    pub struct DirectionalLight {
+        pub direction: [f32; 3],
+        pub color: [f32; 3],
+        pub intensity: f32,
+        ///Only the first directional light casting shadows gets shadow maps.
+        pub casts_shadows: bool,
+    }
+
+    ///Light shining in all directions from the entity's `Position`, fading out at `range`.
+    #[derive(Component, Debug)]
+    #[storage(VecStorage)]
+    pub struct PointLight {
+        pub color: [f32; 3],
+        pub intensity: f32,
+        pub range: f32,
+    }
+
+    ///Light shining from the entity's `Position` along `direction` in a cone,
+    ///fully lit within `inner_angle` and fading out towards `outer_angle`, both in radians from the axis.
+    #[derive(Component, Debug)]
+    #[storage(VecStorage)]
+    pub struct SpotLight {
+        pub direction: [f32; 3],
+        pub color: [f32; 3],
+        pub intensity: f32,
+        pub range: f32,
+        pub inner_angle: f32,
+        pub outer_angle: f32,
+    }
+
     #[derive(Component)]
     #[storage(VecStorage)]
     pub struct Render {
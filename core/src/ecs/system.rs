@@ -13,15 +13,18 @@ use specs::{
 
 use crate::{
     ecs::{
-        ActiveCamera,
+        ActiveCameras,
         components::{Camera, Position, Rotation},
         DeltaTime,
         PlatformEvents,
     },
     api::Event,
     Matrix4f,
+    render::{CameraTarget, CameraView},
 };
-use crate::ecs::components::{Transformation, Velocity};
+use crate::ecs::components::{DirectionalLight, PointLight, SpotLight, Transformation, Velocity};
+use crate::ecs::SceneLights;
+use crate::light::{DirectionalLightData, PointLightData, SpotLightData};
 
 pub struct CameraSystem;
 
@@ -30,19 +33,19 @@ impl<'a> specs::System<'a> for CameraSystem {
                        WriteStorage<'a, Rotation>,
                        WriteStorage<'a, Camera>,
                        Read<'a, PlatformEvents>,
-                       Write<'a, ActiveCamera>,
+                       Write<'a, ActiveCameras>,
                        Read<'a, DeltaTime>,
     );
 
     fn run(&mut self, (pos, mut rot, mut camera, events, mut active, delta): Self::SystemData) {
+        active.0.clear();
         for (pos, rot, camera) in (&pos, &mut rot, &mut camera).join() {
-            let projection: Option<Matrix4f> = None;
-
             for e in events.0.iter() {
-                if let Event::Resize(w, h) = e {
+                if let (Event::Resize(w, h), CameraTarget::Window) = (e, camera.target) {
+                    let [_, _, width, height] = camera.viewport;
                     camera.projection = {
                         glm::perspective(
-                            (*w) as f32 / (*h) as f32,
+                            (*w) as f32 * width / ((*h) as f32 * height),
                             glm::radians(&glm::vec1(camera.fov)).x,
                             0.1,
                             1000.,
@@ -58,8 +61,16 @@ impl<'a> specs::System<'a> for CameraSystem {
                 mtx = glm::rotate(&mtx, glm::radians(&glm::vec1(rot.z)).x, &glm::vec3(0., 0., 1.));
                 glm::inverse(&mtx)
             };
-            active.view_mtx = camera.view;
-            active.proj_mtx = camera.projection;
+            if camera.active {
+                active.0.push(CameraView {
+                    view: camera.view,
+                    projection: camera.projection,
+                    viewport: camera.viewport,
+                    target: camera.target,
+                    priority: camera.priority,
+                    casts_shadows: camera.casts_shadows,
+                });
+            }
         }
     }
 }
@@ -103,3 +114,89 @@ impl<'a> System<'a> for MoveSystem {
     }
 }
 
+
+pub struct LightSystem;
+
+impl<'a> System<'a> for LightSystem {
+    type SystemData = (ReadStorage<'a, Position>,
+                       ReadStorage<'a, DirectionalLight>,
+                       ReadStorage<'a, PointLight>,
+                       ReadStorage<'a, SpotLight>,
+                       Write<'a, SceneLights>);
+
+    fn run(&mut self, (pos, directional, point, spot, mut lights): Self::SystemData) {
+        fn scaled(color: [f32; 3], intensity: f32, w: f32) -> [f32; 4] {
+            [color[0] * intensity, color[1] * intensity, color[2] * intensity, w]
+        }
+
+        let lights = &mut lights.0;
+        lights.clear();
+        for light in directional.join() {
+            let [x, y, z] = light.direction;
+            lights.push_directional(DirectionalLightData {
+                direction: [x, y, z, if light.casts_shadows { 1. } else { 0. }],
+                color: scaled(light.color, light.intensity, 1.),
+            });
+        }
+        for (pos, light) in (&pos, &point).join() {
+            lights.push_point(PointLightData {
+                position: [pos.x, pos.y, pos.z, light.range],
+                color: scaled(light.color, light.intensity, 1.),
+            });
+        }
+        for (pos, light) in (&pos, &spot).join() {
+            let [x, y, z] = light.direction;
+            lights.push_spot(SpotLightData {
+                position: [pos.x, pos.y, pos.z, light.range],
+                direction: [x, y, z, light.outer_angle.cos()],
+                color: scaled(light.color, light.intensity, light.inner_angle.cos()),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::{Builder, RunNow};
+
+    use super::*;
+
+    #[test]
+    fn packs_lights_scaled_by_intensity() {
+        let mut world = World::new();
+        world.register::<Position>();
+        world.register::<DirectionalLight>();
+        world.register::<PointLight>();
+        world.register::<SpotLight>();
+        world.insert(SceneLights::default());
+
+        world.create_entity()
+            .with(DirectionalLight { direction: [0., -1., 0.], color: [1., 0.5, 0.], intensity: 2., casts_shadows: true })
+            .build();
+        world.create_entity()
+            .with(Position { x: 1., y: 2., z: 3. })
+            .with(PointLight { color: [1., 1., 1.], intensity: 0.5, range: 10. })
+            .build();
+        world.create_entity()
+            .with(Position { x: 4., y: 5., z: 6. })
+            .with(SpotLight { direction: [0., 0., -1.], color: [0., 1., 0.], intensity: 3., range: 20., inner_angle: 0., outer_angle: std::f32::consts::FRAC_PI_2 })
+            .build();
+        //point lights without a position are not packed
+        world.create_entity()
+            .with(PointLight { color: [1., 1., 1.], intensity: 1., range: 1. })
+            .build();
+
+        LightSystem.run_now(&world);
+        let lights = world.read_resource::<SceneLights>().0;
+        assert_eq!(lights.counts, [1, 1, 1, 0]);
+        assert_eq!(lights.directional[0], DirectionalLightData { direction: [0., -1., 0., 1.], color: [2., 1., 0., 1.] });
+        assert_eq!(lights.point[0], PointLightData { position: [1., 2., 3., 10.], color: [0.5, 0.5, 0.5, 1.] });
+        assert_eq!(lights.spot[0].position, [4., 5., 6., 20.]);
+        assert_eq!(lights.spot[0].color, [0., 3., 0., 1.]);
+        assert!(lights.spot[0].direction[3].abs() < 1e-6);
+
+        //lights are packed again from scratch every run
+        LightSystem.run_now(&world);
+        assert_eq!(world.read_resource::<SceneLights>().0.counts, [1, 1, 1, 0]);
+    }
+}
@@ -0,0 +1,151 @@
+//!Golden image regression checks, scenes are rendered on the headless backend and compared against
+//!reference PNGs. Setting `RX_UPDATE_GOLDEN` rewrites the references instead of comparing.
+//!
+//!The headless backend does not run shaders, its fixed function program colours fragments by their normal
+//!and the sky white. Goldens therefore catch changes to geometry, culling, depth, viewports and the order of
+//!draws, but not to lighting, materials, shadows or post-processing.
+
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use api::{PlatformManager, WindowConfig};
+use headless::{Backend, PlatformManager as HeadlessPlatformManager};
+
+use crate::capture::{flip_rows, write_png};
+use crate::ecs::layer::EcsLayerBuilder;
+use crate::run::RxEngine;
+
+pub const UPDATE_VAR: &str = "RX_UPDATE_GOLDEN";
+
+///How far a rendering may drift from its reference.
+#[derive(Debug, Clone, Copy)]
+pub struct Tolerance {
+    ///Largest accepted difference of a single channel.
+    pub channel: u8,
+    ///Number of pixels allowed to exceed `channel`.
+    pub pixels: usize,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance { channel: 2, pixels: 0 }
+    }
+}
+
+///Width, height and rgba8 pixels stored bottom to top.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum Mismatch {
+    Size { expected: (u32, u32), actual: (u32, u32) },
+    Pixels { count: usize, max_diff: u8, first: (u32, u32) },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Mismatch::Size { expected, actual } =>
+                write!(f, "Size {:?} differs from reference {:?}", actual, expected),
+            Mismatch::Pixels { count, max_diff, first } =>
+                write!(f, "{} pixels differ by up to {}, first at {:?}", count, max_diff, first),
+        }
+    }
+}
+
+impl Image {
+    pub fn load(path: &Path) -> Result<Image, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let (info, mut reader) = png::Decoder::new(file).read_info()
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        if info.color_type != png::ColorType::RGBA || info.bit_depth != png::BitDepth::Eight {
+            return Err(format!("{}: expected rgba8, got {:?} {:?}", path.display(), info.color_type, info.bit_depth));
+        }
+        let mut pixels = vec![0; info.buffer_size()];
+        reader.next_frame(&mut pixels).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(Image { width: info.width, height: info.height, pixels: flip_rows(info.width, &pixels) })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        }
+        write_png(path, (self.width, self.height), &self.pixels)
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn compare(&self, reference: &Image, tolerance: Tolerance) -> Result<(), Mismatch> {
+        if (self.width, self.height) != (reference.width, reference.height) {
+            return Err(Mismatch::Size {
+                expected: (reference.width, reference.height),
+                actual: (self.width, self.height),
+            });
+        }
+        let mut count = 0;
+        let mut max_diff = 0;
+        let mut first = None;
+        for (i, (a, b)) in self.pixels.chunks_exact(4).zip(reference.pixels.chunks_exact(4)).enumerate() {
+            let diff = a.iter().zip(b).map(|(a, b)| a.max(b) - a.min(b)).max().unwrap_or(0);
+            if diff > tolerance.channel {
+                count += 1;
+                max_diff = max_diff.max(diff);
+                first = first.or(Some((i as u32 % self.width, i as u32 / self.width)));
+            }
+        }
+        match first {
+            Some(first) if count > tolerance.pixels => Err(Mismatch::Pixels { count, max_diff, first }),
+            _ => Ok(()),
+        }
+    }
+}
+
+///Builds an engine on the headless backend with `ecs_layer` and the renderer's assets in `assets`, runs it
+///for `frames` frames and returns the last presented one.
+pub fn render_frames<'l>(config: WindowConfig, assets: &Path, frames: u64, ecs_layer: EcsLayerBuilder<'l, Backend>) -> Image {
+    let size = (config.width as i32, config.height as i32);
+    let mut pm = HeadlessPlatformManager::new(config);
+    pm.set_frame_limit(frames);
+    let (renderer, device) = pm.create_renderer();
+    let mut engine: RxEngine<Backend> = RxEngine::with_assets(pm, renderer, device, assets)
+        .unwrap_or_else(|e| panic!("Engine initialization failed: {}", e));
+    engine.context_mut().renderer.viewport(size.0, size.1);
+    engine.add_layer_builder(ecs_layer);
+    engine.run().unwrap_or_else(|e| panic!("Engine stopped: {}", e));
+
+    let (width, height, pixels) = engine.context().platform.presented_frame();
+    Image { width, height, pixels }
+}
+
+///Compares `image` with the reference `dir/name.png`. Missing references are written and reported as a
+///failure, as are mismatches, which also leave the rendering next to the reference as `name.actual.png`.
+pub fn check(dir: &Path, name: &str, image: &Image, tolerance: Tolerance) -> Result<(), String> {
+    let reference_path = dir.join(format!("{}.png", name));
+    let actual_path: PathBuf = dir.join(format!("{}.actual.png", name));
+    if env::var_os(UPDATE_VAR).is_some() {
+        return image.save(&reference_path);
+    }
+    if !reference_path.exists() {
+        image.save(&reference_path)?;
+        return Err(format!("Reference {} was missing and has been written, review and commit it",
+                           reference_path.display()));
+    }
+    match image.compare(&Image::load(&reference_path)?, tolerance) {
+        Ok(()) => {
+            if actual_path.exists() {
+                let _ = std::fs::remove_file(&actual_path);
+            }
+            Ok(())
+        }
+        Err(mismatch) => {
+            image.save(&actual_path)?;
+            Err(format!("{} does not match {}: {}, rerun with {} set to accept it",
+                        actual_path.display(), reference_path.display(), mismatch, UPDATE_VAR))
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+///Linear radiance image, rows from the top. Environments are equirectangular, `y` up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HdrImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<glm::Vec3>,
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl HdrImage {
+    pub fn open(path: &Path) -> io::Result<HdrImage> {
+        HdrImage::read(BufReader::new(File::open(path)?))
+    }
+
+    ///Reads a Radiance RGBE picture, flat or run length encoded, in the `-Y h +X w` orientation, the only one supported.
+    pub fn read<R: BufRead>(mut reader: R) -> io::Result<HdrImage> {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        if !line.starts_with("#?") {
+            return Err(invalid("Not a Radiance picture"));
+        }
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(invalid("Header is not terminated"));
+            }
+            let line = line.trim();
+            if line.is_empty() {
+                break;
+            }
+            if line.starts_with("FORMAT=") && line != "FORMAT=32-bit_rle_rgbe" {
+                return Err(invalid("Only RGBE pictures are supported"));
+            }
+        }
+        line.clear();
+        reader.read_line(&mut line)?;
+        let (height, width) = match line.split_whitespace().collect::<Vec<_>>()[..] {
+            ["-Y", h, "+X", w] => (h.parse().map_err(|_| invalid("Bad height"))?,
+                                   w.parse().map_err(|_| invalid("Bad width"))?),
+            _ => return Err(invalid("Only -Y h +X w pictures are supported")),
+        };
+
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+        let mut scanline = vec![[0u8; 4]; width as usize];
+        for _ in 0..height {
+            read_scanline(&mut reader, &mut scanline)?;
+            pixels.extend(scanline.iter().map(|&rgbe| decode(rgbe)));
+        }
+        Ok(HdrImage { width, height, pixels })
+    }
+
+    ///Environment of `width` and `height` from the radiance arriving from each direction.
+    pub fn from_fn<F: Fn(glm::Vec3) -> glm::Vec3>(width: u32, height: u32, radiance: F) -> HdrImage {
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| radiance(direction((x as f32 + 0.5) / width as f32, (y as f32 + 0.5) / height as f32)))
+            .collect();
+        HdrImage { width, height, pixels }
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> glm::Vec3 {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    ///Bilinear radiance arriving from `direction`, wrapping around horizontally.
+    pub fn sample(&self, direction: &glm::Vec3) -> glm::Vec3 {
+        let (u, v) = equirect(direction);
+        let x = u * self.width as f32 - 0.5;
+        let y = (v * self.height as f32 - 0.5).clamp(0., self.height as f32 - 1.);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let width = self.width as i64;
+        let c0 = (x0 as i64).rem_euclid(width) as u32;
+        let c1 = (c0 + 1) % self.width;
+        let r0 = y0 as u32;
+        let r1 = (r0 + 1).min(self.height - 1);
+        //weighted sum rather than nested lerps, this runs for every sample of the IBL integration
+        self.pixel(c0, r0) * ((1. - fx) * (1. - fy))
+            + self.pixel(c1, r0) * (fx * (1. - fy))
+            + self.pixel(c0, r1) * ((1. - fx) * fy)
+            + self.pixel(c1, r1) * (fx * fy)
+    }
+
+    ///Resamples to `width` and `height`, averaging the texels each one covers when shrinking.
+    pub fn resize(&self, width: u32, height: u32) -> HdrImage {
+        let (fx, fy) = ((self.width / width).max(1), (self.height / height).max(1));
+        if fx == 1 && fy == 1 {
+            return HdrImage::from_fn(width, height, |d| self.sample(&d));
+        }
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let texels = (0..fy).flat_map(|j| (0..fx).map(move |i| (i, j)));
+                texels.fold(glm::Vec3::zeros(), |sum, (i, j)| {
+                    sum + self.pixel((x * fx + i).min(self.width - 1), (y * fy + j).min(self.height - 1))
+                }) / (fx * fy) as f32
+            })
+            .collect();
+        HdrImage { width, height, pixels }
+    }
+
+    ///Faces of a cube with sides of `size` sampled from this environment, stacked from the top
+    ///in the order of `image::Kind::Cube`.
+    pub fn cube(&self, size: u32) -> HdrImage {
+        let pixels = (0..6 * size)
+            .flat_map(|y| (0..size).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let (s, t) = ((x as f32 + 0.5) / size as f32, ((y % size) as f32 + 0.5) / size as f32);
+                self.sample(&cube_direction(y / size, s * 2. - 1., t * 2. - 1.))
+            })
+            .collect();
+        HdrImage { width: size, height: 6 * size, pixels }
+    }
+
+    ///Texels as Rgba16F with an alpha of 1.
+    pub fn to_rgba16f(&self) -> Vec<u8> {
+        self.pixels.iter()
+            .flat_map(|p| [p.x, p.y, p.z, 1.].to_vec())
+            .flat_map(|c| half(c).to_ne_bytes().to_vec())
+            .collect()
+    }
+}
+
+///Equirectangular coordinates of `direction`, `u` turning around `y` and `v` from `+y` down.
+pub fn equirect(direction: &glm::Vec3) -> (f32, f32) {
+    let y = direction.y / direction.norm();
+    (direction.z.atan2(direction.x) / (2. * PI) + 0.5, y.clamp(-1., 1.).acos() / PI)
+}
+
+///Direction at equirectangular coordinates `u` and `v`, the inverse of `equirect`.
+pub fn direction(u: f32, v: f32) -> glm::Vec3 {
+    let (phi, theta) = ((u - 0.5) * 2. * PI, v * PI);
+    glm::vec3(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin())
+}
+
+///Direction through `s` and `t` of `face` of a cube, both from -1 to 1 along its rows and columns
+///as the faces are laid out for GL and Vulkan.
+pub fn cube_direction(face: u32, s: f32, t: f32) -> glm::Vec3 {
+    match face {
+        0 => glm::vec3(1., -t, -s),
+        1 => glm::vec3(-1., -t, s),
+        2 => glm::vec3(s, 1., t),
+        3 => glm::vec3(s, -1., -t),
+        4 => glm::vec3(s, -t, 1.),
+        _ => glm::vec3(-s, -t, -1.),
+    }
+}
+
+fn decode([r, g, b, e]: [u8; 4]) -> glm::Vec3 {
+    if e == 0 {
+        return glm::Vec3::zeros();
+    }
+    let scale = 2f32.powi(i32::from(e) - (128 + 8));
+    glm::vec3(f32::from(r), f32::from(g), f32::from(b)) * scale
+}
+
+fn read_scanline<R: Read>(reader: &mut R, scanline: &mut [[u8; 4]]) -> io::Result<()> {
+    let mut first = [0u8; 4];
+    reader.read_exact(&mut first)?;
+    let width = scanline.len();
+    let rle = first[0] == 2 && first[1] == 2 && first[2] & 0x80 == 0 && (8..0x8000).contains(&width);
+    if !rle {
+        scanline[0] = first;
+        for pixel in &mut scanline[1..] {
+            reader.read_exact(pixel)?;
+        }
+        return Ok(());
+    }
+    if (usize::from(first[2]) << 8 | usize::from(first[3])) != width {
+        return Err(invalid("Scanline width mismatch"));
+    }
+    //each channel is encoded on its own, as runs of one byte or literal bytes
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let mut byte = [0u8];
+            reader.read_exact(&mut byte)?;
+            let (run, len) = if byte[0] > 128 { (true, usize::from(byte[0]) - 128) } else { (false, usize::from(byte[0])) };
+            if len == 0 || x + len > width {
+                return Err(invalid("Bad scanline run"));
+            }
+            if run {
+                reader.read_exact(&mut byte)?;
+            }
+            for pixel in &mut scanline[x..x + len] {
+                if !run {
+                    reader.read_exact(&mut byte)?;
+                }
+                pixel[channel] = byte[0];
+            }
+            x += len;
+        }
+    }
+    Ok(())
+}
+
+///Nearest half float of `value`, out of range ones become infinite and tiny ones zero.
+fn half(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = (bits >> 16) as u16 & 0x8000;
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x7f_ffff;
+    if value.is_nan() {
+        sign | 0x7e00
+    } else if exponent >= 0x1f {
+        sign | 0x7c00
+    } else if exponent <= 0 {
+        sign
+    } else {
+        //rounded to nearest, a carry into the exponent is still the right encoding
+        sign | (((exponent as u32) << 10 | mantissa >> 13) as u16 + ((mantissa >> 12) & 1) as u16)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_flat_and_run_length_encoded_pictures() {
+        let header = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n";
+        let mut flat = header.to_vec();
+        flat.extend(b"-Y 1 +X 2\n");
+        flat.extend(&[128, 64, 0, 129, 0, 0, 0, 0]);
+        let image = HdrImage::read(&flat[..]).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.pixels, vec![glm::vec3(1., 0.5, 0.), glm::Vec3::zeros()]);
+
+        //8 texels of red 128 and exponent 128, alternating green, no blue
+        let mut rle = header.to_vec();
+        rle.extend(b"-Y 1 +X 8\n");
+        rle.extend(&[2, 2, 0, 8]);
+        rle.extend(&[128 + 8, 128]);
+        rle.extend(&[8, 0, 128, 0, 128, 0, 128, 0, 128]);
+        rle.extend(&[128 + 8, 0]);
+        rle.extend(&[128 + 8, 128]);
+        let image = HdrImage::read(&rle[..]).unwrap();
+        assert_eq!(image.pixels[0], glm::vec3(0.5, 0., 0.));
+        assert_eq!(image.pixels[7], glm::vec3(0.5, 0.5, 0.));
+
+        assert!(HdrImage::read(&b"P6\n"[..]).is_err());
+    }
+
+    #[test]
+    fn maps_directions_to_equirectangular_coordinates() {
+        for &(u, v) in &[(0.1, 0.2), (0.5, 0.5), (0.9, 0.7)] {
+            let (u2, v2) = equirect(&direction(u, v));
+            assert!((u - u2).abs() < 1e-5 && (v - v2).abs() < 1e-5);
+        }
+        assert!((equirect(&glm::vec3(0., 1., 0.)).1).abs() < 1e-6);
+        assert_eq!(half(1.), 0x3c00);
+        assert_eq!(half(-2.5), 0xc100);
+        assert_eq!(half(1e6), 0x7c00);
+    }
+
+    #[test]
+    fn samples_cube_faces_along_their_directions() {
+        let environment = HdrImage::from_fn(64, 32, |d| d);
+        let cube = environment.cube(4);
+        assert_eq!((cube.width, cube.height), (4, 24));
+        //texels next to the centre of each face point roughly along its axis
+        let axes = [glm::vec3(1., 0., 0.), glm::vec3(-1., 0., 0.), glm::vec3(0., 1., 0.),
+            glm::vec3(0., -1., 0.), glm::vec3(0., 0., 1.), glm::vec3(0., 0., -1.)];
+        for (face, axis) in axes.iter().enumerate() {
+            let texel = cube.pixel(2, face as u32 * 4 + 2);
+            assert!(glm::dot(&glm::normalize(&texel), axis) > 0.8, "face {} is {:?}", face, texel);
+        }
+        //top rows of the side faces look up
+        assert!(cube.pixel(1, 0).y > 0.5 && cube.pixel(1, 3).y < -0.5);
+    }
+}
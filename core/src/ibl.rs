@@ -0,0 +1,272 @@
+use std::f32::consts::PI;
+
+use crate::allocator::Allocator;
+use crate::hdr::{self, HdrImage};
+use crate::texture::Texture;
+
+///Levels of the prefiltered environment, roughness goes from 0 at the base to 1 at the last one.
+pub const PREFILTERED_LEVELS: u16 = 5;
+///Extent of the base level of the prefiltered environment, halved per level.
+const PREFILTERED_SIZE: (u32, u32) = (128, 64);
+const IRRADIANCE_SIZE: (u32, u32) = (32, 16);
+///Extent of the environment the irradiance integrates over.
+const IRRADIANCE_SOURCE_SIZE: (u32, u32) = (32, 16);
+const BRDF_SIZE: u32 = 32;
+///GGX samples per texel of the lookup table and of the prefiltered levels, where neighbouring texels overlap.
+const SAMPLES: u32 = 128;
+const PREFILTER_SAMPLES: u32 = 64;
+
+///Index of an environment added with `Renderer::add_environment`, the default sky is 0.
+pub type EnvironmentId = usize;
+
+///Image based lighting of equirectangular environments, the split sum approximation's terms
+///sampled by the PBR pipeline.
+pub struct Ibl<B: api::Backend> {
+    ///Scale and bias of the Fresnel term by view angle and roughness, shared by every environment.
+    pub brdf: Texture<B>,
+    environments: Vec<EnvironmentMaps<B>>,
+}
+
+///Maps integrated from one environment.
+pub struct EnvironmentMaps<B: api::Backend> {
+    ///Cosine weighted radiance over the hemisphere of each normal, divided by pi.
+    pub irradiance: Texture<B>,
+    ///Radiance reflected around each direction, GGX filtered with more roughness at each level.
+    pub prefiltered: Texture<B>,
+}
+
+impl<B: api::Backend> Ibl<B> {
+    ///Integrates the BRDF lookup table on the CPU, environments are added after.
+    pub fn new(device: &B::RendererDevice, allocator: &mut Allocator<B>) -> Result<Self, api::Error> {
+        let brdf = HdrImage {
+            width: BRDF_SIZE,
+            height: BRDF_SIZE,
+            pixels: brdf_lut(BRDF_SIZE).iter().map(|&[scale, bias]| glm::vec3(scale, bias, 0.)).collect(),
+        };
+        let brdf = Texture::upload(device,
+                                   allocator,
+                                   api::image::Kind::D2(BRDF_SIZE, BRDF_SIZE, 1),
+                                   api::image::Format::Rgba16F,
+                                   &brdf.to_rgba16f(),
+                                   api::image::SamplerDesc::new(api::image::Filter::Linear, api::image::WrapMode::Clamp))?;
+        Ok(Ibl { brdf, environments: Vec::new() })
+    }
+
+    ///Integrates the maps of `environment` on the CPU.
+    pub fn add(&mut self, device: &B::RendererDevice, allocator: &mut Allocator<B>, environment: &HdrImage) -> Result<EnvironmentId, api::Error> {
+        let (irradiance, prefiltered) = environment_maps(device, allocator, environment)?;
+        self.environments.push(EnvironmentMaps { irradiance, prefiltered });
+        Ok(self.environments.len() - 1)
+    }
+
+    pub fn get(&self, id: EnvironmentId) -> Option<&EnvironmentMaps<B>> {
+        self.environments.get(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.environments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.environments.is_empty()
+    }
+
+    pub fn free(self, allocator: &mut Allocator<B>) {
+        allocator.free(self.brdf.allocation);
+        for maps in self.environments {
+            allocator.free(maps.irradiance.allocation);
+            allocator.free(maps.prefiltered.allocation);
+        }
+    }
+}
+
+fn environment_maps<B: api::Backend>(device: &B::RendererDevice,
+                                     allocator: &mut Allocator<B>,
+                                     environment: &HdrImage) -> Result<(Texture<B>, Texture<B>), api::Error> {
+    //wrapping around horizontally only, the poles are at the top and bottom edges
+    let sampler = api::image::SamplerDesc {
+        wrap: (api::image::WrapMode::Tile, api::image::WrapMode::Clamp, api::image::WrapMode::Clamp),
+        ..api::image::SamplerDesc::new(api::image::Filter::Linear, api::image::WrapMode::Clamp)
+    };
+    let irradiance = irradiance(environment, IRRADIANCE_SIZE);
+    let irradiance = Texture::upload(device,
+                                     allocator,
+                                     api::image::Kind::D2(irradiance.width, irradiance.height, 1),
+                                     api::image::Format::Rgba16F,
+                                     &irradiance.to_rgba16f(),
+                                     sampler.clone())?;
+    let levels: Vec<Vec<u8>> = prefiltered(environment, PREFILTERED_SIZE, PREFILTERED_LEVELS).iter()
+        .map(HdrImage::to_rgba16f)
+        .collect();
+    let prefiltered = Texture::upload_levels(device,
+                                             allocator,
+                                             api::image::Kind::D2(PREFILTERED_SIZE.0, PREFILTERED_SIZE.1, PREFILTERED_LEVELS),
+                                             api::image::Format::Rgba16F,
+                                             &levels,
+                                             sampler);
+    match prefiltered {
+        Ok(prefiltered) => Ok((irradiance, prefiltered)),
+        Err(e) => {
+            allocator.free(irradiance.allocation);
+            Err(e)
+        }
+    }
+}
+
+///Sky fading from a pale horizon to blue overhead, above a dim ground. Every renderer has it as
+///environment 0 and lights the scene by it unless another environment is active.
+pub fn default_environment() -> HdrImage {
+    HdrImage::from_fn(64, 32, |d| {
+        if d.y >= 0. {
+            glm::lerp(&glm::vec3(1., 0.95, 0.9), &glm::vec3(0.3, 0.5, 0.9), d.y.sqrt())
+        } else {
+            glm::vec3(0.25, 0.22, 0.2) * (1. + d.y * 0.5)
+        }
+    })
+}
+
+///Irradiance map with sides of `size` texels. Each texel is the cosine weighted integral of the environment
+///around its direction, divided by pi.
+pub fn irradiance(environment: &HdrImage, size: (u32, u32)) -> HdrImage {
+    let source = environment.resize(IRRADIANCE_SOURCE_SIZE.0, IRRADIANCE_SOURCE_SIZE.1);
+    //solid angle of a texel shrinks towards the poles with the sine of its polar angle
+    let texel_angle = (2. * PI / source.width as f32) * (PI / source.height as f32);
+    let texels: Vec<(glm::Vec3, glm::Vec3)> = (0..source.height)
+        .flat_map(|y| (0..source.width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let v = (y as f32 + 0.5) / source.height as f32;
+            let l = hdr::direction((x as f32 + 0.5) / source.width as f32, v);
+            (l, source.pixel(x, y) * (v * PI).sin() * texel_angle)
+        })
+        .collect();
+    HdrImage::from_fn(size.0, size.1, |n| {
+        texels.iter()
+            .fold(glm::Vec3::zeros(), |sum, (l, radiance)| sum + radiance * n.dot(l).max(0.)) / PI
+    })
+}
+
+///Prefiltered levels with sides from `size` texels down. The base level is the environment itself.
+pub fn prefiltered(environment: &HdrImage, size: (u32, u32), levels: u16) -> Vec<HdrImage> {
+    (0..levels)
+        .map(|level| {
+            let (width, height) = ((size.0 >> level).max(1), (size.1 >> level).max(1));
+            if level == 0 {
+                return environment.resize(width, height);
+            }
+            let roughness = f32::from(level) / f32::from(levels - 1);
+            //the view and normal are taken to be the reflected direction, so the light directions around it
+            //are the same in its tangent space for every texel
+            let n = glm::vec3(0., 0., 1.);
+            let samples: Vec<glm::Vec3> = (0..PREFILTER_SAMPLES)
+                .map(|i| {
+                    let h = importance_sample_ggx(hammersley(i, PREFILTER_SAMPLES), &n, roughness);
+                    h * 2. * h.z - n
+                })
+                .filter(|l| l.z > 0.)
+                .collect();
+            let weight: f32 = samples.iter().map(|l| l.z).sum();
+            //sampled at twice the level's resolution, coarser than the environment to keep the few samples smooth
+            let source = environment.resize(width * 2, height * 2);
+            HdrImage::from_fn(width, height, |n| {
+                let (tangent, bitangent) = tangent_frame(&n);
+                samples.iter().fold(glm::Vec3::zeros(), |sum, l| {
+                    sum + source.sample(&(tangent * l.x + bitangent * l.y + n * l.z)) * l.z
+                }) / weight
+            })
+        })
+        .collect()
+}
+
+///Split sum lookup table of `size` by `size` texels. It holds the scale and bias of F0, with the cosine of the
+///view angle along `x` and roughness along `y`.
+pub fn brdf_lut(size: u32) -> Vec<[f32; 2]> {
+    let n = glm::vec3(0., 0., 1.);
+    (0..size)
+        .flat_map(|y| {
+            //half vectors only depend on the row's roughness
+            let roughness = (y as f32 + 0.5) / size as f32;
+            let halves: Vec<glm::Vec3> = (0..SAMPLES)
+                .map(|i| importance_sample_ggx(hammersley(i, SAMPLES), &n, roughness))
+                .collect();
+            (0..size).map(move |x| {
+                let n_dot_v = ((x as f32 + 0.5) / size as f32).max(1e-3);
+                let v = glm::vec3((1. - n_dot_v * n_dot_v).sqrt(), 0., n_dot_v);
+                let (a, b) = halves.iter().fold((0., 0.), |(a, b), h| {
+                    let v_dot_h = v.dot(h).max(0.);
+                    let n_dot_l = 2. * v_dot_h * h.z - v.z;
+                    if n_dot_l <= 0. {
+                        return (a, b);
+                    }
+                    let visibility = geometry_smith(n_dot_v, n_dot_l, roughness) * v_dot_h / (h.z * n_dot_v);
+                    let fresnel = (1. - v_dot_h).powi(5);
+                    (a + (1. - fresnel) * visibility, b + fresnel * visibility)
+                });
+                [a / SAMPLES as f32, b / SAMPLES as f32]
+            })
+        })
+        .collect()
+}
+
+///Low discrepancy point `i` of `count` in the unit square.
+fn hammersley(i: u32, count: u32) -> (f32, f32) {
+    (i as f32 / count as f32, i.reverse_bits() as f32 / 4_294_967_296.)
+}
+
+///Half vector around `n` distributed as GGX microfacets of `roughness`.
+fn importance_sample_ggx((x, y): (f32, f32), n: &glm::Vec3, roughness: f32) -> glm::Vec3 {
+    let a = roughness * roughness;
+    let phi = 2. * PI * x;
+    let cos_theta = ((1. - y) / (1. + (a * a - 1.) * y)).sqrt();
+    let sin_theta = (1. - cos_theta * cos_theta).sqrt();
+
+    let (tangent, bitangent) = tangent_frame(n);
+    glm::normalize(&(tangent * phi.cos() * sin_theta + bitangent * phi.sin() * sin_theta + n * cos_theta))
+}
+
+fn tangent_frame(n: &glm::Vec3) -> (glm::Vec3, glm::Vec3) {
+    let up = if n.z.abs() < 0.999 { glm::vec3(0., 0., 1.) } else { glm::vec3(1., 0., 0.) };
+    let tangent = glm::normalize(&up.cross(n));
+    (tangent, n.cross(&tangent))
+}
+
+///Smith shadowing and masking, with roughness remapped as for image based lighting.
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let k = roughness * roughness / 2.;
+    let schlick = |n_dot_x: f32| n_dot_x / (n_dot_x * (1. - k) + k);
+    schlick(n_dot_v) * schlick(n_dot_l)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32, tolerance: f32) -> bool {
+        (a - b).abs() < tolerance
+    }
+
+    #[test]
+    fn integrates_a_uniform_environment() {
+        //radiance of 1 everywhere reaches any surface with pi, and is reflected unchanged
+        let white = HdrImage::from_fn(64, 32, |_| glm::vec3(1., 1., 1.));
+        let irradiance = irradiance(&white, (8, 4));
+        assert!(irradiance.pixels.iter().all(|p| close(p.x, 1., 0.02)), "{:?}", irradiance.pixels);
+
+        let levels = prefiltered(&white, (16, 8), 3);
+        assert_eq!(levels.iter().map(|l| (l.width, l.height)).collect::<Vec<_>>(), vec![(16, 8), (8, 4), (4, 2)]);
+        assert!(levels.iter().all(|l| l.pixels.iter().all(|p| close(p.y, 1., 1e-3))));
+
+        //light from above only lights surfaces facing up
+        let sky = HdrImage::from_fn(64, 32, |d| if d.y > 0. { glm::vec3(1., 1., 1.) } else { glm::Vec3::zeros() });
+        let irradiance = self::irradiance(&sky, (8, 4));
+        assert!(irradiance.pixel(0, 0).x > 0.9 && irradiance.pixel(0, 3).x < 0.1);
+    }
+
+    #[test]
+    fn brdf_lut_conserves_energy() {
+        let lut = brdf_lut(8);
+        assert!(lut.iter().all(|&[a, b]| a >= 0. && b >= 0. && a + b <= 1.01));
+        //smooth surfaces seen head on reflect F0 unchanged
+        let [a, b] = lut[7];
+        assert!(close(a, 1., 0.1) && b < 0.05, "{} {}", a, b);
+    }
+}
@@ -5,7 +5,7 @@ use crate::{
     },
 };
 use crate::imgui;
-use crate::api::Event;
+use crate::api::{Backend, Event};
 use crate::run::{EngineContext, FrameContext};
 
 struct InfoLayer {
@@ -13,13 +13,13 @@ struct InfoLayer {
 }
 
 impl InfoLayer {
-    pub fn new(ctx: &mut EngineContext) -> Self {
+    pub fn new<B: Backend>(ctx: &mut EngineContext<B>) -> Self {
         InfoLayer { loged_events: Vec::with_capacity(10) }
     }
 }
 
-impl Layer for InfoLayer {
-    fn on_update(&mut self, frame: &mut FrameContext, ctx: &mut EngineContext) {
+impl<B: Backend> Layer<B> for InfoLayer {
+    fn on_update(&mut self, frame: &mut FrameContext, ctx: &mut EngineContext<B>) {
         let ui = &frame.ui;
 
         for e in &frame.events {
@@ -36,6 +36,11 @@ impl Layer for InfoLayer {
                 let io: &imgui::Io = ui.io();
                 ui.text(imgui::im_str!("{:.1} fps", ui.imgui().get_frame_rate()));
                 ui.text(imgui::im_str!("{:.1} ms/f", io.delta_time * 1000.));
+                let stats = ctx.renderer.stats();
+                ui.text(imgui::im_str!("{} draws in {} calls", stats.draws, stats.calls));
+                ui.text(imgui::im_str!("{} instances visible, {} culled", stats.instances, stats.culled));
+                let instances = ctx.renderer.instances();
+                ui.text(imgui::im_str!("{} / {} instance capacity used at most", instances.high_water(), instances.capacity()));
                 let mouse_pos = ui.imgui().mouse_pos();
 
                 let [w, h] = io.display_size;
@@ -59,8 +64,8 @@ impl Layer for InfoLayer {
 
 pub struct InfoLayerBuilder;
 
-impl<'l> LayerBuilder<'l> for InfoLayerBuilder {
-    fn build(&self, r: &mut EngineContext) -> Box<dyn Layer + 'l> {
+impl<'l, B: Backend> LayerBuilder<'l, B> for InfoLayerBuilder {
+    fn build(&self, r: &mut EngineContext<B>) -> Box<dyn Layer<B> + 'l> {
         Box::new(InfoLayer::new(r))
     }
 }
\ No newline at end of file
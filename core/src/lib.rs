@@ -1,5 +1,10 @@
 pub extern crate backend_api as api;
+#[cfg(feature = "opengl")]
 pub extern crate backend_opengl as backend;
+#[cfg(feature = "headless")]
+pub extern crate backend_headless as headless;
+#[cfg(feature = "vulkan")]
+pub extern crate backend_vulkan as vulkan;
 pub extern crate imgui;
 ///maths
 pub extern crate nalgebra as na;
@@ -14,6 +19,22 @@ pub extern crate specs_derive;
 
 //pub mod backend;
 pub mod render;
+pub mod allocator;
+pub mod texture;
+pub mod target;
+pub mod capture;
+pub mod batch;
+pub mod stream;
+pub mod cull;
+pub mod material;
+pub mod light;
+pub mod shadow;
+pub mod hdr;
+pub mod ibl;
+pub mod post;
+pub mod skybox;
+#[cfg(feature = "headless")]
+pub mod golden;
 
 ///internal
 pub mod run;
@@ -0,0 +1,138 @@
+use crate::Matrix4f;
+use crate::shadow::Cascades;
+
+///Number of lights of each kind the shaders' `Lights` uniform block holds.
+pub const LIGHTS_LIMIT: usize = 16;
+
+///Direction the light travels in with 1 in `w` when it casts shadows, and its color scaled by intensity.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[repr(C)]
+pub struct DirectionalLightData {
+    pub direction: [f32; 4],
+    pub color: [f32; 4],
+}
+
+///Position with the range in `w` and color scaled by intensity.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[repr(C)]
+pub struct PointLightData {
+    pub position: [f32; 4],
+    pub color: [f32; 4],
+}
+
+///Position with the range in `w`, direction with the cosine of the outer cone angle in `w`
+///and color scaled by intensity with the cosine of the inner cone angle in `w`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[repr(C)]
+pub struct SpotLightData {
+    pub position: [f32; 4],
+    pub direction: [f32; 4],
+    pub color: [f32; 4],
+}
+
+///Contents of the std140 `Lights` block, lights past `LIGHTS_LIMIT` of a kind are dropped.
+#[derive(Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct LightsData {
+    ///Camera position in world space. Specular highlights are computed towards it.
+    pub eye: [f32; 4],
+    ///Number of directional, point and spot lights in use.
+    pub counts: [i32; 4],
+    pub directional: [DirectionalLightData; LIGHTS_LIMIT],
+    pub point: [PointLightData; LIGHTS_LIMIT],
+    pub spot: [SpotLightData; LIGHTS_LIMIT],
+    ///Shadow maps of the first directional light casting shadows, the renderer fits them to the camera.
+    pub shadows: Cascades,
+}
+
+impl Default for LightsData {
+    fn default() -> Self {
+        LightsData {
+            eye: [0.; 4],
+            counts: [0; 4],
+            directional: [DirectionalLightData::default(); LIGHTS_LIMIT],
+            point: [PointLightData::default(); LIGHTS_LIMIT],
+            spot: [SpotLightData::default(); LIGHTS_LIMIT],
+            shadows: Cascades::default(),
+        }
+    }
+}
+
+impl LightsData {
+    pub fn clear(&mut self) {
+        self.counts = [0; 4];
+    }
+
+    pub fn push_directional(&mut self, light: DirectionalLightData) {
+        if let Some(slot) = self.directional.get_mut(self.counts[0] as usize) {
+            *slot = light;
+            self.counts[0] += 1;
+        }
+    }
+
+    pub fn push_point(&mut self, light: PointLightData) {
+        if let Some(slot) = self.point.get_mut(self.counts[1] as usize) {
+            *slot = light;
+            self.counts[1] += 1;
+        }
+    }
+
+    pub fn push_spot(&mut self, light: SpotLightData) {
+        if let Some(slot) = self.spot.get_mut(self.counts[2] as usize) {
+            *slot = light;
+            self.counts[2] += 1;
+        }
+    }
+
+    ///Sets `eye` to the camera position of `view`.
+    pub fn set_eye(&mut self, view: &Matrix4f) {
+        let camera = glm::inverse(view);
+        self.eye = [camera[(0, 3)], camera[(1, 3)], camera[(2, 3)], 1.];
+    }
+
+    ///Index of the first directional light casting shadows. The flag is cleared on the others, as only that light gets maps.
+    pub fn shadow_caster(&mut self) -> Option<usize> {
+        let mut casters = self.directional[..self.counts[0] as usize].iter_mut()
+            .enumerate()
+            .filter(|(_, light)| light.direction[3] > 0.5);
+        let first = casters.next().map(|(i, _)| i);
+        for (_, light) in casters {
+            light.direction[3] = 0.;
+        }
+        first
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::size_of;
+
+    use crate::shadow::CASCADES;
+
+    use super::*;
+
+    #[test]
+    fn packs_lights_up_to_the_limit() {
+        assert_eq!(size_of::<LightsData>(), 32 + LIGHTS_LIMIT * (32 + 32 + 48) + CASCADES * 64 + 16);
+
+        let mut lights = LightsData::default();
+        for _ in 0..LIGHTS_LIMIT + 1 {
+            lights.push_point(PointLightData { position: [1., 2., 3., 10.], color: [1.; 4] });
+        }
+        lights.push_directional(DirectionalLightData::default());
+        assert_eq!(lights.counts, [1, LIGHTS_LIMIT as i32, 0, 0]);
+        assert_eq!(lights.shadow_caster(), None);
+
+        let caster = DirectionalLightData { direction: [0., -1., 0., 1.], color: [1.; 4] };
+        lights.push_directional(caster);
+        lights.push_directional(caster);
+        assert_eq!(lights.shadow_caster(), Some(1));
+        assert_eq!(lights.directional[2].direction[3], 0.);
+
+        lights.set_eye(&glm::translation(&glm::vec3(-1., -2., -3.)));
+        assert_eq!(lights.eye, [1., 2., 3., 1.]);
+
+        lights.clear();
+        assert_eq!(lights.counts, [0; 4]);
+    }
+}
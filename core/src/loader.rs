@@ -6,11 +6,14 @@ use std::path::Path;
 
 use tobj::{Mesh, MTLLoadResult};
 
+use crate::cull::Aabb;
+
 pub struct LoadResult {
     pub positions: Vec<f32>,
     pub uvs: Vec<f32>,
     pub normals: Vec<f32>,
     pub indices: Vec<u32>,
+    pub bounds: Aabb,
 }
 
 pub struct Loader;
@@ -38,6 +41,7 @@ impl Loader {
             uvs: mesh.texcoords.clone(),
             normals: mesh.normals.clone(),
             indices: mesh.indices.clone(),
+            bounds: Aabb::from_positions(&mesh.positions),
         }
     }
 }
\ No newline at end of file
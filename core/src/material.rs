@@ -0,0 +1,181 @@
+use std::mem::size_of;
+
+use api::RendererDevice;
+use api::memory::Properties;
+
+use crate::allocator::{Allocation, Allocator};
+
+///Index of a material registered with the renderer. `Render::material` holds one.
+pub type MaterialId = u32;
+///Index of a pipeline registered with the renderer, 0 is the default one.
+pub type PipelineId = u32;
+///Index of a texture registered with the renderer, 0 is plain white.
+pub type TextureId = u32;
+
+///Blinn-Phong pipeline. Materials draw with it by default.
+pub const LIT_PIPELINE: PipelineId = 0;
+///Metallic-roughness pipeline lit by the environment as well.
+pub const PBR_PIPELINE: PipelineId = 1;
+///Blinn-Phong pipeline blending by the alpha of `base_color`, drawn back to front after the opaque ones.
+pub const TRANSPARENT_PIPELINE: PipelineId = 2;
+///Plain white, leaves the factors it multiplies unchanged.
+pub const WHITE_TEXTURE: TextureId = 0;
+///Normal map of a flat surface.
+pub const FLAT_NORMAL_TEXTURE: TextureId = 1;
+
+///Descriptors the shaders of a pipeline read, the PBR ones also sample the material's maps and the environment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shading {
+    Lit,
+    Pbr,
+}
+
+///Number of materials the shaders' `Materials` uniform block array holds.
+pub const MATERIALS_LIMIT: u32 = 256;
+
+///Look of a surface and the pipeline drawing it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    pub pipeline: PipelineId,
+    pub base_color: [f32; 4],
+    pub roughness: f32,
+    pub metallic: f32,
+    pub emissive: [f32; 3],
+    ///Sampled and multiplied with `base_color`.
+    pub texture: TextureId,
+    ///Tangent space normals. Only the PBR pipeline reads it and the maps below.
+    pub normal_texture: TextureId,
+    ///Roughness in green and metallic in blue, multiplied with the factors.
+    pub metallic_roughness_texture: TextureId,
+    ///Ambient occlusion in red.
+    pub occlusion_texture: TextureId,
+    ///Multiplied with `emissive`.
+    pub emissive_texture: TextureId,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            pipeline: LIT_PIPELINE,
+            base_color: [1., 1., 1., 1.],
+            roughness: 1.,
+            metallic: 0.,
+            emissive: [0.; 3],
+            texture: WHITE_TEXTURE,
+            normal_texture: FLAT_NORMAL_TEXTURE,
+            metallic_roughness_texture: WHITE_TEXTURE,
+            occlusion_texture: WHITE_TEXTURE,
+            emissive_texture: WHITE_TEXTURE,
+        }
+    }
+}
+
+///Parameters of a material as an element of the std140 `Materials` block.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct MaterialData {
+    pub base_color: [f32; 4],
+    pub roughness: f32,
+    pub metallic: f32,
+    _padding: [f32; 2],
+    ///Padded to a vec4.
+    pub emissive: [f32; 4],
+}
+
+impl From<&Material> for MaterialData {
+    fn from(material: &Material) -> Self {
+        MaterialData {
+            base_color: material.base_color,
+            roughness: material.roughness,
+            metallic: material.metallic,
+            _padding: [0.; 2],
+            emissive: [material.emissive[0], material.emissive[1], material.emissive[2], 0.],
+        }
+    }
+}
+
+///Registered materials, their parameters packed into one uniform buffer indexed by material id,
+///along with the descriptor set binding each one's textures.
+pub struct MaterialStore<B: api::Backend> {
+    buffer: B::Buffer,
+    allocation: Allocation,
+    materials: Vec<(Material, B::DescriptorSet)>,
+}
+
+impl<B: api::Backend> MaterialStore<B> {
+    pub fn new(device: &B::RendererDevice, allocator: &mut Allocator<B>) -> Result<Self, api::Error> {
+        let mut buffer = device.create_buffer(api::BufferDescriptor {
+            size: MATERIALS_LIMIT * size_of::<MaterialData>() as u32,
+            usage: api::Usage::Uniform,
+        })?;
+        let allocation = allocator.bind_buffer(device, &mut buffer, Properties::HOST_VISIBLE | Properties::HOST_COHERENT)?;
+        Ok(MaterialStore { buffer, allocation, materials: Vec::new() })
+    }
+
+    ///Buffer of the `Materials` block.
+    pub fn buffer(&self) -> &B::Buffer {
+        &self.buffer
+    }
+
+    ///Packs `material` after the registered ones, fails with `OutOfMemory` past `MATERIALS_LIMIT`.
+    pub fn add(&mut self,
+               device: &B::RendererDevice,
+               allocator: &Allocator<B>,
+               material: Material,
+               set: B::DescriptorSet) -> Result<MaterialId, api::Error> {
+        let id = self.materials.len() as MaterialId;
+        if id >= MATERIALS_LIMIT {
+            return Err(api::Error::OutOfMemory);
+        }
+        let data = MaterialData::from(&material);
+        let ptr = allocator.map(device, &self.allocation)?;
+        unsafe {
+            std::ptr::copy(&data as *const MaterialData as *const u8,
+                           ptr.add(id as usize * size_of::<MaterialData>()),
+                           size_of::<MaterialData>());
+        }
+        allocator.unmap(device, &self.allocation);
+        self.materials.push((material, set));
+        Ok(id)
+    }
+
+    pub fn get(&self, id: MaterialId) -> Option<&Material> {
+        self.materials.get(id as usize).map(|(material, _)| material)
+    }
+
+    ///Descriptor set of the matrices, the material's textures and the `Materials` block.
+    pub fn descriptor_set(&self, id: MaterialId) -> Option<&B::DescriptorSet> {
+        self.materials.get(id as usize).map(|(_, set)| set)
+    }
+
+    pub fn descriptor_sets(&self) -> impl Iterator<Item = &B::DescriptorSet> {
+        self.materials.iter().map(|(_, set)| set)
+    }
+
+    pub fn len(&self) -> usize {
+        self.materials.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.materials.is_empty()
+    }
+
+    pub fn free(self, allocator: &mut Allocator<B>) {
+        allocator.free(self.allocation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packs_std140_elements() {
+        //the floats are followed by a vec4, which starts at the next multiple of 16
+        assert_eq!(size_of::<MaterialData>(), 48);
+        let data = MaterialData::from(&Material { base_color: [0.5, 0., 0., 1.], roughness: 0.25, ..Material::default() });
+        assert_eq!((data.base_color, data.roughness), ([0.5, 0., 0., 1.], 0.25));
+        let data = MaterialData::from(&Material { metallic: 1., emissive: [2., 1., 0.], ..Material::default() });
+        assert_eq!((data.metallic, data.emissive), (1., [2., 1., 0., 0.]));
+    }
+}
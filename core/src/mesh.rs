@@ -1,7 +1,200 @@
-pub struct MeshUploader {
+use std::mem::{size_of, size_of_val};
+use std::ops::Range;
 
+use api::{CommandBuffer, RendererDevice};
+use api::memory::Properties;
+
+use crate::allocator::{Allocation, Allocator};
+use crate::cull::Aabb;
+use crate::loader::LoadResult;
+
+///Index of a mesh in a `MeshStore`. `Render::va` holds one.
+pub type MeshId = u32;
+
+///Interleaved vertex of every stored mesh, uploaded as is so the field order must match the attribute offsets.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub uv: [f32; 2],
+    pub normal: [f32; 3],
+}
+
+impl Vertex {
+    pub fn from_pos_norm(positions: &[f32], uvs: &[f32], normals: &[f32]) -> Vec<Vertex> {
+        assert_eq!(positions.len(), normals.len(), "different size of positions and normals");
+        positions
+            .chunks_exact(3)
+            .zip(normals.chunks_exact(3))
+            .enumerate()
+            .map(|(i, (p, n))| Vertex {
+                position: [p[0], p[1], p[2]],
+                //meshes without texture coordinates sample the corner
+                uv: [
+                    *uvs.get(i * 2).unwrap_or(&0.),
+                    *uvs.get(i * 2 + 1).unwrap_or(&0.),
+                ],
+                normal: [n[0], n[1], n[2]],
+            })
+            .collect()
+    }
+}
+
+///Range of the shared buffers holding one mesh, its indices are relative to `base_vertex`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mesh {
+    pub base_vertex: i32,
+    pub first_index: u32,
+    pub index_count: u32,
+    pub bounds: Aabb,
+}
+
+impl Mesh {
+    ///Arguments drawing `instances` of the mesh with `CommandBuffer::draw_indexed_indirect`.
+    pub fn indirect(&self, instances: Range<u32>) -> api::DrawIndexedIndirectCommand {
+        api::DrawIndexedIndirectCommand {
+            index_count: self.index_count,
+            instance_count: instances.end - instances.start,
+            first_index: self.first_index,
+            base_vertex: self.base_vertex,
+            first_instance: instances.start,
+        }
+    }
+}
+
+///Meshes appended one after another into a vertex and an index buffer, bound once for all of them.
+pub struct MeshStore<B: api::Backend> {
+    vertex: B::Buffer,
+    index: B::Buffer,
+    vertex_mem: Allocation,
+    index_mem: Allocation,
+    vertex_capacity: u32,
+    index_capacity: u32,
+    vertices: u32,
+    indices: u32,
+    meshes: Vec<Mesh>,
 }
 
-impl MeshUploader {
+impl<B: api::Backend> MeshStore<B> {
+    pub fn new(device: &B::RendererDevice,
+               allocator: &mut Allocator<B>,
+               vertex_capacity: u32,
+               index_capacity: u32) -> Result<Self, api::Error> {
+        let host_visible = Properties::HOST_VISIBLE | Properties::HOST_COHERENT;
+        let mut vertex = device.create_buffer(api::BufferDescriptor {
+            size: vertex_capacity * size_of::<Vertex>() as u32,
+            usage: api::Usage::Vertex,
+        })?;
+        let mut index = device.create_buffer(api::BufferDescriptor {
+            size: index_capacity * size_of::<u32>() as u32,
+            usage: api::Usage::Index,
+        })?;
+        let vertex_mem = allocator.bind_buffer(device, &mut vertex, host_visible)?;
+        let index_mem = match allocator.bind_buffer(device, &mut index, host_visible) {
+            Ok(index_mem) => index_mem,
+            Err(e) => {
+                allocator.free(vertex_mem);
+                return Err(e);
+            }
+        };
+        Ok(MeshStore {
+            vertex,
+            index,
+            vertex_mem,
+            index_mem,
+            vertex_capacity,
+            index_capacity,
+            vertices: 0,
+            indices: 0,
+            meshes: Vec::new(),
+        })
+    }
 
-}
\ No newline at end of file
+    ///Store of a single triangle reaching past the corners of clip space, which covers it without a diagonal seam.
+    ///Full-screen passes take its positions as clip space. It lies on the far plane, where the sky's shader puts it too.
+    pub fn fullscreen(device: &B::RendererDevice, allocator: &mut Allocator<B>) -> Result<Self, api::Error> {
+        let mut triangle = MeshStore::new(device, allocator, 3, 3)?;
+        let vertices: Vec<Vertex> = [[-1., -1.], [3., -1.], [-1., 3.]].iter()
+            .map(|&[x, y]| Vertex { position: [x, y, 1.], uv: [(x + 1.) / 2., (y + 1.) / 2.], normal: [0., 0., 1.] })
+            .collect();
+        let positions: Vec<f32> = vertices.iter().flat_map(|v| v.position.to_vec()).collect();
+        triangle.add(device, allocator, &vertices, &[0, 1, 2], Aabb::from_positions(&positions))?;
+        Ok(triangle)
+    }
+
+    ///Appends a mesh enclosed by `bounds`, fails with `OutOfMemory` once either buffer is full.
+    pub fn add(&mut self,
+               device: &B::RendererDevice,
+               allocator: &Allocator<B>,
+               vertices: &[Vertex],
+               indices: &[u32],
+               bounds: Aabb) -> Result<MeshId, api::Error> {
+        if self.vertices as usize + vertices.len() > self.vertex_capacity as usize
+            || self.indices as usize + indices.len() > self.index_capacity as usize {
+            return Err(api::Error::OutOfMemory);
+        }
+
+        let v_ptr = allocator.map(device, &self.vertex_mem)?;
+        unsafe {
+            std::ptr::copy(vertices.as_ptr() as *const u8,
+                           v_ptr.add(self.vertices as usize * size_of::<Vertex>()),
+                           size_of_val(vertices))
+        }
+        allocator.unmap(device, &self.vertex_mem);
+        let i_ptr = allocator.map(device, &self.index_mem)?;
+        unsafe {
+            std::ptr::copy(indices.as_ptr() as *const u8,
+                           i_ptr.add(self.indices as usize * size_of::<u32>()),
+                           size_of_val(indices))
+        }
+        allocator.unmap(device, &self.index_mem);
+
+        self.meshes.push(Mesh {
+            base_vertex: self.vertices as i32,
+            first_index: self.indices,
+            index_count: indices.len() as u32,
+            bounds,
+        });
+        self.vertices += vertices.len() as u32;
+        self.indices += indices.len() as u32;
+        Ok(self.meshes.len() as MeshId - 1)
+    }
+
+    pub fn add_loaded(&mut self,
+                      device: &B::RendererDevice,
+                      allocator: &Allocator<B>,
+                      result: &LoadResult) -> Result<MeshId, api::Error> {
+        let vertices = Vertex::from_pos_norm(&result.positions, &result.uvs, &result.normals);
+        self.add(device, allocator, &vertices, &result.indices, result.bounds)
+    }
+
+    pub fn get(&self, id: MeshId) -> Option<&Mesh> {
+        self.meshes.get(id as usize)
+    }
+
+    pub fn len(&self) -> usize {
+        self.meshes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.meshes.is_empty()
+    }
+
+    ///Binds the vertex buffer at `binding` and the index buffer.
+    pub fn bind(&self, cmd: &mut B::CommandBuffer, binding: u32) {
+        cmd.bind_vertex_buffer(binding, &self.vertex);
+        cmd.bind_index_buffer(&self.index);
+    }
+
+    pub fn draw(&self, cmd: &mut B::CommandBuffer, mesh: &Mesh, instances: Range<u32>) {
+        cmd.draw_indexed_base_vertex(mesh.index_count,
+                                     mesh.first_index * size_of::<u32>() as u32,
+                                     mesh.base_vertex,
+                                     instances);
+    }
+
+    pub fn free(self, allocator: &mut Allocator<B>) {
+        allocator.free(self.vertex_mem);
+        allocator.free(self.index_mem);
+    }
+}
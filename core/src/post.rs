@@ -0,0 +1,349 @@
+use std::mem::size_of;
+use std::path::{Path, PathBuf};
+
+use api::{CommandBuffer, RendererDevice};
+use api::memory::Properties;
+
+use crate::allocator::{Allocation, Allocator};
+use crate::mesh::{MeshStore, Vertex};
+use crate::render::{load_shaders, write_texture};
+use crate::target::RenderTarget;
+use crate::texture::Texture;
+
+///Full-screen pass of the post-process chain, drawing a triangle covering the screen with `fragment`.
+///The fragment shader samples the previous pass's output, or the scene for the first pass, as `u_input`
+///and reads `params` and the size of its input from the `Post` block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PostPass {
+    pub fragment: PathBuf,
+    ///Scale of the pass's output relative to the scene size. A scale of 0.5 renders at half resolution.
+    ///The last pass renders at the size of the screen or target instead.
+    pub scale: f32,
+    pub params: [f32; 4],
+    ///Also samples the scene as `u_scene`, for passes that combine it with the previous output.
+    pub reads_scene: bool,
+}
+
+impl PostPass {
+    pub fn new(fragment: PathBuf) -> PostPass {
+        PostPass { fragment, scale: 1., params: [0.; 4], reads_scene: false }
+    }
+}
+
+///Contents of the std140 `Post` block.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct PostData {
+    pub params: [f32; 4],
+    ///Size of a texel of `u_input` in `xy` and its extent in `zw`.
+    pub texel: [f32; 4],
+}
+
+///Index of a pass in the post-process chain.
+pub type PostPassId = usize;
+
+struct Pass<B: api::Backend> {
+    desc: PostPass,
+    pipeline: B::Pipeline,
+    ///The `Post` block, kept alive for the descriptor set.
+    _uniform: B::Buffer,
+    allocation: Allocation,
+    set: B::DescriptorSet,
+    ///Output of every pass but the last, which renders into the frame's destination.
+    target: Option<RenderTarget<B>>,
+}
+
+///Passes the scene goes through after being rendered into an Rgba16F target, each reading the previous one's output.
+pub struct PostChain<B: api::Backend> {
+    desc_set_layout: B::DescriptorSetLayout,
+    input_layout: B::PipelineLayout,
+    scene_layout: B::PipelineLayout,
+    ///Holds the triangle covering the screen.
+    triangle: MeshStore<B>,
+    vertex: PathBuf,
+    passes: Vec<Pass<B>>,
+    scene: Option<RenderTarget<B>>,
+    ///Extent the targets were created at, zero when they are to be created again.
+    extent: (u32, u32),
+}
+
+impl<B: api::Backend> PostChain<B> {
+    ///Chain without passes, which compiles the passes added to it with the vertex shader at `vertex`.
+    pub fn new(device: &B::RendererDevice, allocator: &mut Allocator<B>, vertex: &Path) -> Result<Self, api::Error> {
+        let desc_set_layout = device.create_descriptor_set_layout(&[
+            api::DescriptorSetLayoutBinding {
+                binding: 0,
+                desc: api::DescriptorType::UniformBuffer,
+            },
+            api::DescriptorSetLayoutBinding {
+                binding: 1,
+                desc: api::DescriptorType::Sampler,
+            },
+            api::DescriptorSetLayoutBinding {
+                binding: 2,
+                desc: api::DescriptorType::Sampler,
+            }
+        ])?;
+        let hints = |reads_scene: bool| {
+            let mut hints = vec![
+                api::PipelineLayoutHint {
+                    location: 0,
+                    hint: api::LayoutHint::Name("Post"),
+                },
+                api::PipelineLayoutHint {
+                    location: 1,
+                    hint: api::LayoutHint::Name("u_input"),
+                }
+            ];
+            if reads_scene {
+                hints.push(api::PipelineLayoutHint {
+                    location: 2,
+                    hint: api::LayoutHint::Name("u_scene"),
+                });
+            }
+            hints
+        };
+        let input_layout = device.create_pipeline_layout(&desc_set_layout, hints(false))?;
+        let scene_layout = device.create_pipeline_layout(&desc_set_layout, hints(true))?;
+
+        Ok(PostChain {
+            desc_set_layout,
+            input_layout,
+            scene_layout,
+            triangle: MeshStore::fullscreen(device, allocator)?,
+            vertex: vertex.to_path_buf(),
+            passes: Vec::new(),
+            scene: None,
+            extent: (0, 0),
+        })
+    }
+
+    ///Compiles `pass` and appends it to the chain, its targets are created with the next frame.
+    pub fn add(&mut self, device: &B::RendererDevice, allocator: &mut Allocator<B>, pass: PostPass) -> Result<PostPassId, api::Error> {
+        let layout = if pass.reads_scene { &self.scene_layout } else { &self.input_layout };
+        //passes cover their whole output whatever depth the vertex shader writes, the headless backend keeps the triangle's 1
+        let pipeline = create_fullscreen_pipeline::<B>(device, layout, &self.vertex, &pass.fragment, api::CompareOp::Always)?;
+        let mut uniform = device.create_buffer(api::BufferDescriptor {
+            size: size_of::<PostData>() as u32,
+            usage: api::Usage::Uniform,
+        })?;
+        let allocation = allocator.bind_buffer(device, &mut uniform, Properties::HOST_VISIBLE | Properties::HOST_COHERENT)?;
+        let set = device.allocate_descriptor_set(&self.desc_set_layout)?;
+        device.write_descriptor_set(api::DescriptorSetWrite {
+            set: &set,
+            binding: 0,
+            descriptor: api::Descriptor::Buffer(&uniform),
+        });
+        self.passes.push(Pass { desc: pass, pipeline, _uniform: uniform, allocation, set, target: None });
+        self.extent = (0, 0);
+        Ok(self.passes.len() - 1)
+    }
+
+    ///Replaces the parameters of `pass`, which its shader reads from the `Post` block.
+    pub fn set_params(&mut self, device: &B::RendererDevice, allocator: &Allocator<B>, pass: PostPassId, params: [f32; 4]) -> Result<(), api::Error> {
+        self.passes[pass].desc.params = params;
+        //written along with the input's size once the targets are created otherwise
+        if self.extent == (0, 0) {
+            return Ok(());
+        }
+        write_params(device, allocator, &self.passes[pass], self.input_extent(pass))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.passes.is_empty()
+    }
+
+    ///Target the scene is to be rendered into.
+    pub fn scene(&self) -> Option<&RenderTarget<B>> {
+        self.scene.as_ref()
+    }
+
+    ///Creates the scene target and the passes' outputs at `extent` unless they already are.
+    pub fn resize(&mut self, device: &B::RendererDevice, allocator: &mut Allocator<B>, extent: (u32, u32)) -> Result<(), api::Error> {
+        if extent == self.extent {
+            return Ok(());
+        }
+        self.free_targets(allocator);
+        self.scene = Some(RenderTarget::new(device,
+                                            allocator,
+                                            extent,
+                                            &[api::image::Format::Rgba16F],
+                                            Some(api::image::Format::D32F))?);
+        let last = self.passes.len().saturating_sub(1);
+        for i in 0..last {
+            let scale = self.passes[i].desc.scale;
+            let scaled = |size: u32| ((size as f32 * scale) as u32).max(1);
+            self.passes[i].target = Some(RenderTarget::new(device,
+                                                           allocator,
+                                                           (scaled(extent.0), scaled(extent.1)),
+                                                           &[api::image::Format::Rgba16F],
+                                                           None)?);
+        }
+
+        let scene = &self.scene.as_ref().unwrap().colors[0];
+        for i in 0..self.passes.len() {
+            let input: &Texture<B> = match i {
+                0 => scene,
+                _ => &self.passes[i - 1].target.as_ref().unwrap().colors[0],
+            };
+            let pass = &self.passes[i];
+            write_texture(device, &pass.set, 1, input);
+            write_texture(device, &pass.set, 2, scene);
+            write_params(device, allocator, pass, self.input_extent(i))?;
+        }
+        self.extent = extent;
+        Ok(())
+    }
+
+    ///Records the passes, the last one rendering into `destination` or the screen.
+    pub fn record(&self, cmd: &mut B::CommandBuffer, destination: Option<&RenderTarget<B>>, clear: [f32; 4]) {
+        for pass in &self.passes {
+            let target = pass.target.as_ref().or(destination);
+            match target {
+                Some(target) => target.begin(cmd, clear),
+                None => cmd.clear_screen((clear[0], clear[1], clear[2], clear[3])),
+            }
+            cmd.bind_pipeline(&pass.pipeline);
+            self.triangle.bind(cmd, 0);
+            let layout = if pass.desc.reads_scene { &self.scene_layout } else { &self.input_layout };
+            cmd.bind_descriptor_set(layout, &pass.set);
+            cmd.draw_indexed(3, 0, 1);
+            if target.is_some() {
+                cmd.end_render_pass();
+            }
+        }
+    }
+
+    ///Extent of what pass `i` samples as `u_input`.
+    fn input_extent(&self, i: usize) -> (u32, u32) {
+        let input = match i {
+            0 => self.scene.as_ref(),
+            _ => self.passes[i - 1].target.as_ref(),
+        };
+        input.map_or((1, 1), |target| target.extent)
+    }
+
+    fn free_targets(&mut self, allocator: &mut Allocator<B>) {
+        for target in self.scene.take().into_iter().chain(self.passes.iter_mut().filter_map(|pass| pass.target.take())) {
+            target.free(allocator);
+        }
+        self.extent = (0, 0);
+    }
+
+    pub fn free(mut self, allocator: &mut Allocator<B>) {
+        self.free_targets(allocator);
+        for pass in self.passes {
+            allocator.free(pass.allocation);
+        }
+        self.triangle.free(allocator);
+    }
+}
+
+fn write_params<B: api::Backend>(device: &B::RendererDevice, allocator: &Allocator<B>, pass: &Pass<B>, input: (u32, u32)) -> Result<(), api::Error> {
+    let (width, height) = (input.0 as f32, input.1 as f32);
+    let data = PostData { params: pass.desc.params, texel: [1. / width, 1. / height, width, height] };
+    let ptr = allocator.map(device, &pass.allocation)?;
+    unsafe {
+        std::ptr::copy(&data as *const PostData as *const u8, ptr, size_of::<PostData>());
+    }
+    allocator.unmap(device, &pass.allocation);
+    Ok(())
+}
+
+///Pipeline reading the positions of `MeshStore::fullscreen`'s triangle, testing its depth with `depth_compare`.
+pub(crate) fn create_fullscreen_pipeline<B: api::Backend>(device: &B::RendererDevice,
+                                                          layout: &B::PipelineLayout,
+                                                          vertex: &Path,
+                                                          fragment: &Path,
+                                                          depth_compare: api::CompareOp) -> Result<B::Pipeline, api::Error> {
+    let shader_set = load_shaders::<B>(device, vertex, fragment)?;
+    let mut pipeline_desc = api::PipelineDescriptor::new(
+        api::Primitive::Triangles,
+        shader_set,
+        layout,
+    );
+    pipeline_desc.depth_stencil.depth_compare = depth_compare;
+
+    pipeline_desc.push_vb(api::VertexBufferDescriptor {
+        binding: 0,
+        stride: size_of::<Vertex>(),
+    });
+
+    pipeline_desc.push_attr(api::AttributeDescriptor {
+        binding: 0,
+        location: 0,
+        data: api::VertexData {
+            offset: 0,
+            data_type: api::DataType::Vec3f32,
+        },
+    });
+
+    device.create_pipeline(pipeline_desc)
+}
+
+#[cfg(all(test, feature = "headless"))]
+mod tests {
+    use api::PlatformManager;
+    use headless::{Backend, PlatformManager as HeadlessPlatformManager};
+
+    use crate::utils::relative_to;
+
+    use super::*;
+
+    fn shader(file: &str) -> PathBuf {
+        relative_to(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/..")), &["client", "src", "test", "post", file])
+    }
+
+    fn input_of(chain: &PostChain<Backend>, pass: usize) -> &Texture<Backend> {
+        match pass {
+            0 => &chain.scene.as_ref().unwrap().colors[0],
+            _ => &chain.passes[pass - 1].target.as_ref().unwrap().colors[0],
+        }
+    }
+
+    fn params_of(device: &headless::RendererDevice, allocator: &Allocator<Backend>, chain: &PostChain<Backend>, pass: usize) -> PostData {
+        let ptr = allocator.map(device, &chain.passes[pass].allocation).unwrap();
+        let data = unsafe { *(ptr as *const PostData) };
+        allocator.unmap(device, &chain.passes[pass].allocation);
+        data
+    }
+
+    #[test]
+    fn feeds_each_pass_the_previous_output_at_its_scale() {
+        let mut pm = HeadlessPlatformManager::new(api::WindowConfig { width: 1, height: 1 });
+        let (_api, device) = pm.create_renderer();
+        let mut allocator = Allocator::new(&device, 1 << 20);
+        let mut chain: PostChain<Backend> = PostChain::new(&device, &mut allocator, &shader("vert.glsl")).unwrap();
+        for pass in [
+            PostPass { scale: 0.5, ..PostPass::new(shader("bright.glsl")) },
+            PostPass { scale: 0.25, ..PostPass::new(shader("blur.glsl")) },
+            PostPass { reads_scene: true, params: [1., 2., 3., 4.], ..PostPass::new(shader("bloom.glsl")) },
+        ] {
+            chain.add(&device, &mut allocator, pass).unwrap();
+        }
+
+        chain.resize(&device, &mut allocator, (40, 20)).unwrap();
+        let extents: Vec<Option<(u32, u32)>> = chain.passes.iter()
+            .map(|pass| pass.target.as_ref().map(|target| target.extent))
+            .collect();
+        assert_eq!(chain.scene().unwrap().extent, (40, 20));
+        assert_eq!(extents, vec![Some((20, 10)), Some((10, 5)), None]);
+        for i in 0..chain.passes.len() {
+            let set = &chain.passes[i].set;
+            assert!(set.holds_image(1, &input_of(&chain, i).view), "input of pass {}", i);
+            assert!(set.holds_image(2, &input_of(&chain, 0).view), "scene of pass {}", i);
+        }
+        assert_eq!(params_of(&device, &allocator, &chain, 2), PostData { params: [1., 2., 3., 4.], texel: [1. / 10., 1. / 5., 10., 5.] });
+
+        //the same extent keeps the targets, another one recreates and rewires them
+        chain.resize(&device, &mut allocator, (40, 20)).unwrap();
+        assert!(chain.passes[1].set.holds_image(1, &input_of(&chain, 1).view));
+        chain.resize(&device, &mut allocator, (8, 8)).unwrap();
+        assert_eq!(chain.passes[0].target.as_ref().unwrap().extent, (4, 4));
+        assert_eq!(chain.passes[1].target.as_ref().unwrap().extent, (2, 2));
+        for i in 0..chain.passes.len() {
+            assert!(chain.passes[i].set.holds_image(1, &input_of(&chain, i).view), "input of pass {}", i);
+        }
+        assert_eq!(params_of(&device, &allocator, &chain, 1).texel, [1. / 4., 1. / 4., 4., 4.]);
+    }
+}
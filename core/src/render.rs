@@ -5,372 +5,855 @@ use std::{
         mpsc::Sender,
     }
 };
+use std::fs;
 use std::mem::size_of;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 
 use api::{CommandBuffer, RendererApi, RendererDevice};
-
+use api::memory::Properties;
+
+use crate::allocator::{Allocation, Allocator, BLOCK_SIZE};
+use crate::batch::{self, Batch, BatchKey, Batcher, FrameStats};
+use crate::stream::StreamBuffer;
+use crate::hdr::HdrImage;
+use crate::ibl::{self, EnvironmentId, Ibl};
+use crate::light::LightsData;
 use crate::loader::Loader;
+use crate::material::{Material, MaterialId, MaterialStore, PipelineId, Shading, TextureId};
+use crate::mesh::{MeshId, MeshStore, Vertex};
+use crate::post::{PostChain, PostPass, PostPassId};
+use crate::shadow::{Cascades, ShadowMaps};
+use crate::skybox::Skybox;
+use crate::target::RenderTarget;
+use crate::texture::Texture;
 use crate::Matrix4f;
-use crate::utils::relative_to_current_path;
+use crate::utils::{relative_to, relative_to_current_path};
+
+pub type DrawIndexed = (MeshId, MaterialId, Matrix4f);
+
+pub type CameraTargetId = u32;
 
-pub type DrawIndexed = (u32, u32, Matrix4f);
+///Where a camera draws the scene.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraTarget {
+    ///The screen, or the offscreen target when one is set, through the post-process chain.
+    Window,
+    ///Image added with `Renderer::add_camera_target`.
+    Image(CameraTargetId),
+}
+
+///What a camera sees of the frame and where it draws it.
+#[derive(Debug, Clone, Copy)]
+pub struct CameraView {
+    pub view: Matrix4f,
+    pub projection: Matrix4f,
+    ///Left, bottom, width and height of the area drawn into, as fractions of the target.
+    pub viewport: [f32; 4],
+    pub target: CameraTarget,
+    ///Cameras draw in increasing priority, over what the ones before them drew to the same target.
+    pub priority: i32,
+    ///Shadow maps are fit to the highest priority camera casting shadows, the others are lit unshadowed.
+    pub casts_shadows: bool,
+}
 
-pub struct Renderer {
-    api: backend::RendererApi,
+impl CameraView {
+    ///Viewport in pixels of a target of `extent`.
+    fn rect(&self, extent: (u32, u32)) -> api::Rect {
+        let [x, y, width, height] = self.viewport;
+        let (w, h) = (extent.0 as f32, extent.1 as f32);
+        let x0 = (x * w).round() as i32;
+        let y0 = (y * h).round() as i32;
+        api::Rect {
+            x: x0,
+            y: y0,
+            width: ((x + width) * w).round() as i32 - x0,
+            height: ((y + height) * h).round() as i32 - y0,
+        }
+    }
+}
+
+impl Default for CameraView {
+    fn default() -> Self {
+        CameraView {
+            view: glm::identity(),
+            projection: glm::identity(),
+            viewport: [0., 0., 1., 1.],
+            target: CameraTarget::Window,
+            priority: 0,
+            casts_shadows: false,
+        }
+    }
+}
 
-    pipeline: backend::Pipeline,
-    vertex: backend::Buffer,
-    index: backend::Buffer,
-    index_count: usize,
-    uniform: backend::Buffer,
-    instanced: backend::Buffer,
+///Per-instance data of the instance buffer, bound at locations 3 to 11.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct Instance {
+    pub mvp: Matrix4f,
+    ///Index into the `Materials` block. It is a float because attributes are fetched as floats.
+    pub material: f32,
+    ///Transforms model space to world space, where lighting is computed.
+    pub model: Matrix4f,
+}
 
-    pipeline_layout: backend::PipelineLayout,
-    desc_set: backend::DescriptorSet,
+///Number of vertices and indices the shared mesh buffers hold.
+const MESH_VERTICES: u32 = 64 * 1024;
+const MESH_INDICES: u32 = 3 * 64 * 1024;
+///Number of instances the instance buffer holds at first. It grows on demand.
+const INSTANCE_CAPACITY: u32 = 1024;
+///Number of draws the indirect buffer holds at first. It grows on demand.
+const INDIRECT_CAPACITY: u32 = 64;
+
+pub struct Renderer<B: api::Backend> {
+    api: B::RendererApi,
+
+    ///Registered pipelines and whether each one blends. Blending pipelines draw back to front after the opaque ones.
+    pipelines: Vec<(B::Pipeline, Shading, bool)>,
+    meshes: MeshStore<B>,
+    materials: MaterialStore<B>,
+    textures: Vec<Texture<B>>,
+    shadows: ShadowMaps<B>,
+    ibl: Ibl<B>,
+    skybox: Skybox<B>,
+    ///Environment the material descriptor sets are lit by.
+    environment: EnvironmentId,
+    uniform: B::Buffer,
+    lights: B::Buffer,
+    instances: StreamBuffer<B, Instance>,
+    indirect: StreamBuffer<B, api::DrawIndexedIndirectCommand>,
+
+    lit_layout: B::PipelineLayout,
+    pbr_layout: B::PipelineLayout,
+    desc_set_layout: B::DescriptorSetLayout,
+    ///When set, the scene is rendered into it instead of the screen.
+    offscreen: Option<RenderTarget<B>>,
+    ///Images cameras draw into rather than the window.
+    camera_targets: Vec<RenderTarget<B>>,
+    post: PostChain<B>,
+    clear_color: [f32; 4],
+    ///Window size in pixels. Captures are rendered at this size.
+    size: (u32, u32),
+    capture: Option<PathBuf>,
 
     sender: Sender<DrawIndexed>,
     receiver: Receiver<DrawIndexed>,
+    batcher: Batcher<Instance>,
+    stats: FrameStats,
 
     last_frame: Frame,
 
-    mesh_mem: backend::Memory,
-    indexes_mem: backend::Memory,
-    uniform_mem: backend::Memory,
-    instanced_mem: backend::Memory
+    uniform_mem: Allocation,
+    lights_mem: Allocation,
+    allocator: Allocator<B>,
 }
 
-impl Renderer {
-    pub fn new(api: backend::RendererApi, device: &backend::RendererDevice) -> Self {
-        let mut path_buf = &relative_to_current_path(&vec!["client", "resources", "cube.obj"]);
-        let mut loader = Loader;
-        let result = loader.load_obj(path_buf);
-
+impl<B: api::Backend> Renderer<B> {
+    pub fn new(api: B::RendererApi, device: &B::RendererDevice) -> Result<Self, api::Error> {
+        Renderer::with_assets(api, device, &relative_to_current_path(&[]))
+    }
 
-        let mut mesh_mem = device.allocate_memory(1024);
-        let mut indexes_mem = device.allocate_memory(1024);
-        let mut uniform_mem = device.allocate_memory(1024);
-        let mut instanced_mem = device.allocate_memory(16 * 4 * 30000);
+    ///Like `new` with the engine's meshes and shaders looked up in `assets` instead of the working directory.
+    pub fn with_assets(api: B::RendererApi, device: &B::RendererDevice, assets: &Path) -> Result<Self, api::Error> {
+        let mut allocator = Allocator::new(device, BLOCK_SIZE);
+        let host_visible = Properties::HOST_VISIBLE | Properties::HOST_COHERENT;
 
+        let mut meshes = MeshStore::new(device, &mut allocator, MESH_VERTICES, MESH_INDICES)?;
+        //mesh 0, what entities render unless they load their own
+        let cube = Loader.load_obj(&relative_to(assets, &["client", "resources", "cube.obj"]));
+        meshes.add_loaded(device, &allocator, &cube)?;
 
-        let static_mesh_buffer = device.create_buffer(api::BufferDescriptor {
-            size: 1024,
-            usage: api::Usage::Vertex,
-        });
-        let static_mesh_index_buffer = device.create_buffer(api::BufferDescriptor {
-            size: 1024,
-            usage: api::Usage::Index,
-        });
-        let uniform = device.create_buffer(api::BufferDescriptor {
+        let mut uniform = device.create_buffer(api::BufferDescriptor {
             size: 1024,
             usage: api::Usage::Uniform,
-        });
-        let instanced = device.create_buffer(api::BufferDescriptor {
-            size: 16 * 4 * 30000,
-            usage: api::Usage::Vertex,
-        });
+        })?;
+        let uniform_mem = allocator.bind_buffer(device, &mut uniform, host_visible)?;
+        let mut lights = device.create_buffer(api::BufferDescriptor {
+            size: size_of::<LightsData>() as u32,
+            usage: api::Usage::Uniform,
+        })?;
+        let lights_mem = allocator.bind_buffer(device, &mut lights, host_visible)?;
+        let instances = StreamBuffer::new(device, &mut allocator, api::Usage::Vertex, INSTANCE_CAPACITY)?;
+        let indirect = StreamBuffer::new(device, &mut allocator, api::Usage::Indirect, INDIRECT_CAPACITY)?;
+
+
+        let desc_set_layout = device.create_descriptor_set_layout(&DESCRIPTORS.iter()
+            .map(|(binding, desc, _)| api::DescriptorSetLayoutBinding { binding: *binding, desc: desc.clone() })
+            .collect::<Vec<_>>())?;
+        let lit_layout = create_pipeline_layout::<B>(device, &desc_set_layout, Shading::Lit)?;
+        let pbr_layout = create_pipeline_layout::<B>(device, &desc_set_layout, Shading::Pbr)?;
+
+        let vertex = relative_to(assets, &["client", "src", "test", "vert.glsl"]);
+        let lit = create_pipeline::<B>(device,
+                                       &lit_layout,
+                                       &vertex,
+                                       &relative_to(assets, &["client", "src", "test", "frag.glsl"]),
+                                       None)?;
+        let pbr = create_pipeline::<B>(device,
+                                       &pbr_layout,
+                                       &vertex,
+                                       &relative_to(assets, &["client", "src", "test", "pbr_frag.glsl"]),
+                                       None)?;
+        let transparent = create_pipeline::<B>(device,
+                                               &lit_layout,
+                                               &vertex,
+                                               &relative_to(assets, &["client", "src", "test", "frag.glsl"]),
+                                               Some(api::BlendState::ALPHA))?;
+
+        //texture 0, sampled by materials without one of their own
+        let white = Texture::upload(device,
+                                    &mut allocator,
+                                    api::image::Kind::D2(2, 2, 2),
+                                    api::image::Format::Rgba8,
+                                    &[255; 2 * 2 * 4],
+                                    api::image::SamplerDesc::new(api::image::Filter::Linear, api::image::WrapMode::Tile))?;
+        //texture 1, the normal map of materials without one of their own
+        let flat = Texture::upload(device,
+                                   &mut allocator,
+                                   api::image::Kind::D2(2, 2, 2),
+                                   api::image::Format::Rgba8,
+                                   &[128, 128, 255, 255].repeat(2 * 2),
+                                   api::image::SamplerDesc::new(api::image::Filter::Linear, api::image::WrapMode::Tile))?;
+        let materials = MaterialStore::new(device, &mut allocator)?;
+        let shadows = ShadowMaps::new(device,
+                                      &mut allocator,
+                                      &relative_to(assets, &["client", "src", "test", "shadow_vert.glsl"]),
+                                      &relative_to(assets, &["client", "src", "test", "shadow_frag.glsl"]))?;
+        let ibl = Ibl::new(device, &mut allocator)?;
+        let skybox = Skybox::new(device,
+                                 &mut allocator,
+                                 &relative_to(assets, &["client", "src", "test", "skybox_vert.glsl"]),
+                                 &relative_to(assets, &["client", "src", "test", "skybox_frag.glsl"]))?;
+        let post = PostChain::new(device, &mut allocator, &relative_to(assets, &["client", "src", "test", "post", "vert.glsl"]))?;
 
+        let (s, r) = mpsc::channel();
+        let mut renderer = Renderer {
+            api,
+            sender: s.clone(),
+            pipelines: vec![(lit, Shading::Lit, false), (pbr, Shading::Pbr, false), (transparent, Shading::Lit, true)],
+            meshes,
+            materials,
+            textures: vec![white, flat],
+            shadows,
+            ibl,
+            skybox,
+            environment: 0,
+            uniform: uniform,
+            lights,
+            lit_layout,
+            pbr_layout,
+            desc_set_layout,
+            offscreen: None,
+            camera_targets: Vec::new(),
+            post,
+            clear_color: [0.5, 0.5, 0.5, 1.],
+            size: (0, 0),
+            capture: None,
+            receiver: r,
+            batcher: Batcher::default(),
+            stats: FrameStats::default(),
+            last_frame: Frame {
+                queue: s.clone(),
+                cameras: Vec::new(),
+                lights: LightsData::default(),
+                environment: None,
+            },
+            uniform_mem,
+            lights_mem,
+            instances,
+            indirect,
 
-        device.bind_buffer_memory(&mut mesh_mem, &static_mesh_buffer);
-        device.bind_buffer_memory(&mut indexes_mem, &static_mesh_index_buffer);
-        device.bind_buffer_memory(&mut uniform_mem, &uniform);
-        device.bind_buffer_memory(&mut instanced_mem, &instanced);
+            allocator,
+        };
+        //environment 0, what the scene is lit by unless another one is active
+        renderer.add_environment(device, &ibl::default_environment())?;
+        //material 0, what entities render unless they register their own
+        renderer.add_material(device, Material::default())?;
+        Ok(renderer)
+    }
+}
 
-        #[derive(Debug)]
-        struct Vertex {
-            position: [f32; 3],
-            uv: [f32; 2],
-            normal: [f32; 3],
-        }
+///Binding, type and shader name of each descriptor in the material descriptor sets.
+const DESCRIPTORS: [(u32, api::DescriptorType, &str); 14] = [
+    (0, api::DescriptorType::UniformBuffer, "Matricies"),
+    (1, api::DescriptorType::Sampler, "u_texture"),
+    (2, api::DescriptorType::UniformBuffer, "Materials"),
+    (3, api::DescriptorType::UniformBuffer, "Lights"),
+    (4, api::DescriptorType::Sampler, "u_shadow0"),
+    (5, api::DescriptorType::Sampler, "u_shadow1"),
+    (6, api::DescriptorType::Sampler, "u_shadow2"),
+    (7, api::DescriptorType::Sampler, "u_normal"),
+    (8, api::DescriptorType::Sampler, "u_metallic_roughness"),
+    (9, api::DescriptorType::Sampler, "u_occlusion"),
+    (10, api::DescriptorType::Sampler, "u_emissive"),
+    (11, api::DescriptorType::Sampler, "u_irradiance"),
+    (12, api::DescriptorType::Sampler, "u_prefiltered"),
+    (13, api::DescriptorType::Sampler, "u_brdf"),
+];
+///Only PBR pipelines read the descriptors past it. OpenGL fails to find them in the other pipelines.
+const LIT_DESCRIPTORS: usize = 7;
+
+///Layout naming the descriptors the pipelines of `shading` read.
+fn create_pipeline_layout<B: api::Backend>(device: &B::RendererDevice,
+                                           desc_set_layout: &B::DescriptorSetLayout,
+                                           shading: Shading) -> Result<B::PipelineLayout, api::Error> {
+    let used = match shading {
+        Shading::Lit => &DESCRIPTORS[..LIT_DESCRIPTORS],
+        Shading::Pbr => &DESCRIPTORS[..],
+    };
+    device.create_pipeline_layout(desc_set_layout, used.iter()
+        .map(|&(location, _, name)| api::PipelineLayoutHint { location, hint: api::LayoutHint::Name(name) }))
+}
 
-        impl Vertex {
-            pub fn from_pos_norm(positions: &Vec<f32>, normals: &Vec<f32>) -> Vec<Vertex> {
-                assert_eq!(positions.len(), normals.len(), "different size of positions and normals");
-                positions
-                    .chunks_exact(3)
-                    .zip(normals.chunks_exact(3))
-                    .map(|(p, n): (&[f32], &[f32])| {
-                        let p = p.to_vec();
-                        let n = n.to_vec();
-                        Vertex {
-                            position: [
-                                *(p.get(0).unwrap()),
-                                *(p.get(1).unwrap()),
-                                *(p.get(2).unwrap()),
-                            ],
-                            uv: [0., 0.],
-                            normal: [
-                                *(n.get(0).unwrap()),
-                                *(n.get(1).unwrap()),
-                                *(n.get(2).unwrap()),
-                            ],
-                        }
-                    }).collect::<Vec<Vertex>>()
-            }
-        };
-        let vertexes = Vertex::from_pos_norm(&result.positions, &result.normals);
-        println!("{:?}", &vertexes);
+pub(crate) fn write_texture<B: api::Backend>(device: &B::RendererDevice, set: &B::DescriptorSet, binding: u32, texture: &Texture<B>) {
+    device.write_descriptor_set(api::DescriptorSetWrite {
+        set,
+        binding,
+        descriptor: api::Descriptor::CombinedImageSampler(&texture.view, &texture.sampler),
+    });
+}
 
+///Shader modules compiled from GLSL sources at `vertex` and `fragment`.
+pub(crate) fn load_shaders<B: api::Backend>(device: &B::RendererDevice,
+                                            vertex: &Path,
+                                            fragment: &Path) -> Result<api::ShaderSet<B>, api::Error> {
+    let read = |path: &Path| fs::read_to_string(path)
+        .map_err(|e| api::Error::Backend(format!("Reading {} failed: {}", path.display(), e)));
+    Ok(api::ShaderSet {
+        vertex: device.create_shader_mod(api::ShaderModDescriptor {
+            stype: api::ShaderType::Vertex,
+            source: api::ShaderSource::Glsl(read(vertex)?),
+        })?,
+        fragment: device.create_shader_mod(api::ShaderModDescriptor {
+            stype: api::ShaderType::Fragment,
+            source: api::ShaderSource::Glsl(read(fragment)?),
+        })?,
+    })
+}
+
+///Pipeline of the mesh and instance layout from GLSL sources at `vertex` and `fragment`.
+///With `blend` it tests depth without writing it, so the transparent surfaces behind each other all show.
+fn create_pipeline<B: api::Backend>(device: &B::RendererDevice,
+                                    layout: &B::PipelineLayout,
+                                    vertex: &Path,
+                                    fragment: &Path,
+                                    blend: Option<api::BlendState>) -> Result<B::Pipeline, api::Error> {
+    let shader_set = load_shaders::<B>(device, vertex, fragment)?;
+    let mut pipeline_desc = api::PipelineDescriptor::new(
+        api::Primitive::Triangles,
+        shader_set,
+        layout,
+    );
+    pipeline_desc.blend = vec![blend];
+    pipeline_desc.depth_stencil.depth_write = blend.is_none();
+
+    pipeline_desc.push_vb(api::VertexBufferDescriptor {
+        binding: 0,
+        stride: size_of::<Vertex>(),
+    });
+
+    pipeline_desc.push_vb(api::VertexBufferDescriptor {
+        binding: 1,
+        stride: size_of::<Instance>(),
+    });
+
+    pipeline_desc.push_attr(api::AttributeDescriptor {
+        binding: 0,
+        location: 0,
+        data: api::VertexData {
+            offset: 0,
+            data_type: api::DataType::Vec3f32,
+        },
+    });
+
+    pipeline_desc.push_attr(api::AttributeDescriptor {
+        binding: 0,
+        location: 1,
+        data: api::VertexData {
+            offset: size_of::<[f32; 3]>(),
+            data_type: api::DataType::Vec2f32,
+        },
+    });
+
+    pipeline_desc.push_attr(api::AttributeDescriptor {
+        binding: 0,
+        location: 2,
+        data: api::VertexData {
+            offset: size_of::<[f32; 3]>() + size_of::<[f32; 2]>(),
+            data_type: api::DataType::Vec3f32,
+        },
+    });
+
+    pipeline_desc.push_attr(api::AttributeDescriptor {
+        binding: 1,
+        location: 3,
+        data: api::VertexData {
+            offset: 0,
+            data_type: api::DataType::Mat4f32,
+        },
+    });
+
+    pipeline_desc.push_attr(api::AttributeDescriptor {
+        binding: 1,
+        location: 7,
+        data: api::VertexData {
+            offset: size_of::<Matrix4f>(),
+            data_type: api::DataType::Float32,
+        },
+    });
+
+    pipeline_desc.push_attr(api::AttributeDescriptor {
+        binding: 1,
+        location: 8,
+        data: api::VertexData {
+            offset: size_of::<Matrix4f>() + size_of::<f32>(),
+            data_type: api::DataType::Mat4f32,
+        },
+    });
+
+    device.create_pipeline(pipeline_desc)
+}
 
-        let b_ptr = device.map_memory(&mesh_mem);
-        unsafe { std::ptr::copy(vertexes.as_ptr() as *mut u8, b_ptr, vertexes.len() * std::mem::size_of::<Vertex>()) }
-        let i_ptr = device.map_memory(&indexes_mem);
-        unsafe { std::ptr::copy(result.indices.as_ptr() as *mut u8, i_ptr, result.indices.len() * size_of::<u32>()) }
+pub struct Frame {
+    queue: Sender<DrawIndexed>,
+    cameras: Vec<CameraView>,
+    lights: LightsData,
+    environment: Option<EnvironmentId>,
+}
 
+impl Frame {
+    pub fn queue(&self) -> Sender<DrawIndexed> {
+        self.queue.clone()
+    }
 
-        device.unmap_memory(&mesh_mem);
-        device.unmap_memory(&indexes_mem);
+    ///Cameras the scene is drawn by, in any order.
+    pub fn set_cameras(&mut self, cameras: Vec<CameraView>) {
+        self.cameras = cameras
+    }
 
+    pub fn set_lights(&mut self, lights: LightsData) {
+        self.lights = lights
+    }
 
-        let desc_set_layout = device.create_descriptor_set_layout(
-            &[
-                api::DescriptorSetLayoutBinding {
-                    binding: 0,
-                    desc: api::DescriptorType::UniformBuffer,
-                }
-            ]);
-
-        let pipeline_layout = device.create_pipeline_layout(
-            &desc_set_layout,
-            vec![
-                api::PipelineLayoutHint {
-                    location: 0,
-                    hint: api::LayoutHint::Name("Matricies"),
-                }
-            ]);
+    ///Environment lighting the scene and drawn behind it, `None` lights it by the default sky
+    ///in front of the clear color.
+    pub fn set_environment(&mut self, environment: Option<EnvironmentId>) {
+        self.environment = environment
+    }
+}
 
-        let render_pass = {
-            let color_attachment = api::Attachment {
-                layout: api::AttachmentLayout::Color
-            };
+impl<B: api::Backend> Renderer<B> {
+    pub fn submit(&mut self, cmd: DrawIndexed) {
+        self.sender.send(cmd);
+    }
 
-            let depth_attachment = api::Attachment {
-                layout: api::AttachmentLayout::Depth
-            };
+    ///Renders following frames into an offscreen target of `size`, or back to the screen when `None`.
+    pub fn set_offscreen(&mut self, device: &B::RendererDevice, size: Option<(u32, u32)>) -> Result<(), api::Error> {
+        if let Some(target) = self.offscreen.take() {
+            target.free(&mut self.allocator);
+        }
+        if let Some(size) = size {
+            self.offscreen = Some(RenderTarget::new(device,
+                                                    &mut self.allocator,
+                                                    size,
+                                                    &[api::image::Format::Rgba8],
+                                                    Some(api::image::Format::D32F))?);
+        }
+        Ok(())
+    }
 
-            device.create_render_pass(vec![color_attachment, depth_attachment])
-        };
+    pub fn offscreen(&self) -> Option<&RenderTarget<B>> {
+        self.offscreen.as_ref()
+    }
 
+    ///Adds an image of `size` for cameras to draw into. Cameras target it by the returned id.
+    pub fn add_camera_target(&mut self, device: &B::RendererDevice, size: (u32, u32)) -> Result<CameraTargetId, api::Error> {
+        let target = RenderTarget::new(device,
+                                       &mut self.allocator,
+                                       size,
+                                       &[api::image::Format::Rgba8],
+                                       Some(api::image::Format::D32F))?;
+        self.camera_targets.push(target);
+        Ok(self.camera_targets.len() as CameraTargetId - 1)
+    }
 
-        let (rimg_mem, rimg, rimg_view) = {
-            let kind = api::image::Kind::D2(600, 400, 1);
-            let rimg = device.create_image(kind);
-//            let req = device.get_img_requirements(img); //TODO
-//            let rimg_mem = device.allocate_memory(req.size);
-            let mut rimg_mem = device.allocate_memory(1024);
-            device.bind_image_memory(&mut rimg_mem, &rimg);
-            let rimg_view = device.create_image_view(&rimg);
-            (rimg_mem, rimg, rimg_view)
-        };
+    pub fn camera_target(&self, id: CameraTargetId) -> Option<&RenderTarget<B>> {
+        self.camera_targets.get(id as usize)
+    }
 
+    ///Color the screen or target is cleared to before the scene is drawn.
+    pub fn set_clear_color(&mut self, color: [f32; 4]) {
+        self.clear_color = color;
+    }
 
-        let (drimg_mem, drimg, drimg_view) = {
-            let kind = api::image::Kind::D2(600, 400, 1);
-            let rimg = device.create_image(kind);
-            let mut rimg_mem = device.allocate_memory(1024);
-            device.bind_image_memory(&mut rimg_mem, &rimg);
-            let rimg_view = device.create_image_view(&rimg);
-            (rimg_mem, rimg, rimg_view)
-        };
+    ///Appends a full-screen pass to the post-process chain, the scene is rendered into an Rgba16F target
+    ///from the next frame on rather than straight to the screen.
+    pub fn add_post_pass(&mut self, device: &B::RendererDevice, pass: PostPass) -> Result<PostPassId, api::Error> {
+        self.post.add(device, &mut self.allocator, pass)
+    }
 
-        let rimg_framebuffer = device.create_framebuffer(&render_pass, vec![&rimg_view, &drimg_view]);
+    pub fn set_post_params(&mut self, device: &B::RendererDevice, pass: PostPassId, params: [f32; 4]) -> Result<(), api::Error> {
+        self.post.set_params(device, &self.allocator, pass, params)
+    }
 
-        let pipeline = {
-            use std::mem::size_of;
-            use std::fs;
+    ///Loads an OBJ into the shared mesh buffers, entities draw it by setting `Render::va` to the returned id.
+    pub fn load_mesh(&mut self, device: &B::RendererDevice, path: &Path) -> Result<MeshId, api::Error> {
+        let result = Loader.load_obj(path);
+        self.meshes.add_loaded(device, &self.allocator, &result)
+    }
 
+    pub fn meshes(&self) -> &MeshStore<B> {
+        &self.meshes
+    }
 
-            let shader_set = api::ShaderSet {
-                vertex: device.create_shader_mod(api::ShaderModDescriptor {
-                    stype: api::ShaderType::Vertex,
-                    source: fs::read_to_string(&relative_to_current_path(&vec!["client", "src", "test", "vert.glsl"])).expect(""),
-                }),
-                fragment: device.create_shader_mod(api::ShaderModDescriptor {
-                    stype: api::ShaderType::Fragment,
-                    source: fs::read_to_string(&relative_to_current_path(&vec!["client", "src", "test", "frag.glsl"])).expect(""),
-                }),
-            };
+    ///Compiles a pipeline with the mesh and instance layout of the default one and the descriptors of `shading`,
+    ///materials draw with it through the returned id. Pipelines with `blend` draw back to front after the opaque ones.
+    pub fn add_pipeline(&mut self,
+                        device: &B::RendererDevice,
+                        shading: Shading,
+                        blend: Option<api::BlendState>,
+                        vertex: &Path,
+                        fragment: &Path) -> Result<PipelineId, api::Error> {
+        let pipeline = create_pipeline::<B>(device, self.pipeline_layout(shading), vertex, fragment, blend)?;
+        self.pipelines.push((pipeline, shading, blend.is_some()));
+        Ok(self.pipelines.len() as PipelineId - 1)
+    }
 
-            let mut pipeline_desc = api::PipelineDescriptor::new(
-                api::Primitive::Triangles,
-                shader_set,
-                &pipeline_layout,
-            );
-
-            pipeline_desc.push_vb(api::VertexBufferDescriptor {
-                binding: 0,
-                stride: size_of::<Vertex>(),
-            });
-
-            pipeline_desc.push_vb(api::VertexBufferDescriptor {
-                binding: 1,
-                stride: size_of::<[[f32; 4]; 4]>(),
-            });
-
-            pipeline_desc.push_attr(api::AttributeDescriptor {
-                binding: 0,
-                location: 0,
-                data: api::VertexData {
-                    offset: 0,
-                    data_type: api::DataType::Vec3f32,
-                },
-            });
-
-            pipeline_desc.push_attr(api::AttributeDescriptor {
-                binding: 0,
-                location: 1,
-                data: api::VertexData {
-                    offset: size_of::<[f32; 3]>(),
-                    data_type: api::DataType::Vec2f32,
-                },
-            });
-
-            pipeline_desc.push_attr(api::AttributeDescriptor {
-                binding: 0,
-                location: 2,
-                data: api::VertexData {
-                    offset: size_of::<[f32; 3]>() + size_of::<[f32; 2]>(),
-                    data_type: api::DataType::Vec3f32,
-                },
-            });
-
-            pipeline_desc.push_attr(api::AttributeDescriptor {
-                binding: 1,
-                location: 3,
-                data: api::VertexData {
-                    offset: 0,
-                    data_type: api::DataType::Mat4f32,
-                },
-            });
-
-            device.create_pipeline(pipeline_desc)
-        };
+    fn pipeline_layout(&self, shading: Shading) -> &B::PipelineLayout {
+        match shading {
+            Shading::Lit => &self.lit_layout,
+            Shading::Pbr => &self.pbr_layout,
+        }
+    }
 
-        let desc_set = device.allocate_descriptor_set(&desc_set_layout);
+    ///Uploads a texture for materials to sample, its levels below the base one are generated.
+    pub fn add_texture(&mut self,
+                       device: &B::RendererDevice,
+                       kind: api::image::Kind,
+                       format: api::image::Format,
+                       texels: &[u8]) -> Result<TextureId, api::Error> {
+        let texture = Texture::upload(device,
+                                      &mut self.allocator,
+                                      kind,
+                                      format,
+                                      texels,
+                                      api::image::SamplerDesc::new(api::image::Filter::Linear, api::image::WrapMode::Tile))?;
+        self.textures.push(texture);
+        Ok(self.textures.len() as TextureId - 1)
+    }
 
+    ///Registers a material, entities render with it by setting `Render::material` to the returned id.
+    ///Fails when its pipeline or any of its textures is not registered.
+    pub fn add_material(&mut self, device: &B::RendererDevice, material: Material) -> Result<MaterialId, api::Error> {
+        if material.pipeline as usize >= self.pipelines.len() {
+            return Err(api::Error::UnknownResource { kind: "pipeline", id: material.pipeline });
+        }
+        let texture = |id: TextureId| self.textures.get(id as usize)
+            .ok_or(api::Error::UnknownResource { kind: "texture", id });
+        let textures = [
+            (1, texture(material.texture)?),
+            (7, texture(material.normal_texture)?),
+            (8, texture(material.metallic_roughness_texture)?),
+            (9, texture(material.occlusion_texture)?),
+            (10, texture(material.emissive_texture)?),
+        ];
+
+        let set = device.allocate_descriptor_set(&self.desc_set_layout)?;
         device.write_descriptor_set(api::DescriptorSetWrite {
-            set: &desc_set,
+            set: &set,
             binding: 0,
-            descriptor: api::Descriptor::Buffer(&uniform),
+            descriptor: api::Descriptor::Buffer(&self.uniform),
         });
-
-
-        let (s, r) = mpsc::channel();
-        Renderer {
-            api,
-            sender: s.clone(),
-            pipeline,
-            vertex: static_mesh_buffer,
-            index: static_mesh_index_buffer,
-            uniform: uniform,
-            pipeline_layout,
-            desc_set,
-            index_count: result.indices.len(),
-            receiver: r,
-            last_frame: Frame {
-                queue: s.clone(),
-                view: glm::identity(),
-                projection: glm::identity(),
-            },
-            mesh_mem,
-            indexes_mem,
-            uniform_mem,
-            instanced,
-
-            instanced_mem
+        device.write_descriptor_set(api::DescriptorSetWrite {
+            set: &set,
+            binding: 2,
+            descriptor: api::Descriptor::Buffer(self.materials.buffer()),
+        });
+        device.write_descriptor_set(api::DescriptorSetWrite {
+            set: &set,
+            binding: 3,
+            descriptor: api::Descriptor::Buffer(&self.lights),
+        });
+        for (binding, texture) in textures.iter() {
+            write_texture(device, &set, *binding, texture);
+        }
+        for (i, map) in self.shadows.maps().enumerate() {
+            write_texture(device, &set, 4 + i as u32, map);
         }
+        self.write_environment(device, &set);
+        self.materials.add(device, &self.allocator, material, set)
     }
-}
 
-pub struct Frame {
-    queue: Sender<DrawIndexed>,
-    view: Matrix4f,
-    projection: Matrix4f,
-}
-
-impl Frame {
-    pub fn queue(&self) -> Sender<DrawIndexed> {
-        self.queue.clone()
+    ///Integrates the lighting of the equirectangular `environment` and uploads it as a cubemap,
+    ///frames light PBR materials by it and draw it as their background once set active.
+    pub fn add_environment(&mut self, device: &B::RendererDevice, environment: &HdrImage) -> Result<EnvironmentId, api::Error> {
+        let id = self.skybox.add(device, &mut self.allocator, environment)?;
+        let lighting = self.ibl.add(device, &mut self.allocator, environment)?;
+        debug_assert_eq!(id, lighting);
+        Ok(id)
     }
 
-    pub fn set_view_matrix(&mut self, mtx: Matrix4f) {
-        self.view = mtx
+    fn write_environment(&self, device: &B::RendererDevice, set: &B::DescriptorSet) {
+        let maps = self.ibl.get(self.environment).unwrap();
+        write_texture(device, set, 11, &maps.irradiance);
+        write_texture(device, set, 12, &maps.prefiltered);
+        write_texture(device, set, 13, &self.ibl.brdf);
     }
 
-    pub fn set_projection_matrix(&mut self, mtx: Matrix4f) {
-        self.projection = mtx
+    pub fn materials(&self) -> &MaterialStore<B> {
+        &self.materials
     }
-}
 
-impl Renderer {
-    pub fn submit(&mut self, cmd: DrawIndexed) {
-        self.sender.send(cmd);
+    pub fn instances(&self) -> &StreamBuffer<B, Instance> {
+        &self.instances
     }
 
-    pub fn process(&self, device: &backend::RendererDevice, frame: &mut Frame) {
-        let mut cmd_buffer = device.create_cmd_buffer();
-        let u_ptr = device.map_memory(&self.uniform_mem);
+    ///Draws the queued commands once for every camera of the frame, in increasing priority. Cameras of the window draw
+    ///into the offscreen target or the screen, and into `capture` as well when given, both through the post-process
+    ///chain when it has passes.
+    pub fn process(&mut self, device: &B::RendererDevice, frame: &mut Frame, capture: Option<&RenderTarget<B>>) -> Result<(), api::Error> {
+        //environments never added are ignored, the scene is lit by the default sky without a background then
+        let environment = frame.environment.filter(|&id| id < self.ibl.len());
+        if environment.unwrap_or(0) != self.environment {
+            self.environment = environment.unwrap_or(0);
+            for set in self.materials.descriptor_sets() {
+                self.write_environment(device, set);
+            }
+        }
 
-        cmd_buffer.bind_pipeline(&self.pipeline);
-//        cmd_buffer.begin_render_pass();
-        cmd_buffer.clear_screen((0.5, 0.5, 0.5, 1.));
-        cmd_buffer.bind_descriptor_set(&self.pipeline_layout, &self.desc_set);
+        //with post-processing the window is rendered into the chain's target, at the size of the screen or offscreen target
+        let extent = self.offscreen.as_ref().map_or(self.size, |target| target.extent);
+        let post = !self.post.is_empty() && extent.0 > 0 && extent.1 > 0;
+        if post {
+            self.post.resize(device, &mut self.allocator, extent)?;
+        }
 
-        cmd_buffer.bind_vertex_buffer(0, &self.vertex);
-        cmd_buffer.bind_vertex_buffer(1, &self.instanced);
-        cmd_buffer.bind_index_buffer(&self.index);
+        //cameras of targets never added are skipped, a frame without any is drawn from the origin
+        let targets = self.camera_targets.len();
+        let mut cameras: Vec<CameraView> = frame.cameras.iter()
+            .filter(|camera| match camera.target {
+                CameraTarget::Window => true,
+                CameraTarget::Image(id) => (id as usize) < targets,
+            })
+            .copied()
+            .collect();
+        if cameras.is_empty() {
+            cameras.push(CameraView::default());
+        }
+        cameras.sort_by_key(|camera| camera.priority);
+        let shadow_camera = shadow_camera(&cameras);
+
+        let commands: Vec<DrawIndexed> = self.receiver.try_iter().collect();
+        let mut stats = FrameStats::default();
+        let mut drawn: Vec<CameraTarget> = Vec::with_capacity(cameras.len());
+        for (i, camera) in cameras.iter().enumerate() {
+            //the first camera of a target clears all of it, later ones only their viewport
+            let clear = !drawn.contains(&camera.target);
+            if clear {
+                drawn.push(camera.target);
+            }
+            let (batches, culled, shadows) = self.prepare_camera(device, frame, camera, &commands, environment, shadow_camera == Some(i))?;
+            let runs = batch::runs(&batches);
+            //transparent batches come last, drawn over the sky in the order the batcher sorted them
+            let pipelines = &self.pipelines;
+            let opaque = runs.iter().take_while(|run| !pipelines[batches[run.start as usize].key.pipeline as usize].2).count();
+            stats.draws += batches.len() as u32;
+            stats.calls += runs.len() as u32;
+            stats.instances += batches.last().map_or(0, |batch| batch.instances.end);
+            stats.culled += culled;
+
+            let mut cmd_buffer = device.create_cmd_buffer();
+            //shadow maps are drawn from the camera's visible instances, before the passes sampling them
+            if shadows {
+                self.shadows.record(&mut cmd_buffer, &self.meshes, &self.instances, &self.indirect, batches.len() as u32);
+            }
+            let destinations: Vec<Option<&RenderTarget<B>>> = match camera.target {
+                CameraTarget::Window if post => vec![self.post.scene()],
+                CameraTarget::Window => std::iter::once(self.offscreen.as_ref()).chain(capture.map(Some)).collect(),
+                CameraTarget::Image(id) => vec![self.camera_targets.get(id as usize)],
+            };
+            for target in destinations {
+                self.begin_camera(&mut cmd_buffer, target, camera, clear);
+                self.record_runs(&mut cmd_buffer, &batches, &runs[..opaque]);
+                if let Some(environment) = environment {
+                    self.skybox.record(&mut cmd_buffer, environment);
+                }
+                self.record_runs(&mut cmd_buffer, &batches, &runs[opaque..]);
+                if target.is_some() {
+                    cmd_buffer.end_render_pass();
+                }
+            }
+            //the next camera rewrites the uniforms, lights and instances read here, execute returns once they were read
+            device.execute(cmd_buffer)?;
+        }
+        self.stats = stats;
+
+        if post {
+            let mut cmd_buffer = device.create_cmd_buffer();
+            for destination in std::iter::once(self.offscreen.as_ref()).chain(capture.map(Some)) {
+                self.post.record(&mut cmd_buffer, destination, self.clear_color);
+            }
+            device.execute(cmd_buffer)?;
+        }
+        Ok(())
+    }
 
+    ///Uploads what drawing `camera` reads: its matrices, the lights and sky seen from it, and the instances of `commands`
+    ///inside its frustum. Returns the batches of those instances, the number culled and whether shadows are cast.
+    ///Only a camera that `casts` casts shadows.
+    fn prepare_camera(&mut self,
+                      device: &B::RendererDevice,
+                      frame: &Frame,
+                      camera: &CameraView,
+                      commands: &[DrawIndexed],
+                      environment: Option<EnvironmentId>,
+                      casts: bool) -> Result<(Vec<Batch>, u32, bool), api::Error> {
+        let u_ptr = self.allocator.map(device, &self.uniform_mem)?;
+        unsafe {
+            std::ptr::copy(camera.view.as_slice().as_ptr() as *mut u8, u_ptr, 1 * 16 * size_of::<u32>());
+            std::ptr::copy(camera.projection.as_slice().as_ptr() as *mut u8, u_ptr.offset(1 * 16 * 4), 1 * 16 * size_of::<u32>());
+        }
+        self.allocator.unmap(device, &self.uniform_mem);
+
+        let mut lights = frame.lights;
+        lights.set_eye(&camera.view);
+        let mut caster = lights.shadow_caster();
+        if let Some(i) = caster {
+            if casts {
+                let [x, y, z, _] = lights.directional[i].direction;
+                lights.shadows = Cascades::fit(&camera.view, &camera.projection, &glm::vec3(x, y, z));
+            } else {
+                lights.directional[i].direction[3] = 0.;
+                caster = None;
+            }
+        }
+        let l_ptr = self.allocator.map(device, &self.lights_mem)?;
         unsafe {
-            std::ptr::copy(frame.view.as_slice().as_ptr() as *mut u8, u_ptr, 1 * 16 * size_of::<u32>());
-            std::ptr::copy(frame.projection.as_slice().as_ptr() as *mut u8, u_ptr.offset(1 * 16 * 4), 1 * 16 * size_of::<u32>());
+            std::ptr::copy(&lights as *const LightsData as *const u8, l_ptr, size_of::<LightsData>());
         }
+        self.allocator.unmap(device, &self.lights_mem);
 
-        let vp = frame.projection * frame.view;
-        let mapped = device.map_memory(&self.instanced_mem);
-
-        let mut n = 0;
-        for (i, cmd) in self.receiver.try_iter().enumerate() {
-//            let va: &backend::VertexArray = ctx.storage().get_ref(&cmd.0).unwrap();
-//            let instance = cmd.1;
-//            let material: &Material = ctx.storage().get_ref(instance.material()).unwrap();
-//
-//            material.bind();
-//            instance.prepare(material);
-//            let shader = material.shader();
-//
-//            shader.load_mat4("r_view", frame.view.as_slice());
-//            shader.load_mat4("r_projection", frame.projection.as_slice());
-//            shader.load_mat4("r_vp", (frame.projection * frame.view).as_slice());
-//            shader.load_mat4("r_transformation", cmd.2.as_slice());
-//            self.api.draw_indexed(va);
-//            shader.unbind();
-            unsafe {
-                std::ptr::copy((vp * cmd.2).as_slice().as_ptr() as *mut u8, mapped.offset(((i as u32) * 16 * 4) as isize), 1 * 16 * size_of::<u32>());
+        if environment.is_some() {
+            self.skybox.update(device, &self.allocator, &camera.view, &camera.projection)?;
+        }
+
+        let vp = camera.projection * camera.view;
+
+        //commands of unknown meshes or materials are dropped, those outside the frustum counted as culled
+        let mut culled = 0;
+        for &(mesh_id, material_id, transformation) in commands {
+            let (mesh, material) = match (self.meshes.get(mesh_id), self.materials.get(material_id)) {
+                (Some(mesh), Some(material)) => (mesh, material),
+                _ => continue,
             };
-            n = i;
+            let mvp = vp * transformation;
+            if !mesh.bounds.visible(&mvp) {
+                culled += 1;
+                continue;
+            }
+            let key = BatchKey { pipeline: material.pipeline, material: material_id, mesh: mesh_id };
+            let instance = Instance { mvp, material: material_id as f32, model: transformation };
+            if self.pipelines[material.pipeline as usize].2 {
+                let distance = -(camera.view * transformation.column(3)).z;
+                self.batcher.push_transparent(key, distance, instance);
+            } else {
+                self.batcher.push(key, instance);
+            }
+        }
+        let (batches, instances) = self.batcher.build();
+        self.instances.write(device, &mut self.allocator, instances)?;
+        let meshes = &self.meshes;
+        self.indirect.write(device, &mut self.allocator, batches.iter()
+            .map(|batch| meshes.get(batch.key.mesh).unwrap().indirect(batch.instances.clone())))?;
+
+        if caster.is_some() {
+            self.shadows.update(device, &mut self.allocator, &lights.shadows)?;
         }
+        Ok((batches, culled, caster.is_some()))
+    }
+
+    ///Begins drawing `camera` into `target` or the screen, restricted to its viewport, which is cleared.
+    ///All of the target is cleared first when `clear`, otherwise what earlier cameras drew around the viewport is kept.
+    fn begin_camera(&self, cmd_buffer: &mut B::CommandBuffer, target: Option<&RenderTarget<B>>, camera: &CameraView, clear: bool) {
+        let [r, g, b, a] = self.clear_color;
+        cmd_buffer.bind_pipeline(&self.pipelines[0].0);
+        match (target, clear) {
+            (Some(target), true) => target.begin(cmd_buffer, self.clear_color),
+            (Some(target), false) => target.resume(cmd_buffer),
+            (None, true) => cmd_buffer.clear_screen((r, g, b, a)),
+            (None, false) => {}
+        }
+        let viewport = camera.rect(target.map_or(self.size, |target| target.extent));
+        cmd_buffer.set_viewport(viewport);
+        cmd_buffer.set_scissor(Some(viewport));
+        if !clear {
+            cmd_buffer.clear_screen((r, g, b, a));
+        }
+    }
 
-        cmd_buffer.draw_indexed(self.index_count as u32, 0, (n + 1) as u32);
+    ///Records the indirect draws of `runs` of `batches`, binding each run's pipeline and material.
+    fn record_runs(&self, cmd_buffer: &mut B::CommandBuffer, batches: &[Batch], runs: &[Range<u32>]) {
+        //vertex buffers are bound again with every pipeline, which OpenGL binds them to
+        let stride = self.indirect.stride();
+        let mut bound = None;
+        for run in runs {
+            let key = batches[run.start as usize].key;
+            let (pipeline, shading, _) = &self.pipelines[key.pipeline as usize];
+            if bound != Some(key.pipeline) {
+                cmd_buffer.bind_pipeline(pipeline);
+                self.meshes.bind(cmd_buffer, 0);
+                cmd_buffer.bind_vertex_buffer(1, self.instances.buffer());
+                bound = Some(key.pipeline);
+            }
+            cmd_buffer.bind_descriptor_set(self.pipeline_layout(*shading), self.materials.descriptor_set(key.material).unwrap());
+            cmd_buffer.draw_indexed_indirect(self.indirect.buffer(), run.start * stride, run.end - run.start, stride);
+        }
+    }
 
-        device.unmap_memory(&self.instanced_mem);
-        device.unmap_memory(&self.uniform_mem);
-        device.execute(cmd_buffer)
+    ///Draws and instances of the last processed frame, summed over its cameras.
+    pub fn stats(&self) -> FrameStats {
+        self.stats
     }
 
-    pub fn viewport(&self, w: i32, h: i32) {
+    pub fn viewport(&mut self, w: i32, h: i32) {
+        self.size = (w as u32, h as u32);
         self.api.viewport(w, h);
     }
 
+    ///Writes the next processed frame to a PNG at `path`.
+    pub fn capture(&mut self, path: PathBuf) {
+        self.capture = Some(path);
+    }
+
     pub fn start(&mut self) -> Frame {
         self.api.clear_color();
         Frame {
             queue: self.sender.clone(),
-            view: self.last_frame.view,
-            projection: self.last_frame.projection,
+            cameras: self.last_frame.cameras.clone(),
+            lights: self.last_frame.lights,
+            environment: self.last_frame.environment,
         }
     }
 
-    pub fn process_frame(&mut self, device: &backend::RendererDevice, frame: &mut Frame) {
-        self.process(device, frame);
+    pub fn process_frame(&mut self, device: &B::RendererDevice, frame: &mut Frame) -> Result<(), api::Error> {
+        let path = match self.capture.take() {
+            Some(path) => path,
+            None => return self.process(device, frame, None),
+        };
+        let target = match RenderTarget::new(device, &mut self.allocator, self.size, &[api::image::Format::Rgba8], Some(api::image::Format::D32F)) {
+            Ok(target) => target,
+            Err(e) => {
+                eprintln!("Capture to {} failed: {}", path.display(), e);
+                return self.process(device, frame, None);
+            }
+        };
+        let pixels = self.process(device, frame, Some(&target))
+            .and_then(|_| device.read_pixels(&target.colors[0].image, 0));
+        target.free(&mut self.allocator);
+        if let Err(e) = crate::capture::write_png(&path, self.size, &pixels?) {
+            eprintln!("Capture to {} failed: {}", path.display(), e);
+        }
+        Ok(())
     }
 
     pub fn end(&mut self, frame: Frame) {
@@ -382,7 +865,123 @@ impl Renderer {
         self.sender.clone()
     }
 
-    pub fn api(&self) -> &backend::RendererApi {
+    pub fn api(&self) -> &B::RendererApi {
         &self.api
     }
-}
\ No newline at end of file
+}
+///Index of the camera shadow maps are drawn for, the highest priority one casting shadows in `cameras` sorted by priority.
+///Shadow maps are drawn once a frame.
+fn shadow_camera(cameras: &[CameraView]) -> Option<usize> {
+    cameras.iter().rposition(|camera| camera.casts_shadows)
+}
+
+#[cfg(all(test, feature = "headless"))]
+mod tests {
+    use api::PlatformManager;
+    use headless::{Backend, PlatformManager as HeadlessPlatformManager, RendererDevice as HeadlessDevice};
+
+    use super::*;
+
+    fn renderer() -> (HeadlessPlatformManager, HeadlessDevice, Renderer<Backend>) {
+        let mut pm = HeadlessPlatformManager::new(api::WindowConfig { width: 8, height: 8 });
+        let (api, device) = pm.create_renderer();
+        let renderer = Renderer::with_assets(api, &device, Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/..")))
+            .unwrap();
+        (pm, device, renderer)
+    }
+
+    ///Whether the set of `material` samples the maps of `environment`.
+    fn lit_by(renderer: &Renderer<Backend>, material: MaterialId, environment: EnvironmentId) -> bool {
+        let set = renderer.materials.descriptor_set(material).unwrap();
+        let maps = renderer.ibl.get(environment).unwrap();
+        set.holds_image(11, &maps.irradiance.view)
+            && set.holds_image(12, &maps.prefiltered.view)
+            && set.holds_image(13, &renderer.ibl.brdf.view)
+    }
+
+    #[test]
+    fn writes_material_descriptors() {
+        let (_pm, device, mut renderer) = renderer();
+        let texture = renderer.add_texture(&device, api::image::Kind::D2(1, 1, 1), api::image::Format::Rgba8, &[255; 4])
+            .unwrap();
+        let material = Material { texture, ..Material::default() };
+        let id = renderer.add_material(&device, material.clone()).unwrap();
+
+        let set = renderer.materials.descriptor_set(id).unwrap();
+        assert!(set.holds_buffer(0, &renderer.uniform));
+        assert!(set.holds_buffer(2, renderer.materials.buffer()));
+        assert!(set.holds_buffer(3, &renderer.lights));
+        for (binding, texture) in [
+            (1, material.texture),
+            (7, material.normal_texture),
+            (8, material.metallic_roughness_texture),
+            (9, material.occlusion_texture),
+            (10, material.emissive_texture),
+        ].iter() {
+            assert!(set.holds_image(*binding, &renderer.textures[*texture as usize].view), "binding {}", binding);
+        }
+        for (i, map) in renderer.shadows.maps().enumerate() {
+            assert!(set.holds_image(4 + i as u32, &map.view));
+        }
+        assert!(lit_by(&renderer, id, 0));
+    }
+
+    #[test]
+    fn rewrites_environment_maps_once_another_is_active() {
+        let (_pm, device, mut renderer) = renderer();
+        let before = renderer.add_material(&device, Material::default()).unwrap();
+        let sky = renderer.add_environment(&device, &crate::ibl::default_environment()).unwrap();
+        assert!(lit_by(&renderer, before, 0));
+        assert!(!lit_by(&renderer, before, sky));
+
+        let mut frame = renderer.start();
+        frame.set_environment(Some(sky));
+        renderer.process(&device, &mut frame, None).unwrap();
+        let after = renderer.add_material(&device, Material::default()).unwrap();
+        assert!(lit_by(&renderer, before, sky));
+        assert!(lit_by(&renderer, after, sky));
+
+        //environments never added fall back to the default one
+        frame.set_environment(Some(sky + 1));
+        renderer.process(&device, &mut frame, None).unwrap();
+        assert!(lit_by(&renderer, before, 0));
+        assert!(lit_by(&renderer, after, 0));
+    }
+
+    #[test]
+    fn fits_shadows_to_the_highest_priority_camera_casting_them() {
+        let camera = |priority, casts_shadows| CameraView { priority, casts_shadows, ..CameraView::default() };
+        assert_eq!(shadow_camera(&[camera(0, true), camera(1, false)]), Some(0));
+        assert_eq!(shadow_camera(&[camera(0, true), camera(1, true), camera(2, false)]), Some(1));
+        assert_eq!(shadow_camera(&[camera(0, false), camera(1, false)]), None);
+        assert_eq!(shadow_camera(&[]), None);
+    }
+
+    #[test]
+    fn draws_each_camera_with_its_own_view() {
+        let (_pm, device, mut renderer) = renderer();
+        renderer.set_clear_color([0., 0., 0., 1.]);
+        let facing = renderer.add_camera_target(&device, (8, 8)).unwrap();
+        let away = renderer.add_camera_target(&device, (8, 8)).unwrap();
+        let camera = |z: f32, target| CameraView {
+            view: glm::translate(&glm::identity(), &glm::vec3(0., 0., z)),
+            projection: glm::perspective(1., 1., 0.1, 100.),
+            target: CameraTarget::Image(target),
+            ..CameraView::default()
+        };
+
+        //the cube is in front of the first camera and behind the second one, drawn after it
+        let mut frame = renderer.start();
+        frame.set_cameras(vec![camera(-5., facing), CameraView { priority: 1, ..camera(5., away) }]);
+        frame.queue().send((0, 0, glm::identity())).unwrap();
+        renderer.process(&device, &mut frame, None).unwrap();
+
+        let drawn = |id| {
+            let image = &renderer.camera_target(id).unwrap().colors[0].image;
+            let pixels = device.read_pixels(image, 0).unwrap();
+            pixels.chunks(4).filter(|texel| texel[..3] != [0, 0, 0]).count()
+        };
+        assert!(drawn(facing) > 0);
+        assert_eq!(drawn(away), 0);
+    }
+}
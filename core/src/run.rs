@@ -1,29 +1,36 @@
-use api::{Event, ImGuiRenderer, PlatformManager, RendererApi, RendererDevice, WindowConfig, Swapchain};
+use std::path::{Path, PathBuf};
+
+use api::{Action, Backend, Event, ImGuiRenderer, PlatformManager, RendererApi, RendererDevice, WindowConfig, Swapchain};
 
 use crate::ecs::layer::EcsLayerBuilder;
 use crate::render::{Frame, Renderer};
 
-pub fn build_engine(config: WindowConfig, ecs_layer: EcsLayerBuilder) -> RxEngine {
-    let mut pm: backend::PlatformManager = backend::PlatformManager::new(config);
-    let (renderer, device): (backend::RendererApi, backend::RendererDevice) = pm.create_renderer();
-    let mut engine = RxEngine::new(pm, renderer, device);
+///F12, writes the frame to the working directory.
+const CAPTURE_KEY: api::Code = 301;
+
+pub fn build_engine<'l, B: Backend>(config: WindowConfig, ecs_layer: EcsLayerBuilder<'l, B>) -> Result<RxEngine<'l, B>, api::Error> {
+    let size = (config.width as i32, config.height as i32);
+    let mut pm: B::PlatformManager = <B::PlatformManager as PlatformManager<B>>::new(config);
+    let (renderer, device): (B::RendererApi, B::RendererDevice) = pm.create_renderer();
+    let mut engine = RxEngine::new(pm, renderer, device)?;
+    engine.ctx.renderer.viewport(size.0, size.1);
     engine.add_layer_builder(ecs_layer);
     engine.add_layer_builder(crate::layer::info_layer::InfoLayerBuilder);
-    engine
+    Ok(engine)
 }
 
-pub struct RxEngine<'l> {
-    layer_dispatcher: LayerDispatcher<'l>,
+pub struct RxEngine<'l, B: Backend> {
+    layer_dispatcher: LayerDispatcher<'l, B>,
     ///[NOTE]: opengl renderer should be destroyed before platform manager
-    ctx: EngineContext,
+    ctx: EngineContext<B>,
     #[cfg(feature = "imgui_debug")]
-    imgui_ctx: ImGuiContext,
+    imgui_ctx: ImGuiContext<B>,
 }
 
-pub struct EngineContext {
-    pub renderer: Renderer,
-    pub platform: backend::PlatformManager,
-    pub renderer_device: backend::RendererDevice,
+pub struct EngineContext<B: Backend> {
+    pub renderer: Renderer<B>,
+    pub platform: B::PlatformManager,
+    pub renderer_device: B::RendererDevice,
 }
 
 //TODO: TickContext;
@@ -45,37 +52,47 @@ pub struct FrameContext {
 }
 
 #[cfg(feature = "imgui_debug")]
-pub struct ImGuiContext {
+pub struct ImGuiContext<B: Backend> {
     pub imgui: imgui::Context,
-    pub imgui_renderer: backend::ImGuiRenderer,
+    pub imgui_renderer: B::ImGuiRenderer,
 }
 
-impl<'l> RxEngine<'l> {
+impl<'l, B: Backend> RxEngine<'l, B> {
     pub fn new(
-        mut platform: backend::PlatformManager,
-        render_api: backend::RendererApi,
-        renderer_device: backend::RendererDevice,
-    ) -> RxEngine<'l> {
+        platform: B::PlatformManager,
+        render_api: B::RendererApi,
+        renderer_device: B::RendererDevice,
+    ) -> Result<RxEngine<'l, B>, api::Error> {
+        RxEngine::with_assets(platform, render_api, renderer_device, &crate::utils::relative_to_current_path(&[]))
+    }
+
+    ///Like `new` with the renderer's assets looked up in `assets` instead of the working directory.
+    pub fn with_assets(
+        mut platform: B::PlatformManager,
+        render_api: B::RendererApi,
+        renderer_device: B::RendererDevice,
+        assets: &Path,
+    ) -> Result<RxEngine<'l, B>, api::Error> {
         #[cfg(feature = "imgui_debug")]
             let mut imgui = imgui::Context::init();
         #[cfg(feature = "imgui_debug")]
             let mut renderer = platform.imgui_renderer(&mut imgui);
 
 
-        RxEngine {
+        Ok(RxEngine {
             ctx: EngineContext {
                 platform,
-                renderer: Renderer::new(render_api, &renderer_device),
+                renderer: Renderer::with_assets(render_api, &renderer_device, assets)?,
                 renderer_device
             },
             #[cfg(feature = "imgui_debug")]
             imgui_ctx: ImGuiContext { imgui, imgui_renderer: renderer },
             layer_dispatcher: LayerDispatcher::new(),
-        }
+        })
     }
-    pub fn run(&mut self) {
+    pub fn run(&mut self) -> Result<(), api::Error> {
         let surface = self.ctx.platform.create_surface();
-        let mut swapchain = self.ctx.renderer_device.create_swapchain(&surface);
+        let mut swapchain = self.ctx.renderer_device.create_swapchain(&surface)?;
 
         let mut current: f64 = 0f64;
         let mut past: f64 = 0f64;
@@ -96,25 +113,45 @@ impl<'l> RxEngine<'l> {
             };
 
             for e in &frame.events {
-                if let Event::Resize(w, h) = e {
-                    self.ctx.renderer.viewport(*w, *h)
+                match e {
+                    Event::Resize(w, h) => self.ctx.renderer.viewport(*w, *h),
+                    Event::Key(CAPTURE_KEY, Action::Press) => {
+                        let path = format!("capture_{:.0}.png", self.ctx.platform.current_time_ms());
+                        self.ctx.renderer.capture(path.into())
+                    }
+                    _ => {}
                 }
             }
 
             self.layer_dispatcher.run_layers(&mut frame, &mut self.ctx);
 
 
-            self.ctx.renderer.process_frame(&self.ctx.renderer_device, &mut frame.frame);
+            if let Err(e) = self.ctx.renderer.process_frame(&self.ctx.renderer_device, &mut frame.frame) {
+                eprintln!("Frame skipped: {}", e);
+            }
 
             #[cfg(feature = "imgui_debug")]
                 self.imgui_ctx.imgui_renderer.render(frame.ui);
             self.ctx.renderer.end(frame.frame);
             swapchain.0.present(0);
         }
+        Ok(())
+    }
+
+    ///Writes the next rendered frame to a PNG at `path`, layers request it through `EngineContext::renderer`.
+    pub fn capture_frame(&mut self, path: impl Into<PathBuf>) {
+        self.ctx.renderer.capture(path.into());
+    }
+
+    pub fn context(&self) -> &EngineContext<B> {
+        &self.ctx
+    }
 
+    pub fn context_mut(&mut self) -> &mut EngineContext<B> {
+        &mut self.ctx
     }
 
-    pub fn add_layer_builder(&mut self, builder: impl LayerBuilder<'l>) {
+    pub fn add_layer_builder(&mut self, builder: impl LayerBuilder<'l, B>) {
         let layer = builder.build(&mut self.ctx);
         self.layer_dispatcher.add_layer(layer);
     }
@@ -126,31 +163,31 @@ impl<'l> RxEngine<'l> {
 
 mod imgui_dev {}
 
-pub trait Layer {
-    fn on_update(&mut self, frame: &mut FrameContext, ctx: &mut EngineContext);
+pub trait Layer<B: Backend> {
+    fn on_update(&mut self, frame: &mut FrameContext, ctx: &mut EngineContext<B>);
 }
 
-pub struct LayerDispatcher<'l> {
-    layers: Vec<Box<dyn Layer + 'l>>
+pub struct LayerDispatcher<'l, B: Backend> {
+    layers: Vec<Box<dyn Layer<B> + 'l>>
 }
 
-impl<'l> LayerDispatcher<'l> {
-    pub fn new() -> LayerDispatcher<'l> {
+impl<'l, B: Backend> LayerDispatcher<'l, B> {
+    pub fn new() -> LayerDispatcher<'l, B> {
         LayerDispatcher { layers: Vec::new() }
     }
 
-    pub fn add_layer(&mut self, layer: Box<dyn Layer + 'l>) {
+    pub fn add_layer(&mut self, layer: Box<dyn Layer<B> + 'l>) {
         self.layers.push(layer);
     }
 
-    pub fn run_layers(&mut self, frame: &mut FrameContext, ctx: &mut EngineContext) {
+    pub fn run_layers(&mut self, frame: &mut FrameContext, ctx: &mut EngineContext<B>) {
         for l in &mut self.layers {
             l.on_update(frame, ctx)
         }
     }
 }
 
-pub trait LayerBuilder<'l> {
-    fn build(&self, r: &mut EngineContext) -> Box<dyn Layer + 'l>;
+pub trait LayerBuilder<'l, B: Backend> {
+    fn build(&self, r: &mut EngineContext<B>) -> Box<dyn Layer<B> + 'l>;
 }
 
@@ -0,0 +1,276 @@
+use std::mem::size_of;
+use std::path::Path;
+
+use api::{CommandBuffer, RendererDevice};
+use api::memory::Properties;
+
+use crate::allocator::{Allocation, Allocator};
+use crate::mesh::{MeshStore, Vertex};
+use crate::render::{load_shaders, Instance};
+use crate::stream::StreamBuffer;
+use crate::target::RenderTarget;
+use crate::texture::Texture;
+use crate::Matrix4f;
+
+///Slices of the camera frustum the shadow casting directional light gets a depth map for.
+pub const CASCADES: usize = 3;
+///Width and height in texels of each cascade's depth map.
+pub const SHADOW_MAP_SIZE: u32 = 1024;
+///Distance from the camera at which shadows end. The far plane is used when it is closer.
+pub const SHADOW_DISTANCE: f32 = 100.;
+///Blend between logarithmic and uniform split distances, higher keeps near cascades smaller.
+const SPLIT_LAMBDA: f32 = 0.75;
+
+///Light space transformations of the cascades and the view space depth each ends at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct Cascades {
+    pub view_projections: [Matrix4f; CASCADES],
+    ///Far distance of each cascade, padded to a vec4.
+    pub splits: [f32; 4],
+}
+
+impl Default for Cascades {
+    fn default() -> Self {
+        Cascades {
+            view_projections: [glm::identity(); CASCADES],
+            splits: [0.; 4],
+        }
+    }
+}
+
+impl Cascades {
+    ///Fits the cascades of a light travelling in `direction` around slices of the frustum of `view` and a perspective `projection`.
+    ///Each is a bounding sphere snapped to texels of its map, so shadow edges stay still as the camera turns and moves.
+    pub fn fit(view: &Matrix4f, projection: &Matrix4f, direction: &glm::Vec3) -> Cascades {
+        //near and far planes recovered from the depth row of the projection
+        let near = projection[(2, 3)] / (projection[(2, 2)] - 1.);
+        let far = (projection[(2, 3)] / (projection[(2, 2)] + 1.)).min(SHADOW_DISTANCE);
+        let (tan_x, tan_y) = (1. / projection[(0, 0)], 1. / projection[(1, 1)]);
+        let camera = glm::inverse(view);
+        let direction = glm::normalize(direction);
+        let up = if direction.y.abs() > 0.99 { glm::vec3(0., 0., 1.) } else { glm::vec3(0., 1., 0.) };
+
+        let mut cascades = Cascades::default();
+        let mut begin = near;
+        for i in 0..CASCADES {
+            let t = (i + 1) as f32 / CASCADES as f32;
+            let end = SPLIT_LAMBDA * near * (far / near).powf(t) + (1. - SPLIT_LAMBDA) * (near + (far - near) * t);
+
+            let corners: Vec<glm::Vec3> = [begin, end].iter()
+                .flat_map(|&d| {
+                    [(-1., -1.), (1., -1.), (-1., 1.), (1., 1.)].iter()
+                        .map(move |&(x, y)| glm::vec4(x * d * tan_x, y * d * tan_y, -d, 1.))
+                })
+                .map(|corner| (camera * corner).xyz())
+                .collect();
+            let center = corners.iter().fold(glm::Vec3::zeros(), |sum, c| sum + c) / corners.len() as f32;
+            //rounded so the map's size only changes with the projection
+            let radius = corners.iter()
+                .map(|c| glm::distance(c, &center))
+                .fold(0., f32::max);
+            let radius = (radius * 16.).ceil() / 16.;
+
+            //casters up to a radius outside the slice still shadow it
+            let light_view = glm::look_at(&(center - direction * radius * 2.), &center, &up);
+            let mut light_projection = glm::ortho(-radius, radius, -radius, radius, 0., radius * 4.);
+            let origin = light_projection * light_view * glm::vec4(0., 0., 0., 1.);
+            let texels = origin.xy() * (SHADOW_MAP_SIZE as f32 / 2.);
+            let offset = (glm::round(&texels) - texels) * (2. / SHADOW_MAP_SIZE as f32);
+            light_projection[(0, 3)] += offset.x;
+            light_projection[(1, 3)] += offset.y;
+
+            cascades.view_projections[i] = light_projection * light_view;
+            cascades.splits[i] = end;
+            begin = end;
+        }
+        cascades
+    }
+}
+
+///Depth only pipeline rendering the scene's instances into one depth map per cascade.
+///Instances culled against the camera are not drawn, so casters outside its view cast no shadows.
+pub struct ShadowMaps<B: api::Backend> {
+    pipeline: B::Pipeline,
+    pipeline_layout: B::PipelineLayout,
+    cascades: Vec<Cascade<B>>,
+}
+
+struct Cascade<B: api::Backend> {
+    target: RenderTarget<B>,
+    ///The cascade's light space transformation, kept alive for its descriptor set.
+    _uniform: B::Buffer,
+    allocation: Allocation,
+    set: B::DescriptorSet,
+}
+
+impl<B: api::Backend> ShadowMaps<B> {
+    ///Creates the depth maps and the pipeline from GLSL sources at `vertex` and `fragment`.
+    pub fn new(device: &B::RendererDevice,
+               allocator: &mut Allocator<B>,
+               vertex: &Path,
+               fragment: &Path) -> Result<Self, api::Error> {
+        let desc_set_layout = device.create_descriptor_set_layout(&[
+            api::DescriptorSetLayoutBinding {
+                binding: 0,
+                desc: api::DescriptorType::UniformBuffer,
+            }
+        ])?;
+        let pipeline_layout = device.create_pipeline_layout(
+            &desc_set_layout,
+            vec![
+                api::PipelineLayoutHint {
+                    location: 0,
+                    hint: api::LayoutHint::Name("Shadow"),
+                }
+            ])?;
+        let host_visible = Properties::HOST_VISIBLE | Properties::HOST_COHERENT;
+        let mut cascades = Vec::with_capacity(CASCADES);
+        for _ in 0..CASCADES {
+            let target = RenderTarget::new(device,
+                                           allocator,
+                                           (SHADOW_MAP_SIZE, SHADOW_MAP_SIZE),
+                                           &[],
+                                           Some(api::image::Format::D32F))?;
+            let mut uniform = device.create_buffer(api::BufferDescriptor {
+                size: size_of::<Matrix4f>() as u32,
+                usage: api::Usage::Uniform,
+            })?;
+            let allocation = allocator.bind_buffer(device, &mut uniform, host_visible)?;
+            let set = device.allocate_descriptor_set(&desc_set_layout)?;
+            device.write_descriptor_set(api::DescriptorSetWrite {
+                set: &set,
+                binding: 0,
+                descriptor: api::Descriptor::Buffer(&uniform),
+            });
+            cascades.push(Cascade { target, _uniform: uniform, allocation, set });
+        }
+        let pipeline = match create_pipeline::<B>(device, &pipeline_layout, &cascades[0].target.render_pass, vertex, fragment) {
+            Ok(pipeline) => pipeline,
+            Err(e) => {
+                for cascade in cascades {
+                    cascade.target.free(allocator);
+                    allocator.free(cascade.allocation);
+                }
+                return Err(e);
+            }
+        };
+        Ok(ShadowMaps { pipeline, pipeline_layout, cascades })
+    }
+
+    ///Depth map of each cascade. The lit pipelines sample it.
+    pub fn maps(&self) -> impl Iterator<Item = &Texture<B>> {
+        self.cascades.iter().map(|cascade| cascade.target.depth.as_ref().unwrap())
+    }
+
+    ///Writes the light space transformation of every cascade.
+    pub fn update(&self, device: &B::RendererDevice, allocator: &mut Allocator<B>, cascades: &Cascades) -> Result<(), api::Error> {
+        for (cascade, view_projection) in self.cascades.iter().zip(&cascades.view_projections) {
+            let ptr = allocator.map(device, &cascade.allocation)?;
+            unsafe {
+                std::ptr::copy(view_projection.as_slice().as_ptr() as *const u8, ptr, size_of::<Matrix4f>());
+            }
+            allocator.unmap(device, &cascade.allocation);
+        }
+        Ok(())
+    }
+
+    ///Records a pass per cascade drawing the first `draws` commands of `indirect` with their instances.
+    pub fn record(&self,
+                  cmd: &mut B::CommandBuffer,
+                  meshes: &MeshStore<B>,
+                  instances: &StreamBuffer<B, Instance>,
+                  indirect: &StreamBuffer<B, api::DrawIndexedIndirectCommand>,
+                  draws: u32) {
+        for cascade in &self.cascades {
+            cascade.target.begin(cmd, [0.; 4]);
+            cmd.bind_pipeline(&self.pipeline);
+            meshes.bind(cmd, 0);
+            cmd.bind_vertex_buffer(1, instances.buffer());
+            cmd.bind_descriptor_set(&self.pipeline_layout, &cascade.set);
+            if draws > 0 {
+                cmd.draw_indexed_indirect(indirect.buffer(), 0, draws, indirect.stride());
+            }
+            cmd.end_render_pass();
+        }
+    }
+
+    pub fn free(self, allocator: &mut Allocator<B>) {
+        for cascade in self.cascades {
+            cascade.target.free(allocator);
+            allocator.free(cascade.allocation);
+        }
+    }
+}
+
+///Pipeline reading only the mesh positions and the instances' model matrices, drawing in passes like `render_pass`.
+fn create_pipeline<B: api::Backend>(device: &B::RendererDevice,
+                                    layout: &B::PipelineLayout,
+                                    render_pass: &B::RenderPass,
+                                    vertex: &Path,
+                                    fragment: &Path) -> Result<B::Pipeline, api::Error> {
+    let shader_set = load_shaders::<B>(device, vertex, fragment)?;
+    let mut pipeline_desc = api::PipelineDescriptor::new(
+        api::Primitive::Triangles,
+        shader_set,
+        layout,
+    );
+    pipeline_desc.render_pass = Some(render_pass);
+
+    pipeline_desc.push_vb(api::VertexBufferDescriptor {
+        binding: 0,
+        stride: size_of::<Vertex>(),
+    });
+
+    pipeline_desc.push_vb(api::VertexBufferDescriptor {
+        binding: 1,
+        stride: size_of::<Instance>(),
+    });
+
+    pipeline_desc.push_attr(api::AttributeDescriptor {
+        binding: 0,
+        location: 0,
+        data: api::VertexData {
+            offset: 0,
+            data_type: api::DataType::Vec3f32,
+        },
+    });
+
+    pipeline_desc.push_attr(api::AttributeDescriptor {
+        binding: 1,
+        location: 8,
+        data: api::VertexData {
+            offset: size_of::<Matrix4f>() + size_of::<f32>(),
+            data_type: api::DataType::Mat4f32,
+        },
+    });
+
+    device.create_pipeline(pipeline_desc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cascades_cover_their_slice_of_the_frustum() {
+        let projection = glm::perspective(16. / 9., glm::quarter_pi(), 0.1, 1000.);
+        let view = glm::look_at(&glm::vec3(3., 2., 10.), &glm::vec3(0., 0., 0.), &glm::vec3(0., 1., 0.));
+        let cascades = Cascades::fit(&view, &projection, &glm::vec3(-1., -1., -1.));
+
+        assert!(cascades.splits[0] > 0.1);
+        assert!(cascades.splits[0] < cascades.splits[1] && cascades.splits[1] < cascades.splits[2]);
+        assert!((cascades.splits[2] - SHADOW_DISTANCE).abs() < 1e-3);
+
+        //points along the view ray at a cascade's depth land inside its map
+        let camera = glm::inverse(&view);
+        let mut begin = 0.1;
+        for (view_projection, &end) in cascades.view_projections.iter().zip(&cascades.splits) {
+            for &d in &[begin, (begin + end) / 2., end] {
+                let p = view_projection * camera * glm::vec4(0., 0., -d, 1.);
+                assert!(p.x.abs() <= 1. && p.y.abs() <= 1. && p.z.abs() <= 1., "{} outside its cascade", d);
+            }
+            begin = end;
+        }
+    }
+}
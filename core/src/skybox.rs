@@ -0,0 +1,135 @@
+use std::mem::size_of;
+use std::path::Path;
+
+use api::{CommandBuffer, RendererDevice};
+use api::memory::Properties;
+
+use crate::allocator::{Allocation, Allocator};
+use crate::hdr::HdrImage;
+use crate::ibl::EnvironmentId;
+use crate::mesh::MeshStore;
+use crate::post::create_fullscreen_pipeline;
+use crate::render::write_texture;
+use crate::texture::Texture;
+use crate::Matrix4f;
+
+///Largest cube side in texels. Environments become cubes with sides of a quarter of their width, up to it.
+const MAX_CUBE_SIZE: u32 = 1024;
+
+///Background of environment cubemaps, drawn after the opaque geometry as a triangle on the far plane.
+///Its depth test passes where the stored depth still equals the far plane, so only what no geometry covered is drawn over.
+pub struct Skybox<B: api::Backend> {
+    pipeline: B::Pipeline,
+    pipeline_layout: B::PipelineLayout,
+    desc_set_layout: B::DescriptorSetLayout,
+    ///Holds the triangle covering the screen.
+    triangle: MeshStore<B>,
+    ///Inverse of the camera's rotation and projection, shared by every environment's set.
+    uniform: B::Buffer,
+    allocation: Allocation,
+    cubemaps: Vec<(Texture<B>, B::DescriptorSet)>,
+}
+
+impl<B: api::Backend> Skybox<B> {
+    ///Compiles the pipeline from GLSL sources at `vertex` and `fragment`, environments are added after.
+    pub fn new(device: &B::RendererDevice,
+               allocator: &mut Allocator<B>,
+               vertex: &Path,
+               fragment: &Path) -> Result<Self, api::Error> {
+        let desc_set_layout = device.create_descriptor_set_layout(&[
+            api::DescriptorSetLayoutBinding {
+                binding: 0,
+                desc: api::DescriptorType::UniformBuffer,
+            },
+            api::DescriptorSetLayoutBinding {
+                binding: 1,
+                desc: api::DescriptorType::Sampler,
+            }
+        ])?;
+        let pipeline_layout = device.create_pipeline_layout(
+            &desc_set_layout,
+            vec![
+                api::PipelineLayoutHint {
+                    location: 0,
+                    hint: api::LayoutHint::Name("Sky"),
+                },
+                api::PipelineLayoutHint {
+                    location: 1,
+                    hint: api::LayoutHint::Name("u_environment"),
+                }
+            ])?;
+        let pipeline = create_fullscreen_pipeline::<B>(device, &pipeline_layout, vertex, fragment, api::CompareOp::LessOrEqual)?;
+
+        let mut uniform = device.create_buffer(api::BufferDescriptor {
+            size: size_of::<Matrix4f>() as u32,
+            usage: api::Usage::Uniform,
+        })?;
+        let allocation = allocator.bind_buffer(device, &mut uniform, Properties::HOST_VISIBLE | Properties::HOST_COHERENT)?;
+        Ok(Skybox {
+            pipeline,
+            pipeline_layout,
+            desc_set_layout,
+            triangle: MeshStore::fullscreen(device, allocator)?,
+            uniform,
+            allocation,
+            cubemaps: Vec::new(),
+        })
+    }
+
+    ///Uploads the equirectangular `environment` as a cubemap.
+    pub fn add(&mut self, device: &B::RendererDevice, allocator: &mut Allocator<B>, environment: &HdrImage) -> Result<EnvironmentId, api::Error> {
+        let size = (environment.width / 4).clamp(1, MAX_CUBE_SIZE);
+        let cubemap = Texture::upload(device,
+                                      allocator,
+                                      api::image::Kind::Cube(size, 1),
+                                      api::image::Format::Rgba16F,
+                                      &environment.cube(size).to_rgba16f(),
+                                      api::image::SamplerDesc::new(api::image::Filter::Linear, api::image::WrapMode::Clamp))?;
+        let set = device.allocate_descriptor_set(&self.desc_set_layout)?;
+        device.write_descriptor_set(api::DescriptorSetWrite {
+            set: &set,
+            binding: 0,
+            descriptor: api::Descriptor::Buffer(&self.uniform),
+        });
+        write_texture(device, &set, 1, &cubemap);
+        self.cubemaps.push((cubemap, set));
+        Ok(self.cubemaps.len() - 1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cubemaps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cubemaps.is_empty()
+    }
+
+    ///Writes the transformation of the far plane to directions around the camera of `view` and `projection`.
+    pub fn update(&self, device: &B::RendererDevice, allocator: &Allocator<B>, view: &Matrix4f, projection: &Matrix4f) -> Result<(), api::Error> {
+        //the sky is infinitely far away, only the camera's rotation moves it
+        let rotation = glm::mat3_to_mat4(&glm::mat4_to_mat3(view));
+        let inverse = glm::inverse(&(projection * rotation));
+        let ptr = allocator.map(device, &self.allocation)?;
+        unsafe {
+            std::ptr::copy(inverse.as_slice().as_ptr() as *const u8, ptr, size_of::<Matrix4f>());
+        }
+        allocator.unmap(device, &self.allocation);
+        Ok(())
+    }
+
+    ///Records drawing `environment` into the bound pass, after its opaque geometry.
+    pub fn record(&self, cmd: &mut B::CommandBuffer, environment: EnvironmentId) {
+        cmd.bind_pipeline(&self.pipeline);
+        self.triangle.bind(cmd, 0);
+        cmd.bind_descriptor_set(&self.pipeline_layout, &self.cubemaps[environment].1);
+        cmd.draw_indexed(3, 0, 1);
+    }
+
+    pub fn free(self, allocator: &mut Allocator<B>) {
+        for (cubemap, _) in self.cubemaps {
+            allocator.free(cubemap.allocation);
+        }
+        allocator.free(self.allocation);
+        self.triangle.free(allocator);
+    }
+}
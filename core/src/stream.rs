@@ -0,0 +1,98 @@
+use std::marker::PhantomData;
+use std::mem::size_of;
+
+use api::RendererDevice;
+use api::memory::Properties;
+
+use crate::allocator::{Allocation, Allocator};
+
+///Buffer of host-written elements that are rewritten every frame.
+///Reallocated with at least twice the capacity whenever a frame writes more elements than fit.
+pub struct StreamBuffer<B: api::Backend, T: Copy> {
+    buffer: B::Buffer,
+    allocation: Allocation,
+    usage: api::Usage,
+    capacity: u32,
+    high_water: u32,
+    _element: PhantomData<T>,
+}
+
+impl<B: api::Backend, T: Copy> StreamBuffer<B, T> {
+    pub fn new(device: &B::RendererDevice,
+               allocator: &mut Allocator<B>,
+               usage: api::Usage,
+               capacity: u32) -> Result<Self, api::Error> {
+        let (buffer, allocation) = Self::allocate(device, allocator, usage, capacity)?;
+        Ok(StreamBuffer { buffer, allocation, usage, capacity, high_water: 0, _element: PhantomData })
+    }
+
+    pub fn buffer(&self) -> &B::Buffer {
+        &self.buffer
+    }
+
+    ///Size of an element in bytes.
+    pub fn stride(&self) -> u32 {
+        size_of::<T>() as u32
+    }
+
+    ///Elements that fit without growing.
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    ///Most elements written in a single frame so far.
+    pub fn high_water(&self) -> u32 {
+        self.high_water
+    }
+
+    ///Replaces the contents with `elements`, growing first when they do not fit.
+    ///The previous buffer is released, so commands recorded with it must have been executed.
+    pub fn write<I>(&mut self,
+                    device: &B::RendererDevice,
+                    allocator: &mut Allocator<B>,
+                    elements: I) -> Result<(), api::Error>
+        where I: ExactSizeIterator<Item=T> {
+        let count = elements.len() as u32;
+        self.reserve(device, allocator, count)?;
+        self.high_water = self.high_water.max(count);
+
+        let ptr = allocator.map(device, &self.allocation)?;
+        for (i, element) in elements.enumerate() {
+            unsafe {
+                std::ptr::copy(&element as *const T as *const u8,
+                               ptr.add(i * size_of::<T>()),
+                               size_of::<T>());
+            }
+        }
+        allocator.unmap(device, &self.allocation);
+        Ok(())
+    }
+
+    fn reserve(&mut self, device: &B::RendererDevice, allocator: &mut Allocator<B>, count: u32) -> Result<(), api::Error> {
+        if count <= self.capacity {
+            return Ok(());
+        }
+        let capacity = count.max(self.capacity * 2);
+        let (buffer, allocation) = Self::allocate(device, allocator, self.usage, capacity)?;
+        allocator.free(std::mem::replace(&mut self.allocation, allocation));
+        self.buffer = buffer;
+        self.capacity = capacity;
+        Ok(())
+    }
+
+    fn allocate(device: &B::RendererDevice,
+                allocator: &mut Allocator<B>,
+                usage: api::Usage,
+                capacity: u32) -> Result<(B::Buffer, Allocation), api::Error> {
+        let mut buffer = device.create_buffer(api::BufferDescriptor {
+            size: capacity * size_of::<T>() as u32,
+            usage,
+        })?;
+        let allocation = allocator.bind_buffer(device, &mut buffer, Properties::HOST_VISIBLE | Properties::HOST_COHERENT)?;
+        Ok((buffer, allocation))
+    }
+
+    pub fn free(self, allocator: &mut Allocator<B>) {
+        allocator.free(self.allocation);
+    }
+}
@@ -0,0 +1,80 @@
+use api::{CommandBuffer, RendererDevice};
+
+use crate::allocator::Allocator;
+use crate::texture::Texture;
+
+///Offscreen framebuffer with sampled colour attachments and an optional depth attachment.
+///Every attachment is cleared when the pass begins, or kept when it resumes, and stored when it ends.
+pub struct RenderTarget<B: api::Backend> {
+    pub render_pass: B::RenderPass,
+    ///Pass loading the attachments, for drawing over what earlier passes left.
+    pub load_pass: B::RenderPass,
+    pub framebuffer: B::Framebuffer,
+    pub colors: Vec<Texture<B>>,
+    pub depth: Option<Texture<B>>,
+    pub extent: (u32, u32),
+}
+
+impl<B: api::Backend> RenderTarget<B> {
+    pub fn new(device: &B::RendererDevice,
+               allocator: &mut Allocator<B>,
+               extent: (u32, u32),
+               colors: &[api::image::Format],
+               depth: Option<api::image::Format>) -> Result<Self, api::Error> {
+        let kind = api::image::Kind::D2(extent.0, extent.1, 1);
+        let sampler = api::image::SamplerDesc::new(api::image::Filter::Linear, api::image::WrapMode::Clamp);
+
+        let attachments = |load| colors.iter()
+            .map(|format| (api::AttachmentLayout::Color, *format))
+            .chain(depth.map(|format| (api::AttachmentLayout::Depth, format)))
+            .map(move |(layout, format)| api::Attachment { layout, format, load, store: api::StoreOp::Store })
+            .collect::<Vec<_>>();
+        let render_pass = device.create_render_pass(attachments(api::LoadOp::Clear))?;
+        let load_pass = device.create_render_pass(attachments(api::LoadOp::Load))?;
+
+        let mut textures = Vec::with_capacity(colors.len() + 1);
+        for format in colors.iter().copied().chain(depth) {
+            match Texture::empty(device, allocator, kind.clone(), format, sampler.clone()) {
+                Ok(texture) => textures.push(texture),
+                Err(e) => {
+                    for texture in textures {
+                        allocator.free(texture.allocation);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        let framebuffer = match device.create_framebuffer(&render_pass, textures.iter().map(|t| &t.view)) {
+            Ok(framebuffer) => framebuffer,
+            Err(e) => {
+                for texture in textures {
+                    allocator.free(texture.allocation);
+                }
+                return Err(e);
+            }
+        };
+
+        let depth = depth.and_then(|_| textures.pop());
+        Ok(RenderTarget { render_pass, load_pass, framebuffer, colors: textures, depth, extent })
+    }
+
+    ///Begins the pass, colour attachments are cleared to `color` and depth to the far plane.
+    pub fn begin(&self, cmd: &mut B::CommandBuffer, color: [f32; 4]) {
+        let clear_values: Vec<api::ClearValue> = self.colors.iter()
+            .map(|_| api::ClearValue::Color(color))
+            .chain(self.depth.iter().map(|_| api::ClearValue::DepthStencil(1., 0)))
+            .collect();
+        cmd.begin_render_pass(&self.render_pass, &self.framebuffer, &clear_values);
+    }
+
+    ///Begins the pass keeping the contents of every attachment.
+    pub fn resume(&self, cmd: &mut B::CommandBuffer) {
+        cmd.begin_render_pass(&self.load_pass, &self.framebuffer, &[]);
+    }
+
+    pub fn free(self, allocator: &mut Allocator<B>) {
+        for texture in self.colors.into_iter().chain(self.depth) {
+            allocator.free(texture.allocation);
+        }
+    }
+}
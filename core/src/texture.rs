@@ -0,0 +1,112 @@
+use api::{CommandBuffer, RendererDevice};
+use api::memory::Properties;
+
+use crate::allocator::{Allocation, Allocator};
+
+///Sampled image with every level filled.
+pub struct Texture<B: api::Backend> {
+    pub image: B::Image,
+    pub view: B::ImageView,
+    pub sampler: B::Sampler,
+    pub allocation: Allocation,
+}
+
+impl<B: api::Backend> Texture<B> {
+    ///Uploads tightly packed `texels` of the base level through a staging buffer,
+    ///the remaining levels are generated from it unless `format` is compressed.
+    pub fn upload(device: &B::RendererDevice,
+                  allocator: &mut Allocator<B>,
+                  kind: api::image::Kind,
+                  format: api::image::Format,
+                  texels: &[u8],
+                  sampler: api::image::SamplerDesc) -> Result<Self, api::Error> {
+        Self::stage(device, allocator, kind, format, &[texels], sampler)
+    }
+
+    ///Uploads tightly packed texels of each level in `levels`, from the base one down.
+    pub fn upload_levels<T: AsRef<[u8]>>(device: &B::RendererDevice,
+                                         allocator: &mut Allocator<B>,
+                                         kind: api::image::Kind,
+                                         format: api::image::Format,
+                                         levels: &[T],
+                                         sampler: api::image::SamplerDesc) -> Result<Self, api::Error> {
+        assert_eq!(levels.len(), kind.levels() as usize, "Texels of every level are needed");
+        let levels: Vec<&[u8]> = levels.iter().map(AsRef::as_ref).collect();
+        Self::stage(device, allocator, kind, format, &levels, sampler)
+    }
+
+    fn stage(device: &B::RendererDevice,
+             allocator: &mut Allocator<B>,
+             kind: api::image::Kind,
+             format: api::image::Format,
+             levels: &[&[u8]],
+             sampler: api::image::SamplerDesc) -> Result<Self, api::Error> {
+        let size: usize = levels.iter().map(|texels| texels.len()).sum();
+        let mut staging = device.create_buffer(api::BufferDescriptor {
+            size: size as u32,
+            usage: api::Usage::TransferSrc,
+        })?;
+        let staging_mem = allocator.bind_buffer(device, &mut staging, Properties::HOST_VISIBLE | Properties::HOST_COHERENT)?;
+
+        let result = allocator.map(device, &staging_mem)
+            .and_then(|ptr| {
+                let mut offset = 0;
+                for texels in levels {
+                    unsafe { std::ptr::copy(texels.as_ptr(), ptr.add(offset), texels.len()) }
+                    offset += texels.len();
+                }
+                allocator.unmap(device, &staging_mem);
+                Self::fill(device, allocator, &staging, kind, format, levels, sampler)
+            });
+        allocator.free(staging_mem);
+        result
+    }
+
+    ///Image with undefined contents, to be rendered into.
+    pub fn empty(device: &B::RendererDevice,
+                 allocator: &mut Allocator<B>,
+                 kind: api::image::Kind,
+                 format: api::image::Format,
+                 sampler: api::image::SamplerDesc) -> Result<Self, api::Error> {
+        let mut image = device.create_image(kind, format)?;
+        let allocation = allocator.bind_image(device, &mut image, Properties::DEVICE_LOCAL)?;
+        Ok(Texture {
+            view: device.create_image_view(&image)?,
+            sampler: device.create_sampler(sampler)?,
+            image,
+            allocation,
+        })
+    }
+
+    fn fill(device: &B::RendererDevice,
+            allocator: &mut Allocator<B>,
+            staging: &B::Buffer,
+            kind: api::image::Kind,
+            format: api::image::Format,
+            levels: &[&[u8]],
+            sampler: api::image::SamplerDesc) -> Result<Self, api::Error> {
+        let mut image = device.create_image(kind.clone(), format)?;
+        let allocation = allocator.bind_image(device, &mut image, Properties::DEVICE_LOCAL)?;
+
+        let mut cmd = device.create_cmd_buffer();
+        let mut offset = 0;
+        for (level, texels) in levels.iter().enumerate() {
+            let mut region = api::image::BufferImageCopy::level(&kind, level as api::image::Level);
+            region.buffer_offset = offset;
+            cmd.copy_buffer_to_image(staging, &image, region);
+            offset += texels.len() as u32;
+        }
+        //levels without texels of their own are generated, which compressed formats can't be
+        if levels.len() < kind.levels() as usize && !format.is_compressed() {
+            cmd.generate_mipmaps(&image);
+        }
+        device.execute(cmd)?;
+
+        Ok(Texture {
+            view: device.create_image_view(&image)?,
+            sampler: device.create_sampler(sampler)?,
+            image,
+            allocation,
+        })
+    }
+}
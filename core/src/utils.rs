@@ -16,8 +16,10 @@ pub fn relative_path(relative: &str, target: &[&str]) -> PathBuf {
 }
 
 pub fn relative_to_current_path(target: &[&str]) -> PathBuf {
-    let mut base_path = env::current_dir().unwrap();
+    relative_to(&env::current_dir().unwrap(), target)
+}
 
+pub fn relative_to(base_path: &Path, target: &[&str]) -> PathBuf {
     let mut path = base_path.to_path_buf();
     for s in target {
         path.push(*s)
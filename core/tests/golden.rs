@@ -0,0 +1,370 @@
+#![cfg(feature = "headless")]
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use specs::{Builder, WorldExt};
+
+use rx_engine::api::WindowConfig;
+use rx_engine::ecs::components::{Camera, DirectionalLight, PointLight, Position, Render, Rotation, SpotLight, Transformation};
+use rx_engine::ecs::ActiveEnvironment;
+use rx_engine::ecs::layer::EcsLayerBuilder;
+use rx_engine::glm;
+use rx_engine::golden::{self, Tolerance};
+use rx_engine::material::{Material, PBR_PIPELINE, TRANSPARENT_PIPELINE};
+use rx_engine::post::PostPass;
+use rx_engine::specs;
+use rx_engine::utils::relative_to;
+
+const FRAMES: u64 = 3;
+
+fn reference_dir() -> &'static Path {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden"))
+}
+
+///Meshes and shaders are looked up relative to the workspace root.
+fn assets() -> &'static Path {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/.."))
+}
+
+///Position and rotation around x, y and z in radians of each of the cube scene's cubes.
+const SCATTERED: [([f32; 3], [f32; 3]); 3] = [
+    ([-2.5, 0., 0.], [0., 0.7, 0.]),
+    ([0., 1.5, 0.], [0.4, 0.7, 0.]),
+    ([2.5, -1., 0.], [0.8, 0.7, 0.]),
+];
+
+///Position and rotation of a transparent cube.
+const NEAR_GLASS: ([f32; 3], [f32; 3]) = ([-1.5, 0., 2.], [0., 45., 0.]);
+const FAR_GLASS: ([f32; 3], [f32; 3]) = ([-0.5, 0., 0.], [0., 0., 0.]);
+
+///Adds `camera` at `position`, turned around x, y and z by `rotation` in degrees.
+fn add_camera(w: &mut specs::World, camera: Camera, [x, y, z]: [f32; 3], [rx, ry, rz]: [f32; 3]) {
+    w.create_entity()
+        .with(camera)
+        .with(Position { x, y, z })
+        .with(Rotation { x: rx, y: ry, z: rz })
+        .build();
+}
+
+///Adds an instance of mesh `va` drawn with `material` at each position and rotation of `placements`.
+fn add_instances(w: &mut specs::World, va: u32, material: u32, placements: &[([f32; 3], [f32; 3])]) {
+    for &([x, y, z], [rx, ry, rz]) in placements {
+        w.create_entity()
+            .with(Transformation { mtx: glm::identity() })
+            .with(Position { x, y, z })
+            .with(Rotation { x: rx, y: ry, z: rz })
+            .with(Render { va, material })
+            .build();
+    }
+}
+
+fn add_transparent_material(ctx: &mut rx_engine::run::EngineContext<rx_engine::headless::Backend>) -> u32 {
+    ctx.renderer.add_material(&ctx.renderer_device, Material { pipeline: TRANSPARENT_PIPELINE, ..Material::default() })
+        .unwrap()
+}
+
+fn cube_scene() -> EcsLayerBuilder<'static, rx_engine::headless::Backend> {
+    EcsLayerBuilder::new(Box::new(|mut w, d, _ctx| {
+        add_camera(&mut w, Camera::default(), [0., 0., 10.], [0.; 3]);
+        add_instances(&mut w, 0, 0, &SCATTERED);
+        (w, d)
+    }))
+}
+
+fn mesh_scene() -> EcsLayerBuilder<'static, rx_engine::headless::Backend> {
+    EcsLayerBuilder::new(Box::new(|mut w, d, ctx| {
+        add_camera(&mut w, Camera::default(), [0., 0., 12.], [0.; 3]);
+        //renders the shadow passes ahead of the scene
+        w.create_entity()
+            .with(DirectionalLight { direction: [-1., -1., -1.], color: [1.; 3], intensity: 1., casts_shadows: true })
+            .build();
+        for (i, file) in ["sphere.obj", "torus.obj", "tetrahedron.obj"].iter().enumerate() {
+            let mesh = ctx.renderer.load_mesh(&ctx.renderer_device, &relative_to(assets(), &["client", "resources", file]))
+                .unwrap();
+            add_instances(&mut w, mesh, 0, &[([3.5 * (i as f32 - 1.), 0., 0.], [1., 0., 0.])]);
+        }
+        (w, d)
+    }))
+}
+
+///The cube scene with a second camera drawing it from above into the top right corner.
+fn minimap_scene() -> EcsLayerBuilder<'static, rx_engine::headless::Backend> {
+    EcsLayerBuilder::new(Box::new(|mut w, d, _ctx| {
+        add_camera(&mut w, Camera::default(), [0., 0., 10.], [0.; 3]);
+        add_camera(&mut w, Camera { priority: 1, viewport: [0.6, 0.6, 0.4, 0.4], ..Camera::default() }, [0., 12., 0.], [-90., 0., 0.]);
+        add_instances(&mut w, 0, 0, &SCATTERED);
+        (w, d)
+    }))
+}
+
+///Four cubes filling the window of the first camera.
+fn quad_scene() -> EcsLayerBuilder<'static, rx_engine::headless::Backend> {
+    EcsLayerBuilder::new(Box::new(|mut w, d, _ctx| {
+        add_quad(&mut w);
+        (w, d)
+    }))
+}
+
+///The quad scene with a second camera sharing the window, which looks away from the cubes in the top right corner.
+fn overlay_scene() -> EcsLayerBuilder<'static, rx_engine::headless::Backend> {
+    EcsLayerBuilder::new(Box::new(|mut w, d, _ctx| {
+        add_camera(&mut w, Camera { priority: 1, viewport: [0.5, 0.5, 0.5, 0.5], ..Camera::default() }, [0., 0., 4.], [0., 180., 0.]);
+        add_quad(&mut w);
+        (w, d)
+    }))
+}
+
+fn add_quad(w: &mut specs::World) {
+    add_camera(w, Camera::default(), [0., 0., 4.], [0.; 3]);
+    let zero = [0.; 3];
+    add_instances(w, 0, 0, &[([-1., -1., 0.], zero), ([1., -1., 0.], zero), ([-1., 1., 0.], zero), ([1., 1., 0.], zero)]);
+}
+
+///An opaque cube on the left and a transparent one on the right, without a background.
+fn glass_scene() -> EcsLayerBuilder<'static, rx_engine::headless::Backend> {
+    EcsLayerBuilder::new(Box::new(|mut w, d, ctx| {
+        add_glass(&mut w, ctx);
+        (w, d)
+    }))
+}
+
+///The glass scene in front of the default sky.
+fn sky_scene() -> EcsLayerBuilder<'static, rx_engine::headless::Backend> {
+    EcsLayerBuilder::new(Box::new(|mut w, d, ctx| {
+        w.insert(ActiveEnvironment(Some(0)));
+        add_glass(&mut w, ctx);
+        (w, d)
+    }))
+}
+
+fn add_glass(w: &mut specs::World, ctx: &mut rx_engine::run::EngineContext<rx_engine::headless::Backend>) {
+    let glass = add_transparent_material(ctx);
+    add_camera(w, Camera::default(), [0., 0., 6.], [0.; 3]);
+    add_instances(w, 0, 0, &[([-1.5, 0., 0.], [0.5, 0.5, 0.])]);
+    add_instances(w, 0, glass, &[([1.5, 0., 0.], [0.5, 0.5, 0.])]);
+}
+
+///A transparent cube in front of the one the pair scene adds.
+fn near_glass_scene() -> EcsLayerBuilder<'static, rx_engine::headless::Backend> {
+    EcsLayerBuilder::new(Box::new(|mut w, d, ctx| {
+        let glass = add_transparent_material(ctx);
+        add_camera(&mut w, Camera::default(), [0., 0., 6.], [0.; 3]);
+        add_instances(&mut w, 0, glass, &[NEAR_GLASS]);
+        (w, d)
+    }))
+}
+
+///Two overlapping transparent cubes, the nearer one submitted first.
+fn glass_pair_scene() -> EcsLayerBuilder<'static, rx_engine::headless::Backend> {
+    EcsLayerBuilder::new(Box::new(|mut w, d, ctx| {
+        let glass = add_transparent_material(ctx);
+        add_camera(&mut w, Camera::default(), [0., 0., 6.], [0.; 3]);
+        add_instances(&mut w, 0, glass, &[NEAR_GLASS, FAR_GLASS]);
+        (w, d)
+    }))
+}
+
+fn capture_path() -> PathBuf {
+    std::env::temp_dir().join("rx_engine_capture_orientation.png")
+}
+
+///A cube above the centre of the view, captured to `capture_path` on the first frame.
+fn raised_cube_scene() -> EcsLayerBuilder<'static, rx_engine::headless::Backend> {
+    EcsLayerBuilder::new(Box::new(|mut w, d, ctx| {
+        ctx.renderer.capture(capture_path());
+        add_camera(&mut w, Camera::default(), [0., 0., 6.], [0.; 3]);
+        add_instances(&mut w, 0, 0, &[([0., 1.5, 0.], [0.; 3])]);
+        (w, d)
+    }))
+}
+
+///The cube scene lit by a shadow casting directional light, a point and a spot light, with a PBR cube in the middle.
+fn lit_scene() -> EcsLayerBuilder<'static, rx_engine::headless::Backend> {
+    EcsLayerBuilder::new(Box::new(|mut w, d, ctx| {
+        let gold = ctx.renderer.add_material(&ctx.renderer_device,
+                                             Material { pipeline: PBR_PIPELINE, metallic: 1., roughness: 0.3, ..Material::default() })
+            .unwrap();
+        add_camera(&mut w, Camera::default(), [0., 0., 10.], [0.; 3]);
+        add_instances(&mut w, 0, 0, &[SCATTERED[0], SCATTERED[2]]);
+        add_instances(&mut w, 0, gold, &[SCATTERED[1]]);
+        w.create_entity()
+            .with(DirectionalLight { direction: [-1., -1., -1.], color: [1.; 3], intensity: 1., casts_shadows: true })
+            .build();
+        w.create_entity()
+            .with(PointLight { color: [1., 0.8, 0.6], intensity: 40., range: 50. })
+            .with(Position { x: 3., y: 3., z: 5. })
+            .build();
+        w.create_entity()
+            .with(SpotLight { direction: [0., 0., -1.], color: [1.; 3], intensity: 20., range: 30., inner_angle: 0.3, outer_angle: 0.5 })
+            .with(Position { x: 0., y: 0., z: 8. })
+            .build();
+        (w, d)
+    }))
+}
+
+///The cube scene run through a tone mapping pass.
+fn post_scene() -> EcsLayerBuilder<'static, rx_engine::headless::Backend> {
+    EcsLayerBuilder::new(Box::new(|mut w, d, ctx| {
+        let tonemap = PostPass { params: [1., 1., 0., 0.], ..PostPass::new(relative_to(assets(), &["client", "src", "test", "post", "tonemap.glsl"])) };
+        ctx.renderer.add_post_pass(&ctx.renderer_device, tonemap).unwrap();
+        add_camera(&mut w, Camera::default(), [0., 0., 10.], [0.; 3]);
+        add_instances(&mut w, 0, 0, &SCATTERED);
+        (w, d)
+    }))
+}
+
+///More cubes than the instance buffer initially holds.
+fn crowd_scene() -> EcsLayerBuilder<'static, rx_engine::headless::Backend> {
+    EcsLayerBuilder::new(Box::new(|mut w, d, _ctx| {
+        add_camera(&mut w, Camera::default(), [0., 0., 120.], [0.; 3]);
+        let grid: Vec<([f32; 3], [f32; 3])> = (0..40 * 30)
+            .map(|i| ([3. * (i % 40) as f32 - 58.5, 3. * (i / 40) as f32 - 43.5, 0.], [0.; 3]))
+            .collect();
+        add_instances(&mut w, 0, 0, &grid);
+        (w, d)
+    }))
+}
+
+///Geometry and depth of the cubes only, the headless backend does not run shaders.
+#[test]
+fn renders_cube_scene() {
+    let image = golden::render_frames(WindowConfig { width: 120, height: 80 }, assets(), FRAMES, cube_scene());
+
+    golden::check(reference_dir(), "cube_scene", &image, Tolerance::default())
+        .unwrap_or_else(|e| panic!("{}", e));
+}
+
+///Runs the light, shadow and PBR passes, which the headless backend draws with the fixed function program.
+///Only that they leave the cube scene's geometry and depth as drawn unlit is checked, not the light values,
+///the shadowing or the BRDF.
+#[test]
+fn shades_the_cube_scene_without_changing_its_geometry() {
+    let image = golden::render_frames(WindowConfig { width: 120, height: 80 }, assets(), FRAMES, lit_scene());
+
+    golden::check(reference_dir(), "cube_scene", &image, Tolerance::default())
+        .unwrap_or_else(|e| panic!("{}", e));
+}
+
+///The headless backend draws the post pass's triangle white, so the chain reaching the window shows as a white frame.
+///What the pass computes from the scene, the tone mapping itself, is not checked.
+#[test]
+fn draws_the_post_chain_over_the_whole_window() {
+    let (width, height) = (40, 20);
+    let image = golden::render_frames(WindowConfig { width, height }, assets(), FRAMES, post_scene());
+
+    let white = [255, 255, 255, 255];
+    for (i, pixel) in image.pixels.chunks(4).enumerate() {
+        assert_eq!(pixel, &white[..], "pixel {:?}", (i as u32 % width, i as u32 / width));
+    }
+}
+
+///Geometry of the loaded meshes, the shadow maps are drawn but how they shade the scene is not checked.
+#[test]
+fn renders_several_meshes() {
+    let image = golden::render_frames(WindowConfig { width: 120, height: 80 }, assets(), FRAMES, mesh_scene());
+
+    golden::check(reference_dir(), "mesh_scene", &image, Tolerance::default())
+        .unwrap_or_else(|e| panic!("{}", e));
+}
+
+///Every instance past the initial capacity is drawn, checked by geometry alone.
+#[test]
+fn grows_instance_buffer() {
+    let image = golden::render_frames(WindowConfig { width: 120, height: 80 }, assets(), FRAMES, crowd_scene());
+
+    golden::check(reference_dir(), "crowd_scene", &image, Tolerance::default())
+        .unwrap_or_else(|e| panic!("{}", e));
+}
+
+///Viewports and draw order of two cameras sharing the window, checked by geometry alone.
+#[test]
+fn draws_every_camera_into_its_viewport() {
+    let image = golden::render_frames(WindowConfig { width: 120, height: 80 }, assets(), FRAMES, minimap_scene());
+
+    golden::check(reference_dir(), "minimap_scene", &image, Tolerance::default())
+        .unwrap_or_else(|e| panic!("{}", e));
+}
+
+///Compares two renderings instead of a reference, cleared pixels do not depend on shading.
+#[test]
+fn clears_only_the_viewport_of_later_cameras() {
+    let (width, height) = (40, 40);
+    let single = golden::render_frames(WindowConfig { width, height }, assets(), FRAMES, quad_scene());
+    let overlaid = golden::render_frames(WindowConfig { width, height }, assets(), FRAMES, overlay_scene());
+
+    let clear = [128, 128, 128, 255];
+    let mut covered = 0;
+    for (i, (before, after)) in single.pixels.chunks(4).zip(overlaid.pixels.chunks(4)).enumerate() {
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        if x >= width / 2 && y >= height / 2 {
+            //the second camera sees nothing, its viewport is left cleared over what the first drew
+            assert_eq!(after, &clear[..], "pixel {:?} inside the viewport", (x, y));
+            covered += (before != &clear[..]) as u32;
+        } else {
+            assert_eq!(after, before, "pixel {:?} outside the viewport", (x, y));
+        }
+    }
+    assert!(covered > 0, "the first camera draws nothing under the second one's viewport");
+}
+
+///Compares renderings with and without a sky, which the headless backend draws white where no geometry is.
+#[test]
+fn draws_the_sky_behind_the_scene_and_before_transparent_objects() {
+    let (width, height) = (40, 20);
+    let plain = golden::render_frames(WindowConfig { width, height }, assets(), FRAMES, glass_scene());
+    let sky = golden::render_frames(WindowConfig { width, height }, assets(), FRAMES, sky_scene());
+
+    let (clear, white) = ([128, 128, 128, 255], [255, 255, 255, 255]);
+    let mut covered = [0; 2];
+    for (i, (before, after)) in plain.pixels.chunks(4).zip(sky.pixels.chunks(4)).enumerate() {
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        if before == &clear[..] {
+            assert_eq!(after, &white[..], "background pixel {:?}", (x, y));
+        } else {
+            //the transparent cube writes no depth, it shows only if drawn after the sky
+            assert_eq!(after, before, "pixel {:?} of a cube", (x, y));
+            covered[(x >= width / 2) as usize] += 1;
+        }
+    }
+    assert!(covered.iter().all(|&c| c > 0), "a cube is missing: {:?}", covered);
+}
+
+///Transparent objects write no depth, where they overlap the one drawn last shows whatever order they were submitted in.
+#[test]
+fn draws_transparent_objects_back_to_front() {
+    let (width, height) = (40, 20);
+    let near = golden::render_frames(WindowConfig { width, height }, assets(), FRAMES, near_glass_scene());
+    let pair = golden::render_frames(WindowConfig { width, height }, assets(), FRAMES, glass_pair_scene());
+
+    let clear = [128, 128, 128, 255];
+    let (mut covered, mut behind) = (0, 0);
+    for (i, (alone, both)) in near.pixels.chunks(4).zip(pair.pixels.chunks(4)).enumerate() {
+        if alone != &clear[..] {
+            assert_eq!(both, alone, "pixel {:?} of the near cube", (i as u32 % width, i as u32 / width));
+            covered += 1;
+        } else if both != &clear[..] {
+            behind += 1;
+        }
+    }
+    assert!(covered > 0 && behind > 0, "the cubes do not both show: {} {}", covered, behind);
+}
+
+///PNG rows start at the top, the cube above the centre must not end up in the bottom half.
+#[test]
+fn captures_frames_top_row_first() {
+    let _ = std::fs::remove_file(capture_path());
+    golden::render_frames(WindowConfig { width: 40, height: 40 }, assets(), FRAMES, raised_cube_scene());
+
+    let (info, mut reader) = png::Decoder::new(File::open(capture_path()).unwrap()).read_info().unwrap();
+    let mut pixels = vec![0; info.buffer_size()];
+    reader.next_frame(&mut pixels).unwrap();
+    let clear = [128, 128, 128, 255];
+    let mut covered = [0; 2];
+    for (i, pixel) in pixels.chunks(4).enumerate() {
+        let top = i as u32 / info.width < info.height / 2;
+        covered[top as usize] += (pixel != &clear[..]) as u32;
+    }
+    assert_eq!(covered[0], 0, "the cube reaches into the bottom half");
+    assert!(covered[1] > 0, "the cube is missing from the top half");
+}
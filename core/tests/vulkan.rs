@@ -0,0 +1,71 @@
+#![cfg(feature = "vulkan")]
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use specs::{Builder, WorldExt};
+
+use rx_engine::api::{PlatformManager, WindowConfig};
+use rx_engine::ecs::components::{Camera, Position, Render, Rotation, Transformation};
+use rx_engine::ecs::layer::EcsLayerBuilder;
+use rx_engine::glm;
+use rx_engine::render::Renderer;
+use rx_engine::run::RxEngine;
+use rx_engine::specs;
+
+fn assets() -> &'static Path {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/.."))
+}
+
+fn capture_path() -> PathBuf {
+    std::env::temp_dir().join("rx_engine_vulkan_capture_orientation.png")
+}
+
+#[test]
+#[ignore] //needs a Vulkan loader and device, such as lavapipe
+fn builds_a_renderer() {
+    let mut pm = rx_engine::vulkan::PlatformManager::headless(WindowConfig { width: 8, height: 8 }).unwrap();
+    let (api, device) = pm.create_renderer();
+    //compiles every shader of the engine against the layouts it builds pipelines with
+    Renderer::<rx_engine::vulkan::Backend>::with_assets(api, &device, assets()).unwrap();
+}
+
+///The viewport is flipped on Vulkan, `read_pixels` must still hand the capture its rows bottom up.
+#[test]
+#[ignore] //needs a Vulkan loader and device, such as lavapipe
+fn captures_frames_top_row_first() {
+    let _ = std::fs::remove_file(capture_path());
+    let mut pm = rx_engine::vulkan::PlatformManager::headless(WindowConfig { width: 40, height: 40 }).unwrap();
+    pm.set_frame_limit(3);
+    let (api, device) = pm.create_renderer();
+    let mut engine: RxEngine<rx_engine::vulkan::Backend> = RxEngine::with_assets(pm, api, device, assets()).unwrap();
+    engine.add_layer_builder(EcsLayerBuilder::new(Box::new(|mut w, d, ctx| {
+        ctx.renderer.capture(capture_path());
+        w.create_entity()
+            .with(Camera::default())
+            .with(Position { x: 0., y: 0., z: 6. })
+            .with(Rotation { x: 0., y: 0., z: 0. })
+            .build();
+        w.create_entity()
+            .with(Transformation { mtx: glm::identity() })
+            .with(Position { x: 0., y: 1.5, z: 0. })
+            .with(Rotation { x: 0., y: 0., z: 0. })
+            .with(Render { va: 0, material: 0 })
+            .build();
+        (w, d)
+    })));
+    engine.run().unwrap();
+
+    let (info, mut reader) = png::Decoder::new(File::open(capture_path()).unwrap()).read_info().unwrap();
+    let mut pixels = vec![0; info.buffer_size()];
+    reader.next_frame(&mut pixels).unwrap();
+    //the clear colour of 0.5 may round either way
+    let clear = |pixel: &[u8]| pixel[..3].iter().all(|&c| c == 127 || c == 128);
+    let mut covered = [0; 2];
+    for (i, pixel) in pixels.chunks(4).enumerate() {
+        let top = i as u32 / info.width < info.height / 2;
+        covered[top as usize] += !clear(pixel) as u32;
+    }
+    assert_eq!(covered[0], 0, "the cube reaches into the bottom half");
+    assert!(covered[1] > 0, "the cube is missing from the top half");
+}
@@ -20,7 +20,7 @@ fn main() {
         )
             .unwrap();
 
-        Registry::new(Api::Gl, (3, 3), Profile::Core, Fallbacks::All, ["GL_ARB_base_instance"])
+        Registry::new(Api::Gl, (3, 3), Profile::Core, Fallbacks::All, ["GL_ARB_base_instance", "GL_ARB_draw_indirect", "GL_ARB_multi_draw_indirect"])
             .write_bindings(StructGenerator, &mut file)
             .unwrap();
     }